@@ -17,11 +17,13 @@ fn main() {
     let save_image_fn = |node: &WzNodeArc| {
         let node_read = node.read().unwrap();
         if node_read.try_as_png().is_some() {
-            let image = get_image(&node).unwrap();
+            let image = get_image(node).unwrap();
             /* the name of image is easily got conflect */
-            let save_name = node_read.get_full_path().replace("/", "-");
+            let save_name = node_read.get_full_path().replace('/', "-");
             /* resolving image will auto resolve image from _inlink and _outlink */
-            image.save(format!("{out}/{save_name}.png")).unwrap();
+            image
+                .save(std::path::Path::new(&out).join(format!("{save_name}.png")))
+                .unwrap();
         }
     };
     match method.as_encoded_bytes() {
@@ -33,14 +35,14 @@ fn main() {
         }
         b"base" => {
             /* resolve from base.wz */
-            let base_node = resolve_base(&path, None).unwrap();
+            let base_node = resolve_base(path, None).unwrap();
 
             /* this will take millions years */
             walk_node(&base_node, true, &save_image_fn);
         }
         b"folder" => {
             /* resolve whole wz folder */
-            let root_node = resolve_root_wz_file_dir(&path, None).unwrap();
+            let root_node = resolve_root_wz_file_dir(path, None).unwrap();
 
             walk_node(&root_node, true, &save_image_fn);
         }
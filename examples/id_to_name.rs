@@ -12,7 +12,7 @@ fn main() {
     let base_path = args.get(1).expect("missing base path");
     let target_id = args.get(2).expect("missing target id");
     let target_id = target_id.to_str().expect("invalid target id format");
-    let base_node = resolve_base(&base_path, None).unwrap();
+    let base_node = resolve_base(base_path, None).unwrap();
 
     let start = std::time::Instant::now();
 
@@ -20,33 +20,21 @@ fn main() {
         let mut nodes = vec![];
         let base_node = base_node.read().unwrap();
 
-        base_node
-            .at_path("String/Cash.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Consume.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Eqp.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Map.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Mob.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Npc.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Pet.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Skill.img")
-            .map(|node| nodes.push(node));
-        base_node
-            .at_path("String/Ins.img")
-            .map(|node| nodes.push(node));
+        for path in [
+            "String/Cash.img",
+            "String/Consume.img",
+            "String/Eqp.img",
+            "String/Map.img",
+            "String/Mob.img",
+            "String/Npc.img",
+            "String/Pet.img",
+            "String/Skill.img",
+            "String/Ins.img",
+        ] {
+            if let Some(node) = base_node.at_path(path) {
+                nodes.push(node);
+            }
+        }
 
         nodes
     };
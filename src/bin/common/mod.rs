@@ -0,0 +1,23 @@
+use std::path::Path;
+use wz_reader::util::{node_util, resolve_base};
+use wz_reader::{WzNode, WzNodeArc};
+
+/// Load the path as a parsed node, a folder or `Base.wz` is resolved as the whole game data.
+pub fn load(path: &str) -> Result<WzNodeArc, Box<dyn std::error::Error>> {
+    let file_path = Path::new(path);
+
+    if file_path.is_dir() || file_path.ends_with("Base.wz") {
+        return Ok(resolve_base(path, None).map_err(|e| format!("{path}: {e}"))?);
+    }
+
+    let node = match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("ms") => WzNode::from_ms_file(path, None)?,
+        Some("img") => WzNode::from_img_file(path, None, None)?,
+        _ => WzNode::from_wz_file(path, None)?,
+    };
+
+    let node = node.into_lock();
+    node_util::parse_node(&node)?;
+
+    Ok(node)
+}
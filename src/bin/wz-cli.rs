@@ -0,0 +1,153 @@
+mod common;
+
+use common::load;
+use image::ImageFormat;
+use std::io::Write;
+use std::process::ExitCode;
+use wz_reader::util::{export_images, export_sounds, node_util, parse_all, summarize};
+use wz_reader::{WzNodeArc, WzObjectType};
+
+const USAGE: &str = "usage: wz-cli <command> <file> [args]
+
+<file> can be a .wz, .ms, .img file or a folder contains Base.wz
+
+commands:
+  info <file> [--deep]                  show the file and count the nodes, --deep will parse every image
+  ls <file> [path]                      list the children of the node at path
+  cat <file> <path>                     print the node at path as json
+  export-images <file> <out> [path]     save every canvas under the path as png
+  export-sounds <file> <out> [path]     save every sound under the path";
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
+/// Get the node at path and parse it, empty path is the root itself.
+fn get_node(root: &WzNodeArc, path: Option<&str>) -> Result<WzNodeArc, Box<dyn std::error::Error>> {
+    let path = path.unwrap_or("").trim_matches('/');
+
+    let node = if path.is_empty() {
+        root.clone()
+    } else {
+        root.read()
+            .unwrap()
+            .at_path_parsed(path)
+            .map_err(|e| format!("{path}: {e}"))?
+    };
+
+    node_util::parse_node(&node)?;
+
+    Ok(node)
+}
+
+fn info(root: &WzNodeArc, deep: bool) -> CliResult {
+    if deep {
+        for (path, e) in parse_all(root) {
+            eprintln!("failed to parse {path}: {e}");
+        }
+    }
+
+    let node_read = root.read().unwrap();
+    match &node_read.object_type {
+        WzObjectType::File(file) => println!(
+            "{}: wz file, patch version {}, encver {}",
+            node_read.name, file.wz_file_meta.patch_version, file.wz_file_meta.wz_version_header
+        ),
+        WzObjectType::MsFile(file) => println!(
+            "{}: ms file, {} entries",
+            node_read.name, file.header.entry_count
+        ),
+        _ => println!("{node_read}"),
+    }
+    drop(node_read);
+
+    print!("{}", summarize(root));
+
+    Ok(())
+}
+
+fn ls(node: &WzNodeArc) -> CliResult {
+    let node_read = node.read().unwrap();
+
+    let mut children = node_read.children.values().collect::<Vec<_>>();
+    children.sort_by(|a, b| a.read().unwrap().name.cmp(&b.read().unwrap().name));
+
+    /* not using println, so piping to `head` won't panic */
+    let mut out = std::io::stdout().lock();
+    for child in children {
+        writeln!(out, "{}", child.read().unwrap())?;
+    }
+
+    Ok(())
+}
+
+fn cat(node: &WzNodeArc) -> CliResult {
+    for (path, e) in parse_all(node) {
+        eprintln!("failed to parse {path}: {e}");
+    }
+
+    let json = node.read().unwrap().to_simple_json()?;
+    writeln!(
+        std::io::stdout().lock(),
+        "{}",
+        serde_json::to_string_pretty(&json)?
+    )?;
+
+    Ok(())
+}
+
+fn report<T>(result: wz_reader::util::ExportResult<T>) {
+    for (path, e) in &result.failed {
+        eprintln!("failed to export {path}: {e}");
+    }
+    println!(
+        "exported: {}, skipped: {}, failed: {}",
+        result.exported.len(),
+        result.skipped.len(),
+        result.failed.len()
+    );
+}
+
+fn run(args: &[String]) -> CliResult {
+    let (Some(command), Some(file)) = (args.first(), args.get(1)) else {
+        return Err(USAGE.into());
+    };
+    let rest = &args[2..];
+
+    let root = load(file)?;
+
+    match command.as_str() {
+        "info" => info(&root, rest.iter().any(|arg| arg == "--deep")),
+        "ls" => ls(&get_node(&root, rest.first().map(String::as_str))?),
+        "cat" => {
+            let path = rest.first().ok_or("cat need a path")?;
+            cat(&get_node(&root, Some(path))?)
+        }
+        "export-images" => {
+            let out = rest.first().ok_or("export-images need a output folder")?;
+            let node = get_node(&root, rest.get(1).map(String::as_str))?;
+            report(export_images(&node, out, ImageFormat::Png)?);
+            Ok(())
+        }
+        "export-sounds" => {
+            let out = rest.first().ok_or("export-sounds need a output folder")?;
+            let node = get_node(&root, rest.get(1).map(String::as_str))?;
+            report(export_sounds(&node, out)?);
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+// usage:
+//   cargo run --features cli --bin wz-cli -- info "path/to/Mob.wz"
+//   cargo run --features cli --bin wz-cli -- cat "path/to/Mob.wz" "100100.img/info"
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match run(&args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
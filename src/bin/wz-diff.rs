@@ -0,0 +1,91 @@
+mod common;
+
+use common::load;
+use std::io::Write;
+use std::process::ExitCode;
+use wz_reader::util::{diff_nodes, DiffKind};
+use wz_reader::WzNodeArc;
+
+const USAGE: &str = "usage: wz-diff <old> <new> [--json] [--path <path>]
+
+<old> and <new> can be a Base.wz, a folder contains Base.wz, or any .wz, .ms, .img file
+
+options:
+  --json            print the changes as json
+  --path <path>     only compare the node at path, like Mob/100100.img";
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+fn get_node(root: &WzNodeArc, path: Option<&str>) -> CliResult<WzNodeArc> {
+    let Some(path) = path.map(|path| path.trim_matches('/')) else {
+        return Ok(root.clone());
+    };
+
+    Ok(root
+        .read()
+        .unwrap()
+        .at_path_parsed(path)
+        .map_err(|e| format!("{path}: {e}"))?)
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+    let mut is_json = false;
+    let mut path = None;
+    let mut files = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => is_json = true,
+            "--path" => path = Some(args.next().ok_or("--path need a value")?.as_str()),
+            _ => files.push(arg),
+        }
+    }
+
+    let [old, new] = files.as_slice() else {
+        return Err(USAGE.into());
+    };
+
+    let old = get_node(&load(old)?, path)?;
+    let new = get_node(&load(new)?, path)?;
+
+    let diffs = diff_nodes(&old, &new, true);
+
+    /* not using println, so piping to `head` won't panic */
+    let mut out = std::io::stdout().lock();
+
+    if is_json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&diffs)?)?;
+        return Ok(());
+    }
+
+    for diff in &diffs {
+        writeln!(out, "{diff}")?;
+    }
+
+    let count = |kind: DiffKind| diffs.iter().filter(|diff| diff.kind == kind).count();
+    writeln!(
+        out,
+        "{} added, {} removed, {} changed",
+        count(DiffKind::Added),
+        count(DiffKind::Removed),
+        count(DiffKind::Changed)
+    )?;
+
+    Ok(())
+}
+
+// usage:
+//   cargo run --features cli --bin wz-diff -- "old/Data/Base.wz" "new/Data/Base.wz"
+//   cargo run --features cli --bin wz-diff -- "old/Mob.wz" "new/Mob.wz" --path 100100.img --json
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match run(&args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -3,6 +3,9 @@ use crate::{
 };
 use std::sync::Arc;
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -42,7 +45,29 @@ fn get_wz_directory_type_from_byte(byte: u8) -> WzDirectoryType {
     }
 }
 
+/// The type of a entry in the `WzDirectory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WzDirectoryEntryType {
+    Directory,
+    Image,
+}
+
+/// The raw metadata of a entry in the `WzDirectory`, as it stored in the wz file.
+#[derive(Debug, Clone)]
+pub struct WzDirectoryEntry {
+    pub entry_type: WzDirectoryEntryType,
+    pub name: WzNodeName,
+    /// position of the entry itself(the type byte) in the wz file.
+    pub entry_offset: usize,
+    /// position of the shared type byte and name, `None` when they are right in the entry.
+    pub name_offset: Option<usize>,
+    /// position of the data, same as the `offset` of the created `WzDirectory` or `WzImage`.
+    pub offset: usize,
+    pub block_size: usize,
+    pub checksum: i32,
+}
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default)]
 pub struct WzDirectory {
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -53,6 +78,9 @@ pub struct WzDirectory {
     pub block_size: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub hash: usize,
+    /// the checksum stored in the parent `WzDirectory` entry, `None` when it's the root of a wz file.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub checksum: Option<i32>,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub is_parsed: bool,
 }
@@ -64,6 +92,7 @@ impl WzDirectory {
             offset,
             block_size,
             hash: 0,
+            checksum: None,
             is_parsed,
         }
     }
@@ -71,11 +100,15 @@ impl WzDirectory {
         self.hash = hash;
         self
     }
+    pub fn with_checksum(mut self, checksum: i32) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
 
     pub fn verify_hash(&self) -> Result<(), Error> {
         let reader = self.reader.create_slice_reader();
 
-        reader.seek(self.offset);
+        reader.try_seek(self.offset)?;
 
         let entry_count = reader.read_wz_int()?;
 
@@ -89,12 +122,12 @@ impl WzDirectory {
 
             match dir_type {
                 WzDirectoryType::UnknownType => {
-                    reader.skip(4 + 4 + 2);
+                    reader.try_skip(4 + 4 + 2)?;
                     continue;
                 }
                 WzDirectoryType::RetrieveStringFromOffset => {
                     // skip read string offset
-                    reader.skip(4);
+                    reader.try_skip(4)?;
                 }
                 WzDirectoryType::WzDirectory | WzDirectoryType::WzImage => {
                     reader.read_wz_string()?;
@@ -119,10 +152,12 @@ impl WzDirectory {
         Ok(())
     }
 
-    pub fn resolve_children(&self, parent: &WzNodeArc) -> Result<WzNodeArcVec, Error> {
+    /// Read the entries of the directory without creating any node, the unknown entries are skipped
+    /// like `resolve_children`.
+    pub fn read_entries(&self) -> Result<Vec<WzDirectoryEntry>, Error> {
         let reader = self.reader.create_slice_reader();
 
-        reader.seek(self.offset);
+        reader.try_seek(self.offset)?;
 
         let entry_count = reader.read_wz_int()?;
 
@@ -130,18 +165,20 @@ impl WzDirectory {
             return Err(Error::InvalidEntryCount);
         }
 
-        let mut nodes: WzNodeArcVec = Vec::with_capacity(entry_count as usize);
+        let mut entries = Vec::with_capacity(entry_count as usize);
 
         for _ in 0..entry_count {
+            let entry_offset = reader.pos.get();
             let dir_byte = reader.read_u8()?;
             let mut dir_type = get_wz_directory_type_from_byte(dir_byte);
 
             let fname: WzNodeName;
+            let mut name_offset = None;
 
             match dir_type {
                 WzDirectoryType::UnknownType => {
                     /* unknown, just skip this chunk */
-                    reader.skip(4 + 4 + 2);
+                    reader.try_skip(4 + 4 + 2)?;
                     continue;
                 }
                 WzDirectoryType::RetrieveStringFromOffset => {
@@ -150,10 +187,11 @@ impl WzDirectory {
                     let offset = reader.header.fstart + str_offset as usize;
 
                     dir_type = get_wz_directory_type_from_byte(reader.read_u8_at(offset)?);
-                    fname = reader.read_wz_string_at_offset(offset + 1)?.into();
+                    fname = reader.read_wz_name_at_offset(offset + 1)?;
+                    name_offset = Some(offset);
                 }
                 WzDirectoryType::WzDirectory | WzDirectoryType::WzImage => {
-                    fname = reader.read_wz_string()?.into();
+                    fname = reader.read_wz_name()?;
                 }
                 WzDirectoryType::NewUnknownType => {
                     println!("NewUnknownType: {}", dir_byte);
@@ -162,7 +200,7 @@ impl WzDirectory {
             }
 
             let fsize = reader.read_wz_int()?;
-            let _checksum = reader.read_wz_int()?;
+            let checksum = reader.read_wz_int()?;
             let offset = reader.read_wz_offset(self.hash, None)?;
             let buf_start = offset;
 
@@ -172,38 +210,78 @@ impl WzDirectory {
                 return Err(Error::InvalidWzVersion);
             }
 
-            match dir_type {
-                WzDirectoryType::WzDirectory => {
-                    let wz_dir = WzDirectory::new(offset, fsize as usize, &self.reader, false)
-                        .with_hash(self.hash);
+            let entry_type = match dir_type {
+                WzDirectoryType::WzDirectory => WzDirectoryEntryType::Directory,
+                WzDirectoryType::WzImage => WzDirectoryEntryType::Image,
+                _ => {
+                    // should never be here
+                    continue;
+                }
+            };
+
+            entries.push(WzDirectoryEntry {
+                entry_type,
+                name: fname,
+                entry_offset,
+                name_offset,
+                offset,
+                block_size: fsize as usize,
+                checksum,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    pub fn resolve_children(&self, parent: &WzNodeArc) -> Result<WzNodeArcVec, Error> {
+        let entries = self.read_entries()?;
+
+        let mut nodes: WzNodeArcVec = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let fname = entry.name;
+
+            match entry.entry_type {
+                WzDirectoryEntryType::Directory => {
+                    let wz_dir =
+                        WzDirectory::new(entry.offset, entry.block_size, &self.reader, false)
+                            .with_hash(self.hash)
+                            .with_checksum(entry.checksum);
 
                     let obj_node = WzNode::new(&fname, wz_dir, Some(parent));
 
                     nodes.push((fname, obj_node.into_lock()));
                 }
-                WzDirectoryType::WzImage => {
-                    let wz_image = WzImage::new(&fname, offset, fsize as usize, &self.reader);
+                WzDirectoryEntryType::Image => {
+                    let wz_image =
+                        WzImage::new(&fname, entry.offset, entry.block_size, &self.reader)
+                            .with_checksum(entry.checksum);
 
                     let obj_node = WzNode::new(&fname, wz_image, Some(parent));
 
                     nodes.push((fname, obj_node.into_lock()));
                 }
-                _ => {
-                    // should never be here
-                }
             }
         }
 
-        for (_, node) in nodes.iter() {
+        let resolve_sub_dir = |(_, node): &(WzNodeName, WzNodeArc)| -> Result<(), Error> {
             let mut write = node.write().unwrap();
             if let WzObjectType::Directory(dir) = &mut write.object_type {
                 let children = dir.resolve_children(node)?;
+                dir.is_parsed = true;
 
                 for (name, child) in children {
                     write.children.insert(name, child);
                 }
             }
-        }
+            Ok(())
+        };
+
+        /* the sub directories are independent, the big trees have hundreds of them */
+        #[cfg(feature = "rayon")]
+        nodes.par_iter().try_for_each(resolve_sub_dir)?;
+        #[cfg(not(feature = "rayon"))]
+        nodes.iter().try_for_each(resolve_sub_dir)?;
 
         Ok(nodes)
     }
@@ -2,8 +2,6 @@ use crate::{
     directory, reader, version, Reader, SharedWzMutableKey, WzDirectory, WzNodeArc, WzNodeArcVec,
     WzNodeCast, WzObjectType, WzReader, WzSliceReader,
 };
-use memmap2::Mmap;
-use std::fs::File;
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -31,6 +29,7 @@ pub enum Error {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default)]
 pub struct WzFileMeta {
     /// path of wz file
@@ -47,6 +46,7 @@ pub struct WzFileMeta {
 
 /// Root of the `WzNode`, represents the Wz file itself and contains `WzFileMeta`
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default)]
 pub struct WzFile {
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -57,11 +57,35 @@ pub struct WzFile {
     pub block_size: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub is_parsed: bool,
+    /// the file has been closed by `WzNode::close_file`, it will be reopened when parsing.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub is_closed: bool,
     #[cfg_attr(feature = "serde", serde(flatten))]
     pub wz_file_meta: WzFileMeta,
 }
 
 impl WzFile {
+    /// Same as `from_file`, but the mmap and header reading run on tokio's blocking pool.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async<P>(
+        path: P,
+        wz_iv: Option<[u8; 4]>,
+        patch_version: Option<i32>,
+        existing_key: Option<&SharedWzMutableKey>,
+    ) -> Result<WzFile, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let existing_key = existing_key.cloned();
+
+        crate::util::blocking::run_blocking(move || {
+            Self::from_file(path, wz_iv, patch_version, existing_key.as_ref())
+        })
+        .await
+        .map_err(std::io::Error::from)?
+    }
+
     pub fn from_file<P>(
         path: P,
         wz_iv: Option<[u8; 4]>,
@@ -71,8 +95,7 @@ impl WzFile {
     where
         P: AsRef<std::path::Path>,
     {
-        let file: File = File::open(&path)?;
-        let map = unsafe { Mmap::map(&file)? };
+        let map = reader::open_reader_buffer(&path)?;
 
         let block_size = map.len();
 
@@ -83,12 +106,14 @@ impl WzFile {
             version::guess_iv_from_wz_file(&map).ok_or(Error::UnableToGuessVersion)?
         };
 
-        let reader = if let Some(keys) = existing_key {
-            WzReader::new(map)
-                .with_iv(wz_iv)
-                .with_existing_keys(keys.clone())
-        } else {
-            WzReader::new(map).with_iv(wz_iv)
+        // don't expand a new keys just to drop it when the existing one can be used
+        let reader = match existing_key {
+            Some(keys) if keys.read().unwrap().iv == wz_iv => {
+                let mut reader = WzReader::new(map);
+                reader.wz_iv = wz_iv;
+                reader.with_existing_keys(keys.clone())
+            }
+            _ => WzReader::new(map).with_iv(wz_iv),
         };
 
         let offset = reader.get_wz_fstart().map_err(|_| Error::InvalidWzFile)? + 2;
@@ -105,15 +130,49 @@ impl WzFile {
             offset: offset as usize,
             block_size,
             is_parsed: false,
+            is_closed: false,
             reader: Arc::new(reader),
             wz_file_meta,
         })
     }
+    /// Release the file, the reader will be replaced by a empty one that only keep the iv and keys.
+    /// The mapping is actually closed when the nodes still holding the old reader are dropped.
+    ///
+    /// Use `WzNode::close_file` instead, it also clear the children.
+    pub(crate) fn close(&mut self) {
+        let mut reader = WzReader::default();
+        reader.wz_iv = self.reader.wz_iv;
+        self.reader = Arc::new(reader.with_existing_keys(self.reader.keys.clone()));
+        self.is_parsed = false;
+        self.is_closed = true;
+    }
+    /// Open the file again with the same path, iv, keys and patch version.
+    pub fn reopen(&mut self) -> Result<(), Error> {
+        let patch_version = Some(self.wz_file_meta.patch_version).filter(|version| *version != -1);
+
+        let file = WzFile::from_file(
+            &self.wz_file_meta.path,
+            Some(self.reader.wz_iv),
+            patch_version,
+            Some(&self.reader.keys),
+        )?;
+
+        self.reader = file.reader;
+        self.offset = file.offset;
+        self.block_size = file.block_size;
+        self.is_closed = false;
+
+        Ok(())
+    }
     pub fn parse(
         &mut self,
         parent: &WzNodeArc,
         patch_version: Option<i32>,
     ) -> Result<WzNodeArcVec, Error> {
+        if self.is_closed {
+            self.reopen()?;
+        }
+
         let reader = self.reader.clone();
 
         let mut wz_file_meta = WzFileMeta {
@@ -126,7 +185,7 @@ impl WzFile {
 
         let slice_reader = reader.create_slice_reader();
 
-        let (wz_with_encrypt_version_header, encrypt_version) = check_64bit_client(&slice_reader);
+        let (wz_with_encrypt_version_header, encrypt_version) = check_64bit_client(&slice_reader)?;
 
         wz_file_meta.wz_version_header = if wz_with_encrypt_version_header {
             encrypt_version as i32
@@ -147,9 +206,13 @@ impl WzFile {
             /* there has code in maplelib to detect version from maplestory.exe here */
 
             for ver_to_decode in guess_range {
-                wz_file_meta.hash =
-                    check_and_get_version_hash(wz_file_meta.wz_version_header, ver_to_decode)
-                        as usize;
+                let hash =
+                    check_and_get_version_hash(wz_file_meta.wz_version_header, ver_to_decode);
+                /* the encver doesn't match, no need to decode */
+                if hash == 0 {
+                    continue;
+                }
+                wz_file_meta.hash = hash as usize;
                 if let Ok(childs) = self.try_decode_with_wz_version_number(
                     parent,
                     &slice_reader,
@@ -219,11 +282,7 @@ impl WzFile {
             match check_byte {
                 0x73 | 0x1b | 0x01 => {}
                 _ => {
-                    /* 0x30, 0x6C, 0xBC */
-                    println!(
-                        "UnknownImageHeader: check_byte = {}, File Name = {}",
-                        check_byte, name
-                    );
+                    /* 0x30, 0x6C, 0xBC, it's just a wrong guess when guessing the version, don't print it */
                     return Err(Error::UnknownImageHeader(check_byte, name.to_string()));
                 }
             }
@@ -247,24 +306,25 @@ impl WzFile {
 
 const WZ_VERSION_HEADER_64BIT_START: u16 = 770;
 
-fn check_64bit_client(wz_reader: &WzSliceReader) -> (bool, u16) {
-    let encrypt_version = wz_reader.read_u16_at(wz_reader.header.fstart).unwrap();
+/// Check the wz file holds the encver in header or not, the truncated header is a error instead of panic.
+fn check_64bit_client(wz_reader: &WzSliceReader) -> Result<(bool, u16), reader::Error> {
+    let encrypt_version = wz_reader.read_u16_at(wz_reader.header.fstart)?;
 
     if wz_reader.header.fsize >= 2 {
         if encrypt_version > 0xff {
-            return (false, 0);
+            return Ok((false, 0));
         }
         if encrypt_version == 0x80 {
-            let prop_count = wz_reader.read_i32_at(wz_reader.header.fstart + 2).unwrap();
+            let prop_count = wz_reader.read_i32_at(wz_reader.header.fstart + 2)?;
             if prop_count > 0 && (prop_count & 0xff) == 0 && prop_count <= 0xffff {
-                return (false, 0);
+                return Ok((false, 0));
             }
         }
         /* the only place return actual encrypt_version */
-        return (true, encrypt_version);
+        return Ok((true, encrypt_version));
     }
 
-    (false, 0)
+    Ok((false, 0))
 }
 
 fn check_and_get_version_hash(encver: i32, patch_version: i32) -> i32 {
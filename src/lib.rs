@@ -4,24 +4,27 @@ mod header;
 pub mod ms;
 pub mod node;
 mod node_cast;
+mod node_getter;
 mod node_name;
 mod object;
 pub mod property;
 pub mod reader;
+pub mod sync;
 pub mod util;
 pub mod version;
 pub mod wz_image;
 
-pub use directory::WzDirectory;
+pub use directory::{WzDirectory, WzDirectoryEntry, WzDirectoryEntryType};
 pub use file::WzFile;
 pub use header::*;
 pub use ms::file::MsFile;
 pub use ms::ms_image::MsImage;
 pub use node::{WzNode, WzNodeArc, WzNodeArcVec};
 pub use node_cast::*;
+pub use node_getter::*;
 pub use node_name::*;
 pub use object::*;
-pub use reader::{Reader, SharedWzMutableKey, WzReader, WzSliceReader};
+pub use reader::{DataSource, DynDataSource, Reader, SharedWzMutableKey, WzReader, WzSliceReader};
 pub use wz_image::{
     WzImage, WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET, WZ_IMAGE_HEADER_BYTE_WITH_OFFSET,
 };
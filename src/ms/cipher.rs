@@ -0,0 +1,104 @@
+use super::ms_image::MsEntryMeta;
+
+/// The key derivation and parameters of the `.ms` encryption. The `Default` is the scheme of the current clients,
+/// private or regional builds may tweak it, override the fields to open them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, ms::cipher::MsCipher};
+/// fn header_key(name_with_salt: &str) -> [u8; 16] {
+///     let mut key = [0x5A; 16];
+///     for (i, byte) in name_with_salt.bytes().enumerate() {
+///         key[i % 16] ^= byte;
+///     }
+///     key
+/// }
+///
+/// let cipher = MsCipher {
+///     header_key,
+///     ..Default::default()
+/// };
+///
+/// let node = WzNode::from_ms_file_with_cipher("path/to/Mob_00000.ms", cipher, None).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MsCipher {
+    /// derive the snow key of the 9 bytes header from the lowercase file name with the salt.
+    pub header_key: fn(&str) -> [u8; 16],
+    /// derive the snow key of the entry table from the lowercase file name with the salt.
+    pub entry_table_key: fn(&str) -> [u8; 16],
+    /// derive the snow key of a image from it's entry.
+    pub image_key: fn(&MsEntryMeta) -> [u8; 16],
+    /// `(modulus, base)` of the random bytes count before the salt, computed from the sum of the file name bytes.
+    pub random_bytes: (usize, usize),
+    /// `(modulus, base)` of the padding after the header, computed from the sum of the file name bytes * 3.
+    pub header_padding: (usize, usize),
+    /// the expected snow version in the header.
+    pub snow_version: u8,
+    /// the length at the beginning of the image that encrypted twice.
+    pub double_encrypted_len: usize,
+}
+
+impl Default for MsCipher {
+    fn default() -> Self {
+        Self {
+            header_key: default_header_key,
+            entry_table_key: default_entry_table_key,
+            image_key: default_image_key,
+            random_bytes: (312, 30),
+            header_padding: (212, 33),
+            snow_version: 2,
+            double_encrypted_len: 1024,
+        }
+    }
+}
+
+pub fn default_header_key(name_with_salt: &str) -> [u8; 16] {
+    let bytes = name_with_salt.as_bytes();
+    let mut snow_key: [u8; 16] = [0; 16];
+    for (i, key) in snow_key.iter_mut().enumerate() {
+        *key = bytes[i % bytes.len()].wrapping_add(i as u8);
+    }
+    snow_key
+}
+
+pub fn default_entry_table_key(name_with_salt: &str) -> [u8; 16] {
+    let bytes = name_with_salt.as_bytes();
+    let len = bytes.len();
+    let mut snow_key: [u8; 16] = [0; 16];
+    for i in 0_u8..16_u8 {
+        let byte = bytes[len - 1 - i as usize % len];
+        snow_key[i as usize] = i.wrapping_add((i % 3 + 2).wrapping_mul(byte));
+    }
+    snow_key
+}
+
+pub fn default_image_key(meta: &MsEntryMeta) -> [u8; 16] {
+    let mut key_hash = 0x811C9DC5;
+    for b in meta.key_salt.chars() {
+        key_hash = (key_hash ^ b as u32).wrapping_mul(0x1000193);
+    }
+
+    // extract each  digit from key_hash, like 1234 -> [1,2,3,4]
+    let key_hash_digits: Vec<u8> = key_hash.to_string().chars().map(|c| c as u8 - 48).collect();
+
+    let mut img_key = [0_u8; 16];
+
+    let bytes = meta.entry_name.as_bytes();
+
+    for (i, key) in img_key.iter_mut().enumerate() {
+        let char = bytes[i % bytes.len()];
+        let digit = key_hash_digits[i % key_hash_digits.len()] % 2;
+        let digit2 = (key_hash_digits[(i + 1) % key_hash_digits.len()] + i as u8) % 5;
+        let ekey_idx = key_hash_digits[(i + 2) % key_hash_digits.len()] + i as u8;
+        let ekey = meta.entry_key[(ekey_idx % meta.entry_key.len() as u8) as usize];
+
+        // it kinda hard to read
+        // i + char * (digit + ekey + digit2)
+        *key = (i as u8)
+            .wrapping_add(char.wrapping_mul(digit.wrapping_add(ekey).wrapping_add(digit2)));
+    }
+
+    img_key
+}
@@ -1,10 +1,10 @@
-use crate::{reader, WzNode, WzNodeArc, WzNodeArcVec, WzNodeName, WzReader};
-use memmap2::Mmap;
-use std::fs::File;
+use crate::{reader, WzNode, WzNodeArc, WzNodeArcVec, WzReader};
 use std::sync::Arc;
 
+use super::cipher::MsCipher;
 use super::header::{self, MsHeader};
 use super::ms_image::{MsEntryMeta, MsImage};
+use super::name_map::MsNameMap;
 use super::utils;
 
 #[cfg(feature = "serde")]
@@ -28,6 +28,7 @@ pub enum Error {
 
 /// Root of the `WzNode`, represents the Wz file itself and contains `MsFileMeta`
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default)]
 pub struct MsFile {
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -38,6 +39,11 @@ pub struct MsFile {
     pub is_parsed: bool,
     #[cfg_attr(feature = "serde", serde(flatten))]
     pub header: MsHeader,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cipher: MsCipher,
+    /// the readable names of the hashed entry names, the children are named by it when parsing.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub name_map: Option<Arc<MsNameMap>>,
 }
 
 impl MsFile {
@@ -45,32 +51,37 @@ impl MsFile {
     where
         P: AsRef<std::path::Path>,
     {
-        let file: File = File::open(&path)?;
-        let map = unsafe { Mmap::map(&file)? };
+        Self::from_file_with_cipher(path, MsCipher::default())
+    }
+    /// Same as `from_file`, but with custom key derivation and parameters for the tweaked `.ms` files.
+    pub fn from_file_with_cipher<P>(path: P, cipher: MsCipher) -> Result<MsFile, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let map = reader::open_reader_buffer(&path)?;
 
         let block_size = map.len();
 
         let reader = WzReader::new(map);
 
-        let ms_header = MsHeader::from_ms_file(path, &reader)?;
+        let ms_header = MsHeader::from_ms_file_with_cipher(path, &reader, &cipher)?;
 
         Ok(MsFile {
             block_size,
             is_parsed: false,
             reader: Arc::new(reader),
             header: ms_header,
+            cipher,
+            name_map: None,
         })
     }
+    pub fn with_name_map(mut self, name_map: Arc<MsNameMap>) -> Self {
+        self.name_map = Some(name_map);
+        self
+    }
     pub fn parse(&mut self, parent: &WzNodeArc) -> Result<WzNodeArcVec, Error> {
         // decrypt with another snow key
-        let file_name_with_salt_bytes = self.header.name_with_salt.as_bytes();
-        let file_name_with_salt_len = file_name_with_salt_bytes.len();
-        let mut snow_key: [u8; 16] = [0; 16];
-        for i in 0_u8..16_u8 {
-            let byte = file_name_with_salt_bytes
-                [file_name_with_salt_len - 1 - i as usize % file_name_with_salt_len];
-            snow_key[i as usize] = i + (i % 3 + 2).wrapping_mul(byte);
-        }
+        let snow_key = (self.cipher.entry_table_key)(&self.header.name_with_salt);
 
         let data = self.reader.get_slice(0..self.block_size);
         let mut snow_reader = utils::Snow2Reader::new(data, snow_key);
@@ -104,7 +115,15 @@ impl MsFile {
                 unk2,
                 entry_key,
             };
-            let image = MsImage::new(meta, &self.reader);
+            let mut image = MsImage::new(meta, &self.reader).with_cipher(self.cipher);
+            /* the meta keep the stored name, the image key is derived from it */
+            if let Some(name) = self
+                .name_map
+                .as_ref()
+                .and_then(|map| map.get(&image.meta.entry_name))
+            {
+                image.name = name.into();
+            }
 
             ms_images.push(image);
         }
@@ -121,13 +140,171 @@ impl MsFile {
             image.meta.start_pos = actual_start as i32;
         }
 
-        return Ok(ms_images
+        Ok(ms_images
             .drain(..)
             .map(|image| {
-                let name = WzNodeName::from(image.meta.entry_name.clone());
+                let name = image.name.clone();
                 let node = WzNode::new(&name, image, Some(parent));
                 (name, node.into_lock())
             })
-            .collect());
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ms::snow2_decryptor::Snow2Decryptor;
+    use crate::{WzNodeCast, WzObjectType};
+
+    /// the snow cipher only subtract the key stream, so add it back to encrypt.
+    fn encrypt(key: [u8; 16], data: &mut [u8]) {
+        let mut decryptor = Snow2Decryptor::new(key);
+        for chunk in data.chunks_exact_mut(4) {
+            let key_stream = 0_u32.wrapping_sub(decryptor.decrypt_block(&0));
+            let plain = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            chunk.copy_from_slice(&plain.wrapping_add(key_stream).to_le_bytes());
+        }
+    }
+
+    /// build a `.ms` file with a single entry
+    fn build_ms_file(
+        file_name: &str,
+        entry_name: &str,
+        cipher: &MsCipher,
+        image: &[u8],
+    ) -> Vec<u8> {
+        let name_sum = file_name.bytes().map(|b| b as usize).sum::<usize>();
+        let (rand_modulus, rand_base) = cipher.random_bytes;
+        let mut buf = vec![0x11_u8; name_sum % rand_modulus + rand_base];
+
+        let salt = "salt";
+        buf.extend([salt.len() as u8 ^ 0x11, 0, 0, 0]);
+        for byte in salt.bytes() {
+            buf.extend([byte ^ 0x11, 0]);
+        }
+
+        /* the hash is computed from the stored bytes */
+        let salt_sum = salt.bytes().map(|b| (b ^ 0x11) as i32).sum::<i32>();
+        let entry_count = 1;
+        let hash = (salt.len() as i32 ^ 0x11) + cipher.snow_version as i32 + entry_count + salt_sum;
+        let mut header = hash.to_le_bytes().to_vec();
+        header.push(cipher.snow_version);
+        header.extend(entry_count.to_le_bytes());
+        header.extend([0; 3]);
+        let name_with_salt = format!("{file_name}{salt}");
+        encrypt((cipher.header_key)(&name_with_salt), &mut header);
+        let hstart = buf.len();
+        buf.extend(header);
+
+        let (padding_modulus, padding_base) = cipher.header_padding;
+        let estart = hstart + 9 + (name_sum * 3) % padding_modulus + padding_base;
+        buf.resize(estart, 0);
+
+        let size_aligned = image.len() + (4 - image.len() % 4) % 4;
+        let mut table = (entry_name.len() as i32).to_le_bytes().to_vec();
+        table.extend(entry_name.encode_utf16().flat_map(|c| c.to_le_bytes()));
+        for value in [0, 0, 0, image.len() as i32, size_aligned as i32, 0, 0] {
+            table.extend(value.to_le_bytes());
+        }
+        table.extend([7; 16]);
+        table.resize(table.len() + (4 - table.len() % 4) % 4, 0);
+        encrypt((cipher.entry_table_key)(&name_with_salt), &mut table);
+        buf.extend(table);
+
+        let data_start = buf.len() + (0x400 - buf.len() % 0x400) % 0x400;
+        buf.resize(data_start, 0);
+
+        let meta = MsEntryMeta {
+            key_salt: salt.to_string(),
+            entry_name: entry_name.to_string(),
+            entry_key: [7; 16],
+            ..Default::default()
+        };
+        let image_key = (cipher.image_key)(&meta);
+        let mut data = image.to_vec();
+        data.resize(size_aligned, 0);
+        encrypt(image_key, &mut data);
+        let double_len = cipher.double_encrypted_len.min(data.len());
+        encrypt(image_key, &mut data[..double_len]);
+        buf.extend(data);
+
+        buf
+    }
+
+    #[test]
+    fn test_custom_cipher() {
+        let cipher = MsCipher {
+            header_key: |_| [1; 16],
+            entry_table_key: |_| [2; 16],
+            image_key: |_| [3; 16],
+            random_bytes: (7, 40),
+            header_padding: (5, 10),
+            snow_version: 3,
+            double_encrypted_len: 16,
+        };
+
+        let image = std::fs::read("tests/test.img").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.ms");
+        std::fs::write(
+            &path,
+            build_ms_file("custom.ms", "test.img", &cipher, &image),
+        )
+        .unwrap();
+
+        assert!(MsFile::from_file(&path).is_err());
+
+        let node = WzNode::from_ms_file_with_cipher(&path, cipher, None)
+            .unwrap()
+            .into_lock();
+        node.write().unwrap().parse(&node).unwrap();
+
+        let ms_image = node.read().unwrap().at("test.img").unwrap();
+        ms_image.write().unwrap().parse(&ms_image).unwrap();
+        let int = ms_image.read().unwrap().at_path("1/int").unwrap();
+        assert!(matches!(
+            ms_image.read().unwrap().object_type,
+            WzObjectType::Image(_)
+        ));
+        assert_eq!(int.read().unwrap().try_as_int(), Some(&1));
+
+        let path = dir.path().join("default.ms");
+        let default = build_ms_file("default.ms", "test.img", &MsCipher::default(), &image);
+        std::fs::write(&path, default).unwrap();
+
+        let node = WzNode::from_ms_file(&path, None).unwrap().into_lock();
+        node.write().unwrap().parse(&node).unwrap();
+        let ms_image = node.read().unwrap().at("test.img").unwrap();
+        ms_image.write().unwrap().parse(&ms_image).unwrap();
+        assert!(ms_image.read().unwrap().at_path("2/string").is_some());
+    }
+
+    #[test]
+    fn test_name_map() {
+        let hash = crate::ms::name_map::default_name_hash;
+
+        let image = std::fs::read("tests/test.img").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashed.ms");
+        let hashed = hash("mob.img");
+        let buf = build_ms_file("hashed.ms", &hashed, &MsCipher::default(), &image);
+        std::fs::write(&path, buf).unwrap();
+
+        let mut name_map = MsNameMap::new();
+        name_map.insert_candidates(["npc.img", "mob.img"], hash);
+        assert_eq!(name_map.len(), 2);
+        assert_eq!(name_map.resolve("unknown"), "unknown");
+
+        let ms_file = MsFile::from_file(&path)
+            .unwrap()
+            .with_name_map(Arc::new(name_map));
+        let node = WzNode::from_str("hashed", ms_file, None).into_lock();
+        node.write().unwrap().parse(&node).unwrap();
+
+        assert!(node.read().unwrap().at(&hashed).is_none());
+        let ms_image = node.read().unwrap().at("mob.img").unwrap();
+        ms_image.write().unwrap().parse(&ms_image).unwrap();
+        assert!(ms_image.read().unwrap().at_path("1/int").is_some());
     }
 }
@@ -2,6 +2,7 @@
 use crate::reader::{self, Reader, WzReader};
 use scroll::{Pread, LE};
 
+use super::cipher::MsCipher;
 use super::snow2_decryptor::Snow2Decryptor;
 
 #[cfg(feature = "serde")]
@@ -13,13 +14,14 @@ pub enum Error {
     ReaderError(#[from] reader::Error),
     #[error("Error reading binary: {0}")]
     ReadError(#[from] scroll::Error),
-    #[error("Unsupported snow version, expected 2 but got {0}")]
+    #[error("Unsupported snow version: {0}")]
     UnsupportedSnowVersion(u8),
     #[error("Hash mismatch, expected {0} but got {1}")]
     HashMismatch(i32, i32),
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Default)]
 pub struct MsHeader {
     // name: String,
@@ -38,6 +40,18 @@ pub struct MsHeader {
 
 impl MsHeader {
     pub fn from_ms_file<P>(path: P, reader: &WzReader) -> Result<Self, Error>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Self::from_ms_file_with_cipher(path, reader, &MsCipher::default())
+    }
+
+    /// Same as `from_ms_file`, but with custom key derivation and parameters.
+    pub fn from_ms_file_with_cipher<P>(
+        path: P,
+        reader: &WzReader,
+        cipher: &MsCipher,
+    ) -> Result<Self, Error>
     where
         P: AsRef<std::path::Path>,
     {
@@ -54,7 +68,9 @@ impl MsHeader {
 
         // all the code is from https://github.com/Kagamia/WzComparerR2/pull/271/files#diff-d0d53b2411f7d680fb0c7c32bbf10138be0f7e662555cbc28d27353fbd2741d0
         // 1. random bytes
-        let rand_byte_count = file_name_bytes.iter().map(|&b| b as usize).sum::<usize>() % 312 + 30;
+        let (rand_modulus, rand_base) = cipher.random_bytes;
+        let rand_byte_count =
+            file_name_bytes.iter().map(|&b| b as usize).sum::<usize>() % rand_modulus + rand_base;
         let rand_bytes = reader.get_slice(offset..rand_byte_count);
         offset += rand_byte_count;
 
@@ -79,12 +95,7 @@ impl MsHeader {
         // generate snow key based on filename+keySalt
         let file_name_with_salt = format!("{}{}", file_name, &salt_string);
 
-        let file_name_with_salt_bytes = file_name_with_salt.as_bytes();
-        let mut snow_key: [u8; 16] = [0; 16];
-        for i in 0..16 {
-            snow_key[i] = file_name_with_salt_bytes[i % file_name_with_salt.len()] + i as u8;
-        }
-        let mut snow_decryptor = Snow2Decryptor::new(snow_key);
+        let mut snow_decryptor = Snow2Decryptor::new((cipher.header_key)(&file_name_with_salt));
 
         let hstart = offset;
         // the snow decryptor is decrypting 4 bytes at a time, so we need to decrypt 12 bytes for only 9 bytes of data
@@ -99,8 +110,7 @@ impl MsHeader {
         let entry_count = decrypt_data.pread_with::<i32>(5, LE)?;
 
         // verify  snowversion and hash
-        const EXPECTED_VERSION: u8 = 2;
-        if version != EXPECTED_VERSION {
+        if version != cipher.snow_version {
             return Err(Error::UnsupportedSnowVersion(version));
         }
 
@@ -115,14 +125,15 @@ impl MsHeader {
         }
 
         // 4. skip random bytes
+        let (padding_modulus, padding_base) = cipher.header_padding;
         let estart = hstart
             + 9
             + file_name_bytes
                 .iter()
                 .map(|&b| b as usize * 3)
                 .sum::<usize>()
-                % 212
-            + 33;
+                % padding_modulus
+            + padding_base;
 
         Ok(MsHeader {
             key_salt: salt_string,
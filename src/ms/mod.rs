@@ -1,5 +1,7 @@
+pub mod cipher;
 pub mod file;
 pub mod header;
 pub mod ms_image;
+pub mod name_map;
 pub mod snow2_decryptor;
 pub mod utils;
@@ -1,7 +1,8 @@
-use crate::{WzImage, WzReader};
+use crate::{WzImage, WzNodeName, WzReader};
 use std::cmp;
 use std::sync::Arc;
 
+use super::cipher::MsCipher;
 use super::snow2_decryptor::Snow2Decryptor;
 
 #[cfg(feature = "serde")]
@@ -21,6 +22,7 @@ pub struct MsEntryMeta {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(default))]
 #[derive(Debug, Clone, Default)]
 pub struct MsImage {
@@ -32,6 +34,11 @@ pub struct MsImage {
     pub block_size: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub meta: MsEntryMeta,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cipher: MsCipher,
+    /// the name of the converted `WzImage`, it's the `entry_name` unless renamed by `MsNameMap`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub name: WzNodeName,
 }
 
 impl MsImage {
@@ -40,37 +47,19 @@ impl MsImage {
             reader: Arc::clone(reader),
             offset: meta.start_pos as usize,
             block_size: meta.size_aligned as usize,
+            name: meta.entry_name.clone().into(),
             meta,
+            cipher: MsCipher::default(),
         }
     }
+    pub fn with_cipher(mut self, cipher: MsCipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
 
     /// make a WzImage from the MsImage, this process will allocate a new buffer instead of using MsFile's buffer
     pub fn to_wz_image(&self) -> WzImage {
-        // calc snow key for entry
-        let mut key_hash = 0x811C9DC5;
-        for b in self.meta.key_salt.chars() {
-            key_hash = (key_hash ^ b as u32).wrapping_mul(0x1000193);
-        }
-
-        // extract each  digit from key_hash, like 1234 -> [1,2,3,4]
-        let key_hash_digits: Vec<u8> = key_hash.to_string().chars().map(|c| c as u8 - 48).collect();
-
-        let mut img_key = [0_u8; 16];
-
-        let bytes = self.meta.entry_name.as_bytes();
-
-        for i in 0..16 {
-            let char = bytes[i % bytes.len()];
-            let digit = key_hash_digits[i % key_hash_digits.len()] % 2;
-            let digit2 = (key_hash_digits[(i + 1) % key_hash_digits.len()] + i as u8) % 5;
-            let ekey_idx = key_hash_digits[(i + 2) % key_hash_digits.len()] + i as u8;
-            let ekey = self.meta.entry_key[(ekey_idx % self.meta.entry_key.len() as u8) as usize];
-
-            // it kinda hard to read
-            // i + char * (digit + ekey + digit2)
-            img_key[i] = (i as u8)
-                .wrapping_add(char.wrapping_mul(digit.wrapping_add(ekey).wrapping_add(digit2)));
-        }
+        let img_key = (self.cipher.image_key)(&self.meta);
 
         let mut image_buffer = self
             .reader
@@ -80,7 +69,7 @@ impl MsImage {
         // decrypt initial 1024 bytes twice
         {
             let mut snow_decryptor = Snow2Decryptor::new(img_key);
-            let min_len = cmp::min(1024, image_buffer.len());
+            let min_len = cmp::min(self.cipher.double_encrypted_len, image_buffer.len());
             snow_decryptor.decrypt_slice(&mut image_buffer[..min_len]);
         }
 
@@ -93,10 +82,11 @@ impl MsImage {
 
         WzImage {
             reader: Arc::new(image_reader),
-            name: self.meta.entry_name.clone().into(),
+            name: self.name.clone(),
             offset: 0,
             block_size: self.meta.size as usize,
             is_parsed: false,
+            checksum: None,
         }
     }
 }
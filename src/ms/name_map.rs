@@ -0,0 +1,97 @@
+use hashbrown::HashMap;
+
+/// The readable names of the hashed entry names in `.ms` files, set it to `MsFile::name_map` so the children
+/// are named by it when parsing. The entries not in the map keep their stored names.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, MsFile, ms::name_map::{default_name_hash, MsNameMap}};
+/// # use std::sync::Arc;
+/// let mut name_map = MsNameMap::new();
+/// name_map.insert("5f2a9c1e", "100100.img");
+/// // or compute the hashes from the known names
+/// name_map.insert_candidates(["100101.img", "100102.img"], default_name_hash);
+///
+/// let ms_file = MsFile::from_file("path/to/Mob_00000.ms").unwrap().with_name_map(Arc::new(name_map));
+/// let node = WzNode::from_str("Mob_00000", ms_file, None).into_lock();
+/// node.write().unwrap().parse(&node).unwrap();
+///
+/// assert!(node.read().unwrap().at("100100.img").is_some());
+/// ```
+/// The FNV-1a 32 of the lowercase entry name in hex, the same hash `default_image_key` uses for the key salt.
+/// The hashed names differ between builds, pass your own function to `MsNameMap::insert_candidates`
+/// when they don't match.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::ms::name_map::default_name_hash;
+/// assert_eq!(default_name_hash("Mob.img"), default_name_hash("mob.img"));
+/// assert_eq!(default_name_hash("mob.img").len(), 8);
+/// ```
+pub fn default_name_hash(name: &str) -> String {
+    let mut hash: u32 = 0x811C9DC5;
+    for c in name.to_lowercase().chars() {
+        hash = (hash ^ c as u32).wrapping_mul(0x1000193);
+    }
+    format!("{hash:08x}")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MsNameMap {
+    names: HashMap<String, String>,
+}
+
+impl MsNameMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mapping, returns the previous readable name of the hashed name.
+    pub fn insert(&mut self, hashed: &str, name: &str) -> Option<String> {
+        self.names.insert(hashed.to_string(), name.to_string())
+    }
+
+    /// Add the candidate names with their hashes computed by `hash`, like `default_name_hash`,
+    /// the wrong candidates just never match.
+    pub fn insert_candidates<I, S, F>(&mut self, names: I, hash: F)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        F: Fn(&str) -> String,
+    {
+        for name in names {
+            let name = name.as_ref();
+            self.names.insert(hash(name), name.to_string());
+        }
+    }
+
+    pub fn get(&self, hashed: &str) -> Option<&str> {
+        self.names.get(hashed).map(String::as_str)
+    }
+
+    /// Get the readable name, or the hashed name itself when it's unknown.
+    pub fn resolve<'a>(&'a self, hashed: &'a str) -> &'a str {
+        self.get(hashed).unwrap_or(hashed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl<H: Into<String>, N: Into<String>> FromIterator<(H, N)> for MsNameMap {
+    fn from_iter<T: IntoIterator<Item = (H, N)>>(iter: T) -> Self {
+        Self {
+            names: iter
+                .into_iter()
+                .map(|(hashed, name)| (hashed.into(), name.into()))
+                .collect(),
+        }
+    }
+}
@@ -1,10 +1,19 @@
+use crate::sync::RwLock;
 use crate::{
-    directory, file, ms, property, util::node_util, version, wz_image, MsFile, SharedWzMutableKey,
-    WzFile, WzImage, WzNodeCast, WzNodeName, WzObjectType,
+    directory, file, ms, property,
+    util::{
+        node_util,
+        stats::{self, ParseStage},
+        WzDeferredProperty,
+    },
+    version, wz_image, MsFile, SharedWzMutableKey, WzFile, WzImage, WzNodeCast, WzNodeName,
+    WzObjectType,
 };
 use hashbrown::HashMap;
+use std::fmt;
 use std::path::Path;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, Weak};
+use std::time::Instant;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -28,10 +37,18 @@ pub enum Error {
 
     #[error("Node not found")]
     NodeNotFound,
+
+    #[error("Can't attach a node to itself or it's descendant")]
+    CircularAttach,
+
+    #[cfg(feature = "tokio")]
+    #[error("The blocking task is cancelled: {0}")]
+    BlockingTaskError(#[from] tokio::task::JoinError),
 }
 
 /// A basic unit of wz_reader
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug)]
 pub struct WzNode {
     pub name: WzNodeName,
@@ -40,12 +57,73 @@ pub struct WzNode {
     #[cfg_attr(feature = "serde", serde(skip))]
     pub parent: Weak<RwLock<WzNode>>,
     #[cfg_attr(feature = "serde", serde(with = "arc_node_serde"))]
+    #[cfg_attr(
+        feature = "schemars",
+        schemars(with = "std::collections::HashMap<String, WzNode>")
+    )]
     pub children: HashMap<WzNodeName, Arc<RwLock<WzNode>>>,
 }
 
+/// How UOL and `_inlink`/`_outlink` are rendered in `to_simple_json_with_options`.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonLinkMode {
+    /// keep as it is, UOL become the path string and link stay as a child.
+    #[default]
+    Raw,
+    /// UOL become the target's json, canvas with link use the target's metadata and the link child is removed.
+    /// Unresolvable one will fallback to `Raw`.
+    Resolve,
+    /// remove UOL and link from the output.
+    Omit,
+}
+
+/// Options for `to_simple_json_with_options`, the default is the same as `to_simple_json`.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+pub struct SimpleJsonOptions {
+    /// insert children in sorted order, only matter when serde_json's `preserve_order` feature is enabled,
+    /// otherwise the keys are always sorted.
+    pub sort_keys: bool,
+    /// children deeper than `max_depth` will be omitted, the root is depth 0.
+    pub max_depth: Option<usize>,
+    /// whether PNG's width/height and Sound's duration/type are included.
+    pub inline_meta: bool,
+    pub link_mode: JsonLinkMode,
+}
+
+#[cfg(feature = "json")]
+impl Default for SimpleJsonOptions {
+    fn default() -> Self {
+        Self {
+            sort_keys: false,
+            max_depth: None,
+            inline_meta: true,
+            link_mode: JsonLinkMode::Raw,
+        }
+    }
+}
+
 pub type WzNodeArc = Arc<RwLock<WzNode>>;
 pub type WzNodeArcVec = Vec<(WzNodeName, WzNodeArc)>;
 
+/// Get the child, or the child of the target when the node is a uol that kept in the tree.
+fn at_through_uol(node: &WzNodeArc, name: &str) -> Option<WzNodeArc> {
+    let node_read = node.read().unwrap();
+
+    if let Some(child) = node_read.at(name) {
+        return Some(child);
+    }
+
+    node_read.try_as_uol()?;
+    drop(node_read);
+
+    node_util::resolve_uol_target(node)?
+        .read()
+        .unwrap()
+        .at(name)
+}
+
 impl From<WzNode> for WzNodeArc {
     fn from(node: WzNode) -> Self {
         node.into_lock()
@@ -131,6 +209,20 @@ impl WzNode {
         Ok(WzNode::new(&name.into(), ms_file, parent))
     }
 
+    /// Same as `from_ms_file`, but with custom key derivation and parameters, see `MsCipher`.
+    pub fn from_ms_file_with_cipher<P>(
+        path: P,
+        cipher: ms::cipher::MsCipher,
+        parent: Option<&WzNodeArc>,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let name = path.as_ref().file_stem().unwrap().to_str().unwrap();
+        let ms_file = MsFile::from_file_with_cipher(&path, cipher)?;
+        Ok(WzNode::new(&name.into(), ms_file, parent))
+    }
+
     /// Create a `WzNode` from a any `.img` file. If version is not provided, it will try to detect the version.
     ///
     /// # Errors
@@ -170,7 +262,21 @@ impl WzNode {
     }
 
     /// Parse the node base on the object type.
+    #[inline]
     pub fn parse(&mut self, parent: &WzNodeArc) -> Result<(), Error> {
+        self.parse_with_uol_policy(parent, node_util::UolPolicy::default())
+    }
+
+    /// Same as `parse`, but decide how to deal with the uol nodes.
+    pub fn parse_with_uol_policy(
+        &mut self,
+        parent: &WzNodeArc,
+        uol_policy: node_util::UolPolicy,
+    ) -> Result<(), Error> {
+        let stats = stats::current();
+        let start = Instant::now();
+        let stage = ParseStage::from_object_type(&self.object_type);
+
         let (childs, uol_nodes): (WzNodeArcVec, Vec<WzNodeArc>) = match self.object_type {
             WzObjectType::Directory(ref mut directory) => {
                 if directory.is_parsed {
@@ -214,19 +320,61 @@ impl WzNode {
             _ => return Ok(()),
         };
 
+        if let (Some(stats), Some(stage)) = (&stats, stage) {
+            stats.record_parse(stage, start.elapsed(), &childs);
+        }
+
         self.children.reserve(childs.len());
 
         for (name, child) in childs {
             self.children.insert(name, child);
         }
 
+        let start = Instant::now();
+
         for node in uol_nodes {
-            node_util::resolve_uol(&node, Some(self));
+            match uol_policy {
+                node_util::UolPolicy::ReplaceInPlace => node_util::resolve_uol(&node, Some(self)),
+                node_util::UolPolicy::KeepAndAnnotate => node_util::annotate_uol(&node, Some(self)),
+                node_util::UolPolicy::LazyOnAccess => {}
+            }
+        }
+
+        if let Some(stats) = &stats {
+            stats.record_time(ParseStage::Uol, start.elapsed());
         }
 
         Ok(())
     }
 
+    /// Same as `parse`, but the `WzImage` only parse the first `max_depth` levels of properties,
+    /// see `WzImage::parse_to_depth`. The image is marked as parsed, the deferred ones have to be expanded
+    /// by `WzDeferredProperty::expand`. Other nodes are parsed as usual.
+    pub fn parse_to_depth(
+        &mut self,
+        parent: &WzNodeArc,
+        max_depth: usize,
+    ) -> Result<Vec<WzDeferredProperty>, Error> {
+        let WzObjectType::Image(image) = &mut self.object_type else {
+            self.parse(parent)?;
+            return Ok(Vec::new());
+        };
+
+        if image.is_parsed {
+            return Ok(Vec::new());
+        }
+
+        let (childs, deferred) = image.parse_to_depth(Some(parent), max_depth)?;
+        image.is_parsed = true;
+
+        self.children.reserve(childs.len());
+        for (name, child) in childs {
+            self.children.insert(name, child);
+        }
+
+        Ok(deferred)
+    }
+
     /// Clear the node childrens and set the node to unparsed.
     #[inline]
     pub fn unparse(&mut self) {
@@ -246,6 +394,102 @@ impl WzNode {
         self.children.clear();
     }
 
+    /// Close the `WzFile`'s mapping(or the buffer without `mmap` feature) and clear the children,
+    /// it will be reopened when parsing again, like `at_path_parsed`. The mapping is actually released
+    /// when the nodes still held by others are dropped.
+    ///
+    /// Returns `false` when it's not a `WzFile`, already closed, or it has children from other files
+    /// (like the `Base.wz` or the `Map.wz` from `resolve_base`), those can't be rebuilt by parsing it again.
+    ///
+    /// Note: the whole subtree is dropped and parsed from the file again, so any change made to it(edited values,
+    /// added or removed nodes) is lost, the node doesn't track the changes so it can't refuse. Don't close a file
+    /// that has been edited, or save the changes before closing it.
+    pub fn close_file(&mut self) -> bool {
+        let WzObjectType::File(file) = &mut self.object_type else {
+            return false;
+        };
+
+        if file.is_closed {
+            return false;
+        }
+
+        let is_own_child = |child: &WzNodeArc| match &child.read().unwrap().object_type {
+            WzObjectType::Directory(dir) => Arc::ptr_eq(&dir.reader, &file.reader),
+            WzObjectType::Image(image) => Arc::ptr_eq(&image.reader, &file.reader),
+            _ => false,
+        };
+
+        if !self.children.values().all(is_own_child) {
+            return false;
+        }
+
+        file.close();
+        self.children.clear();
+        self.children.shrink_to_fit();
+
+        true
+    }
+
+    /// Estimate the memory used by the node and it's whole subtree in bytes, include the nodes, names,
+    /// children maps, parsed strings and the decrypted buffers of the images from `MsFile`.
+    /// The mmaped wz file is not included, it's managed by the OS.
+    ///
+    /// It's a rough number, useful to decide when to `unparse` some branches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let before = root.read().unwrap().estimate_memory();
+    ///
+    /// let child = WzNode::from_str("child", 2, Some(&root)).into_lock();
+    /// root.write().unwrap().add(&child);
+    ///
+    /// assert!(root.read().unwrap().estimate_memory() > before);
+    /// ```
+    pub fn estimate_memory(&self) -> usize {
+        self.estimate_memory_inner(false)
+    }
+
+    fn estimate_memory_inner(&self, is_in_ms_file: bool) -> usize {
+        use std::mem::size_of;
+
+        /* the Arc's counters and the node, name is shared with the key in parent's children */
+        let mut size = size_of::<usize>() * 2 + size_of::<RwLock<WzNode>>();
+        size += size_of::<usize>() * 2 + self.name.len();
+
+        size += self.children.capacity() * (size_of::<(WzNodeName, WzNodeArc)>() + 1);
+
+        size += match &self.object_type {
+            WzObjectType::File(_) => size_of::<WzFile>(),
+            WzObjectType::MsFile(_) => size_of::<MsFile>(),
+            WzObjectType::Directory(_) => size_of::<directory::WzDirectory>(),
+            WzObjectType::MsImage(image) => {
+                size_of::<ms::ms_image::MsImage>()
+                    + image.meta.key_salt.capacity()
+                    + image.meta.entry_name.capacity()
+            }
+            /* the image decrypted from MsFile has it's own buffer */
+            WzObjectType::Image(image) if is_in_ms_file => size_of::<WzImage>() + image.block_size,
+            WzObjectType::Image(_) => size_of::<WzImage>(),
+            WzObjectType::Property(property::WzSubProperty::PNG(_)) => size_of::<property::WzPng>(),
+            WzObjectType::Property(property::WzSubProperty::Sound(_)) => {
+                size_of::<property::WzSound>()
+            }
+            WzObjectType::Value(property::WzValue::ParsedString(string)) => string.capacity(),
+            _ => 0,
+        };
+
+        let is_ms_file = matches!(self.object_type, WzObjectType::MsFile(_));
+
+        size + self
+            .children
+            .values()
+            .map(|child| child.read().unwrap().estimate_memory_inner(is_ms_file))
+            .sum::<usize>()
+    }
+
     /// Add a child to the node. It just shorten the `node.write().unwrap().children.insert(name, child)`.
     /// If you have a lot node need to add, consider mauanlly `let mut = node.write().unwrap()`.
     #[inline]
@@ -254,6 +498,69 @@ impl WzNode {
             .insert(node.read().unwrap().name.clone(), Arc::clone(node));
     }
 
+    /// Remove a child from the node, and clear the child's parent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+    /// root.write().unwrap().add(&child);
+    ///
+    /// let removed = root.write().unwrap().remove_child("child").unwrap();
+    ///
+    /// assert!(root.read().unwrap().at("child").is_none());
+    /// assert!(removed.read().unwrap().parent.upgrade().is_none());
+    /// ```
+    pub fn remove_child(&mut self, name: &str) -> Option<WzNodeArc> {
+        let child = self.children.remove(name)?;
+        child.write().unwrap().parent = Weak::new();
+        Some(child)
+    }
+
+    /// Recursively remove every descendant that `f` returns `true`, the removed node's parent will be cleared.
+    /// The subtree of a removed node won't be visited. Returns the number of removed nodes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::{WzNode, WzNodeCast};
+    /// # use wz_reader::property::WzSound;
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let sound = WzNode::from_str("sound", WzSound::default(), Some(&root)).into_lock();
+    /// root.write().unwrap().add(&sound);
+    ///
+    /// let removed = root.write().unwrap().prune(|node| node.try_as_sound().is_some());
+    ///
+    /// assert_eq!(removed, 1);
+    /// assert!(root.read().unwrap().children.is_empty());
+    /// ```
+    pub fn prune<F>(&mut self, f: F) -> usize
+    where
+        F: Fn(&WzNode) -> bool,
+    {
+        self.prune_inner(&f)
+    }
+
+    fn prune_inner(&mut self, f: &dyn Fn(&WzNode) -> bool) -> usize {
+        let mut removed = 0;
+
+        self.children.retain(|_, child| {
+            let mut child = child.write().unwrap();
+            if f(&child) {
+                child.parent = Weak::new();
+                removed += 1;
+                false
+            } else {
+                removed += child.prune_inner(f);
+                true
+            }
+        });
+
+        removed
+    }
+
     /// Returns the full path of the WzNode.
     ///
     /// # Examples
@@ -268,16 +575,37 @@ impl WzNode {
     /// assert_eq!(grandchild.read().unwrap().get_full_path(), "root/1/2");
     /// ```
     pub fn get_full_path(&self) -> String {
-        let mut path = self.name.to_string();
-        let mut parent = self.parent.upgrade();
-        while let Some(parent_inner) = parent {
-            let read = parent_inner.read().unwrap();
-            path = format!("{}/{}", &read.name, path);
-            parent = read.parent.upgrade();
-        }
+        let mut path = String::new();
+        /* writing to a String never fails */
+        self.write_full_path(&mut path).unwrap();
         path
     }
 
+    /// Write the full path of the WzNode, same as `get_full_path` but without allocating,
+    /// useful when printing or comparing the paths of many nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// use std::fmt::Write;
+    ///
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let child = WzNode::from_str("1", 1, Some(&root)).into_lock();
+    ///
+    /// let mut line = String::from("path: ");
+    /// child.read().unwrap().write_full_path(&mut line).unwrap();
+    ///
+    /// assert_eq!(line, "path: root/1");
+    /// ```
+    pub fn write_full_path<W: fmt::Write + ?Sized>(&self, out: &mut W) -> fmt::Result {
+        if let Some(parent) = self.parent.upgrade() {
+            parent.read().unwrap().write_full_path(out)?;
+            out.write_char('/')?;
+        }
+        out.write_str(&self.name)
+    }
+
     /// Returns the path of the WzNode but start from root. It useful when you need this path later to find this node from root.
     ///
     /// # Examples
@@ -403,7 +731,23 @@ impl WzNode {
             self.at(path)
         }
     }
-    /// Get node by path like `a/b/c`.
+    /// The annotated target of a uol node, only set when parsing with `UolPolicy::KeepAndAnnotate`,
+    /// use `node_util::resolve_uol_target` to get the target in any case.
+    #[inline]
+    pub fn uol_target(&self) -> Option<WzNodeArc> {
+        match &self.object_type {
+            WzObjectType::Value(property::WzValue::UOL(uol)) => uol.uol_target(),
+            _ => None,
+        }
+    }
+    #[inline]
+    pub(crate) fn set_uol_target(&mut self, target: &WzNodeArc) {
+        if let WzObjectType::Value(property::WzValue::UOL(uol)) = &mut self.object_type {
+            uol.set_uol_target(target);
+        }
+    }
+    /// Get node by path like `a/b/c`. The uol kept by `UolPolicy::KeepAndAnnotate` or `UolPolicy::LazyOnAccess`
+    /// in the middle of the path is followed to it's target, so it finds the same node as `UolPolicy::ReplaceInPlace`.
     ///
     /// # Examples
     ///
@@ -424,21 +768,20 @@ impl WzNode {
         let mut pathes = path.split('/');
         let first = self.at(pathes.next().unwrap());
         if let Some(first) = first {
-            pathes.try_fold(first, |node, name| node.read().unwrap().at(name))
+            pathes.try_fold(first, |node, name| at_through_uol(&node, name))
         } else {
             None
         }
     }
-    /// Get node by path like `a/b/c` and parse all nodes in the path.
+    /// Get node by path like `a/b/c` and parse all nodes in the path, the kept uol is followed like `at_path`.
     pub fn at_path_parsed(&self, path: &str) -> Result<WzNodeArc, Error> {
         let mut pathes = path.split('/');
 
         let first = self.at(pathes.next().unwrap());
         if let Some(first) = first {
             pathes.try_fold(first, |node, name| {
-                let mut write = node.write().unwrap();
-                write.parse(&node)?;
-                write.at(name).ok_or(Error::NodeNotFound)
+                node.write().unwrap().parse(&node)?;
+                at_through_uol(&node, name).ok_or(Error::NodeNotFound)
             })
         } else {
             Err(Error::NodeNotFound)
@@ -512,13 +855,65 @@ impl WzNode {
         self.filter_parent(|node| matches!(node.object_type, WzObjectType::Image(_)))
     }
 
+    /// Get the `Base` that `_outlink` start from, it's the `Base.wz` file, or the virtual `Base` directory
+    /// from the `.ms` or `.img` layouts.
     #[inline]
     pub fn get_base_wz_file(&self) -> Option<WzNodeArc> {
         self.filter_parent(|node| {
-            matches!(node.object_type, WzObjectType::File(_)) && node.name.as_str() == "Base"
+            matches!(
+                node.object_type,
+                WzObjectType::File(_) | WzObjectType::Directory(_)
+            ) && node.name.as_str() == "Base"
         })
     }
 
+    /// Run `f` with the `WzFile` of the node, itself or the nearest parent.
+    fn with_wz_file<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&WzFile) -> T,
+    {
+        if let WzObjectType::File(file) = &self.object_type {
+            return Some(f(file));
+        }
+        let parent_file =
+            self.filter_parent(|node| matches!(node.object_type, WzObjectType::File(_)))?;
+        let parent_read = parent_file.read().unwrap();
+        match &parent_read.object_type {
+            WzObjectType::File(file) => Some(f(file)),
+            _ => None,
+        }
+    }
+
+    /// Get the `WzFileMeta` of the `WzFile` that the node belongs to, `None` when it's not from a wz file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use wz_reader::{WzNode, util::node_util};
+    /// let wz_file = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+    /// node_util::parse_node(&wz_file).unwrap();
+    ///
+    /// let info = wz_file.read().unwrap().at_path_parsed("100100.img/info").unwrap();
+    /// let info_read = info.read().unwrap();
+    ///
+    /// println!("{:?} {:?}", info_read.wz_patch_version(), info_read.wz_iv());
+    /// ```
+    pub fn wz_file_meta(&self) -> Option<file::WzFileMeta> {
+        self.with_wz_file(|file| file.wz_file_meta.clone())
+    }
+
+    /// Get the patch version of the `WzFile` that the node belongs to, `None` when it's not from a wz file
+    /// or the version is not detected yet(the file is not parsed).
+    pub fn wz_patch_version(&self) -> Option<i32> {
+        self.with_wz_file(|file| file.wz_file_meta.patch_version)
+            .filter(|version| *version != -1)
+    }
+
+    /// Get the iv of the `WzFile` that the node belongs to, `None` when it's not from a wz file.
+    pub fn wz_iv(&self) -> Option<[u8; 4]> {
+        self.with_wz_file(|file| file.reader.wz_iv)
+    }
+
     /// Transfer all children to another node. It will merge the children instead of replace to new one.
     pub fn transfer_childs(&mut self, to: &WzNodeArc) {
         let mut write = to.write().unwrap();
@@ -540,62 +935,363 @@ impl WzNode {
         serde_json::to_value(self)
     }
 
+    /// Generate the JSON Schema of the `to_json` output, feed it to the tools like `json-schema-to-typescript`
+    /// to get the types instead of guessing the tagging.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// let schema = WzNode::to_json_schema();
+    ///
+    /// assert_eq!(schema["title"], "WzNode");
+    /// ```
+    #[cfg(feature = "schemars")]
+    pub fn to_json_schema() -> serde_json::Value {
+        let schema = schemars::schema_for!(WzNode);
+        serde_json::to_value(schema).unwrap_or_default()
+    }
+
     /// Generate simple json only name and value.
     #[cfg(feature = "json")]
+    #[inline]
     pub fn to_simple_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        self.to_simple_json_with_options(&SimpleJsonOptions::default())
+    }
+
+    /// Same as `to_simple_json`, but with options to control the output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// # use wz_reader::node::SimpleJsonOptions;
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+    /// let grand_child = WzNode::from_str("grand_child", 2, Some(&child)).into_lock();
+    /// root.write().unwrap().add(&child);
+    /// child.write().unwrap().add(&grand_child);
+    ///
+    /// let options = SimpleJsonOptions {
+    ///     max_depth: Some(1),
+    ///     ..Default::default()
+    /// };
+    /// let json = root.read().unwrap().to_simple_json_with_options(&options).unwrap();
+    ///
+    /// assert_eq!(json.to_string(), r#"{"child":1}"#);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_simple_json_with_options(
+        &self,
+        options: &SimpleJsonOptions,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        let self_arc = if options.link_mode == JsonLinkMode::Resolve {
+            self.parent
+                .upgrade()
+                .and_then(|parent| parent.read().unwrap().at(&self.name))
+        } else {
+            None
+        };
+        self.to_simple_json_inner(self_arc.as_ref(), options, 0)
+    }
+
+    #[cfg(feature = "json")]
+    fn to_simple_json_inner(
+        &self,
+        self_arc: Option<&WzNodeArc>,
+        options: &SimpleJsonOptions,
+        depth: usize,
+    ) -> Result<serde_json::Value, serde_json::Error> {
         use crate::property::WzSubProperty;
         use serde_json::{to_value, Map, Value};
 
-        if self.children.is_empty() {
-            match &self.object_type {
-                WzObjectType::Value(value_type) => return Ok(value_type.clone().into()),
-                WzObjectType::Property(WzSubProperty::PNG(inner)) => return to_value(inner),
-                WzObjectType::Property(WzSubProperty::Sound(inner)) => return to_value(inner),
-                _ => return Ok(Value::Null),
+        let is_resolve = options.link_mode == JsonLinkMode::Resolve;
+
+        if is_resolve && self.try_as_uol().is_some() {
+            if let Some(target) = self_arc.and_then(node_util::resolve_uol_target) {
+                /* not resolving uol in the target again, prevent circular reference */
+                let options = SimpleJsonOptions {
+                    link_mode: JsonLinkMode::Raw,
+                    ..options.clone()
+                };
+                return target
+                    .read()
+                    .unwrap()
+                    .to_simple_json_inner(None, &options, depth);
             }
         }
 
-        let mut json = Map::new();
+        let link_target = if is_resolve && self.try_as_png().is_some() {
+            self_arc.and_then(|node| node_util::resolve_link(node, false))
+        } else {
+            None
+        };
+        let skip_links = options.link_mode == JsonLinkMode::Omit || link_target.is_some();
 
-        match &self.object_type {
+        let meta = match &self.object_type {
+            _ if !options.inline_meta => None,
             WzObjectType::Property(WzSubProperty::PNG(inner)) => {
-                let dict = to_value(inner)?;
+                let target_png = link_target
+                    .as_ref()
+                    .and_then(|target| target.read().unwrap().try_as_png().map(to_value));
+                Some(target_png.unwrap_or_else(|| to_value(inner))?)
+            }
+            WzObjectType::Property(WzSubProperty::Sound(inner)) => Some(to_value(inner)?),
+            _ => None,
+        };
 
-                if let Value::Object(dict) = dict {
-                    for (name, value) in dict {
-                        json.insert(name, value);
+        let mut children = if options
+            .max_depth
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            Vec::new()
+        } else {
+            self.children
+                .iter()
+                .filter(|(name, child)| {
+                    if skip_links && matches!(name.as_str(), "_inlink" | "_outlink") {
+                        return false;
                     }
-                }
+                    options.link_mode != JsonLinkMode::Omit
+                        || child.read().unwrap().try_as_uol().is_none()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        if children.is_empty() {
+            return match &self.object_type {
+                WzObjectType::Value(value_type) => Ok(value_type.clone().into()),
+                _ => Ok(meta.unwrap_or(Value::Null)),
+            };
+        }
+
+        if options.sort_keys {
+            children.sort_by(|a, b| a.0.cmp(b.0));
+        }
+
+        let mut json = Map::new();
+
+        if let Some(Value::Object(dict)) = meta {
+            for (name, value) in dict {
+                json.insert(name, value);
             }
-            WzObjectType::Property(WzSubProperty::Sound(inner)) => {
-                let dict = to_value(inner)?;
+        }
 
-                if let Value::Object(dict) = dict {
-                    for (name, value) in dict {
-                        json.insert(name, value);
-                    }
-                }
+        for (name, value) in children {
+            let child = value.read().unwrap();
+            json.insert(
+                name.to_string(),
+                child.to_simple_json_inner(Some(value), options, depth + 1)?,
+            );
+        }
+
+        Ok(Value::Object(json))
+    }
+
+    /// Generate yaml with only name and value like `to_simple_json`, the keys are sorted,
+    /// easier for human to read small img like skill or quest data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::WzNode;
+    /// let root = WzNode::from_str("root", 1, None).into_lock();
+    /// let child = WzNode::from_str("hp", 100, Some(&root)).into_lock();
+    /// root.write().unwrap().add(&child);
+    ///
+    /// assert_eq!(root.read().unwrap().to_simple_yaml().unwrap(), "hp: 100\n");
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn to_simple_yaml(&self) -> Result<String, serde_yaml::Error> {
+        use serde::ser::Error;
+
+        let json = self.to_simple_json().map_err(serde_yaml::Error::custom)?;
+        serde_yaml::to_string(&json)
+    }
+
+    /// Generate json with low-level metadata of every node, like the property type, offset and block size,
+    /// useful for reverse-engineering the format.
+    ///
+    /// `property_type` is the type byte read from the image(`11` for a short stored as `11`, `8` for string...),
+    /// or the extended property name(`"Canvas"`, `"UOL"`...) for the extended ones. It's `null` for the nodes not
+    /// parsed from a image, like the directories or the values created by hand. The type bytes are read from
+    /// the image again, since the nodes don't keep them.
+    #[cfg(feature = "json")]
+    pub fn to_debug_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        let (property_types, mut path) = self.image_property_types();
+        self.to_debug_json_inner(property_types.as_ref(), &mut path)
+    }
+
+    /// The type bytes of the image this node belongs to, and the path of the node relative to the image.
+    #[cfg(feature = "json")]
+    fn image_property_types(&self) -> (Option<HashMap<String, u8>>, String) {
+        let read_property_types = |object_type: &WzObjectType| match object_type {
+            WzObjectType::Image(image) => Some(image.read_property_types().ok()),
+            WzObjectType::MsImage(image) => Some(image.to_wz_image().read_property_types().ok()),
+            _ => None,
+        };
+
+        if let Some(property_types) = read_property_types(&self.object_type) {
+            return (property_types, String::new());
+        }
+
+        let mut names = vec![self.name.as_str().to_string()];
+        let mut parent = self.parent.upgrade();
+
+        while let Some(node) = parent {
+            let node_read = node.read().unwrap();
+            if let Some(property_types) = read_property_types(&node_read.object_type) {
+                names.reverse();
+                return (property_types, names.join("/"));
+            }
+            names.push(node_read.name.as_str().to_string());
+            parent = node_read.parent.upgrade();
+        }
+
+        (None, String::new())
+    }
+
+    #[cfg(feature = "json")]
+    fn to_debug_json_inner(
+        &self,
+        property_types: Option<&HashMap<String, u8>>,
+        path: &mut String,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        use crate::property::{WzSubProperty, WzValue};
+        use serde_json::{json, to_value, Map, Value};
+
+        let mut json = Map::new();
+
+        let extended_name = match &self.object_type {
+            WzObjectType::Value(WzValue::UOL(_)) => Some("UOL"),
+            WzObjectType::Value(WzValue::Vector(_)) => Some("Shape2D#Vector2D"),
+            WzObjectType::Value(WzValue::RawData(_)) => Some("RawData"),
+            WzObjectType::Value(WzValue::Video(_)) => Some("Canvas#Video"),
+            WzObjectType::Property(WzSubProperty::Property) => Some("Property"),
+            WzObjectType::Property(WzSubProperty::Convex) => Some("Shape2D#Convex2D"),
+            WzObjectType::Property(WzSubProperty::PNG(_)) => Some("Canvas"),
+            WzObjectType::Property(WzSubProperty::Sound(_)) => Some("Sound_DX8"),
+            _ => None,
+        };
+
+        /* the properties inside a convex have no type byte, but they are extended */
+        let recorded = property_types.and_then(|types| types.get(path.as_str()).copied());
+        let property_type = match (recorded, extended_name) {
+            (Some(9) | None, Some(name)) => json!(name),
+            (Some(property_type), _) => json!(property_type),
+            (None, None) => Value::Null,
+        };
+
+        json.insert("type".to_string(), json!(self.object_type.type_name()));
+        json.insert("property_type".to_string(), property_type);
+
+        let mut meta = Map::new();
+        let mut insert_range = |range: std::ops::Range<usize>| {
+            meta.insert("offset".to_string(), json!(range.start));
+            meta.insert("block_size".to_string(), json!(range.len()));
+        };
+
+        match &self.object_type {
+            WzObjectType::File(file) => insert_range(file.offset..file.offset + file.block_size),
+            WzObjectType::MsFile(file) => insert_range(0..file.block_size),
+            WzObjectType::Directory(dir) => insert_range(dir.offset..dir.offset + dir.block_size),
+            WzObjectType::Image(image) => {
+                insert_range(image.offset..image.offset + image.block_size)
             }
+            WzObjectType::MsImage(image) => {
+                insert_range(image.offset..image.offset + image.block_size)
+            }
+            WzObjectType::Property(WzSubProperty::PNG(png)) => {
+                insert_range(png.get_buffer_range());
+                meta.insert("format".to_string(), json!(png.format()));
+                meta.insert("header".to_string(), json!(png.header));
+            }
+            WzObjectType::Property(WzSubProperty::Sound(sound)) => {
+                insert_range(sound.get_buffer_range());
+                let header_range = sound.get_header_range();
+                meta.insert("header_offset".to_string(), json!(header_range.start));
+                meta.insert("header_size".to_string(), json!(header_range.len()));
+            }
+            WzObjectType::Value(WzValue::String(string) | WzValue::UOL(string)) => {
+                let string_meta = string.get_meta();
+                meta.insert("offset".to_string(), json!(string_meta.offset));
+                meta.insert("length".to_string(), json!(string_meta.length));
+                meta.insert(
+                    "string_type".to_string(),
+                    to_value(&string_meta.string_type)?,
+                );
+            }
+            WzObjectType::Value(WzValue::Lua(lua)) => insert_range(lua.get_buffer_range()),
+            WzObjectType::Value(WzValue::RawData(raw)) => insert_range(raw.get_buffer_range()),
+            WzObjectType::Value(WzValue::Video(video)) => insert_range(video.get_buffer_range()),
             _ => {}
         }
 
-        for (name, value) in self.children.iter() {
-            let child = value.read().unwrap();
-            json.insert(name.to_string(), child.to_simple_json()?);
+        json.insert("meta".to_string(), Value::Object(meta));
+
+        match &self.object_type {
+            WzObjectType::Value(value) => {
+                json.insert("value".to_string(), value.clone().into());
+            }
+            WzObjectType::Property(WzSubProperty::PNG(inner)) => {
+                json.insert("value".to_string(), to_value(inner)?);
+            }
+            WzObjectType::Property(WzSubProperty::Sound(inner)) => {
+                json.insert("value".to_string(), to_value(inner)?);
+            }
+            _ => {}
+        }
+
+        if !self.children.is_empty() {
+            let mut children = Map::new();
+            for (name, value) in self.children.iter() {
+                let child = value.read().unwrap();
+                let child_json = if matches!(
+                    child.object_type,
+                    WzObjectType::Image(_) | WzObjectType::MsImage(_)
+                ) {
+                    child.to_debug_json()?
+                } else {
+                    let len = path.len();
+                    if len > 0 {
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                    let child_json = child.to_debug_json_inner(property_types, path);
+                    path.truncate(len);
+                    child_json?
+                };
+                children.insert(name.to_string(), child_json);
+            }
+            json.insert("children".to_string(), Value::Object(children));
         }
 
         Ok(Value::Object(json))
     }
 }
 
+/// A short summary of the node, like `speed (Int: 10), 0 children`.
+impl std::fmt::Display for WzNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}), {} children",
+            self.name,
+            self.object_type,
+            self.children.len()
+        )
+    }
+}
+
 #[cfg(feature = "serde")]
 mod arc_node_serde {
+    use crate::sync::RwLock;
     use crate::WzNodeName;
     use hashbrown::HashMap;
     use serde::de::Deserializer;
     use serde::ser::{SerializeMap, Serializer};
     use serde::{Deserialize, Serialize};
-    use std::sync::{Arc, RwLock};
+    use std::sync::Arc;
 
     pub fn serialize<S, T>(
         val: &HashMap<WzNodeName, Arc<RwLock<T>>>,
@@ -625,15 +1321,125 @@ mod arc_node_serde {
     }
 }
 
-#[cfg(feature = "serde")]
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::util::node_util;
 
     #[cfg(feature = "serde")]
     use serde_json::json;
 
+    #[test]
+    fn test_estimate_memory() {
+        use crate::property::WzString;
+
+        let root = WzNode::from_str(
+            "root.img",
+            WzImage {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+        let empty = root.read().unwrap().estimate_memory();
+
+        let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+        let text =
+            WzNode::from_str("text", WzString::from_str("a", [0; 4]), Some(&child)).into_lock();
+        child.write().unwrap().add(&text);
+        root.write().unwrap().add(&child);
+
+        let with_children = root.read().unwrap().estimate_memory();
+        assert!(with_children > empty);
+        assert!(with_children > child.read().unwrap().estimate_memory());
+
+        root.write().unwrap().unparse();
+
+        assert!(root.read().unwrap().estimate_memory() < with_children);
+    }
+
+    #[test]
+    fn test_prune() {
+        use crate::property::WzSound;
+
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let child1 = WzNode::from_str("1", 1, Some(&root)).into_lock();
+        let child2 = WzNode::from_str("2", WzSound::default(), Some(&root)).into_lock();
+        let child11 = WzNode::from_str("1-1", WzSound::default(), Some(&child1)).into_lock();
+        let child12 = WzNode::from_str("1-2", 1, Some(&child1)).into_lock();
+        let child21 = WzNode::from_str("2-1", WzSound::default(), Some(&child2)).into_lock();
+        root.write().unwrap().add(&child1);
+        root.write().unwrap().add(&child2);
+        child1.write().unwrap().add(&child11);
+        child1.write().unwrap().add(&child12);
+        child2.write().unwrap().add(&child21);
+
+        let removed = root
+            .write()
+            .unwrap()
+            .prune(|node| node.try_as_sound().is_some());
+
+        assert_eq!(removed, 2);
+        assert!(root.read().unwrap().at("2").is_none());
+        assert!(root.read().unwrap().at_path("1/1-1").is_none());
+        assert!(root.read().unwrap().at_path("1/1-2").is_some());
+        assert!(child2.read().unwrap().parent.upgrade().is_none());
+        assert!(child11.read().unwrap().parent.upgrade().is_none());
+        /* subtree of removed node is untouched */
+        assert!(child2.read().unwrap().at("2-1").is_some());
+    }
+
+    #[test]
+    fn test_at_path_through_uol() {
+        use crate::property::{WzString, WzValue};
+
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let dir = WzNode::from_str("dir", 1, Some(&root)).into_lock();
+        let target = WzNode::from_str("target", 1, Some(&dir)).into_lock();
+        let child = WzNode::from_str("child", 1, Some(&target)).into_lock();
+        let uol = WzNode::from_str(
+            "uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("target", [0; 4]))),
+            Some(&dir),
+        )
+        .into_lock();
+        root.write().unwrap().add(&dir);
+        dir.write().unwrap().add(&target);
+        dir.write().unwrap().add(&uol);
+        target.write().unwrap().add(&child);
+
+        let root_read = root.read().unwrap();
+
+        let found = root_read.at_path("dir/uol/child").unwrap();
+        assert!(Arc::ptr_eq(&found, &child));
+        let found = root_read.at_path_parsed("dir/uol/child").unwrap();
+        assert!(Arc::ptr_eq(&found, &child));
+        assert!(root_read.at_path("dir/uol/none").is_none());
+        /* the uol itself is still kept */
+        assert!(root_read
+            .at_path("dir/uol")
+            .unwrap()
+            .read()
+            .unwrap()
+            .try_as_uol()
+            .is_some());
+        assert!(uol.read().unwrap().uol_target().is_none());
+    }
+
+    #[test]
+    fn test_display_wz_node() {
+        let root = WzNode::from_str("root", WzImage::default(), None).into_lock();
+        let child = WzNode::from_str("speed", 10, Some(&root)).into_lock();
+        root.write().unwrap().add(&child);
+
+        assert_eq!(root.read().unwrap().to_string(), "root (Image), 1 children");
+        assert_eq!(
+            child.read().unwrap().to_string(),
+            "speed (Int: 10), 0 children"
+        );
+    }
+
+    #[cfg(feature = "serde")]
     #[test]
     fn test_serialize_wz_node() {
         let root = WzNode::from_str("root", 1, None).into_lock();
@@ -693,6 +1499,7 @@ mod test {
         assert_eq!(json, result);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
     fn test_deserialize_wz_node() {
         let source = json!({
@@ -760,6 +1567,274 @@ mod test {
         assert_eq!(node111_parent.read().unwrap().name.as_str(), "1-1");
     }
 
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_node_to_simple_json_with_options() {
+        use crate::property::{WzPng, WzString, WzValue};
+
+        let img = WzNode::from_str("1.img", WzImage::default(), None).into_lock();
+
+        let mut png = WzPng::default();
+        png.width = 5;
+        let canvas1 = WzNode::from_str("canvas1", png, Some(&img)).into_lock();
+        let canvas2 = WzNode::from_str("canvas2", WzPng::default(), Some(&img)).into_lock();
+        let inlink = WzNode::from_str(
+            "_inlink",
+            WzString::from_str("canvas1", [0; 4]),
+            Some(&canvas2),
+        )
+        .into_lock();
+        let uol = WzNode::from_str(
+            "uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("canvas1", [0; 4]))),
+            Some(&img),
+        )
+        .into_lock();
+
+        img.write().unwrap().add(&canvas1);
+        img.write().unwrap().add(&canvas2);
+        img.write().unwrap().add(&uol);
+        canvas2.write().unwrap().add(&inlink);
+
+        let to_json = |options: SimpleJsonOptions| {
+            img.read()
+                .unwrap()
+                .to_simple_json_with_options(&options)
+                .unwrap()
+        };
+
+        assert_eq!(
+            to_json(SimpleJsonOptions::default()),
+            img.read().unwrap().to_simple_json().unwrap()
+        );
+
+        assert_eq!(
+            to_json(SimpleJsonOptions {
+                link_mode: JsonLinkMode::Resolve,
+                ..Default::default()
+            }),
+            json!({
+                "canvas1": { "width": 5, "height": 0 },
+                "canvas2": { "width": 5, "height": 0 },
+                "uol": { "width": 5, "height": 0 }
+            })
+        );
+
+        assert_eq!(
+            to_json(SimpleJsonOptions {
+                link_mode: JsonLinkMode::Omit,
+                inline_meta: false,
+                ..Default::default()
+            }),
+            json!({
+                "canvas1": null,
+                "canvas2": null
+            })
+        );
+
+        assert_eq!(
+            to_json(SimpleJsonOptions {
+                max_depth: Some(1),
+                ..Default::default()
+            }),
+            json!({
+                "canvas1": { "width": 5, "height": 0 },
+                "canvas2": { "width": 0, "height": 0 },
+                "uol": "canvas1"
+            })
+        );
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_node_to_simple_yaml() {
+        use crate::property::{Vector2D, WzString};
+
+        let root = WzNode::from_str("root", WzImage::default(), None).into_lock();
+        let info = WzNode::from_str("info", 1, Some(&root)).into_lock();
+        let name =
+            WzNode::from_str("name", WzString::from_str("foo", [0; 4]), Some(&info)).into_lock();
+        let origin = WzNode::from_str("origin", Vector2D(1, 2), Some(&info)).into_lock();
+        root.write().unwrap().add(&info);
+        info.write().unwrap().add(&origin);
+        info.write().unwrap().add(&name);
+
+        assert_eq!(
+            root.read().unwrap().to_simple_yaml().unwrap(),
+            "info:\n  name: foo\n  origin:\n    x: 1\n    y: 2\n"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_node_to_debug_json() {
+        use crate::property::{WzPng, WzString};
+        use crate::WzReader;
+
+        /* the ascii string with zero key */
+        fn wz_str(str: &str) -> Vec<u8> {
+            let mut buf = vec![(-(str.len() as i8)) as u8];
+            buf.extend(str.bytes().zip(0xAA_u8..).map(|(c, mask)| c ^ mask));
+            buf
+        }
+
+        /* info(Property) { speed: 5 stored as `11` }, hp: 7 stored as `19` */
+        let mut info = vec![0x73];
+        info.extend(wz_str("Property"));
+        info.extend([0, 0, 1, 0]);
+        info.extend(wz_str("speed"));
+        info.extend([11, 5, 0]);
+
+        let mut buf = vec![0x73];
+        buf.extend(wz_str("Property"));
+        buf.extend([0, 0, 2, 0]);
+        buf.extend(wz_str("info"));
+        buf.push(9);
+        buf.extend((info.len() as u32).to_le_bytes());
+        buf.extend(info);
+        buf.push(0);
+        buf.extend(wz_str("hp"));
+        buf.extend([19, 7]);
+
+        let reader = Arc::new(WzReader::from_buff(&buf));
+        let root = WzNode::from_str(
+            "root",
+            WzImage::new(&"root".into(), 0, buf.len(), &reader),
+            None,
+        )
+        .into_lock();
+        root.write().unwrap().parse(&root).unwrap();
+
+        /* nodes created by hand */
+        let png = WzPng::new(&Default::default(), (1, 2), (2, 0), (10, 20), 0x9C78);
+        let canvas = WzNode::from_str("canvas", png, Some(&root)).into_lock();
+        let string = WzNode::from_str("string", WzString::from_str("foo", [0; 4]), Some(&canvas))
+            .into_lock();
+        root.write().unwrap().add(&canvas);
+        canvas.write().unwrap().add(&string);
+
+        let json = root.read().unwrap().to_debug_json().unwrap();
+
+        let result = json!({
+            "type": "Image",
+            "property_type": null,
+            "meta": { "offset": 0, "block_size": buf.len() },
+            "children": {
+                "info": {
+                    "type": "Property",
+                    "property_type": "Property",
+                    "meta": {},
+                    "children": {
+                        "speed": {
+                            "type": "Short",
+                            "property_type": 11,
+                            "meta": {},
+                            "value": 5
+                        }
+                    }
+                },
+                "hp": {
+                    "type": "Int",
+                    "property_type": 19,
+                    "meta": {},
+                    "value": 7
+                },
+                "canvas": {
+                    "type": "PNG",
+                    "property_type": "Canvas",
+                    "meta": { "offset": 10, "block_size": 20, "format": 2, "header": 0x9C78 },
+                    "value": { "width": 1, "height": 2 },
+                    "children": {
+                        "string": {
+                            "type": "String",
+                            "property_type": null,
+                            "meta": { "offset": 0, "length": 3, "string_type": "Ascii" },
+                            "value": "foo"
+                        }
+                    }
+                }
+            }
+        });
+
+        assert_eq!(json, result);
+
+        /* a node inside the image finds it's type byte too */
+        let speed = root.read().unwrap().at_path("info/speed").unwrap();
+        let json = speed.read().unwrap().to_debug_json().unwrap();
+        assert_eq!(json["property_type"], json!(11));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_node_to_json_schema() {
+        use crate::property::{Vector2D, WzPng, WzSound, WzString, WzSubProperty, WzValue};
+
+        fn collect_enums(value: &serde_json::Value, result: &mut Vec<String>) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(serde_json::Value::Array(values)) = map.get("enum") {
+                        result.extend(values.iter().filter_map(|v| v.as_str().map(String::from)));
+                    }
+                    map.values().for_each(|v| collect_enums(v, result));
+                }
+                serde_json::Value::Array(values) => {
+                    values.iter().for_each(|v| collect_enums(v, result))
+                }
+                _ => {}
+            }
+        }
+        fn collect_types(value: &serde_json::Value, result: &mut Vec<String>) {
+            result.push(value["type"].as_str().unwrap().to_string());
+            if let Some(children) = value["children"].as_object() {
+                children.values().for_each(|v| collect_types(v, result));
+            }
+        }
+
+        let root = WzNode::from_str("root", WzImage::default(), None).into_lock();
+        let children: Vec<(&str, WzObjectType)> = vec![
+            ("int", 1.into()),
+            ("double", WzObjectType::Value(WzValue::Double(1.5))),
+            ("string", WzString::from_str("foo", [0; 4]).into()),
+            ("vector", Vector2D(1, 2).into()),
+            ("null", WzObjectType::Value(WzValue::Null)),
+            ("png", WzPng::default().into()),
+            ("sound", WzSound::default().into()),
+            ("convex", WzObjectType::Property(WzSubProperty::Convex)),
+            ("property", WzObjectType::Property(WzSubProperty::Property)),
+        ];
+        for (name, object_type) in children {
+            let child = WzNode::from_str(name, object_type, Some(&root)).into_lock();
+            root.write().unwrap().add(&child);
+        }
+
+        let json = root.read().unwrap().to_json().unwrap();
+        let schema = WzNode::to_json_schema();
+
+        let mut tags = Vec::new();
+        collect_enums(&schema, &mut tags);
+        let mut types = Vec::new();
+        collect_types(&json, &mut types);
+
+        assert_eq!(types.len(), 10);
+        for ty in types {
+            assert!(tags.contains(&ty), "{ty} is not in the schema");
+        }
+
+        let definitions = &schema["definitions"];
+        assert_eq!(
+            definitions["WzNode"]["properties"]["children"]["additionalProperties"]["$ref"],
+            "#/definitions/WzNode"
+        );
+        assert_eq!(
+            definitions["WzNode"]["required"],
+            json!(["children", "name"])
+        );
+        assert!(definitions["WzPng"]["properties"]["width"].is_object());
+        assert!(definitions["WzSound"]["properties"]["sound_type"].is_object());
+        /* the data that can't be serialized are not in the schema */
+        assert!(!tags.contains(&"RawData".to_string()));
+    }
+
     #[cfg(feature = "json")]
     #[test]
     fn test_node_to_simple_json() {
@@ -128,13 +128,134 @@ impl WzNodeCast for WzNode {
     try_as_wz_value!(try_as_uol, UOL, WzString);
 }
 
+/// Consuming version of `WzNodeCast`, moving the inner value out of the node without cloning.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzNodeIntoCast};
+/// let wz_int = WzNode::from_str("test", 1, None);
+///
+/// assert_eq!(wz_int.try_into_int(), Some(1));
+/// ```
+pub trait WzNodeIntoCast: Sized {
+    fn try_into_file(self) -> Option<WzFile>;
+    fn try_into_directory(self) -> Option<WzDirectory>;
+    fn try_into_image(self) -> Option<WzImage>;
+
+    fn try_into_sub_property(self) -> Option<WzSubProperty>;
+    fn try_into_value(self) -> Option<WzValue>;
+
+    fn try_into_png(self) -> Option<WzPng>;
+    fn try_into_sound(self) -> Option<WzSound>;
+    fn try_into_string(self) -> Option<WzString>;
+
+    fn try_into_lua(self) -> Option<WzLua>;
+    fn try_into_raw_data(self) -> Option<WzRawData>;
+    fn try_into_video(self) -> Option<WzVideo>;
+
+    fn try_into_vector2d(self) -> Option<Vector2D>;
+    fn try_into_short(self) -> Option<i16>;
+    fn try_into_int(self) -> Option<i32>;
+    fn try_into_long(self) -> Option<i64>;
+    fn try_into_float(self) -> Option<f32>;
+    fn try_into_double(self) -> Option<f64>;
+    fn try_into_uol(self) -> Option<WzString>;
+}
+
+macro_rules! try_into_boxed {
+    ($func_name:ident, $variant:ident, $result:ty) => {
+        #[inline]
+        fn $func_name(self) -> Option<$result> {
+            match self.object_type {
+                WzObjectType::$variant(inner) => Some(*inner),
+                _ => None,
+            }
+        }
+    };
+}
+
+macro_rules! try_into_wz_value {
+    ($func_name:ident, $variant:ident, $result:ident) => {
+        #[inline]
+        fn $func_name(self) -> Option<$result> {
+            match self.object_type {
+                WzObjectType::Value(WzValue::$variant(inner)) => Some(inner),
+                _ => None,
+            }
+        }
+    };
+}
+
+impl WzNodeIntoCast for WzNode {
+    try_into_boxed!(try_into_file, File, WzFile);
+    try_into_boxed!(try_into_directory, Directory, WzDirectory);
+    try_into_boxed!(try_into_image, Image, WzImage);
+
+    #[inline]
+    fn try_into_sub_property(self) -> Option<WzSubProperty> {
+        match self.object_type {
+            WzObjectType::Property(inner) => Some(inner),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn try_into_value(self) -> Option<WzValue> {
+        match self.object_type {
+            WzObjectType::Value(inner) => Some(inner),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn try_into_png(self) -> Option<WzPng> {
+        match self.object_type {
+            WzObjectType::Property(WzSubProperty::PNG(png)) => Some(*png),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn try_into_sound(self) -> Option<WzSound> {
+        match self.object_type {
+            WzObjectType::Property(WzSubProperty::Sound(sound)) => Some(*sound),
+            _ => None,
+        }
+    }
+    #[inline]
+    fn try_into_string(self) -> Option<WzString> {
+        match self.object_type {
+            WzObjectType::Value(WzValue::String(string))
+            | WzObjectType::Value(WzValue::UOL(string)) => Some(string),
+            _ => None,
+        }
+    }
+
+    try_into_wz_value!(try_into_lua, Lua, WzLua);
+    try_into_wz_value!(try_into_raw_data, RawData, WzRawData);
+    try_into_wz_value!(try_into_video, Video, WzVideo);
+
+    try_into_wz_value!(try_into_vector2d, Vector, Vector2D);
+    try_into_wz_value!(try_into_short, Short, i16);
+    try_into_wz_value!(try_into_int, Int, i32);
+    try_into_wz_value!(try_into_long, Long, i64);
+    try_into_wz_value!(try_into_float, Float, f32);
+    try_into_wz_value!(try_into_double, Double, f64);
+    try_into_wz_value!(try_into_uol, UOL, WzString);
+}
+
+impl From<WzNode> for WzObjectType {
+    #[inline]
+    fn from(node: WzNode) -> Self {
+        node.object_type
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
     use crate::property::{WzSoundType, WzStringMeta};
     use crate::WzReader;
-    use memmap2::Mmap;
     use std::fs::OpenOptions;
     use std::sync::Arc;
 
@@ -146,11 +267,12 @@ mod test {
             .read(true)
             .write(true)
             .create(true)
-            .open(file_path)?;
+            .truncate(true)
+            .open(&file_path)?;
 
         file.set_len(200)?;
 
-        let map = unsafe { Mmap::map(&file)? };
+        let map = crate::reader::open_reader_buffer(&file_path)?;
 
         Ok(WzReader::new(map))
     }
@@ -162,6 +284,7 @@ mod test {
             offset: 0,
             block_size: 0,
             is_parsed: false,
+            is_closed: false,
             reader: Arc::new(reader),
             wz_file_meta: Default::default(),
         };
@@ -347,4 +470,38 @@ mod test {
         assert!(node.try_as_file().is_none());
         assert_eq!(node.try_as_double(), Some(&1.0));
     }
+
+    #[test]
+    fn try_into_value() {
+        let node = WzNode::from_str("test", 1, None);
+        assert_eq!(node.try_into_int(), Some(1));
+
+        let node = WzNode::from_str("test", 1, None);
+        assert!(node.try_into_file().is_none());
+
+        let node = WzNode::from_str("test", Vector2D(1, 2), None);
+        assert_eq!(node.try_into_vector2d(), Some(Vector2D(1, 2)));
+    }
+
+    #[test]
+    fn try_into_png() {
+        let reader = Arc::new(setup_wz_reader().unwrap());
+        let png = WzPng::new(&reader, (2, 3), (1, 1), (0, 1), 0);
+        let node = WzNode::from_str("test", png, None);
+
+        let png = node.try_into_png();
+        assert!(png.is_some());
+        assert_eq!(png.unwrap().width, 2);
+    }
+
+    #[test]
+    fn into_object_type() {
+        let node = WzNode::from_str("test", 1_i16, None);
+        let object_type: WzObjectType = node.into();
+
+        assert!(matches!(
+            object_type,
+            WzObjectType::Value(WzValue::Short(1))
+        ));
+    }
 }
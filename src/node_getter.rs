@@ -0,0 +1,188 @@
+use crate::property::{Vector2D, WzValue};
+use crate::{util::node_util, WzNode, WzNodeArc, WzNodeCast, WzObjectType};
+
+/// Trait for getting typed value by path, it will resolve uol along the way,
+/// and convert between number types or from numeric string like `WzValue::as_i64`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzNodeGetter};
+/// # use wz_reader::property::Vector2D;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let info = WzNode::from_str("info", 1, Some(&root)).into_lock();
+/// let speed = WzNode::from_str("speed", 10_i16, Some(&info)).into_lock();
+/// let origin = WzNode::from_str("origin", Vector2D(1, 2), Some(&info)).into_lock();
+///
+/// root.write().unwrap().add(&info);
+/// info.write().unwrap().add(&speed);
+/// info.write().unwrap().add(&origin);
+///
+/// assert_eq!(root.get_int_at("info/speed"), Some(10));
+/// assert_eq!(root.get_vec2_at("info/origin"), Some(Vector2D(1, 2)));
+/// assert_eq!(root.get_int_or("info/hp", 100), 100);
+/// ```
+pub trait WzNodeGetter {
+    /// Get node by path like `at_path`, but will resolve uol node along the way.
+    fn get_resolved_at(&self, path: &str) -> Option<WzNodeArc>;
+
+    fn get_int_at(&self, path: &str) -> Option<i32> {
+        self.get_long_at(path)
+            .and_then(|value| i32::try_from(value).ok())
+    }
+    fn get_long_at(&self, path: &str) -> Option<i64> {
+        get_long(&self.get_resolved_at(path)?.read().unwrap())
+    }
+    fn get_float_at(&self, path: &str) -> Option<f64> {
+        get_float(&self.get_resolved_at(path)?.read().unwrap())
+    }
+    fn get_str_at(&self, path: &str) -> Option<String> {
+        get_str(&self.get_resolved_at(path)?.read().unwrap())
+    }
+    fn get_vec2_at(&self, path: &str) -> Option<Vector2D> {
+        self.get_resolved_at(path)?
+            .read()
+            .unwrap()
+            .try_as_vector2d()
+            .copied()
+    }
+
+    fn get_int_or(&self, path: &str, default: i32) -> i32 {
+        self.get_int_at(path).unwrap_or(default)
+    }
+    fn get_long_or(&self, path: &str, default: i64) -> i64 {
+        self.get_long_at(path).unwrap_or(default)
+    }
+    fn get_float_or(&self, path: &str, default: f64) -> f64 {
+        self.get_float_at(path).unwrap_or(default)
+    }
+    fn get_str_or(&self, path: &str, default: &str) -> String {
+        self.get_str_at(path).unwrap_or_else(|| default.to_string())
+    }
+}
+
+fn get_long(node: &WzNode) -> Option<i64> {
+    match &node.object_type {
+        WzObjectType::Value(value) => value.as_i64(),
+        _ => None,
+    }
+}
+
+fn get_float(node: &WzNode) -> Option<f64> {
+    match &node.object_type {
+        WzObjectType::Value(value) => value.as_f64(),
+        _ => None,
+    }
+}
+
+fn get_str(node: &WzNode) -> Option<String> {
+    match &node.object_type {
+        WzObjectType::Value(WzValue::String(string)) => string.get_string().ok(),
+        WzObjectType::Value(WzValue::ParsedString(string)) => Some(string.clone()),
+        _ => None,
+    }
+}
+
+/// Resolve the node if it's a uol.
+fn resolve_if_uol(node: WzNodeArc) -> Option<WzNodeArc> {
+    if node.read().unwrap().try_as_uol().is_some() {
+        node_util::resolve_uol_target(&node)
+    } else {
+        Some(node)
+    }
+}
+
+impl WzNodeGetter for WzNode {
+    fn get_resolved_at(&self, path: &str) -> Option<WzNodeArc> {
+        let mut pathes = path.split('/');
+        let first = resolve_if_uol(self.at(pathes.next()?)?)?;
+        pathes.try_fold(first, |node, name| {
+            let next = node.read().unwrap().at(name)?;
+            resolve_if_uol(next)
+        })
+    }
+}
+
+impl WzNodeGetter for WzNodeArc {
+    #[inline]
+    fn get_resolved_at(&self, path: &str) -> Option<WzNodeArc> {
+        self.read().unwrap().get_resolved_at(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::property::WzString;
+    use crate::util::test_util::add_child;
+
+    fn setup_node_tree() -> WzNodeArc {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let info = add_child("info", 1, &root);
+
+        add_child("short", 1_i16, &info);
+        add_child("long", i64::MAX, &info);
+        add_child("float", 1.5_f32, &info);
+        add_child("numeric_string", WzString::from_str("42", [0; 4]), &info);
+        add_child("string", WzString::from_str("hello", [0; 4]), &info);
+        add_child("vec", Vector2D(3, 4), &info);
+        add_child(
+            "uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("short", [0; 4]))),
+            &info,
+        );
+        add_child(
+            "info_uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("info", [0; 4]))),
+            &root,
+        );
+
+        root
+    }
+
+    #[test]
+    fn test_get_int_at() {
+        let root = setup_node_tree();
+
+        assert_eq!(root.get_int_at("info/short"), Some(1));
+        assert_eq!(root.get_int_at("info/numeric_string"), Some(42));
+        assert_eq!(root.get_int_at("info/long"), None);
+        assert_eq!(root.get_long_at("info/long"), Some(i64::MAX));
+        assert_eq!(root.get_int_at("info/string"), None);
+        assert_eq!(root.get_int_or("info/not_exist", 7), 7);
+    }
+
+    #[test]
+    fn test_get_float_at() {
+        let root = setup_node_tree();
+
+        assert_eq!(root.get_float_at("info/float"), Some(1.5));
+        assert_eq!(root.get_float_at("info/short"), Some(1.0));
+        assert_eq!(root.get_float_at("info/numeric_string"), Some(42.0));
+    }
+
+    #[test]
+    fn test_get_str_at() {
+        let root = setup_node_tree();
+
+        assert_eq!(root.get_str_at("info/string").as_deref(), Some("hello"));
+        assert_eq!(root.get_str_at("info/short"), None);
+        assert_eq!(root.get_str_or("info/none", "default"), "default");
+    }
+
+    #[test]
+    fn test_get_vec2_at() {
+        let root = setup_node_tree();
+
+        assert_eq!(root.get_vec2_at("info/vec"), Some(Vector2D(3, 4)));
+        assert_eq!(root.get_vec2_at("info/short"), None);
+    }
+
+    #[test]
+    fn test_get_with_uol() {
+        let root = setup_node_tree();
+
+        assert_eq!(root.get_int_at("info/uol"), Some(1));
+        assert_eq!(root.get_int_at("info_uol/short"), Some(1));
+    }
+}
@@ -76,3 +76,16 @@ impl Serialize for WzNodeName {
         serializer.serialize_str(self.as_str())
     }
 }
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for WzNodeName {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
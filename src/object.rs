@@ -2,6 +2,7 @@ use crate::property::{
     Vector2D, WzLua, WzPng, WzRawData, WzSound, WzString, WzSubProperty, WzValue, WzVideo,
 };
 use crate::{MsFile, MsImage, WzDirectory, WzFile, WzImage};
+use std::fmt;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -82,14 +83,79 @@ from_impl_wz_value!(WzLua, Lua);
 from_impl_wz_property!(WzPng, PNG);
 from_impl_wz_property!(WzSound, Sound);
 
+impl WzObjectType {
+    /// The type name of the object, like `"Image"`, `"PNG"` or `"Int"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            WzObjectType::File(_) => "File",
+            WzObjectType::MsFile(_) => "MsFile",
+            WzObjectType::Image(_) => "Image",
+            WzObjectType::MsImage(_) => "MsImage",
+            WzObjectType::Directory(_) => "Directory",
+            WzObjectType::Property(WzSubProperty::Convex) => "Convex",
+            WzObjectType::Property(WzSubProperty::Property) => "Property",
+            WzObjectType::Property(WzSubProperty::PNG(_)) => "PNG",
+            WzObjectType::Property(WzSubProperty::Sound(_)) => "Sound",
+            WzObjectType::Value(value) => value.type_name(),
+        }
+    }
+}
+
+// the tagged variants of `WzObjectType`, only used to derive the schema, a doc comment here will end up in the schema
+#[cfg(feature = "schemars")]
+#[derive(schemars::JsonSchema)]
+#[schemars(tag = "type", content = "data")]
+#[allow(dead_code)]
+enum WzObjectTypeTagged {
+    File(WzFile),
+    MsFile(MsFile),
+    Image(WzImage),
+    MsImage(MsImage),
+    Directory(WzDirectory),
+}
+
+/// The derive doesn't know the untagged variants, so the `Property` and `Value` are added as it is,
+/// like `{"type": "Int", "data": 1}` instead of `{"type": "Value", "data": {"type": "Int", "data": 1}}`.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for WzObjectType {
+    fn schema_name() -> String {
+        "WzObjectType".to_string()
+    }
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{SchemaObject, SubschemaValidation};
+
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                one_of: Some(vec![
+                    WzObjectTypeTagged::json_schema(gen),
+                    gen.subschema_for::<WzSubProperty>(),
+                    gen.subschema_for::<WzValue>(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Display the type, and the value when it's a `WzValue`, like `Int: 1`.
+impl fmt::Display for WzObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WzObjectType::Property(property) => write!(f, "{property}"),
+            WzObjectType::Value(WzValue::Null) => write!(f, "Null"),
+            WzObjectType::Value(value) => write!(f, "{}: {value}", value.type_name()),
+            _ => write!(f, "{}", self.type_name()),
+        }
+    }
+}
+
 #[cfg(feature = "serde")]
 #[cfg(test)]
 mod test {
     use super::*;
 
-    #[cfg(feature = "serde")]
-    use serde_json;
-
     #[cfg(feature = "serde")]
     #[test]
     fn test_serialize_main_wz_object() {
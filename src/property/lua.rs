@@ -1,6 +1,6 @@
 use crate::util::maple_crypto_constants::{WZ_GMSIV, WZ_MSEAIV};
 use crate::util::WzMutableKey;
-use crate::WzReader;
+use crate::{reader::DataSource, WzReader};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -17,15 +17,51 @@ pub enum WzLuaParseError {
 }
 
 /// WzLua use to store lua information and extraction method.
-#[derive(Debug, Clone, Default)]
-pub struct WzLua {
-    reader: Arc<WzReader>,
+#[derive(Debug, Default)]
+pub struct WzLua<R: ?Sized = WzReader> {
+    reader: Arc<R>,
     offset: usize,
     length: usize,
 }
 
+impl<R: ?Sized> Clone for WzLua<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            length: self.length,
+        }
+    }
+}
+
 impl WzLua {
-    pub fn new(reader: &Arc<WzReader>, offset: usize, length: usize) -> Self {
+    /// Create from script text, it will be encrypted with the iv like the lua in wz file.
+    /// Note: `extract_lua` can only guess GMS, MSEA and zero iv back.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzLua;
+    /// # use wz_reader::util::maple_crypto_constants::WZ_GMSIV;
+    /// let lua = WzLua::from_script("print(1)", WZ_GMSIV);
+    ///
+    /// assert_eq!(lua.extract_lua().unwrap(), "print(1)");
+    /// ```
+    pub fn from_script(script: &str, iv: [u8; 4]) -> Self {
+        let mut data = script.as_bytes().to_vec();
+        let mut keys = WzMutableKey::from_iv(iv);
+
+        keys.ensure_key_size(data.len()).unwrap();
+        keys.decrypt_slice(&mut data);
+
+        let reader = Arc::new(WzReader::from_buff(&data).with_iv(iv));
+
+        Self::new(&reader, 0, data.len())
+    }
+}
+
+impl<R: DataSource + ?Sized> WzLua<R> {
+    pub fn new(reader: &Arc<R>, offset: usize, length: usize) -> Self {
         Self {
             reader: Arc::clone(reader),
             offset,
@@ -33,6 +69,25 @@ impl WzLua {
         }
     }
 
+    /// The offset of the data in the wz file.
+    #[inline]
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    #[inline]
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
+    #[inline]
+    pub fn get_buffer_range(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.length
+    }
+    /// The raw encrypted lua data.
+    #[inline]
+    pub fn get_buffer(&self) -> &[u8] {
+        self.reader.get_slice(self.get_buffer_range())
+    }
+
     /// extract lua string from wz file
     pub fn extract_lua(&self) -> Result<String, WzLuaParseError> {
         let data = self
@@ -81,8 +136,6 @@ impl WzLua {
 #[cfg(test)]
 mod test {
     use super::*;
-    use memmap2::MmapMut;
-    use std::fs::OpenOptions;
     use tempfile;
 
     fn generate_encrypted_text(text: &str, iv: [u8; 4]) -> Vec<u8> {
@@ -102,21 +155,13 @@ mod test {
         let dir = tempfile::tempdir()?;
         let file_path = dir.path().join("test.lua");
 
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(file_path)?;
-
-        file.set_len(len as u64)?;
-
-        let mut map = unsafe { MmapMut::map_mut(&file)? };
-
         let encrypted = generate_encrypted_text(lua_text, iv);
 
-        (&mut map[..len]).copy_from_slice(&encrypted);
+        std::fs::write(&file_path, encrypted)?;
 
-        let reader = Arc::new(WzReader::new(map.make_read_only()?).with_iv(iv));
+        let map = crate::reader::open_reader_buffer(&file_path)?;
+
+        let reader = Arc::new(WzReader::new(map).with_iv(iv));
 
         Ok(WzLua::new(&reader, 0, len))
     }
@@ -151,6 +196,18 @@ mod test {
         assert_eq!(text.unwrap(), "print(1234567)");
     }
 
+    #[test]
+    fn should_create_from_script() {
+        let script = "local a = 1\nprint(a)";
+
+        for iv in [WZ_GMSIV, WZ_MSEAIV, [0, 0, 0, 0]] {
+            let lua = WzLua::from_script(script, iv);
+
+            assert_eq!(lua.get_buffer(), generate_encrypted_text(script, iv));
+            assert_eq!(lua.extract_lua().unwrap(), script);
+        }
+    }
+
     #[test]
     fn should_error_unknown_iv() {
         let lua = setup_lua([1, 2, 3, 4]).unwrap();
@@ -19,6 +19,8 @@ pub use string::*;
 pub use vector::*;
 pub use video::*;
 
+use std::fmt;
+
 // #[derive(Debug, Clone)]
 // pub enum WzPropertyType {
 //   Null,
@@ -44,6 +46,7 @@ pub use video::*;
 /// A WzProperty potentially contains childrens.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(tag = "type", content = "data"))]
 pub enum WzSubProperty {
     Convex,
@@ -55,8 +58,9 @@ pub enum WzSubProperty {
 
 /// Some basic value, more like a primitive type.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(tag = "type", content = "data"))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum WzValue {
     #[cfg_attr(feature = "serde", serde(skip))]
     RawData(WzRawData),
@@ -75,12 +79,225 @@ pub enum WzValue {
     String(WzString),
     ParsedString(String),
     #[cfg_attr(feature = "serde", serde(other))]
+    #[default]
     Null,
 }
 
-impl Default for WzValue {
-    fn default() -> Self {
-        Self::Null
+impl WzValue {
+    /// The variant name of the value, like `"Int"` or `"String"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            WzValue::RawData(_) => "RawData",
+            WzValue::Video(_) => "Video",
+            WzValue::Lua(_) => "Lua",
+            WzValue::Short(_) => "Short",
+            WzValue::Int(_) => "Int",
+            WzValue::Long(_) => "Long",
+            WzValue::Float(_) => "Float",
+            WzValue::Double(_) => "Double",
+            WzValue::Vector(_) => "Vector",
+            WzValue::UOL(_) => "UOL",
+            WzValue::String(_) | WzValue::ParsedString(_) => "String",
+            WzValue::Null => "Null",
+        }
+    }
+
+    /// Decode the string value, returns `None` for non-string value.
+    fn get_str(&self) -> Option<String> {
+        match self {
+            WzValue::String(string) => string.get_string().ok(),
+            WzValue::ParsedString(string) => Some(string.clone()),
+            _ => None,
+        }
+    }
+
+    /// Coerce number value or numeric string to `i64`, float will be truncated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzValue;
+    /// assert_eq!(WzValue::Short(1).as_i64(), Some(1));
+    /// assert_eq!(WzValue::Double(1.5).as_i64(), Some(1));
+    /// assert_eq!(WzValue::ParsedString("10".to_string()).as_i64(), Some(10));
+    /// assert_eq!(WzValue::Null.as_i64(), None);
+    /// ```
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            WzValue::Short(value) => Some(*value as i64),
+            WzValue::Int(value) => Some(*value as i64),
+            WzValue::Long(value) => Some(*value),
+            WzValue::Float(value) => Some(*value as i64),
+            WzValue::Double(value) => Some(*value as i64),
+            _ => {
+                let string = self.get_str()?;
+                let string = string.trim();
+                string
+                    .parse()
+                    .ok()
+                    .or_else(|| string.parse::<f64>().ok().map(|value| value as i64))
+            }
+        }
+    }
+
+    /// Coerce number value or numeric string to `f64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzValue;
+    /// assert_eq!(WzValue::Int(1).as_f64(), Some(1.0));
+    /// assert_eq!(WzValue::ParsedString("0.5".to_string()).as_f64(), Some(0.5));
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            WzValue::Short(value) => Some(*value as f64),
+            WzValue::Int(value) => Some(*value as f64),
+            WzValue::Long(value) => Some(*value as f64),
+            WzValue::Float(value) => Some(*value as f64),
+            WzValue::Double(value) => Some(*value),
+            _ => self.get_str()?.trim().parse().ok(),
+        }
+    }
+
+    /// Coerce number value or string to `bool`, non-zero number and `"true"` is `true`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzValue;
+    /// assert_eq!(WzValue::Int(1).as_bool(), Some(true));
+    /// assert_eq!(WzValue::Short(0).as_bool(), Some(false));
+    /// assert_eq!(WzValue::ParsedString("true".to_string()).as_bool(), Some(true));
+    /// ```
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Some(string) = self.get_str() {
+            match string.trim() {
+                "true" => return Some(true),
+                "false" => return Some(false),
+                _ => {}
+            }
+        }
+        self.as_f64().map(|value| value != 0.0)
+    }
+}
+
+impl fmt::Display for WzSubProperty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WzSubProperty::Convex => write!(f, "Convex"),
+            WzSubProperty::Property => write!(f, "Property"),
+            WzSubProperty::PNG(png) => write!(f, "PNG({}x{})", png.width, png.height),
+            WzSubProperty::Sound(sound) => {
+                write!(f, "Sound({:?}, {}ms)", sound.sound_type, sound.duration)
+            }
+        }
+    }
+}
+
+/// Display the value itself, binary data like lua or video only show a placeholder.
+impl fmt::Display for WzValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WzValue::RawData(_) => write!(f, "<raw data>"),
+            WzValue::Video(_) => write!(f, "<video>"),
+            WzValue::Lua(_) => write!(f, "<lua>"),
+            WzValue::Short(value) => write!(f, "{value}"),
+            WzValue::Int(value) => write!(f, "{value}"),
+            WzValue::Long(value) => write!(f, "{value}"),
+            WzValue::Float(value) => write!(f, "{value}"),
+            WzValue::Double(value) => write!(f, "{value}"),
+            WzValue::Vector(vec) => write!(f, "{vec}"),
+            WzValue::UOL(string) | WzValue::String(string) => match string.get_string() {
+                Ok(string) => write!(f, "{string}"),
+                Err(_) => write!(f, "<invalid string>"),
+            },
+            WzValue::ParsedString(string) => write!(f, "{string}"),
+            WzValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Compare a float with an integer exactly, `i64` can't always fit in `f64` so casting either side is lossy.
+fn float_eq_int(float: f64, int: i64) -> bool {
+    /* `i64::MAX as f64` is rounded up to 2^63 which is out of range, so the upper bound is exclusive */
+    float.fract() == 0.0
+        && float >= i64::MIN as f64
+        && float < i64::MAX as f64
+        && float as i64 == int
+}
+
+/// Numeric value can compare with any number type, integer value compare exactly.
+macro_rules! impl_partial_eq_int {
+    ($($t:ty),*) => {
+        $(
+            impl PartialEq<$t> for WzValue {
+                fn eq(&self, other: &$t) -> bool {
+                    match self {
+                        WzValue::Short(value) => *value as i64 == *other as i64,
+                        WzValue::Int(value) => *value as i64 == *other as i64,
+                        WzValue::Long(value) => *value == *other as i64,
+                        WzValue::Float(value) => float_eq_int(*value as f64, *other as i64),
+                        WzValue::Double(value) => float_eq_int(*value, *other as i64),
+                        _ => false,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_partial_eq_float {
+    ($($t:ty),*) => {
+        $(
+            impl PartialEq<$t> for WzValue {
+                fn eq(&self, other: &$t) -> bool {
+                    match self {
+                        WzValue::Short(value) => *value as f64 == *other as f64,
+                        WzValue::Int(value) => *value as f64 == *other as f64,
+                        WzValue::Long(value) => float_eq_int(*other as f64, *value),
+                        WzValue::Float(value) => *value as f64 == *other as f64,
+                        WzValue::Double(value) => *value == *other as f64,
+                        _ => false,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_partial_eq_int!(i16, i32, i64);
+impl_partial_eq_float!(f32, f64);
+
+/// Only `WzValue::String` and `WzValue::ParsedString` can equal to a string, UOL won't.
+impl PartialEq<str> for WzValue {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            WzValue::String(string) => string.get_string().is_ok_and(|s| s == other),
+            WzValue::ParsedString(string) => string == other,
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<&str> for WzValue {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for WzValue {
+    #[inline]
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Vector2D> for WzValue {
+    #[inline]
+    fn eq(&self, other: &Vector2D) -> bool {
+        matches!(self, WzValue::Vector(vec) if vec == other)
     }
 }
 
@@ -116,14 +333,11 @@ impl From<WzValue> for Value {
 mod test {
     use super::*;
 
-    #[cfg(feature = "serde")]
-    use serde_json;
-
     #[cfg(feature = "serde")]
     #[test]
     fn test_serialize_wz_sub_property() {
-        let png = WzSubProperty::PNG(Box::new(WzPng::default()));
-        let sound = WzSubProperty::Sound(Box::new(WzSound::default()));
+        let png = WzSubProperty::PNG(Box::default());
+        let sound = WzSubProperty::Sound(Box::default());
         let property = WzSubProperty::Property;
         let convex = WzSubProperty::Convex;
 
@@ -309,3 +523,78 @@ mod test {
         assert_eq!(parsed_string_json, Value::String("string".to_string()));
     }
 }
+
+#[cfg(test)]
+mod value_test {
+    use super::*;
+
+    #[test]
+    fn test_wz_value_coercion() {
+        let string = WzValue::String(WzString::from_str(" 12 ", [0; 4]));
+
+        assert_eq!(string.as_i64(), Some(12));
+        assert_eq!(string.as_f64(), Some(12.0));
+        assert_eq!(string.as_bool(), Some(true));
+
+        assert_eq!(WzValue::Long(i64::MAX).as_i64(), Some(i64::MAX));
+        assert_eq!(WzValue::Float(2.5).as_f64(), Some(2.5));
+        assert_eq!(WzValue::ParsedString("1.9".to_string()).as_i64(), Some(1));
+        assert_eq!(
+            WzValue::ParsedString("false".to_string()).as_bool(),
+            Some(false)
+        );
+        assert_eq!(WzValue::ParsedString("abc".to_string()).as_bool(), None);
+        assert_eq!(WzValue::Vector(Vector2D(1, 1)).as_i64(), None);
+        assert_eq!(WzValue::Null.as_bool(), None);
+    }
+
+    #[test]
+    fn test_wz_value_partial_eq() {
+        assert_eq!(WzValue::Short(5), 5);
+        assert_eq!(WzValue::Int(5), 5_i16);
+        assert_eq!(WzValue::Long(5), 5_i32);
+        assert_eq!(WzValue::Int(5), 5.0);
+        assert_eq!(WzValue::Float(1.5), 1.5_f64);
+        assert_eq!(WzValue::Double(2.0), 2);
+        assert_ne!(WzValue::Double(2.5), 2);
+        assert_ne!(WzValue::Long(i64::MAX), -1_i32);
+        assert_ne!(WzValue::Long(i64::MAX), i64::MAX as f64);
+        assert_ne!(WzValue::Long(i64::MAX - 1), i64::MAX as f64);
+        assert_eq!(WzValue::Long(1 << 62), (1_i64 << 62) as f64);
+        assert_ne!(WzValue::Double(i64::MAX as f64), i64::MAX);
+        assert_eq!(WzValue::Double(-4.0), -4_i64);
+
+        assert_eq!(WzValue::String(WzString::from_str("foo", [0; 4])), "foo");
+        assert_eq!(WzValue::ParsedString("foo".to_string()), "foo".to_string());
+        assert_ne!(WzValue::UOL(WzString::from_str("foo", [0; 4])), "foo");
+        assert_ne!(WzValue::ParsedString("5".to_string()), 5);
+        assert_ne!(WzValue::Int(5), "5");
+
+        assert_eq!(WzValue::Vector(Vector2D(1, 2)), Vector2D(1, 2));
+        assert_ne!(WzValue::Null, 0);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut png = WzPng::default();
+        png.width = 10;
+        png.height = 20;
+
+        assert_eq!(WzValue::Int(1).to_string(), "1");
+        assert_eq!(WzValue::Double(1.5).to_string(), "1.5");
+        assert_eq!(WzValue::Vector(Vector2D(1, 2)).to_string(), "(1, 2)");
+        assert_eq!(
+            WzValue::UOL(WzString::from_str("../1", [0; 4])).to_string(),
+            "../1"
+        );
+        assert_eq!(WzValue::ParsedString("foo".into()).to_string(), "foo");
+        assert_eq!(WzValue::Null.to_string(), "null");
+
+        assert_eq!(WzSubProperty::PNG(Box::new(png)).to_string(), "PNG(10x20)");
+        assert_eq!(
+            WzSubProperty::Sound(Box::default()).to_string(),
+            "Sound(Binary, 0ms)"
+        );
+        assert_eq!(WzSubProperty::Property.to_string(), "Property");
+    }
+}
@@ -1,15 +1,21 @@
-use crate::property::string::resolve_string_from_node;
-use crate::util::color::{SimpleColor, SimpleColorAlpha};
+use crate::reader::{self, DataSource};
+#[cfg(feature = "image")]
 use crate::{
-    property::WzSubProperty,
-    reader::{self, Reader},
+    property::{string::resolve_string_from_node, WzSubProperty},
+    util::color::{SimpleColor, SimpleColorAlpha},
     util::node_util,
     WzNodeArc, WzObjectType,
 };
-use flate2::{Decompress, FlushDecompress};
+#[cfg(feature = "image")]
+use flate2::{write::ZlibEncoder, Compression};
+use flate2::{Decompress, FlushDecompress, Status};
+#[cfg(feature = "image")]
 use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
-#[cfg(feature = "rayon")]
+#[cfg(all(feature = "image", feature = "rayon"))]
 use rayon::prelude::*;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::Write;
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -24,12 +30,22 @@ pub enum WzPngParseError {
     #[error("Unknown format: {0}")]
     UnknownFormat(u32),
 
+    #[error("Format {0} is not supported for encoding")]
+    UnsupportedEncodeFormat(u32),
+
+    #[error("Format {0} can't encode a {1}x{2} image")]
+    UnsupportedEncodeSize(u32, u32, u32),
+
+    #[error("deflate raw data failed")]
+    DeflateError(#[from] std::io::Error),
+
     #[error("Unsupported header: {0}")]
     UnsupportedHeader(i32),
 
     #[error("Error reading color: {0}")]
     ReadColorError(#[from] reader::Error),
 
+    #[cfg(feature = "image")]
     #[error(transparent)]
     SaveError(#[from] image::ImageError),
 
@@ -38,13 +54,76 @@ pub enum WzPngParseError {
 
     #[error("Not a PNG property")]
     NotPngProperty,
+
+    #[error("Unknown z layer: {0}")]
+    UnknownZLayer(String),
+}
+
+/// The native pixel layout of a canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WzPixelFormat {
+    /// format `1`, 2 bytes per pixel.
+    Bgra4444,
+    /// format `2`, 4 bytes per pixel.
+    Bgra8888,
+    /// format `257`, 2 bytes per pixel.
+    Argb1555,
+    /// format `513` and `517`(expanded from the 16x16 blocks), 2 bytes per pixel.
+    Rgb565,
+    /// format `3` and `1026`, 16 bytes per 4x4 block.
+    Dxt3,
+    /// format `2050`, 16 bytes per 4x4 block.
+    Dxt5,
+}
+
+impl WzPixelFormat {
+    /// Get the pixel format by the sum of `format1` and `format2`.
+    pub fn from_format(format: u32) -> Option<Self> {
+        match format {
+            1 => Some(WzPixelFormat::Bgra4444),
+            2 => Some(WzPixelFormat::Bgra8888),
+            257 => Some(WzPixelFormat::Argb1555),
+            513 | 517 => Some(WzPixelFormat::Rgb565),
+            3 | 1026 => Some(WzPixelFormat::Dxt3),
+            2050 => Some(WzPixelFormat::Dxt5),
+            _ => None,
+        }
+    }
+    /// Whether it's a block compressed format.
+    #[inline]
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, WzPixelFormat::Dxt3 | WzPixelFormat::Dxt5)
+    }
+}
+
+/// The decoded pixel data of a canvas in it's native format, see `WzPng::extract_raw_pixels`.
+#[derive(Debug, Clone)]
+pub struct WzRawPixels {
+    pub format: WzPixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
 }
 
+#[cfg(feature = "image")]
 type ImageBufferRgbaChunk = ImageBuffer<Rgba<u8>, Vec<u8>>;
+#[cfg(feature = "image")]
 type ImageBufferRgbChunk = ImageBuffer<Rgb<u8>, Vec<u8>>;
 
 /// A helper get image from `WzNodeArc`, will also resolve `_inlink` or `_outlink`
+#[cfg(feature = "image")]
 pub fn get_image(node: &WzNodeArc) -> Result<DynamicImage, WzPngParseError> {
+    get_image_inner(node, false)
+}
+
+/// Same as `get_image`, but decoded by `WzPng::extract_png_premultiplied`.
+#[cfg(feature = "image")]
+pub fn get_image_premultiplied(node: &WzNodeArc) -> Result<DynamicImage, WzPngParseError> {
+    get_image_inner(node, true)
+}
+
+#[cfg(feature = "image")]
+fn get_image_inner(node: &WzNodeArc, premultiplied: bool) -> Result<DynamicImage, WzPngParseError> {
     let node_read = node.read().unwrap();
     match &node_read.object_type {
         WzObjectType::Property(WzSubProperty::PNG(png)) => {
@@ -54,7 +133,7 @@ pub fn get_image(node: &WzNodeArc) -> Result<DynamicImage, WzPngParseError> {
                 .and_then(|inlink| node_util::resolve_inlink(&inlink, node));
 
             if let Some(target) = inlink_target {
-                return get_image(&target);
+                return get_image_inner(&target, premultiplied);
             }
 
             let outlink_target = node_read
@@ -63,20 +142,26 @@ pub fn get_image(node: &WzNodeArc) -> Result<DynamicImage, WzPngParseError> {
                 .and_then(|outlink| node_util::resolve_outlink(&outlink, node, true));
 
             if let Some(target) = outlink_target {
-                return get_image(&target);
+                return get_image_inner(&target, premultiplied);
             }
 
-            png.extract_png()
+            png.decode(premultiplied)
         }
         _ => Err(WzPngParseError::NotPngProperty),
     }
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Default)]
-pub struct WzPng {
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(rename = "WzPng", bound = ""))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "R: Default"))
+)]
+#[derive(Debug, Default)]
+pub struct WzPng<R: ?Sized = reader::WzReader> {
     #[cfg_attr(feature = "serde", serde(skip))]
-    reader: Arc<reader::WzReader>,
+    reader: Arc<R>,
     #[cfg_attr(feature = "serde", serde(skip))]
     offset: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -92,15 +177,68 @@ pub struct WzPng {
     pub height: u32,
 }
 
+impl<R: ?Sized> Clone for WzPng<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            block_size: self.block_size,
+            format1: self.format1,
+            format2: self.format2,
+            header: self.header,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+#[cfg(feature = "image")]
 impl WzPng {
+    /// Create a `WzPng` from image, the pixels will be converted to the wz pixel `format` and compressed with zlib.
+    /// Supported formats are `1`(BGRA4444), `2`(BGRA8888), `257`(ARGB1555), `513`(RGB565),
+    /// `517`(RGB565 per 16x16 block), `1026`(DXT3) and `2050`(DXT5).
+    ///
+    /// The DXT formats need the size to be multiple of 4, and `517` multiple of 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzPng;
+    /// let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])).into();
+    ///
+    /// let png = WzPng::from_image(&image, 2).unwrap();
+    ///
+    /// assert_eq!(png.extract_png().unwrap(), image);
+    /// ```
+    pub fn from_image(image: &DynamicImage, format: u32) -> Result<WzPng, WzPngParseError> {
+        let pixels = encode_pixels(&image.to_rgba8(), format)?;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&pixels)?;
+        let compressed = encoder.finish()?;
+
+        let header = u16::from_le_bytes([compressed[0], compressed[1]]) as i32;
+        let reader = Arc::new(reader::WzReader::from_buff(&compressed));
+
+        Ok(WzPng::new(
+            &reader,
+            (image.width(), image.height()),
+            (format, 0),
+            (0, compressed.len()),
+            header,
+        ))
+    }
+}
+
+impl<R: DataSource + ?Sized> WzPng<R> {
     pub fn new(
-        reader: &Arc<reader::WzReader>,
+        reader: &Arc<R>,
         size: (u32, u32),
         format: (u32, u32),
         data_range: (usize, usize),
         header: i32,
-    ) -> WzPng {
-        WzPng {
+    ) -> Self {
+        Self {
             reader: Arc::clone(reader),
             offset: data_range.0,
             block_size: data_range.1,
@@ -111,6 +249,35 @@ impl WzPng {
             header,
         }
     }
+    /// The compressed pixel data, might be encrypted when it's not start with zlib header.
+    #[inline]
+    pub fn get_raw_buffer(&self) -> &[u8] {
+        self.reader.get_slice(self.get_buffer_range())
+    }
+    /// The iv of the wz file this png belongs to.
+    #[inline]
+    pub fn get_iv(&self) -> [u8; 4] {
+        self.reader.wz_iv()
+    }
+    #[inline]
+    pub fn get_format_pair(&self) -> (u32, u32) {
+        (self.format1, self.format2)
+    }
+    /// The offset of the compressed pixel data in the wz file(or the image file).
+    #[inline]
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    /// The size of the compressed pixel data.
+    #[inline]
+    pub fn get_block_size(&self) -> usize {
+        self.block_size
+    }
+    /// The range of the compressed pixel data in the wz file.
+    #[inline]
+    pub fn get_buffer_range(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.block_size
+    }
     #[inline]
     pub fn format(&self) -> u32 {
         self.format1 + self.format2
@@ -122,27 +289,82 @@ impl WzPng {
             || self.header == 0x0178
             || self.header == 0x5E78
     }
+    #[cfg(feature = "image")]
     pub fn extract_png(&self) -> Result<DynamicImage, WzPngParseError> {
-        let data = self
-            .reader
-            .get_slice(self.offset..(self.offset + self.block_size));
+        self.decode(false)
+    }
+    /// Same as `extract_png`, but the color is multiplied by alpha during the pixel converting,
+    /// for the renderers using premultiplied alpha. The formats without alpha are the same as `extract_png`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzPng;
+    /// let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 100, 0, 128])).into();
+    /// let png = WzPng::from_image(&image, 2).unwrap();
+    ///
+    /// let image = png.extract_png_premultiplied().unwrap();
+    ///
+    /// assert_eq!(image.to_rgba8().get_pixel(0, 0).0, [128, 50, 0, 128]);
+    /// ```
+    #[cfg(feature = "image")]
+    pub fn extract_png_premultiplied(&self) -> Result<DynamicImage, WzPngParseError> {
+        self.decode(true)
+    }
+    #[cfg(feature = "image")]
+    fn decode(&self, premultiplied: bool) -> Result<DynamicImage, WzPngParseError> {
         /* decompress */
-        let pixels = self.get_raw_data(data)?;
+        let pixels = self.get_raw_data()?;
+        let (width, height) = (self.width, self.height);
 
         match self.format() {
-            1 => get_image_from_bgra4444(pixels, self.width, self.height),
-            2 => get_image_from_bgra8888(pixels, self.width, self.height),
-            3 | 1026 => get_image_from_dxt3(&pixels, self.width, self.height),
-            257 => get_image_from_argb1555(&pixels, self.width, self.height),
-            513 => get_image_from_rgb565(&pixels, self.width, self.height),
+            1 => get_image_from_bgra4444(pixels, width, height, premultiplied),
+            2 => get_image_from_bgra8888(pixels, width, height, premultiplied),
+            3 | 1026 => get_image_from_dxt3(&pixels, width, height, premultiplied),
+            257 => get_image_from_argb1555(&pixels, width, height, premultiplied),
+            513 => get_image_from_rgb565(&pixels, width, height),
             517 => {
-                let decoded = get_pixel_data_form_517(&pixels, self.width, self.height);
-                get_image_from_rgb565(&decoded, self.width, self.height)
+                let decoded = get_pixel_data_form_517(&pixels, width, height);
+                get_image_from_rgb565(&decoded, width, height)
             }
-            2050 => get_image_from_dxt5(&pixels, self.width, self.height),
+            2050 => get_image_from_dxt5(&pixels, width, height, premultiplied),
             _ => Err(WzPngParseError::UnknownFormat(self.format())),
         }
     }
+    /// Get the inflated pixel data without converting to rgba, the DXT blocks are kept compressed,
+    /// so it can be uploaded to the GPU directly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::{WzPng, WzPixelFormat};
+    /// let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])).into();
+    /// let png = WzPng::from_image(&image, 2).unwrap();
+    ///
+    /// let pixels = png.extract_raw_pixels().unwrap();
+    ///
+    /// assert_eq!(pixels.format, WzPixelFormat::Bgra8888);
+    /// assert_eq!(pixels.data, [0, 0, 255, 255].repeat(4));
+    /// ```
+    pub fn extract_raw_pixels(&self) -> Result<WzRawPixels, WzPngParseError> {
+        let format = WzPixelFormat::from_format(self.format())
+            .ok_or(WzPngParseError::UnknownFormat(self.format()))?;
+
+        let pixels = self.get_raw_data()?;
+
+        let data = if self.format() == 517 {
+            get_pixel_data_form_517(&pixels, self.width, self.height)
+        } else {
+            pixels
+        };
+
+        Ok(WzRawPixels {
+            format,
+            width: self.width,
+            height: self.height,
+            data,
+        })
+    }
     fn get_buff_size(&self) -> Result<usize, WzPngParseError> {
         match self.format() {
             1 | 257 | 513 => Ok((self.width * self.height * 2) as usize),
@@ -156,60 +378,340 @@ impl WzPng {
             _ => Err(WzPngParseError::UnknownFormat(self.format())),
         }
     }
-    fn get_raw_data(&self, data: &[u8]) -> Result<Vec<u8>, WzPngParseError> {
+    fn get_raw_data(&self) -> Result<Vec<u8>, WzPngParseError> {
         let capacity = self.get_buff_size()?;
 
+        let (with_header, data) = self.get_compressed_data()?;
+
+        inflate(with_header, &data, capacity)
+    }
+    /// Inflate the pixel data into the `writer` chunk by chunk, so the whole pixel data won't be hold in memory.
+    /// Returns the size of the inflated data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzPng;
+    /// let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])).into();
+    /// let png = WzPng::from_image(&image, 2).unwrap();
+    ///
+    /// let mut pixels = Vec::new();
+    /// let size = png.inflate_to(&mut pixels).unwrap();
+    ///
+    /// assert_eq!(size, 2 * 2 * 4);
+    /// ```
+    pub fn inflate_to<W: Write>(&self, writer: &mut W) -> Result<u64, WzPngParseError> {
+        let (with_header, data) = self.get_compressed_data()?;
+
+        with_decompressor(with_header, |decompressor| {
+            let mut chunk = vec![0; INFLATE_CHUNK_SIZE];
+
+            loop {
+                let consumed = decompressor.total_in() as usize;
+                let before_out = decompressor.total_out();
+
+                let status = decompressor.decompress(
+                    &data[consumed..],
+                    &mut chunk,
+                    FlushDecompress::None,
+                )?;
+
+                let written = (decompressor.total_out() - before_out) as usize;
+                writer.write_all(&chunk[..written])?;
+
+                let no_progress = written == 0 && decompressor.total_in() as usize == consumed;
+                if status == Status::StreamEnd || no_progress {
+                    break;
+                }
+            }
+
+            Ok(decompressor.total_out())
+        })
+    }
+    /// The zlib stream of the pixel data, decrypted if it's stored in encrypted chunks,
+    /// so it can be copied to other place without decoding and re-encoding.
+    ///
+    /// # Errors
+    /// `ReadColorError` when the size of a encrypted chunk is broken.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzPng;
+    /// let image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])).into();
+    /// let png = WzPng::from_image(&image, 2).unwrap();
+    ///
+    /// assert_eq!(png.raw_compressed().unwrap(), png.get_raw_buffer());
+    /// ```
+    pub fn raw_compressed(&self) -> Result<Cow<'_, [u8]>, WzPngParseError> {
+        let data = self.get_raw_buffer();
+
         if self.has_zlib_header() {
-            inflate(true, data, capacity)
-        } else {
-            let mut keys = self.reader.keys.write().unwrap();
+            return Ok(Cow::Borrowed(data));
+        }
+
+        let mut keys = self.reader.keys().write().unwrap();
+
+        let total_end = self.offset + self.block_size;
 
-            let total_end = self.offset + self.block_size;
+        let mut offset = self.offset;
+        let mut end = 0;
 
-            let mut offset = self.offset;
-            let mut end = 0;
+        let mut decrypted = Vec::with_capacity(self.block_size);
 
-            let mut decrypted = Vec::with_capacity(self.block_size);
+        while offset < total_end {
+            let block_size = self.reader.read_i32_at(offset)? as usize;
+            offset += 4;
 
-            while offset < total_end {
-                let block_size = self.reader.read_i32_at(offset).unwrap() as usize;
-                offset += 4;
+            if offset + block_size > total_end {
+                return Err(
+                    reader::Error::OutOfBounds(offset, offset + block_size, total_end).into(),
+                );
+            }
+
+            let data = self.reader.get_slice(offset..(offset + block_size));
+            offset += block_size;
 
-                let data = self.reader.get_slice(offset..(offset + block_size));
-                offset += block_size;
+            decrypted.extend_from_slice(data);
 
-                decrypted.extend_from_slice(data);
+            keys.ensure_key_size(data.len()).unwrap();
 
-                keys.ensure_key_size(data.len()).unwrap();
+            keys.decrypt_slice(&mut decrypted[end..(end + block_size)]);
 
-                keys.decrypt_slice(&mut decrypted[end..(end + block_size)]);
+            end += block_size;
+        }
 
-                end += block_size;
+        Ok(Cow::Owned(decrypted))
+    }
+    fn get_compressed_data(&self) -> Result<(bool, Cow<'_, [u8]>), WzPngParseError> {
+        Ok(match self.raw_compressed()? {
+            Cow::Borrowed(data) => (true, Cow::Borrowed(data)),
+            Cow::Owned(mut decrypted) => {
+                /* the total chunk shoud start decryption at index 2 */
+                decrypted.drain(..2.min(decrypted.len()));
+                (false, Cow::Owned(decrypted))
             }
+        })
+    }
+}
+
+/// Convert rgba pixels to the wz pixel format.
+#[cfg(feature = "image")]
+fn encode_pixels(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    format: u32,
+) -> Result<Vec<u8>, WzPngParseError> {
+    let (width, height) = image.dimensions();
+    let block_size = match format {
+        517 => 16,
+        1026 | 2050 => 4,
+        _ => 1,
+    };
+
+    if width % block_size != 0 || height % block_size != 0 {
+        return Err(WzPngParseError::UnsupportedEncodeSize(
+            format, width, height,
+        ));
+    }
+
+    let pixels = image.pixels();
+
+    let data = match format {
+        1 => pixels
+            .flat_map(|Rgba([r, g, b, a])| [(g & 0xF0) | (b >> 4), (a & 0xF0) | (r >> 4)])
+            .collect(),
+        2 => pixels
+            .flat_map(|Rgba([r, g, b, a])| [*b, *g, *r, *a])
+            .collect(),
+        257 => pixels
+            .flat_map(|Rgba([r, g, b, a])| {
+                let alpha = if *a >= 0x80 { 0x8000 } else { 0 };
+                let color =
+                    alpha | ((*r as u16 >> 3) << 10) | ((*g as u16 >> 3) << 5) | (*b as u16 >> 3);
+                color.to_le_bytes()
+            })
+            .collect(),
+        513 => pixels
+            .flat_map(|Rgba([r, g, b, _])| to_rgb565([*r, *g, *b]).to_le_bytes())
+            .collect(),
+        517 => encode_517(image),
+        1026 => encode_dxt(image, encode_alpha_dxt3),
+        2050 => encode_dxt(image, encode_alpha_dxt5),
+        _ => return Err(WzPngParseError::UnsupportedEncodeFormat(format)),
+    };
+
+    Ok(data)
+}
+
+#[cfg(feature = "image")]
+#[inline]
+fn to_rgb565([r, g, b]: [u8; 3]) -> u16 {
+    ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3)
+}
+
+/// Average every 16x16 block into a single rgb565 color, the reverse of `get_pixel_data_form_517`.
+#[cfg(feature = "image")]
+fn encode_517(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut data = Vec::with_capacity((width * height / 128) as usize);
+
+    for y in (0..height).step_by(16) {
+        for x in (0..width).step_by(16) {
+            let mut sum = [0u32; 3];
+            for j in 0..16 {
+                for i in 0..16 {
+                    let Rgba([r, g, b, _]) = image.get_pixel(x + i, y + j);
+                    sum[0] += *r as u32;
+                    sum[1] += *g as u32;
+                    sum[2] += *b as u32;
+                }
+            }
+            let average = sum.map(|c| ((c + 128) / 256) as u8);
+            data.extend_from_slice(&to_rgb565(average).to_le_bytes());
+        }
+    }
+
+    data
+}
 
-            /* the total chunk shoud start decryption at index 2 */
-            inflate(false, &decrypted[2..], capacity)
+/// Compress the image into 4x4 blocks, every block is 8 bytes of alpha from `encode_alpha`
+/// followed by 8 bytes of color, the same layout `get_image_from_dxt3` and `get_image_from_dxt5` read.
+#[cfg(feature = "image")]
+fn encode_dxt(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    encode_alpha: fn(&[Rgba<u8>; 16]) -> [u8; 8],
+) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut data = Vec::with_capacity((width * height) as usize);
+
+    for y in (0..height).step_by(4) {
+        for x in (0..width).step_by(4) {
+            let block: [Rgba<u8>; 16] =
+                std::array::from_fn(|i| *image.get_pixel(x + i as u32 % 4, y + i as u32 / 4));
+
+            data.extend_from_slice(&encode_alpha(&block));
+            data.extend_from_slice(&encode_color_block(&block));
+        }
+    }
+
+    data
+}
+
+/// Use the bounding box of the block as the two end colors, then pick the nearest color of the table for every pixel.
+#[cfg(feature = "image")]
+fn encode_color_block(block: &[Rgba<u8>; 16]) -> [u8; 8] {
+    let mut min = [u8::MAX; 3];
+    let mut max = [u8::MIN; 3];
+    for Rgba(pixel) in block {
+        for c in 0..3 {
+            min[c] = min[c].min(pixel[c]);
+            max[c] = max[c].max(pixel[c]);
         }
     }
+
+    /* c0 >= c1 here, so it's always the 4 colors mode unless the whole block is one color */
+    let c0 = to_rgb565(max);
+    let c1 = to_rgb565(min);
+    let color_table = create_color_table(c0, c1);
+
+    let mut indices = 0u32;
+    for (i, Rgba([r, g, b, _])) in block.iter().enumerate() {
+        let distance = |color: &Rgb<u8>| {
+            let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            d(*r, color.r()) + d(*g, color.g()) + d(*b, color.b())
+        };
+        let index = (0..4).min_by_key(|&j| distance(&color_table[j])).unwrap();
+        indices |= (index as u32) << (i * 2);
+    }
+
+    let mut encoded = [0u8; 8];
+    encoded[0..2].copy_from_slice(&c0.to_le_bytes());
+    encoded[2..4].copy_from_slice(&c1.to_le_bytes());
+    encoded[4..8].copy_from_slice(&indices.to_le_bytes());
+    encoded
+}
+
+/// 4 bits alpha for each pixel.
+#[cfg(feature = "image")]
+fn encode_alpha_dxt3(block: &[Rgba<u8>; 16]) -> [u8; 8] {
+    let mut encoded = [0u8; 8];
+    for (i, Rgba([_, _, _, a])) in block.iter().enumerate() {
+        let alpha = ((*a as u16 * 15 + 127) / 255) as u8;
+        encoded[i / 2] |= alpha << (i % 2 * 4);
+    }
+    encoded
+}
+
+/// The max and min alpha of the block, then 3 bits index to the interpolated alpha table for each pixel.
+#[cfg(feature = "image")]
+fn encode_alpha_dxt5(block: &[Rgba<u8>; 16]) -> [u8; 8] {
+    let alphas = block.map(|Rgba([_, _, _, a])| a);
+    let a0 = *alphas.iter().max().unwrap();
+    let a1 = *alphas.iter().min().unwrap();
+    let alpha_table = create_alpha_table_dxt5(a0, a1);
+
+    let mut indices = 0u64;
+    for (i, a) in alphas.iter().enumerate() {
+        let index = (0..8)
+            .min_by_key(|&j| (alpha_table[j] as i16 - *a as i16).abs())
+            .unwrap();
+        indices |= (index as u64) << (i * 3);
+    }
+
+    let mut encoded = [0u8; 8];
+    encoded[0] = a0;
+    encoded[1] = a1;
+    encoded[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+    encoded
+}
+
+const INFLATE_CHUNK_SIZE: usize = 0x8000;
+
+thread_local! {
+    /// reused decompressors, index 0 for raw deflate and 1 for zlib
+    static DECOMPRESSORS: RefCell<[Option<Decompress>; 2]> = const { RefCell::new([None, None]) };
+}
+
+/// Run `f` with a per thread decompressor, so extracting many canvases won't create a new one every time.
+fn with_decompressor<R>(with_header: bool, f: impl FnOnce(&mut Decompress) -> R) -> R {
+    let index = with_header as usize;
+
+    /* take it out, so nested call will just create a new one */
+    let mut decompressor = DECOMPRESSORS
+        .with(|pool| pool.borrow_mut()[index].take())
+        .map(|mut decompressor| {
+            decompressor.reset(with_header);
+            decompressor
+        })
+        .unwrap_or_else(|| Decompress::new(with_header));
+
+    let result = f(&mut decompressor);
+
+    DECOMPRESSORS.with(|pool| pool.borrow_mut()[index] = Some(decompressor));
+
+    result
 }
 
 #[inline]
 fn inflate(with_header: bool, data: &[u8], capacity: usize) -> Result<Vec<u8>, WzPngParseError> {
-    let mut deflater = Decompress::new(with_header);
-    let mut result = Vec::with_capacity(capacity);
+    with_decompressor(with_header, |deflater| {
+        let mut result = Vec::with_capacity(capacity);
 
-    if let Err(e) = deflater.decompress_vec(data, &mut result, FlushDecompress::Sync) {
-        return Err(WzPngParseError::from(e));
-    };
+        if let Err(e) = deflater.decompress_vec(data, &mut result, FlushDecompress::Sync) {
+            return Err(WzPngParseError::from(e));
+        };
 
-    Ok(result)
+        Ok(result)
+    })
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn get_image_from_bgra4444(
     raw_data: Vec<u8>,
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     #[cfg(feature = "rayon")]
     let imgbuffer = image::ImageBuffer::from_par_fn(width, height, |x, y| {
@@ -230,7 +732,7 @@ fn get_image_from_bgra4444(
         let a = pixel & 0xF0;
         let a = a | (a >> 4);
 
-        image::Rgba([r, g, b, a])
+        to_rgba([r, g, b, a], premultiplied)
     });
     #[cfg(not(feature = "rayon"))]
     let imgbuffer = image::ImageBuffer::from_fn(width, height, |x, y| {
@@ -251,17 +753,18 @@ fn get_image_from_bgra4444(
         let a = pixel & 0xF0;
         let a = a | (a >> 4);
 
-        image::Rgba([r, g, b, a])
+        to_rgba([r, g, b, a], premultiplied)
     });
 
     Ok(imgbuffer.into())
 }
 
-#[cfg(feature = "rayon")]
+#[cfg(all(feature = "image", feature = "rayon"))]
 fn get_image_from_dxt3(
     raw_data: &[u8],
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     let image_buffer_chunks = raw_data
         .par_chunks(16)
@@ -287,7 +790,7 @@ fn get_image_from_dxt3(
                         img_buffer.put_pixel(
                             x,
                             y,
-                            image::Rgba([color.r(), color.g(), color.b(), alpha]),
+                            to_rgba([color.r(), color.g(), color.b(), alpha], premultiplied),
                         );
                     }
                 }
@@ -309,11 +812,12 @@ fn get_image_from_dxt3(
     Ok(img_buffer.into())
 }
 
-#[cfg(not(feature = "rayon"))]
+#[cfg(all(feature = "image", not(feature = "rayon")))]
 fn get_image_from_dxt3(
     raw_data: &[u8],
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     let mut img_buffer = image::ImageBuffer::new(width, height);
 
@@ -343,7 +847,7 @@ fn get_image_from_dxt3(
                     img_buffer.put_pixel(
                         x + i,
                         y + j,
-                        image::Rgba([color.r(), color.g(), color.b(), alpha]),
+                        to_rgba([color.r(), color.g(), color.b(), alpha], premultiplied),
                     );
                 }
             }
@@ -353,11 +857,12 @@ fn get_image_from_dxt3(
     Ok(img_buffer.into())
 }
 
-#[cfg(feature = "rayon")]
+#[cfg(all(feature = "image", feature = "rayon"))]
 fn get_image_from_dxt5(
     raw_data: &[u8],
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     let image_buffer_chunks = raw_data
         .par_chunks(16)
@@ -385,7 +890,7 @@ fn get_image_from_dxt5(
                         img_buffer.put_pixel(
                             x,
                             y,
-                            image::Rgba([color.r(), color.g(), color.b(), alpha]),
+                            to_rgba([color.r(), color.g(), color.b(), alpha], premultiplied),
                         );
                     }
                 }
@@ -406,11 +911,12 @@ fn get_image_from_dxt5(
     Ok(img_buffer.into())
 }
 
-#[cfg(not(feature = "rayon"))]
+#[cfg(all(feature = "image", not(feature = "rayon")))]
 fn get_image_from_dxt5(
     raw_data: &[u8],
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     let mut img_buffer = image::ImageBuffer::new(width, height);
 
@@ -443,7 +949,7 @@ fn get_image_from_dxt5(
                     img_buffer.put_pixel(
                         x + i,
                         y + j,
-                        image::Rgba([color.r(), color.g(), color.b(), alpha]),
+                        to_rgba([color.r(), color.g(), color.b(), alpha], premultiplied),
                     );
                 }
             }
@@ -492,6 +998,18 @@ fn get_pixel_data_form_517(raw_data: &[u8], width: u32, height: u32) -> Vec<u8>
     pixels
 }
 
+/// Build the pixel, multiply the color by alpha when `premultiplied`.
+#[cfg(feature = "image")]
+#[inline]
+fn to_rgba([r, g, b, a]: [u8; 4], premultiplied: bool) -> Rgba<u8> {
+    if !premultiplied || a == 255 {
+        return Rgba([r, g, b, a]);
+    }
+    let multiply = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+    Rgba([multiply(r), multiply(g), multiply(b), a])
+}
+
+#[cfg(feature = "image")]
 #[inline]
 fn create_color_table(c0: u16, c1: u16) -> [Rgb<u8>; 4] {
     let color1 = Rgb::<u8>::from_rgb565(c0);
@@ -530,6 +1048,7 @@ fn create_color_table(c0: u16, c1: u16) -> [Rgb<u8>; 4] {
     [color1, color2, color3, color4]
 }
 
+#[cfg(feature = "image")]
 #[inline]
 #[allow(dead_code)]
 fn expand_color_table(color_table: &mut [Rgb<u8>; 4], c0: u16, c1: u16) {
@@ -565,6 +1084,7 @@ fn expand_color_table(color_table: &mut [Rgb<u8>; 4], c0: u16, c1: u16) {
     }
 }
 
+#[cfg(all(feature = "image", feature = "rayon"))]
 #[inline]
 fn create_color_index_table(raw_data: &[u8]) -> [u8; 16] {
     let mut color_index_table = [0u8; 16];
@@ -574,6 +1094,7 @@ fn create_color_index_table(raw_data: &[u8]) -> [u8; 16] {
     color_index_table
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn expand_color_index_table(color_index_table: &mut [u8; 16], raw_data: &[u8]) {
     // raw_data should be a [u8; 4];
@@ -586,6 +1107,7 @@ fn expand_color_index_table(color_index_table: &mut [u8; 16], raw_data: &[u8]) {
     }
 }
 
+#[cfg(all(feature = "image", feature = "rayon"))]
 #[inline]
 fn create_alpha_table_dxt3(raw_data: &[u8]) -> [u8; 16] {
     let mut alpha_table = [0u8; 16];
@@ -595,6 +1117,7 @@ fn create_alpha_table_dxt3(raw_data: &[u8]) -> [u8; 16] {
     alpha_table
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn expand_alpha_table_dxt3(alpha_table: &mut [u8; 16], raw_data: &[u8]) {
     // raw_data should be a [u8; 8];
@@ -608,6 +1131,7 @@ fn expand_alpha_table_dxt3(alpha_table: &mut [u8; 16], raw_data: &[u8]) {
     }
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn create_alpha_table_dxt5(a0: u8, a1: u8) -> [u8; 8] {
     let mut alpha_table = [0u8; 8];
@@ -617,6 +1141,7 @@ fn create_alpha_table_dxt5(a0: u8, a1: u8) -> [u8; 8] {
     alpha_table
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn expand_alpha_table_dxt5(alpha_table: &mut [u8; 8], a0: u8, a1: u8) {
     alpha_table[0] = a0;
@@ -636,6 +1161,7 @@ fn expand_alpha_table_dxt5(alpha_table: &mut [u8; 8], a0: u8, a1: u8) {
     }
 }
 
+#[cfg(all(feature = "image", feature = "rayon"))]
 #[inline]
 fn create_alpha_index_table_dxt5(raw_data: &[u8]) -> [u8; 16] {
     let mut alpha_index_table = [0u8; 16];
@@ -645,6 +1171,7 @@ fn create_alpha_index_table_dxt5(raw_data: &[u8]) -> [u8; 16] {
     alpha_index_table
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn expand_alpha_index_table_dxt5(alpha_index_table: &mut [u8; 16], raw_data: &[u8]) {
     // raw_data should be a [u8; 6];
@@ -660,36 +1187,45 @@ fn expand_alpha_index_table_dxt5(alpha_index_table: &mut [u8; 16], raw_data: &[u
     }
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn get_image_from_bgra8888(
     raw_data: Vec<u8>,
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     #[cfg(feature = "rayon")]
     let img_buffer = image::ImageBuffer::from_par_fn(width, height, |x, y| {
         let i = (x + y * width) as usize * 4;
-        image::Rgba([
-            raw_data[i + 2],
-            raw_data[i + 1],
-            raw_data[i],
-            raw_data[i + 3],
-        ])
+        to_rgba(
+            [
+                raw_data[i + 2],
+                raw_data[i + 1],
+                raw_data[i],
+                raw_data[i + 3],
+            ],
+            premultiplied,
+        )
     });
     #[cfg(not(feature = "rayon"))]
     let img_buffer = image::ImageBuffer::from_fn(width, height, |x, y| {
         let i = (x + y * width) as usize * 4;
-        image::Rgba([
-            raw_data[i + 2],
-            raw_data[i + 1],
-            raw_data[i],
-            raw_data[i + 3],
-        ])
+        to_rgba(
+            [
+                raw_data[i + 2],
+                raw_data[i + 1],
+                raw_data[i],
+                raw_data[i + 3],
+            ],
+            premultiplied,
+        )
     });
 
     Ok(img_buffer.into())
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn get_image_from_rgb565(
     raw_data: &[u8],
@@ -709,11 +1245,13 @@ fn get_image_from_rgb565(
     Ok(img_buffer.into())
 }
 
+#[cfg(feature = "image")]
 #[inline]
 fn get_image_from_argb1555(
     raw_data: &[u8],
     width: u32,
     height: u32,
+    premultiplied: bool,
 ) -> Result<DynamicImage, WzPngParseError> {
     let mut img_buffer: ImageBufferRgbaChunk = image::ImageBuffer::new(width, height);
     img_buffer
@@ -721,9 +1259,276 @@ fn get_image_from_argb1555(
         .try_for_each::<_, Result<(), WzPngParseError>>(|(x, y, pixel)| {
             let i = (x + y * width) as usize * 2;
             let color = reader::read_u16_at(raw_data, i)?;
-            *pixel = Rgba::<u8>::from_argb1555(color);
+            let Rgba(color) = Rgba::<u8>::from_argb1555(color);
+            *pixel = to_rgba(color, premultiplied);
             Ok(())
         })?;
 
     Ok(img_buffer.into())
 }
+
+#[cfg(all(test, feature = "image"))]
+mod test {
+    use super::*;
+
+    fn setup_image() -> DynamicImage {
+        image::RgbaImage::from_fn(4, 2, |x, y| {
+            image::Rgba([
+                (x * 64) as u8,
+                (y * 128) as u8,
+                0xFF,
+                if x % 2 == 0 { 0xFF } else { 0 },
+            ])
+        })
+        .into()
+    }
+
+    #[test]
+    fn test_from_image_bgra8888() {
+        let image = setup_image();
+        let png = WzPng::from_image(&image, 2).unwrap();
+
+        assert_eq!(png.width, 4);
+        assert_eq!(png.height, 2);
+        assert_eq!(png.format(), 2);
+        assert!(png.has_zlib_header());
+        assert_eq!(png.extract_png().unwrap().to_rgba8(), image.to_rgba8());
+    }
+
+    #[test]
+    fn test_from_image_lossy_formats() {
+        let image =
+            image::RgbaImage::from_pixel(2, 2, image::Rgba([0xFF, 0x00, 0xFF, 0xFF])).into();
+
+        for format in [1, 257, 513] {
+            let png = WzPng::from_image(&image, format).unwrap();
+            let extracted = png.extract_png().unwrap().to_rgba8();
+
+            assert_eq!(
+                extracted.get_pixel(0, 0),
+                &image::Rgba([0xFF, 0x00, 0xFF, 0xFF])
+            );
+        }
+    }
+
+    #[test]
+    fn test_inflate_to() {
+        /* bigger than a single chunk */
+        let image = image::RgbaImage::from_fn(160, 160, |x, y| {
+            image::Rgba([x as u8, y as u8, (x ^ y) as u8, 0xFF])
+        })
+        .into();
+        let png = WzPng::from_image(&image, 2).unwrap();
+
+        let mut pixels = Vec::new();
+        let size = png.inflate_to(&mut pixels).unwrap();
+
+        assert_eq!(size, 160 * 160 * 4);
+        assert_eq!(pixels, png.get_raw_data().unwrap());
+
+        /* the reused decompressor should be reset */
+        let mut again = Vec::new();
+        png.inflate_to(&mut again).unwrap();
+        assert_eq!(pixels, again);
+        assert_eq!(png.extract_png().unwrap().to_rgba8(), image.to_rgba8());
+    }
+
+    #[test]
+    fn test_png_with_other_data_source() {
+        let image = setup_image();
+        let png = WzPng::from_image(&image, 2).unwrap();
+
+        let source = Arc::new(reader::WzBaseReader::new(png.get_raw_buffer().to_vec()));
+        let vec_png = WzPng::new(
+            &source,
+            (png.width, png.height),
+            png.get_format_pair(),
+            (0, png.get_raw_buffer().len()),
+            png.header,
+        );
+
+        assert_eq!(vec_png.extract_png().unwrap().to_rgba8(), image.to_rgba8());
+    }
+
+    #[test]
+    fn test_raw_compressed_chunked() {
+        use crate::util::maple_crypto_constants::WZ_GMSIV;
+        use crate::util::WzMutableKey;
+
+        let image = setup_image();
+        let png = WzPng::from_image(&image, 2).unwrap();
+        let compressed = png.raw_compressed().unwrap().into_owned();
+
+        /* split into encrypted chunks, every chunk decrypt from the start of the keys */
+        let mut key = WzMutableKey::from_iv(WZ_GMSIV);
+        key.ensure_key_size(compressed.len()).unwrap();
+        let mut buff = Vec::new();
+        for chunk in compressed.chunks(10) {
+            let mut chunk = chunk.to_vec();
+            key.decrypt_slice(&mut chunk);
+            buff.extend_from_slice(&(chunk.len() as i32).to_le_bytes());
+            buff.extend_from_slice(&chunk);
+        }
+
+        let reader = Arc::new(reader::WzReader::from_buff(&buff).with_iv(WZ_GMSIV));
+        let chunked = WzPng::new(
+            &reader,
+            (png.width, png.height),
+            png.get_format_pair(),
+            (0, buff.len()),
+            0,
+        );
+
+        assert_eq!(chunked.raw_compressed().unwrap(), compressed);
+        assert_eq!(chunked.extract_png().unwrap().to_rgba8(), image.to_rgba8());
+
+        /* the chunk size runs past the block */
+        let size = buff.len() as i32;
+        buff[..4].copy_from_slice(&size.to_le_bytes());
+        let reader = Arc::new(reader::WzReader::from_buff(&buff).with_iv(WZ_GMSIV));
+        let broken = WzPng::new(
+            &reader,
+            (png.width, png.height),
+            png.get_format_pair(),
+            (0, buff.len()),
+            0,
+        );
+
+        assert!(matches!(
+            broken.raw_compressed(),
+            Err(WzPngParseError::ReadColorError(_))
+        ));
+    }
+
+    #[test]
+    fn test_extract_raw_pixels() {
+        let image =
+            image::RgbaImage::from_pixel(4, 2, image::Rgba([0xFF, 0x00, 0xFF, 0xFF])).into();
+        let png = WzPng::from_image(&image, 513).unwrap();
+
+        let pixels = png.extract_raw_pixels().unwrap();
+
+        assert_eq!(pixels.format, WzPixelFormat::Rgb565);
+        assert!(!pixels.format.is_compressed());
+        assert_eq!((pixels.width, pixels.height), (4, 2));
+        assert_eq!(pixels.data, 0xF81F_u16.to_le_bytes().repeat(8));
+
+        assert_eq!(WzPixelFormat::from_format(2050), Some(WzPixelFormat::Dxt5));
+        assert_eq!(WzPixelFormat::from_format(4), None);
+    }
+
+    #[test]
+    fn test_extract_png_premultiplied() {
+        let image = image::RgbaImage::from_fn(4, 4, |x, y| {
+            image::Rgba([255, (x * 60) as u8, (y * 60) as u8, (x * y * 17) as u8])
+        })
+        .into();
+
+        for format in [1, 2, 257, 513] {
+            let png = WzPng::from_image(&image, format).unwrap();
+
+            let straight = png.extract_png().unwrap().to_rgba8();
+            let premultiplied = png.extract_png_premultiplied().unwrap().to_rgba8();
+
+            for (straight, premultiplied) in straight.pixels().zip(premultiplied.pixels()) {
+                let [r, g, b, a] = straight.0;
+                assert_eq!(premultiplied.0, to_rgba([r, g, b, a], true).0);
+                assert!(premultiplied.0[..3].iter().all(|c| *c <= a));
+            }
+        }
+
+        assert_eq!(to_rgba([255, 255, 255, 0], true).0, [0, 0, 0, 0]);
+        assert_eq!(to_rgba([200, 100, 50, 255], true).0, [200, 100, 50, 255]);
+        assert_eq!(to_rgba([255, 128, 0, 128], true).0, [128, 64, 0, 128]);
+    }
+
+    #[test]
+    fn test_from_image_unsupported_format() {
+        let image = setup_image();
+
+        assert!(matches!(
+            WzPng::from_image(&image, 4),
+            Err(WzPngParseError::UnsupportedEncodeFormat(4))
+        ));
+        /* 4x2 isn't made of 4x4 blocks */
+        assert!(matches!(
+            WzPng::from_image(&image, 1026),
+            Err(WzPngParseError::UnsupportedEncodeSize(1026, 4, 2))
+        ));
+    }
+
+    #[test]
+    fn test_from_image_dxt() {
+        let image = image::RgbaImage::from_fn(8, 8, |x, y| {
+            let color = if (x / 4 + y / 4) % 2 == 0 {
+                [0xFF, 0x00, 0xFF]
+            } else {
+                [0x00, 0xFF, 0x00]
+            };
+            /* alpha 0, 17, 34 ... 255 can be stored exactly in 4 bits */
+            image::Rgba([color[0], color[1], color[2], ((x + y) * 17) as u8])
+        });
+
+        let png = WzPng::from_image(&image.clone().into(), 1026).unwrap();
+
+        assert_eq!(png.get_raw_data().unwrap().len(), 8 * 8);
+        assert_eq!(png.extract_png().unwrap().to_rgba8(), image);
+
+        /* DXT5 interpolates 8 alpha levels between the max and min of the block */
+        let png = WzPng::from_image(&image.clone().into(), 2050).unwrap();
+        let extracted = png.extract_png().unwrap().to_rgba8();
+
+        assert_eq!(png.get_raw_data().unwrap().len(), 8 * 8);
+        for (a, b) in extracted.pixels().zip(image.pixels()) {
+            assert_eq!(a.0[..3], b.0[..3]);
+            assert!(a.0[3].abs_diff(b.0[3]) <= 8);
+        }
+
+        /* a color gradient in a block is approximated by the 4 colors */
+        let gradient = image::RgbaImage::from_fn(4, 4, |x, _| {
+            image::Rgba([(x * 85) as u8, 0, 0, 0xFF - (x * 85) as u8])
+        });
+        for format in [1026, 2050] {
+            let png = WzPng::from_image(&gradient.clone().into(), format).unwrap();
+            let extracted = png.extract_png().unwrap().to_rgba8();
+
+            for (a, b) in extracted.pixels().zip(gradient.pixels()) {
+                assert!(a.0[..3].iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= 8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_image_517() {
+        let image = image::RgbaImage::from_fn(32, 16, |x, y| {
+            if x < 16 {
+                image::Rgba([0xFF, 0x00, 0xFF, 0xFF])
+            } else {
+                /* averaged to 0x80 */
+                let g = if (x + y) % 2 == 0 { 0x00 } else { 0xFF };
+                image::Rgba([0x00, g, 0x00, 0xFF])
+            }
+        });
+        let png = WzPng::from_image(&image.into(), 517).unwrap();
+        let extracted = png.extract_png().unwrap().to_rgba8();
+
+        assert_eq!(png.get_raw_data().unwrap().len(), 2 * 2);
+        assert_eq!(
+            extracted.get_pixel(0, 0),
+            &image::Rgba([0xFF, 0x00, 0xFF, 0xFF])
+        );
+        assert_eq!(
+            extracted.get_pixel(15, 15),
+            &image::Rgba([0xFF, 0x00, 0xFF, 0xFF])
+        );
+        assert_eq!(
+            extracted.get_pixel(20, 3),
+            &image::Rgba([0x00, 0x82, 0x00, 0xFF])
+        );
+
+        assert!(matches!(
+            WzPng::from_image(&setup_image(), 517),
+            Err(WzPngParseError::UnsupportedEncodeSize(517, 4, 2))
+        ));
+    }
+}
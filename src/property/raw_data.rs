@@ -1,24 +1,60 @@
-use crate::WzReader;
+use crate::{reader::DataSource, WzReader};
 use std::ops::Range;
 use std::sync::Arc;
 
-#[derive(Debug, Clone, Default)]
-pub struct WzRawData {
-    pub reader: Arc<WzReader>,
+#[derive(Debug, Default)]
+pub struct WzRawData<R: ?Sized = WzReader> {
+    pub reader: Arc<R>,
     offset: usize,
     length: usize,
 }
 
+impl<R: ?Sized> Clone for WzRawData<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            length: self.length,
+        }
+    }
+}
+
 impl WzRawData {
-    pub fn new(reader: &Arc<WzReader>, offset: usize, length: usize) -> Self {
+    /// Create from owned bytes, useful for inserting any blob like font or spine json into a tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzRawData;
+    /// let raw_data = WzRawData::from_bytes(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(raw_data.get_buffer(), &[1, 2, 3]);
+    /// ```
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let reader = Arc::new(WzReader::from_buff(&bytes));
+        Self::new(&reader, 0, bytes.len())
+    }
+}
+
+impl<R: DataSource + ?Sized> WzRawData<R> {
+    pub fn new(reader: &Arc<R>, offset: usize, length: usize) -> Self {
         Self {
             reader: Arc::clone(reader),
             offset,
             length,
         }
     }
+    /// The offset of the data in the wz file.
+    #[inline]
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    #[inline]
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
     #[inline]
-    fn get_buffer_range(&self) -> Range<usize> {
+    pub fn get_buffer_range(&self) -> Range<usize> {
         self.offset..self.offset + self.length
     }
     #[inline]
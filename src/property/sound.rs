@@ -1,6 +1,6 @@
-use crate::reader::{read_i32_at, WzReader};
+use crate::reader::{read_i32_at, read_u16_at, read_u32_at, DataSource, WzReader};
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::{
     io::{Seek, Write},
@@ -22,9 +22,14 @@ pub enum WzSoundError {
 
     #[error("Not a Sound property")]
     NotSoundProperty,
+
+    #[cfg(any(feature = "mp3-duration", feature = "pcm"))]
+    #[error("Failed to decode sound: {0}")]
+    DecodeError(#[from] symphonia::core::errors::Error),
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum WzSoundType {
     Mp3,
@@ -34,10 +39,16 @@ pub enum WzSoundType {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Default)]
-pub struct WzSound {
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "schemars", schemars(rename = "WzSound", bound = ""))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "", deserialize = "R: Default"))
+)]
+#[derive(Debug, Default)]
+pub struct WzSound<R: ?Sized = WzReader> {
     #[cfg_attr(feature = "serde", serde(skip))]
-    reader: Arc<WzReader>,
+    reader: Arc<R>,
     #[cfg_attr(feature = "serde", serde(skip))]
     offset: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
@@ -50,6 +61,20 @@ pub struct WzSound {
     pub sound_type: WzSoundType,
 }
 
+impl<R: ?Sized> Clone for WzSound<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            length: self.length,
+            header_offset: self.header_offset,
+            header_size: self.header_size,
+            duration: self.duration,
+            sound_type: self.sound_type.clone(),
+        }
+    }
+}
+
 const WAV_HEADER: [u8; 44] = [
     0x52, 0x49, 0x46, 0x46, //"RIFF"
     0, 0, 0, 0, //ChunkSize
@@ -61,6 +86,99 @@ const WAV_HEADER: [u8; 44] = [
     0, 0, 0, 0, //chunk2Size
 ];
 
+/// The media type guids before the wave format in the sound header, it's the same in every Sound_DX8.
+const SOUND_HEADER: [u8; 51] = [
+    0x02, //
+    0x83, 0xEB, 0x36, 0xE4, 0x4F, 0x52, 0xCE, 0x11, 0x9F, 0x53, 0x00, 0x20, 0xAF, 0x0B, 0xA7,
+    0x70, // MEDIATYPE_Stream
+    0x8B, 0xEB, 0x36, 0xE4, 0x4F, 0x52, 0xCE, 0x11, 0x9F, 0x53, 0x00, 0x20, 0xAF, 0x0B, 0xA7,
+    0x70, // MEDIASUBTYPE_WAVE
+    0x00, 0x01, //
+    0x81, 0x9F, 0x58, 0x05, 0x56, 0xC3, 0xCE, 0x11, 0xBF, 0x01, 0x00, 0xAA, 0x00, 0x55, 0x59,
+    0x5A, // FORMAT_WaveFormatEx
+];
+
+const MP3_BITRATES_V1: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const MP3_BITRATES_V2: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+/// The needed part of a mpeg layer 3 frame header.
+struct Mp3Frame {
+    is_v1: bool,
+    channels: u16,
+    sample_rate: u32,
+    /// kbps
+    bitrate: u32,
+    size: usize,
+}
+
+impl Mp3Frame {
+    fn parse(header: &[u8]) -> Option<Self> {
+        if header.len() < 4 || header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = (header[1] >> 3) & 0b11;
+        let layer = (header[1] >> 1) & 0b11;
+        /* only layer 3, and version 1 is reserved */
+        if layer != 0b01 || version == 0b01 {
+            return None;
+        }
+
+        let is_v1 = version == 0b11;
+        let bitrate_index = (header[2] >> 4) as usize;
+        let sample_rate_index = ((header[2] >> 2) & 0b11) as usize;
+        let padding = ((header[2] >> 1) & 1) as usize;
+
+        if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+            return None;
+        }
+
+        let bitrate = if is_v1 {
+            MP3_BITRATES_V1[bitrate_index]
+        } else {
+            MP3_BITRATES_V2[bitrate_index]
+        };
+        let sample_rate = match version {
+            0b11 => [44100, 48000, 32000],
+            0b10 => [22050, 24000, 16000],
+            _ => [11025, 12000, 8000],
+        }[sample_rate_index];
+        let channels = if header[3] >> 6 == 0b11 { 1 } else { 2 };
+        let size = (Self::samples(is_v1) / 8 * bitrate * 1000 / sample_rate) as usize + padding;
+
+        Some(Self {
+            is_v1,
+            channels,
+            sample_rate,
+            bitrate,
+            size,
+        })
+    }
+    #[inline]
+    fn samples(is_v1: bool) -> u32 {
+        if is_v1 {
+            1152
+        } else {
+            576
+        }
+    }
+}
+
+/// Skip the ID3v2 tag if there is one.
+fn skip_id3v2(data: &[u8]) -> usize {
+    if data.len() < 10 || &data[0..3] != b"ID3" {
+        return 0;
+    }
+    let size = data[6..10]
+        .iter()
+        .fold(0_usize, |size, byte| (size << 7) | (*byte & 0x7F) as usize);
+    let footer = if data[5] & 0x10 != 0 { 10 } else { 0 };
+
+    10 + size + footer
+}
+
 fn get_frequency_header(header: &[u8]) -> u32 {
     if header.len() <= 0x3c {
         0
@@ -69,6 +187,93 @@ fn get_frequency_header(header: &[u8]) -> u32 {
     }
 }
 
+/// Decode every packet of the sound file data, the broken packets are skipped. Returns the sample rate.
+#[cfg(any(feature = "mp3-duration", feature = "pcm"))]
+fn decode_sound<F>(
+    data: Vec<u8>,
+    sound_type: &WzSoundType,
+    mut on_decoded: F,
+) -> Result<u32, WzSoundError>
+where
+    F: FnMut(symphonia::core::audio::AudioBufferRef),
+{
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error as DecodeError;
+    use symphonia::core::formats::{FormatOptions, FormatReader};
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::default::formats::MpaReader;
+
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(data)), Default::default());
+    let options = FormatOptions::default();
+
+    let mut format: Box<dyn FormatReader> = match sound_type {
+        WzSoundType::Mp3 => Box::new(MpaReader::try_new(source, &options)?),
+        #[cfg(feature = "pcm")]
+        WzSoundType::Wav => Box::new(symphonia::default::formats::WavReader::try_new(
+            source, &options,
+        )?),
+        _ => return Err(WzSoundError::UnsupportedFormat),
+    };
+
+    let track = format
+        .default_track()
+        .ok_or(WzSoundError::UnsupportedFormat)?;
+    let track_id = track.id;
+    let mut sample_rate = track.codec_params.sample_rate;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                sample_rate.get_or_insert(decoded.spec().rate);
+                on_decoded(decoded);
+            }
+            Err(DecodeError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    sample_rate
+        .filter(|rate| *rate > 0)
+        .ok_or(WzSoundError::UnsupportedFormat)
+}
+
+/// The sound decoded by `WzSound::decode_pcm`.
+#[cfg(feature = "pcm")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WzSoundPcm {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// interleaved samples in `-1.0..=1.0`, like `[left, right, left, right, ...]`.
+    pub samples: Vec<f32>,
+}
+
+#[cfg(feature = "pcm")]
+impl WzSoundPcm {
+    /// The number of samples per channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+    /// The duration in milliseconds.
+    pub fn duration(&self) -> u32 {
+        if self.sample_rate == 0 {
+            return 0;
+        }
+        (self.frames() as u64 * 1000 / self.sample_rate as u64) as u32
+    }
+}
+
 pub fn get_sound_type_from_header(header: &[u8], file_size: u32, duration: u32) -> WzSoundType {
     let frequency = get_frequency_header(header);
     if header.len() == 0x46 {
@@ -83,8 +288,130 @@ pub fn get_sound_type_from_header(header: &[u8], file_size: u32, duration: u32)
 }
 
 impl WzSound {
+    /// Create from the header blob and payload that ready to put into a Sound_DX8.
+    fn from_parts(wave_format: &[u8], data: &[u8], duration: u32, sound_type: WzSoundType) -> Self {
+        let mut buff = Vec::with_capacity(SOUND_HEADER.len() + 1 + wave_format.len() + data.len());
+        buff.extend_from_slice(&SOUND_HEADER);
+        buff.push(wave_format.len() as u8);
+        buff.extend_from_slice(wave_format);
+
+        let header_size = buff.len();
+        buff.extend_from_slice(data);
+
+        let reader = Arc::new(WzReader::from_buff(&buff));
+
+        Self::new(
+            &reader,
+            header_size,
+            data.len() as u32,
+            0,
+            header_size,
+            duration,
+            sound_type,
+        )
+    }
+    /// Create a mp3 sound from the whole mp3 file data, the header and duration will be built from the frames.
+    /// Only MPEG layer 3 is supported.
+    pub fn from_mp3(data: &[u8]) -> Result<Self, WzSoundError> {
+        let start = skip_id3v2(data);
+        let first = Mp3Frame::parse(data.get(start..).unwrap_or(&[]))
+            .ok_or(WzSoundError::UnsupportedFormat)?;
+
+        /* walk through every frame to get the exact duration, also works with VBR */
+        let mut samples = 0_u64;
+        let mut pos = start;
+        while let Some(frame) = data.get(pos..).and_then(Mp3Frame::parse) {
+            samples += Mp3Frame::samples(frame.is_v1) as u64;
+            pos += frame.size;
+        }
+        let duration = (samples * 1000 / first.sample_rate as u64) as u32;
+
+        /* MPEGLAYER3WAVEFORMAT */
+        let mut wave_format = Vec::with_capacity(30);
+        wave_format.extend_from_slice(&0x55_u16.to_le_bytes()); // WAVE_FORMAT_MPEGLAYER3
+        wave_format.extend_from_slice(&first.channels.to_le_bytes());
+        wave_format.extend_from_slice(&first.sample_rate.to_le_bytes());
+        wave_format.extend_from_slice(&(first.bitrate * 1000 / 8).to_le_bytes());
+        wave_format.extend_from_slice(&1_u16.to_le_bytes()); // block align
+        wave_format.extend_from_slice(&0_u16.to_le_bytes()); // bits per sample
+        wave_format.extend_from_slice(&12_u16.to_le_bytes()); // extra size
+        wave_format.extend_from_slice(&1_u16.to_le_bytes()); // MPEGLAYER3_ID_MPEG
+        wave_format.extend_from_slice(&2_u32.to_le_bytes()); // MPEGLAYER3_FLAG_PADDING_OFF
+        wave_format.extend_from_slice(&(first.size as u16).to_le_bytes());
+        wave_format.extend_from_slice(&1_u16.to_le_bytes()); // frames per block
+        wave_format.extend_from_slice(&1393_u16.to_le_bytes()); // codec delay
+
+        Ok(Self::from_parts(
+            &wave_format,
+            data,
+            duration,
+            WzSoundType::Mp3,
+        ))
+    }
+    /// Create a wav sound from the whole wav file data, the payload will be the data chunk only like parsed one.
+    /// Only the plain `WAVEFORMATEX` fmt chunk is supported.
+    pub fn from_wav(data: &[u8]) -> Result<Self, WzSoundError> {
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return Err(WzSoundError::UnsupportedFormat);
+        }
+
+        let mut fmt = None;
+        let mut payload = None;
+        let mut pos = 12;
+
+        while pos + 8 <= data.len() {
+            let chunk_id = &data[pos..pos + 4];
+            let chunk_size = read_u32_at(data, pos + 4).unwrap() as usize;
+            let chunk = data
+                .get(pos + 8..pos + 8 + chunk_size)
+                .ok_or(WzSoundError::UnsupportedFormat)?;
+
+            match chunk_id {
+                b"fmt " => fmt = Some(chunk),
+                b"data" => payload = Some(chunk),
+                _ => {}
+            }
+
+            /* chunks are word aligned */
+            pos += 8 + chunk_size + (chunk_size & 1);
+        }
+
+        let (Some(fmt), Some(payload)) = (fmt, payload) else {
+            return Err(WzSoundError::UnsupportedFormat);
+        };
+
+        let wave_format = match fmt.len() {
+            16 => [fmt, &[0, 0]].concat(),
+            18 if read_u16_at(fmt, 16).unwrap() == 0 => fmt.to_vec(),
+            _ => return Err(WzSoundError::UnsupportedFormat),
+        };
+
+        let avg_bytes_per_sec = read_u32_at(fmt, 8).unwrap() as u64;
+        if avg_bytes_per_sec == 0 {
+            return Err(WzSoundError::UnsupportedFormat);
+        }
+        let duration = (payload.len() as u64 * 1000 / avg_bytes_per_sec) as u32;
+
+        Ok(Self::from_parts(
+            &wave_format,
+            payload,
+            duration,
+            WzSoundType::Wav,
+        ))
+    }
+    #[inline]
+    pub fn from_mp3_file<P: AsRef<Path>>(path: P) -> Result<Self, WzSoundError> {
+        Self::from_mp3(&std::fs::read(path)?)
+    }
+    #[inline]
+    pub fn from_wav_file<P: AsRef<Path>>(path: P) -> Result<Self, WzSoundError> {
+        Self::from_wav(&std::fs::read(path)?)
+    }
+}
+
+impl<R: DataSource + ?Sized> WzSound<R> {
     pub fn new(
-        reader: &Arc<WzReader>,
+        reader: &Arc<R>,
         offset: usize,
         length: u32,
         header_offset: usize,
@@ -102,14 +429,41 @@ impl WzSound {
             sound_type,
         }
     }
+    /// The offset of the sound data in the wz file, the header is placed before it.
+    #[inline]
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
     #[inline]
-    fn get_buffer_range(&self) -> Range<usize> {
+    pub fn get_length(&self) -> usize {
+        self.length as usize
+    }
+    #[inline]
+    pub fn get_header_offset(&self) -> usize {
+        self.header_offset
+    }
+    #[inline]
+    pub fn get_header_size(&self) -> usize {
+        self.header_size
+    }
+    #[inline]
+    pub fn get_buffer_range(&self) -> Range<usize> {
         self.offset..self.offset + self.length as usize
     }
     #[inline]
-    fn get_header_range(&self) -> Range<usize> {
+    pub fn get_header_range(&self) -> Range<usize> {
         self.header_offset..self.header_offset + self.header_size
     }
+    /// The raw sound data without the wav header.
+    #[inline]
+    pub fn get_raw_buffer(&self) -> &[u8] {
+        self.reader.get_slice(self.get_buffer_range())
+    }
+    /// The raw header data without modify.
+    #[inline]
+    pub fn get_raw_header(&self) -> &[u8] {
+        self.reader.get_slice(self.get_header_range())
+    }
     pub fn get_wav_header(&self) -> Vec<u8> {
         let header = self.reader.get_slice(self.get_header_range());
         let chunk_size = (self.length + 36).to_le_bytes();
@@ -124,6 +478,84 @@ impl WzSound {
 
         wav_header
     }
+    /// Get the actual duration in milliseconds, the `duration` from the header is often wrong or zero.
+    /// The mp3 sound will be decoded to count the samples, and the wav sound is computed from the data length.
+    ///
+    /// # Errors
+    /// `WzSoundError::UnsupportedFormat` when it's binary sound or can't find any frame.
+    #[cfg(feature = "mp3-duration")]
+    pub fn get_decoded_duration(&self) -> Result<u32, WzSoundError> {
+        match self.sound_type {
+            WzSoundType::Mp3 => {
+                let mut samples = 0_u64;
+                let sample_rate = decode_sound(
+                    self.get_raw_buffer().to_vec(),
+                    &self.sound_type,
+                    |decoded| samples += decoded.frames() as u64,
+                )?;
+                Ok((samples * 1000 / sample_rate as u64) as u32)
+            }
+            WzSoundType::Wav => {
+                /* the WAVEFORMATEX is at 0x34, same as the one copied to wav header */
+                let avg_bytes_per_sec = read_u32_at(self.get_raw_header(), 0x34 + 8)
+                    .ok()
+                    .filter(|avg| *avg > 0)
+                    .ok_or(WzSoundError::UnsupportedFormat)?;
+                Ok((self.length as u64 * 1000 / avg_bytes_per_sec as u64) as u32)
+            }
+            WzSoundType::Binary => Err(WzSoundError::UnsupportedFormat),
+        }
+    }
+    /// Decode the mp3 or wav sound into f32 samples, so it can be played directly.
+    ///
+    /// # Errors
+    /// `WzSoundError::UnsupportedFormat` when it's binary sound, `WzSoundError::DecodeError` when the codec
+    /// is not supported(like adpcm) or the data is broken.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use wz_reader::{WzNode, WzNodeCast};
+    /// # let node = WzNode::from_str("bgm", 1, None).into_lock();
+    /// let node_read = node.read().unwrap();
+    /// let sound = node_read.try_as_sound().unwrap();
+    ///
+    /// let pcm = sound.decode_pcm().unwrap();
+    /// println!("{}hz {}ch {}ms", pcm.sample_rate, pcm.channels, pcm.duration());
+    /// ```
+    #[cfg(feature = "pcm")]
+    pub fn decode_pcm(&self) -> Result<WzSoundPcm, WzSoundError> {
+        use symphonia::core::audio::SampleBuffer;
+
+        let data = match self.sound_type {
+            WzSoundType::Mp3 => self.get_raw_buffer().to_vec(),
+            WzSoundType::Wav => self.get_buffer(),
+            WzSoundType::Binary => return Err(WzSoundError::UnsupportedFormat),
+        };
+
+        let mut pcm = WzSoundPcm::default();
+        let mut buffer: Option<SampleBuffer<f32>> = None;
+
+        pcm.sample_rate = decode_sound(data, &self.sound_type, |decoded| {
+            let spec = *decoded.spec();
+            let capacity = decoded.capacity() as u64;
+
+            let buffer = match &mut buffer {
+                Some(buffer)
+                    if buffer.capacity() as u64 >= capacity * spec.channels.count() as u64 =>
+                {
+                    buffer
+                }
+                _ => buffer.insert(SampleBuffer::new(capacity, spec)),
+            };
+
+            pcm.channels = spec.channels.count() as u16;
+            buffer.copy_interleaved_ref(decoded);
+            pcm.samples.extend_from_slice(buffer.samples());
+        })?;
+
+        Ok(pcm)
+    }
     /// Write the sound to a writer. Will inculde the header if the sound is a wav file.
     pub fn write_to<W>(&self, writer: &mut W) -> Result<(), WzSoundError>
     where
@@ -169,3 +601,157 @@ impl WzSound {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn wav_file(data: &[u8]) -> Vec<u8> {
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16_u32.to_le_bytes());
+        wav.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1_u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&8000_u32.to_le_bytes());
+        wav.extend_from_slice(&16000_u32.to_le_bytes());
+        wav.extend_from_slice(&2_u16.to_le_bytes());
+        wav.extend_from_slice(&16_u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(data);
+        wav
+    }
+
+    #[test]
+    fn test_sound_from_wav() {
+        let data = vec![7_u8; 8000];
+        let wav = wav_file(&data);
+        let sound = WzSound::from_wav(&wav).unwrap();
+
+        assert_eq!(sound.sound_type, WzSoundType::Wav);
+        assert_eq!(sound.duration, 500);
+        assert_eq!(sound.get_raw_buffer(), &data[..]);
+        assert_eq!(
+            get_sound_type_from_header(sound.get_raw_header(), 8000, 500),
+            WzSoundType::Wav
+        );
+        /* rebuild the same wav file */
+        assert_eq!(sound.get_buffer(), wav);
+    }
+
+    #[test]
+    fn test_sound_offsets() {
+        let data = vec![7_u8; 100];
+        let sound = WzSound::from_wav(&wav_file(&data)).unwrap();
+
+        assert_eq!(sound.get_header_offset(), 0);
+        assert_eq!(sound.get_offset(), sound.get_header_size());
+        assert_eq!(sound.get_length(), 100);
+        assert_eq!(
+            sound.get_buffer_range(),
+            sound.get_offset()..sound.get_offset() + sound.get_length()
+        );
+    }
+
+    #[test]
+    fn test_sound_from_mp3() {
+        /* MPEG1 layer 3, 128kbps, 44100hz, joint stereo, no padding */
+        let frame_header = [0xFF, 0xFB, 0x90, 0x44];
+        let frame_size = 144 * 128000 / 44100;
+        let mut mp3 = Vec::new();
+        for _ in 0..100 {
+            mp3.extend_from_slice(&frame_header);
+            mp3.resize(mp3.len() + frame_size - 4, 0);
+        }
+
+        let sound = WzSound::from_mp3(&mp3).unwrap();
+        let header = sound.get_raw_header();
+
+        assert_eq!(sound.sound_type, WzSoundType::Mp3);
+        assert_eq!(sound.duration, 100 * 1152 * 1000 / 44100);
+        assert_eq!(sound.get_raw_buffer(), &mp3[..]);
+        assert_eq!(header.len(), 0x52);
+        assert_eq!(get_frequency_header(header), 44100);
+        assert_eq!(
+            get_sound_type_from_header(header, mp3.len() as u32, sound.duration),
+            WzSoundType::Mp3
+        );
+    }
+
+    #[cfg(feature = "mp3-duration")]
+    #[test]
+    fn test_decoded_duration() {
+        /* same frames as test_sound_from_mp3, the empty frames decode to silence */
+        let frame_header = [0xFF, 0xFB, 0x90, 0x44];
+        let frame_size = 144 * 128000 / 44100;
+        let mut mp3 = Vec::new();
+        for _ in 0..100 {
+            mp3.extend_from_slice(&frame_header);
+            mp3.resize(mp3.len() + frame_size - 4, 0);
+        }
+
+        let mut sound = WzSound::from_mp3(&mp3).unwrap();
+        sound.duration = 0;
+        assert_eq!(
+            sound.get_decoded_duration().unwrap(),
+            100 * 1152 * 1000 / 44100
+        );
+
+        let wav = WzSound::from_wav(&wav_file(&[7_u8; 8000])).unwrap();
+        assert_eq!(wav.get_decoded_duration().unwrap(), 500);
+
+        let binary = WzSound {
+            sound_type: WzSoundType::Binary,
+            ..wav
+        };
+        assert!(matches!(
+            binary.get_decoded_duration(),
+            Err(WzSoundError::UnsupportedFormat)
+        ));
+    }
+
+    #[cfg(feature = "pcm")]
+    #[test]
+    fn test_decode_pcm() {
+        let data = [0_i16, 16384, -16384, 32767]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect::<Vec<_>>();
+        let wav = WzSound::from_wav(&wav_file(&data)).unwrap();
+
+        let pcm = wav.decode_pcm().unwrap();
+        assert_eq!(pcm.sample_rate, 8000);
+        assert_eq!(pcm.channels, 1);
+        assert_eq!(pcm.samples.len(), 4);
+        assert_eq!(pcm.samples[0], 0.0);
+        assert_eq!(pcm.samples[1], 0.5);
+        assert_eq!(pcm.samples[2], -0.5);
+
+        let frame_header = [0xFF, 0xFB, 0x90, 0x44];
+        let frame_size = 144 * 128000 / 44100;
+        let mut mp3 = Vec::new();
+        for _ in 0..10 {
+            mp3.extend_from_slice(&frame_header);
+            mp3.resize(mp3.len() + frame_size - 4, 0);
+        }
+        let pcm = WzSound::from_mp3(&mp3).unwrap().decode_pcm().unwrap();
+        assert_eq!(pcm.sample_rate, 44100);
+        assert_eq!(pcm.channels, 2);
+        assert_eq!(pcm.frames(), 10 * 1152);
+        assert!(pcm.samples.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_sound_from_invalid_data() {
+        assert!(matches!(
+            WzSound::from_mp3(&[0; 16]),
+            Err(WzSoundError::UnsupportedFormat)
+        ));
+        assert!(matches!(
+            WzSound::from_wav(b"RIFF0000WAVE"),
+            Err(WzSoundError::UnsupportedFormat)
+        ));
+    }
+}
@@ -1,5 +1,10 @@
-use crate::{reader, util::WzMutableKey, Reader, WzNodeArc, WzNodeCast, WzReader};
-use std::sync::{Arc, RwLock};
+use crate::sync::RwLock;
+use crate::{
+    reader::{self, DataSource},
+    util::{get_shared_keys, WzMutableKey},
+    WzNode, WzNodeArc, WzNodeCast, WzReader,
+};
+use std::sync::{Arc, OnceLock, Weak};
 use thiserror::Error;
 
 #[cfg(feature = "serde")]
@@ -15,19 +20,14 @@ pub enum WzStringParseError {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum WzStringType {
+    #[default]
     Ascii,
     Unicode,
     Empty,
 }
 
-impl Default for WzStringType {
-    fn default() -> Self {
-        Self::Ascii
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct WzStringMeta {
     /// string start offset
@@ -38,14 +38,18 @@ pub struct WzStringMeta {
 }
 
 /// `WzString` only hold the string information.
-#[derive(Debug, Clone, Default)]
-pub struct WzString {
-    reader: Arc<WzReader>,
+#[derive(Debug, Default)]
+pub struct WzString<R: ?Sized = WzReader> {
+    reader: Arc<R>,
     /// string start offset
     offset: usize,
     /// string length
     length: u32,
     pub string_type: WzStringType,
+    /// the plain string that not encrypted yet, created by `from_plain`.
+    plain: Option<Arc<str>>,
+    /// the resolved target when it's a uol kept by `UolPolicy::KeepAndAnnotate`.
+    uol_target: Weak<RwLock<WzNode>>,
 }
 
 impl WzStringMeta {
@@ -82,32 +86,42 @@ impl WzStringMeta {
     }
 }
 
-impl WzString {
-    pub fn from_meta(meta: WzStringMeta, reader: &Arc<WzReader>) -> Self {
+impl<R: ?Sized> Clone for WzString<R> {
+    fn clone(&self) -> Self {
         Self {
-            reader: Arc::clone(reader),
-            offset: meta.offset,
-            length: meta.length,
-            string_type: meta.string_type,
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            length: self.length,
+            string_type: self.string_type.clone(),
+            plain: self.plain.clone(),
+            uol_target: self.uol_target.clone(),
         }
     }
+}
+
+impl WzString {
     /// Create a new `WzString` it will encrypt the string with the given iv.
     pub fn from_str(str: &str, iv: [u8; 4]) -> Self {
-        let mut mtbkeys = WzMutableKey::from_iv(iv);
-
-        let len;
-
         let meta_type = if str.is_empty() {
-            len = 0;
             WzStringType::Empty
         } else if str.is_ascii() {
-            len = str.len();
             WzStringType::Ascii
         } else {
-            len = str.chars().count() * 2;
             WzStringType::Unicode
         };
 
+        Self::from_str_with_type(str, iv, meta_type)
+    }
+    /// Same as `from_str`, but encrypt the string as the given type instead of guessing from the content.
+    pub fn from_str_with_type(str: &str, iv: [u8; 4], meta_type: WzStringType) -> Self {
+        let mut mtbkeys = WzMutableKey::from_iv(iv);
+
+        let len = match meta_type {
+            WzStringType::Empty => 0,
+            WzStringType::Ascii => str.len(),
+            WzStringType::Unicode => str.encode_utf16().count() * 2,
+        };
+
         let encrypted = encrypt_str(&mut mtbkeys, str, &meta_type);
 
         let mut reader = WzReader::from_buff(&encrypted);
@@ -120,15 +134,257 @@ impl WzString {
             offset: 0,
             length: len as u32,
             string_type: meta_type,
+            plain: None,
+            uol_target: Weak::new(),
+        }
+    }
+    /// Create a new `WzString` from the decrypted bytes like `get_raw_bytes` returns, the bytes of ascii string
+    /// or the utf16(little endian) of unicode string, so the string not valid utf8 or utf16 is kept as is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::{WzString, WzStringType};
+    /// let string = WzString::from_raw_bytes(&[0xB0, 0xA1], [0; 4], WzStringType::Ascii);
+    ///
+    /// assert_eq!(string.get_raw_bytes().unwrap(), [0xB0, 0xA1]);
+    /// ```
+    pub fn from_raw_bytes(bytes: &[u8], iv: [u8; 4], string_type: WzStringType) -> Self {
+        let mut mtbkeys = WzMutableKey::from_iv(iv);
+
+        let encrypted = encrypt_raw(&mut mtbkeys, bytes.to_vec(), &string_type);
+
+        let mut reader = WzReader::from_buff(&encrypted);
+
+        reader.wz_iv = iv;
+        reader.keys = Arc::new(RwLock::new(mtbkeys));
+
+        WzString {
+            reader: Arc::new(reader),
+            offset: 0,
+            length: encrypted.len() as u32,
+            string_type,
+            plain: None,
+            uol_target: Weak::new(),
         }
     }
+    /// Create a new `WzString` that only keep the plain string, the encryption is deferred to
+    /// `encrypt` with the output iv, cheaper when building a large tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzString;
+    /// let string = WzString::from_plain("hello");
+    ///
+    /// assert_eq!(string.get_string().unwrap(), "hello");
+    /// assert_eq!(string.encrypt([0; 4]).unwrap(), WzString::from_str("hello", [0; 4]).encrypt([0; 4]).unwrap());
+    /// ```
+    pub fn from_plain(str: &str) -> Self {
+        let (string_type, length) = if str.is_empty() {
+            (WzStringType::Empty, 0)
+        } else if str.is_ascii() {
+            (WzStringType::Ascii, str.len())
+        } else {
+            (WzStringType::Unicode, str.encode_utf16().count() * 2)
+        };
+
+        WzString {
+            reader: Arc::clone(empty_reader()),
+            offset: 0,
+            length: length as u32,
+            string_type,
+            plain: Some(Arc::from(str)),
+            uol_target: Weak::new(),
+        }
+    }
+}
+
+/// The reader of plain strings, they never read from it so one is shared by all of them.
+fn empty_reader() -> &'static Arc<WzReader> {
+    static EMPTY_READER: OnceLock<Arc<WzReader>> = OnceLock::new();
+    EMPTY_READER.get_or_init(Arc::default)
+}
+
+impl<R: DataSource + ?Sized> WzString<R> {
+    pub fn from_meta(meta: WzStringMeta, reader: &Arc<R>) -> Self {
+        Self {
+            reader: Arc::clone(reader),
+            offset: meta.offset,
+            length: meta.length,
+            string_type: meta.string_type,
+            plain: None,
+            uol_target: Weak::new(),
+        }
+    }
+    /// The annotated target when the string is the path of a uol node, see `WzNode::uol_target`.
+    #[inline]
+    pub fn uol_target(&self) -> Option<WzNodeArc> {
+        self.uol_target.upgrade()
+    }
+    #[inline]
+    pub(crate) fn set_uol_target(&mut self, target: &WzNodeArc) {
+        self.uol_target = Arc::downgrade(target);
+    }
+    /// Whether the string is created by `from_plain` and not encrypted.
     #[inline]
-    /// Decode string from wz file.
+    pub fn is_plain(&self) -> bool {
+        self.plain.is_some()
+    }
+    /// Encrypt the string with the given iv, returns the encrypted bytes.
+    /// The key stream comes from `get_shared_keys`, so it's reused while a reader or the caller holds the keys of the iv.
+    pub fn encrypt(&self, iv: [u8; 4]) -> Result<Vec<u8>, WzStringParseError> {
+        let string = self.get_string()?;
+        let keys = get_shared_keys(iv);
+        let mut keys = keys.write().unwrap();
+        Ok(encrypt_str(&mut keys, &string, &self.string_type))
+    }
+    /// The iv of the wz file this string belongs to.
+    #[inline]
+    pub fn get_iv(&self) -> [u8; 4] {
+        self.reader.wz_iv()
+    }
+    /// Get the offset, length and type of the string in the wz file.
+    #[inline]
+    pub fn get_meta(&self) -> WzStringMeta {
+        WzStringMeta::new(self.offset, self.length, self.string_type.clone())
+    }
+    #[inline]
+    /// Decode string from wz file, the invalid characters are replaced like `get_string_lossy`.
     pub fn get_string(&self) -> Result<String, WzStringParseError> {
+        self.get_string_lossy()
+    }
+    /// Decode string from wz file, the invalid utf8 or utf16 are replaced with `U+FFFD`.
+    pub fn get_string_lossy(&self) -> Result<String, WzStringParseError> {
+        if let Some(plain) = &self.plain {
+            return Ok(plain.to_string());
+        }
         self.reader
             .resolve_wz_string_meta(&self.string_type, self.offset, self.length as usize)
             .map_err(WzStringParseError::from)
     }
+    /// Same as `get_string_lossy`, but returns error when the string is not valid utf8 or utf16.
+    pub fn try_get_string(&self) -> Result<String, WzStringParseError> {
+        if let Some(plain) = &self.plain {
+            return Ok(plain.to_string());
+        }
+        self.reader
+            .try_resolve_wz_string_meta(&self.string_type, self.offset, self.length as usize)
+            .map_err(WzStringParseError::from)
+    }
+    /// Get the decrypted bytes without decoding, the bytes of ascii string, or the utf16(little endian)
+    /// of unicode string, so the legacy content(like the ascii string in other codepage) can be decoded by the caller.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use wz_reader::property::WzString;
+    /// let ascii = WzString::from_str("hi", [0x4D, 0x23, 0xC7, 0x2B]);
+    /// assert_eq!(ascii.get_raw_bytes().unwrap(), b"hi");
+    ///
+    /// let unicode = WzString::from_str("測", [0; 4]);
+    /// assert_eq!(unicode.get_raw_bytes().unwrap(), 0x6E2C_u16.to_le_bytes());
+    /// ```
+    pub fn get_raw_bytes(&self) -> Result<Vec<u8>, WzStringParseError> {
+        if let Some(plain) = &self.plain {
+            return Ok(match self.string_type {
+                WzStringType::Unicode => plain.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+                _ => plain.as_bytes().to_vec(),
+            });
+        }
+
+        let length = self.length as usize;
+
+        Ok(match self.string_type {
+            WzStringType::Empty => Vec::new(),
+            WzStringType::Ascii => self.reader.resolve_ascii_raw(self.offset, length)?,
+            WzStringType::Unicode => self
+                .reader
+                .resolve_unicode_raw(self.offset, length)?
+                .into_iter()
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+        })
+    }
+}
+
+/// A string encrypted once, to compare with many `WzString`s by the raw bytes without decoding them,
+/// much faster when scanning a large tree for a known string.
+///
+/// The `WzString` from a file with other iv will be decoded to compare.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::property::{WzString, WzStringNeedle};
+/// let iv = [0x4D, 0x23, 0xC7, 0x2B];
+/// let needle = WzStringNeedle::new("source", iv);
+///
+/// assert!(needle.matches(&WzString::from_str("source", iv)));
+/// assert!(!needle.matches(&WzString::from_str("sources", iv)));
+/// assert!(needle.matches(&WzString::from_str("source", [0; 4])));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WzStringNeedle {
+    plain: String,
+    iv: [u8; 4],
+    ascii: Vec<u8>,
+    unicode: Vec<u8>,
+}
+
+impl WzStringNeedle {
+    /// Encrypt the needle with the iv of the file going to scan, as both ascii and unicode,
+    /// since the string type in the file is not always the same.
+    pub fn new(needle: &str, iv: [u8; 4]) -> Self {
+        let mut keys = WzMutableKey::from_iv(iv);
+
+        Self {
+            ascii: encrypt_str(&mut keys, needle, &WzStringType::Ascii),
+            unicode: encrypt_str(&mut keys, needle, &WzStringType::Unicode),
+            plain: needle.to_string(),
+            iv,
+        }
+    }
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.plain
+    }
+    #[inline]
+    pub fn iv(&self) -> [u8; 4] {
+        self.iv
+    }
+    /// Whether the string is the same as the needle.
+    pub fn matches<R: DataSource + ?Sized>(&self, string: &WzString<R>) -> bool {
+        if let Some(plain) = &string.plain {
+            return **plain == *self.plain;
+        }
+
+        if string.get_iv() != self.iv {
+            return string
+                .get_string()
+                .map(|string| string == self.plain)
+                .unwrap_or(false);
+        }
+
+        let encrypted = match string.string_type {
+            WzStringType::Empty => return self.plain.is_empty(),
+            WzStringType::Ascii => &self.ascii,
+            WzStringType::Unicode => &self.unicode,
+        };
+
+        encrypted.len() == string.length as usize
+            && string
+                .reader
+                .get_slice(string.offset..string.offset + encrypted.len())
+                == encrypted.as_slice()
+    }
+    /// Whether the node is a string node and the same as the needle.
+    pub fn matches_node(&self, node: &WzNodeArc) -> bool {
+        node.read()
+            .unwrap()
+            .try_as_string()
+            .is_some_and(|string| self.matches(string))
+    }
 }
 
 #[inline]
@@ -145,30 +401,41 @@ pub(crate) fn encrypt_str(
     keys: &mut WzMutableKey,
     str: &str,
     string_type: &WzStringType,
+) -> Vec<u8> {
+    let bytes = match string_type {
+        WzStringType::Empty => Vec::new(),
+        WzStringType::Unicode => str.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        WzStringType::Ascii => str.as_bytes().to_vec(),
+    };
+
+    encrypt_raw(keys, bytes, string_type)
+}
+
+/// Encrypt the decrypted bytes, the utf16(little endian) for unicode string, a odd trailing byte is dropped.
+pub(crate) fn encrypt_raw(
+    keys: &mut WzMutableKey,
+    mut bytes: Vec<u8>,
+    string_type: &WzStringType,
 ) -> Vec<u8> {
     match string_type {
         WzStringType::Empty => Vec::new(),
         WzStringType::Unicode => {
-            let mut bytes = str.encode_utf16().collect::<Vec<_>>();
+            bytes.truncate(bytes.len() / 2 * 2);
+
+            keys.ensure_key_size(bytes.len()).unwrap();
+
+            for (i, chunk) in bytes.chunks_exact_mut(2).enumerate() {
+                let key1 = *keys.try_at(i * 2).unwrap_or(&0) as u16;
+                let key2 = *keys.try_at(i * 2 + 1).unwrap_or(&0) as u16;
+                let i = (i + 0xAAAA) as u16;
+                let c = u16::from_le_bytes([chunk[0], chunk[1]]) ^ i ^ key1 ^ (key2 << 8);
 
-            keys.ensure_key_size(bytes.len() * 2).unwrap();
+                chunk.copy_from_slice(&c.to_le_bytes());
+            }
 
             bytes
-                .iter_mut()
-                .enumerate()
-                .flat_map(|(i, b)| {
-                    let key1 = *keys.try_at(i * 2).unwrap_or(&0) as u16;
-                    let key2 = *keys.try_at(i * 2 + 1).unwrap_or(&0) as u16;
-                    let i = (i + 0xAAAA) as u16;
-                    *b ^= i ^ key1 ^ (key2 << 8);
-
-                    b.to_le_bytes().to_vec()
-                })
-                .collect()
         }
         WzStringType::Ascii => {
-            let mut bytes = str.bytes().collect::<Vec<_>>();
-
             keys.ensure_key_size(bytes.len()).unwrap();
 
             for (i, b) in bytes.iter_mut().enumerate() {
@@ -184,7 +451,7 @@ pub(crate) fn encrypt_str(
 }
 
 #[cfg(feature = "serde")]
-impl Serialize for WzString {
+impl<R: DataSource + ?Sized> Serialize for WzString<R> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -194,6 +461,20 @@ impl Serialize for WzString {
         serializer.serialize_str(&str)
     }
 }
+
+/// Serialized as the decoded string.
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for WzString {
+    fn is_referenceable() -> bool {
+        false
+    }
+    fn schema_name() -> String {
+        String::schema_name()
+    }
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
 #[cfg(feature = "serde")]
 use serde::de::{self, Deserializer, Visitor};
 #[cfg(feature = "serde")]
@@ -215,7 +496,7 @@ impl<'de> Deserialize<'de> for WzString {
             where
                 E: de::Error,
             {
-                Ok(WzString::from_str(value, Default::default()))
+                Ok(WzString::from_plain(value))
             }
         }
 
@@ -301,6 +582,99 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_wz_string_create_plain_unicode() -> Result<()> {
+        let wz_string = WzString::from_plain("測試");
+
+        assert!(wz_string.is_plain());
+        assert_eq!(wz_string.string_type, WzStringType::Unicode);
+        assert_eq!(wz_string.get_string()?, "測試");
+
+        let iv = [0x4D, 0x23, 0xC7, 0x2B];
+        let encrypted = WzString::from_str("測試", iv);
+
+        assert!(!encrypted.is_plain());
+        assert_eq!(wz_string.encrypt(iv)?, encrypted.encrypt(iv)?);
+        assert_ne!(wz_string.encrypt(iv)?, wz_string.encrypt([0; 4])?);
+
+        /* plain strings share the same empty reader */
+        assert!(Arc::ptr_eq(
+            &wz_string.reader,
+            &WzString::from_plain("other").reader
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wz_string_raw_bytes() -> Result<()> {
+        let iv = [0x4D, 0x23, 0xC7, 0x2B];
+
+        /* a cp949 string that is not valid utf8 */
+        let legacy = [0xC7, 0xD1, 0xB1, 0xDB];
+        let mut keys = WzMutableKey::from_iv(iv);
+        keys.ensure_key_size(legacy.len()).unwrap();
+        let encrypted = legacy
+            .iter()
+            .enumerate()
+            .map(|(i, b)| b ^ (i + 0xAA) as u8 ^ keys.try_at(i).unwrap())
+            .collect::<Vec<_>>();
+
+        let reader = Arc::new(WzReader::from_buff(&encrypted).with_iv(iv));
+        let string = WzString::from_meta(WzStringMeta::new_ascii(0, 4), &reader);
+
+        assert_eq!(string.get_raw_bytes()?, legacy);
+        assert!(string.get_string_lossy()?.contains('\u{FFFD}'));
+        assert_eq!(string.get_string()?, string.get_string_lossy()?);
+        assert!(string.try_get_string().is_err());
+
+        let unicode = WzString::from_str("測試", iv);
+        assert_eq!(
+            unicode.get_raw_bytes()?,
+            [0x6E2C_u16.to_le_bytes(), 0x8A66_u16.to_le_bytes()].concat()
+        );
+        assert_eq!(unicode.try_get_string()?, "測試");
+        assert_eq!(
+            WzString::from_plain("測試").get_raw_bytes()?,
+            unicode.get_raw_bytes()?
+        );
+        assert!(WzString::from_str("", iv).get_raw_bytes()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_needle() {
+        let iv = [0x4D, 0x23, 0xC7, 0x2B];
+        let needle = WzStringNeedle::new("測試", iv);
+
+        assert!(needle.matches(&WzString::from_str("測試", iv)));
+        assert!(!needle.matches(&WzString::from_str("測", iv)));
+        assert!(!needle.matches(&WzString::from_str("測驗", iv)));
+        assert!(needle.matches(&WzString::from_plain("測試")));
+        assert!(needle.matches(&WzString::from_str("測試", [0; 4])));
+
+        /* same string stored in another type */
+        let needle = WzStringNeedle::new("source", iv);
+        let unicode = WzString::from_str_with_type("source", iv, WzStringType::Unicode);
+        assert!(needle.matches(&unicode));
+        assert!(!needle.matches(&WzString::from_str("sourcE", iv)));
+        assert!(!needle.matches(&WzString::from_str("", iv)));
+        assert!(WzStringNeedle::new("", iv).matches(&WzString::from_str("", iv)));
+
+        /* the string in the middle of a buffer */
+        let encrypted = WzString::from_str("source", iv).encrypt(iv).unwrap();
+        let reader = WzReader::from_buff(&[[0xFF; 3].as_slice(), &encrypted].concat()).with_iv(iv);
+        let string = WzString::from_meta(WzStringMeta::new_ascii(3, 6), &Arc::new(reader));
+        assert!(needle.matches(&string));
+        assert_eq!(string.get_string().unwrap(), "source");
+
+        let node = WzNode::from_str("path", WzString::from_str("source", iv), None).into_lock();
+        assert!(needle.matches_node(&node));
+        let node = WzNode::from_str("path", 1, None).into_lock();
+        assert!(!needle.matches_node(&node));
+    }
+
     #[test]
     fn test_resolve_from_node_success() -> Result<()> {
         let node =
@@ -5,6 +5,7 @@ use std::ops::{Add, Div, Mul, Sub};
 use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vector2D(pub i32, pub i32);
 
@@ -19,6 +20,12 @@ impl Vector2D {
     }
 }
 
+impl From<(i32, i32)> for Vector2D {
+    fn from((x, y): (i32, i32)) -> Self {
+        Vector2D(x, y)
+    }
+}
+
 impl fmt::Display for Vector2D {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({}, {})", self.0, self.1)
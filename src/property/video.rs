@@ -1,24 +1,51 @@
-use crate::WzReader;
+use crate::{reader::DataSource, WzReader};
 use std::ops::Range;
 use std::sync::Arc;
 
-#[derive(Debug, Clone, Default)]
-pub struct WzVideo {
-    pub reader: Arc<WzReader>,
+#[derive(Debug, Default)]
+pub struct WzVideo<R: ?Sized = WzReader> {
+    pub reader: Arc<R>,
     offset: usize,
     length: usize,
 }
 
+impl<R: ?Sized> Clone for WzVideo<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            offset: self.offset,
+            length: self.length,
+        }
+    }
+}
+
 impl WzVideo {
-    pub fn new(reader: &Arc<WzReader>, offset: usize, length: usize) -> Self {
+    /// Create from owned bytes of the video file.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        let reader = Arc::new(WzReader::from_buff(&bytes));
+        Self::new(&reader, 0, bytes.len())
+    }
+}
+
+impl<R: DataSource + ?Sized> WzVideo<R> {
+    pub fn new(reader: &Arc<R>, offset: usize, length: usize) -> Self {
         Self {
             reader: Arc::clone(reader),
             offset,
             length,
         }
     }
+    /// The offset of the data in the wz file.
+    #[inline]
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+    #[inline]
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
     #[inline]
-    fn get_buffer_range(&self) -> Range<usize> {
+    pub fn get_buffer_range(&self) -> Range<usize> {
         self.offset..self.offset + self.length
     }
     #[inline]
@@ -1,11 +1,14 @@
+use crate::sync::RwLock;
+#[cfg(feature = "mmap")]
 use memmap2::Mmap;
 use scroll::{Pread, LE};
-use std::cell::Cell;
-use std::sync::{Arc, RwLock};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::sync::Arc;
 
 use crate::property::{encrypt_str, WzStringMeta, WzStringType};
-use crate::util::WzMutableKey;
-use crate::WzHeader;
+use crate::util::{get_shared_keys, stats, WzMutableKey};
+use crate::{WzHeader, WzNodeName};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -17,12 +20,114 @@ pub enum Error {
     ReadUtf8Error(#[from] std::string::FromUtf8Error),
     #[error("Error reading utf16 string: {0}")]
     ReadUtf16Error(#[from] std::string::FromUtf16Error),
+    #[error("Out of bounds: range {0}..{1} with data size {2}")]
+    OutOfBounds(usize, usize, usize),
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
 pub type SharedWzMutableKey = Arc<RwLock<WzMutableKey>>;
 
+/// the key size expanded when creating the reader with iv, most of the strings are shorter than this,
+/// so decrypting them won't need to lock the keys.
+pub const PRE_EXPAND_KEY_SIZE: usize = 0x4000;
+
+/// A snapshot of the expanded keys taken when the reader created, decrypting with it doesn't need any lock.
+/// It's only used when the reader's `keys` still the same one, otherwise fallback to locking the `keys`.
+#[derive(Debug, Clone)]
+pub(crate) struct KeySnapshot {
+    source: SharedWzMutableKey,
+    keys: Arc<Vec<u8>>,
+    without_decrypt: bool,
+}
+
+impl KeySnapshot {
+    fn new(source: &SharedWzMutableKey) -> Self {
+        let key = source.read().unwrap();
+        KeySnapshot {
+            source: Arc::clone(source),
+            keys: key.snapshot_keys(),
+            without_decrypt: key.without_decrypt,
+        }
+    }
+    /// expand the keys to `PRE_EXPAND_KEY_SIZE` first if needed.
+    fn expanded(source: &SharedWzMutableKey) -> Self {
+        let is_need_mut = {
+            let key = source.read().unwrap();
+            !key.is_enough(PRE_EXPAND_KEY_SIZE) && !key.without_decrypt
+        };
+        if is_need_mut {
+            /* failing here is fine, decrypting will fallback to expand it again */
+            let _ = source.write().unwrap().ensure_key_size(PRE_EXPAND_KEY_SIZE);
+        }
+        Self::new(source)
+    }
+    fn decrypt(&self, buf: &[u8], keys: &SharedWzMutableKey) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(buf.len());
+        self.decrypt_into(buf, keys, &mut out)?;
+        Ok(out)
+    }
+    fn decrypt_into(&self, buf: &[u8], keys: &SharedWzMutableKey, out: &mut Vec<u8>) -> Result<()> {
+        if Arc::ptr_eq(&self.source, keys) {
+            if self.without_decrypt {
+                out.clear();
+                out.extend_from_slice(buf);
+                return Ok(());
+            }
+            if let Some(keys) = self.keys.get(0..buf.len()) {
+                stats::record_decrypted(buf.len());
+                out.clear();
+                out.extend(buf.iter().zip(keys).map(|(byte, key)| byte ^ key));
+                return Ok(());
+            }
+        }
+
+        stats::record_decrypted(buf.len());
+        decrypt_slice_into(buf, keys, out)
+    }
+    fn is_without_decrypt(&self, keys: &SharedWzMutableKey) -> bool {
+        if Arc::ptr_eq(&self.source, keys) {
+            self.without_decrypt
+        } else {
+            keys.read().unwrap().without_decrypt
+        }
+    }
+    /// borrow the `buf` directly when no need to decrypt, otherwise decrypt into `out`.
+    fn decrypt_with<'a>(
+        &self,
+        buf: &'a [u8],
+        keys: &SharedWzMutableKey,
+        out: &'a mut Vec<u8>,
+    ) -> Result<&'a [u8]> {
+        if self.is_without_decrypt(keys) {
+            return Ok(buf);
+        }
+        self.decrypt_into(buf, keys, out)?;
+        Ok(out)
+    }
+    fn decrypt_cow<'a>(&self, buf: &'a [u8], keys: &SharedWzMutableKey) -> Result<Cow<'a, [u8]>> {
+        if self.is_without_decrypt(keys) {
+            return Ok(Cow::Borrowed(buf));
+        }
+        self.decrypt(buf, keys).map(Cow::Owned)
+    }
+}
+
+/// The storage the properties read their data from, properties like `WzPng` and `WzSound` are generic over it,
+/// implement it to let them read from other storage than the mmaped wz file.
+pub trait DataSource: Reader {
+    /// the raw data in the range, panic when out of range.
+    fn get_slice(&self, range: std::ops::Range<usize>) -> &[u8];
+    /// the iv of the data belongs to.
+    fn wz_iv(&self) -> [u8; 4];
+    /// the keys use to decrypt the strings and encrypted canvases.
+    fn keys(&self) -> &SharedWzMutableKey;
+}
+
+/// A type-erased `DataSource`, the properties can be created with `Arc<DynDataSource>`
+/// like `WzRawData::<DynDataSource>::new`, to plug in a custom storage without being generic all the way.
+pub type DynDataSource = dyn DataSource + Send + Sync;
+
 /// A basic reader for reading data, it store original data, and can't not
 /// read data without provide offset of the data.
 #[derive(Debug)]
@@ -30,21 +135,45 @@ pub struct WzBaseReader<T: Sized + AsRef<[u8]>> {
     pub map: T,
     pub wz_iv: [u8; 4],
     pub keys: Arc<RwLock<WzMutableKey>>,
+    key_snapshot: KeySnapshot,
+}
+
+/// The buffer of `WzReader`, `Mmap` with the `mmap` feature, otherwise the whole file read into memory.
+#[cfg(feature = "mmap")]
+pub type WzReaderBuffer = Mmap;
+/// The buffer of `WzReader`, `Mmap` with the `mmap` feature, otherwise the whole file read into memory.
+#[cfg(not(feature = "mmap"))]
+pub type WzReaderBuffer = Vec<u8>;
+
+/// the Mmap(or Vec without `mmap` feature) impl for WzBaseReader
+pub type WzReader = WzBaseReader<WzReaderBuffer>;
+
+/// Open the file as the buffer of `WzReader`.
+#[cfg(feature = "mmap")]
+pub fn open_reader_buffer<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<WzReaderBuffer> {
+    let file = std::fs::File::open(path)?;
+    unsafe { Mmap::map(&file) }
 }
 
-/// the Mmap impl for WzBaseReader
-pub type WzReader = WzBaseReader<Mmap>;
+/// Open the file as the buffer of `WzReader`.
+#[cfg(not(feature = "mmap"))]
+pub fn open_reader_buffer<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<WzReaderBuffer> {
+    std::fs::read(path)
+}
 
+#[cfg(feature = "mmap")]
 impl Default for WzBaseReader<Mmap> {
     fn default() -> Self {
         let memmap = memmap2::MmapMut::map_anon(1)
             .unwrap()
             .make_read_only()
             .unwrap();
+        let keys = Arc::new(RwLock::new(WzMutableKey::new([0; 4], [0; 32])));
         WzBaseReader {
             map: memmap,
             wz_iv: [0; 4],
-            keys: Arc::new(RwLock::new(WzMutableKey::new([0; 4], [0; 32]))),
+            key_snapshot: KeySnapshot::new(&keys),
+            keys,
         }
     }
 }
@@ -58,13 +187,37 @@ pub struct WzSliceReader<'a> {
     _save_pos: Cell<usize>,
     pub header: WzHeader<'a>,
     pub keys: Arc<RwLock<WzMutableKey>>,
+    key_snapshot: KeySnapshot,
 }
 
 static WZ_OFFSET: i32 = 0x581C3F6D;
 
+/// ascii strings not longer than this will be resolved on the stack
+const STACK_STRING_SIZE: usize = 64;
+
 pub trait Reader {
     fn get_size(&self) -> usize;
     fn get_decrypt_slice(&self, range: std::ops::Range<usize>) -> Result<Vec<u8>>;
+    /// Same as `get_decrypt_slice`, but write the result into `out`(cleared first), so the buffer can be reused.
+    fn decrypt_slice_into(&self, range: std::ops::Range<usize>, out: &mut Vec<u8>) -> Result<()> {
+        let decrypted = self.get_decrypt_slice(range)?;
+        out.clear();
+        out.extend_from_slice(&decrypted);
+        Ok(())
+    }
+    /// Same as `get_decrypt_slice`, but borrow the original data when it doesn't need to decrypt(the iv is zero).
+    fn get_decrypt_cow(&self, range: std::ops::Range<usize>) -> Result<Cow<'_, [u8]>> {
+        self.get_decrypt_slice(range).map(Cow::Owned)
+    }
+    /// Like `get_decrypt_cow`, but decrypt into the `buffer` instead of allocating a new one.
+    fn get_decrypt_slice_with<'a>(
+        &'a self,
+        range: std::ops::Range<usize>,
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<&'a [u8]> {
+        self.decrypt_slice_into(range, buffer)?;
+        Ok(buffer)
+    }
     fn read_u8_at(&self, pos: usize) -> Result<u8>;
     fn read_u16_at(&self, pos: usize) -> Result<u16>;
     fn read_u32_at(&self, pos: usize) -> Result<u32>;
@@ -85,24 +238,26 @@ pub trait Reader {
         }
     }
     fn resolve_unicode_raw(&self, offset: usize, length: usize) -> Result<Vec<u16>> {
-        let decrypted = self.get_decrypt_slice(offset..(offset + length))?;
-        let mut strvec = Vec::with_capacity(length / 2);
+        with_decrypt_buffer(|buffer| {
+            let decrypted = self.get_decrypt_slice_with(offset..(offset + length), buffer)?;
+            let mut strvec = Vec::with_capacity(length / 2);
 
-        for (i, chunk) in decrypted.chunks(2).enumerate() {
-            let c = u16::from_le_bytes([chunk[0], chunk[1]]);
-            strvec.push(resolve_unicode_char(c, i as i32));
-        }
+            for (i, chunk) in decrypted.chunks(2).enumerate() {
+                let c = u16::from_le_bytes([chunk[0], chunk[1]]);
+                strvec.push(resolve_unicode_char(c, i as i32));
+            }
 
-        Ok(strvec)
+            Ok(strvec)
+        })
     }
     fn resolve_ascii_raw(&self, offset: usize, length: usize) -> Result<Vec<u8>> {
-        let mut decrypted = self.get_decrypt_slice(offset..(offset + length))?;
-
-        decrypted.iter_mut().enumerate().for_each(|(i, byte)| {
-            *byte = resolve_ascii_char(*byte, i as i32);
-        });
+        let decrypted = self.get_decrypt_cow(offset..(offset + length))?;
 
-        Ok(decrypted)
+        Ok(decrypted
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| resolve_ascii_char(*byte, i as i32))
+            .collect())
     }
     #[inline]
     fn resolve_wz_string_meta(
@@ -118,11 +273,9 @@ pub trait Reader {
 
                 Ok(String::from_utf16_lossy(&strvec))
             }
-            WzStringType::Ascii => {
-                let strvec = self.resolve_ascii_raw(offset, length)?;
-
-                Ok(String::from_utf8_lossy(&strvec).to_string())
-            }
+            WzStringType::Ascii => with_resolved_ascii(self, offset, length, |bytes| {
+                String::from_utf8_lossy(bytes).into_owned()
+            }),
         }
     }
     #[inline]
@@ -150,16 +303,22 @@ pub trait Reader {
 
 impl<T: AsRef<[u8]>> WzBaseReader<T> {
     pub fn new(map: T) -> Self {
+        let keys = Arc::new(RwLock::new(WzMutableKey::new([0; 4], [0; 32])));
         WzBaseReader {
             map,
-            keys: Arc::new(RwLock::new(WzMutableKey::new([0; 4], [0; 32]))),
+            key_snapshot: KeySnapshot::new(&keys),
+            keys,
             wz_iv: [0; 4],
         }
     }
+    /// set the iv and use the keys shared by all readers with the same iv(see `get_shared_keys`),
+    /// the keys will be expanded to `PRE_EXPAND_KEY_SIZE` here if not yet.
     pub fn with_iv(self, iv: [u8; 4]) -> Self {
+        let keys = get_shared_keys(iv);
         WzBaseReader {
             wz_iv: iv,
-            keys: Arc::new(RwLock::new(WzMutableKey::from_iv(iv))),
+            key_snapshot: KeySnapshot::expanded(&keys),
+            keys,
             ..self
         }
     }
@@ -170,16 +329,20 @@ impl<T: AsRef<[u8]>> WzBaseReader<T> {
         if keys_iv != self.wz_iv {
             self
         } else {
-            WzBaseReader { keys, ..self }
+            WzBaseReader {
+                key_snapshot: KeySnapshot::expanded(&keys),
+                keys,
+                ..self
+            }
         }
     }
 
     #[inline]
-    pub fn try_header(&self) -> Result<WzHeader> {
+    pub fn try_header(&self) -> Result<WzHeader<'_>> {
         self.map.as_ref().pread::<WzHeader>(0)
     }
     #[inline]
-    pub fn create_header(&self) -> WzHeader {
+    pub fn create_header(&self) -> WzHeader<'_> {
         self.map
             .as_ref()
             .pread::<WzHeader>(0)
@@ -202,12 +365,18 @@ impl<T: AsRef<[u8]>> WzBaseReader<T> {
         WzHeader::get_wz_fsize(self.map.as_ref())
     }
     #[inline]
-    pub fn create_slice_reader_without_hash(&self) -> WzSliceReader {
-        WzSliceReader::new(self.map.as_ref(), &self.keys).with_header(WzHeader::default())
+    pub fn create_slice_reader_without_hash(&self) -> WzSliceReader<'_> {
+        self.new_slice_reader().with_header(WzHeader::default())
     }
     #[inline]
-    pub fn create_slice_reader(&self) -> WzSliceReader {
-        WzSliceReader::new(self.map.as_ref(), &self.keys).with_header(self.create_header())
+    pub fn create_slice_reader(&self) -> WzSliceReader<'_> {
+        self.new_slice_reader().with_header(self.create_header())
+    }
+    fn new_slice_reader(&self) -> WzSliceReader<'_> {
+        WzSliceReader {
+            key_snapshot: self.key_snapshot.clone(),
+            ..WzSliceReader::without_snapshot(self.map.as_ref(), &self.keys)
+        }
     }
     /// create a encrypt string from current `WzReader`
     #[inline]
@@ -220,6 +389,29 @@ impl<T: AsRef<[u8]>> WzBaseReader<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> DataSource for WzBaseReader<T> {
+    #[inline]
+    fn get_slice(&self, range: std::ops::Range<usize>) -> &[u8] {
+        WzBaseReader::get_slice(self, range)
+    }
+    #[inline]
+    fn wz_iv(&self) -> [u8; 4] {
+        self.wz_iv
+    }
+    #[inline]
+    fn keys(&self) -> &SharedWzMutableKey {
+        &self.keys
+    }
+}
+
+#[cfg(not(feature = "mmap"))]
+impl Default for WzBaseReader<Vec<u8>> {
+    fn default() -> Self {
+        WzBaseReader::new(Vec::new())
+    }
+}
+
+#[cfg(feature = "mmap")]
 impl WzBaseReader<Mmap> {
     pub fn from_buff(buff: &[u8]) -> Self {
         let is_empty = buff.is_empty();
@@ -228,22 +420,37 @@ impl WzBaseReader<Mmap> {
         if !is_empty {
             memmap.copy_from_slice(buff);
         }
-        WzReader {
-            map: memmap.make_read_only().unwrap(),
-            keys: Arc::new(RwLock::new(WzMutableKey::new([0; 4], [0; 32]))),
-            wz_iv: [0; 4],
-        }
+        WzReader::new(memmap.make_read_only().unwrap())
+    }
+}
+
+#[cfg(not(feature = "mmap"))]
+impl WzBaseReader<Vec<u8>> {
+    pub fn from_buff(buff: &[u8]) -> Self {
+        WzReader::new(buff.to_vec())
     }
 }
 
 impl<'a> WzSliceReader<'a> {
     pub fn new(buf: &'a [u8], key: &Arc<RwLock<WzMutableKey>>) -> Self {
+        WzSliceReader {
+            key_snapshot: KeySnapshot::new(key),
+            ..Self::without_snapshot(buf, key)
+        }
+    }
+    /// the snapshot is a placeholder that always fallback to lock the keys, caller should replace it.
+    fn without_snapshot(buf: &'a [u8], key: &Arc<RwLock<WzMutableKey>>) -> Self {
         WzSliceReader {
             buf,
             pos: Cell::new(0),
             _save_pos: Cell::new(0),
             header: Default::default(),
             keys: Arc::clone(key),
+            key_snapshot: KeySnapshot {
+                source: Arc::clone(key),
+                keys: Arc::default(),
+                without_decrypt: false,
+            },
         }
     }
     #[inline]
@@ -274,6 +481,32 @@ impl<'a> WzSliceReader<'a> {
     pub fn skip(&self, len: usize) {
         self.pos.set(self.pos.get() + len);
     }
+    /// Make sure `offset..offset + len` is inside the data.
+    #[inline]
+    pub fn check_range(&self, offset: usize, len: usize) -> Result<()> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.buf.len() => Ok(()),
+            _ => Err(Error::OutOfBounds(
+                offset,
+                offset.saturating_add(len),
+                self.buf.len(),
+            )),
+        }
+    }
+    /// Same as `seek`, but returns error instead of moving past the end.
+    #[inline]
+    pub fn try_seek(&self, pos: usize) -> Result<()> {
+        self.check_range(pos, 0)?;
+        self.pos.set(pos);
+        Ok(())
+    }
+    /// Same as `skip`, but returns error instead of moving past the end.
+    #[inline]
+    pub fn try_skip(&self, len: usize) -> Result<()> {
+        self.check_range(self.pos.get(), len)?;
+        self.pos.set(self.pos.get() + len);
+        Ok(())
+    }
     #[inline]
     pub fn save_pos(&self) {
         self._save_pos.set(self.pos.get());
@@ -386,6 +619,50 @@ impl<'a> WzSliceReader<'a> {
 
         Ok(string)
     }
+    /// Same as `read_wz_string`, but create the `WzNodeName` directly, ascii name won't need the extra `String`.
+    pub fn read_wz_name(&self) -> Result<WzNodeName> {
+        let small_len = self.read_i8()?;
+
+        match self.get_wz_string_type(small_len) {
+            WzStringType::Empty => Ok(WzNodeName::default()),
+            WzStringType::Unicode => self.read_unicode_string(small_len).map(WzNodeName::from),
+            WzStringType::Ascii => {
+                let len = self.read_ascii_str_len(small_len)? as usize;
+
+                let name = with_resolved_ascii(self, self.pos.get(), len, |bytes| {
+                    WzNodeName::from(String::from_utf8_lossy(bytes).as_ref())
+                })?;
+
+                self.skip(len);
+
+                Ok(name)
+            }
+        }
+    }
+    #[inline]
+    pub fn read_wz_name_at_offset(&self, offset: usize) -> Result<WzNodeName> {
+        self.save_pos();
+
+        self.pos.set(offset);
+        let name = self.read_wz_name();
+
+        self.restore_pos();
+        name
+    }
+    /// Same as `read_wz_string_block`, but returns `WzNodeName`.
+    #[inline]
+    pub fn read_wz_name_block(&self, offset: usize) -> Result<WzNodeName> {
+        let string_type = self.read_u8()?;
+
+        match string_type {
+            0 | 0x73 => self.read_wz_name(),
+            1 | 0x1B => {
+                let append_offset = self.read_i32()?;
+                self.read_wz_name_at_offset(offset + append_offset as usize)
+            }
+            _ => Ok(WzNodeName::default()),
+        }
+    }
     #[inline]
     pub fn read_wz_string_meta_at(&self, offset: usize) -> Result<WzStringMeta> {
         self.save_pos();
@@ -507,18 +784,9 @@ impl<'a> WzSliceReader<'a> {
         // let offset: usize = self.pos.get();
         let offset = offset.unwrap_or(self.pos.get());
 
-        let fstart = self.header.fstart;
-
-        let offset = offset.wrapping_sub(fstart) ^ 0xFFFFFFFF;
-        let offset = offset.wrapping_mul(hash) & 0xFFFFFFFF;
-        let offset = offset.wrapping_sub(WZ_OFFSET as usize);
-        let offset = (offset as i32).rotate_left((offset as u32) & 0x1F) as usize & 0xFFFFFFFF;
-
-        let encrypted_offset = self.read_u32()? as usize;
-        let offset = (offset ^ encrypted_offset) & 0xFFFFFFFF;
-        let offset = offset.wrapping_add(fstart * 2) & 0xFFFFFFFF;
+        let encrypted_offset = self.read_u32()?;
 
-        Ok(offset)
+        Ok(decrypt_wz_offset(offset, self.header.fstart, hash, encrypted_offset) as usize)
     }
 }
 
@@ -598,8 +866,27 @@ impl<T: AsRef<[u8]>> Reader for WzBaseReader<T> {
     }
     #[inline]
     fn get_decrypt_slice(&self, range: std::ops::Range<usize>) -> Result<Vec<u8>> {
-        let len = range.len();
-        get_decrypt_slice(&self.map.as_ref()[range], len, &self.keys)
+        self.key_snapshot
+            .decrypt(&self.map.as_ref()[range], &self.keys)
+    }
+    #[inline]
+    fn decrypt_slice_into(&self, range: std::ops::Range<usize>, out: &mut Vec<u8>) -> Result<()> {
+        self.key_snapshot
+            .decrypt_into(&self.map.as_ref()[range], &self.keys, out)
+    }
+    #[inline]
+    fn get_decrypt_cow(&self, range: std::ops::Range<usize>) -> Result<Cow<'_, [u8]>> {
+        self.key_snapshot
+            .decrypt_cow(&self.map.as_ref()[range], &self.keys)
+    }
+    #[inline]
+    fn get_decrypt_slice_with<'a>(
+        &'a self,
+        range: std::ops::Range<usize>,
+        buffer: &'a mut Vec<u8>,
+    ) -> Result<&'a [u8]> {
+        self.key_snapshot
+            .decrypt_with(&self.map.as_ref()[range], &self.keys, buffer)
     }
 }
 
@@ -650,8 +937,25 @@ impl<'a> Reader for WzSliceReader<'a> {
     }
     #[inline]
     fn get_decrypt_slice(&self, range: std::ops::Range<usize>) -> Result<Vec<u8>> {
-        let len = range.len();
-        get_decrypt_slice(&self.buf[range], len, &self.keys)
+        self.key_snapshot.decrypt(&self.buf[range], &self.keys)
+    }
+    #[inline]
+    fn decrypt_slice_into(&self, range: std::ops::Range<usize>, out: &mut Vec<u8>) -> Result<()> {
+        self.key_snapshot
+            .decrypt_into(&self.buf[range], &self.keys, out)
+    }
+    #[inline]
+    fn get_decrypt_cow(&self, range: std::ops::Range<usize>) -> Result<Cow<'_, [u8]>> {
+        self.key_snapshot.decrypt_cow(&self.buf[range], &self.keys)
+    }
+    #[inline]
+    fn get_decrypt_slice_with<'b>(
+        &'b self,
+        range: std::ops::Range<usize>,
+        buffer: &'b mut Vec<u8>,
+    ) -> Result<&'b [u8]> {
+        self.key_snapshot
+            .decrypt_with(&self.buf[range], &self.keys, buffer)
     }
 }
 
@@ -754,16 +1058,23 @@ pub fn read_wz_offset(
     offset: usize,
     hash: usize,
 ) -> Result<usize> {
-    let offset = offset.wrapping_sub(fstart) ^ 0xFFFFFFFF;
-    let offset = offset.wrapping_mul(hash) & 0xFFFFFFFF;
-    let offset = offset.wrapping_sub(WZ_OFFSET as usize);
-    let offset = offset.rotate_left((offset as u32) & 0x1F) & 0xFFFFFFFF;
-
     let encrypted_offset = buf.pread_with::<u32>(encrypted_offset, LE)?;
-    let offset = (offset ^ encrypted_offset as usize) & 0xFFFFFFFF;
-    let offset = offset.wrapping_add(fstart * 2) & 0xFFFFFFFF;
 
-    Ok(offset)
+    Ok(decrypt_wz_offset(offset, fstart, hash, encrypted_offset) as usize)
+}
+
+/// The offset math is done in `u32` with wrapping, only the low 32 bits of the inputs matter,
+/// so it's the same on 32-bit(wasm) and 64-bit targets.
+#[inline]
+fn decrypt_wz_offset(offset: usize, fstart: usize, hash: usize, encrypted_offset: u32) -> u32 {
+    let fstart = fstart as u32;
+
+    let offset = (offset as u32).wrapping_sub(fstart) ^ 0xFFFFFFFF;
+    let offset = offset.wrapping_mul(hash as u32);
+    let offset = offset.wrapping_sub(WZ_OFFSET as u32);
+    let offset = offset.rotate_left(offset & 0x1F);
+
+    (offset ^ encrypted_offset).wrapping_add(fstart.wrapping_mul(2))
 }
 
 pub fn read_unicode_string(buf: &[u8], sl: i8) -> Result<String> {
@@ -824,6 +1135,18 @@ pub fn get_decrypt_slice(
     len: usize,
     keys: &Arc<RwLock<WzMutableKey>>,
 ) -> Result<Vec<u8>> {
+    let mut original = Vec::with_capacity(len);
+    decrypt_slice_into(&buf[0..len], keys, &mut original)?;
+    Ok(original)
+}
+
+/// Same as `get_decrypt_slice`, but write the result into `out`(cleared first).
+pub fn decrypt_slice_into(
+    buf: &[u8],
+    keys: &Arc<RwLock<WzMutableKey>>,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let len = buf.len();
     let is_need_mut = {
         let read = keys.read().unwrap();
         !read.is_enough(len) && !read.without_decrypt
@@ -837,13 +1160,69 @@ pub fn get_decrypt_slice(
 
     let keys = keys.read().unwrap();
 
-    let mut original = buf.to_vec();
+    out.clear();
+    out.extend_from_slice(buf);
 
     if !keys.without_decrypt {
-        keys.decrypt_slice(&mut original);
+        keys.decrypt_slice(out);
     }
 
-    Ok(original)
+    Ok(())
+}
+
+/// Resolve the ascii string and pass the bytes to `f`. With zero iv the data is read in place,
+/// and short string is resolved on the stack, so nothing will be allocated before `f`.
+pub fn with_resolved_ascii<T, R: Reader + ?Sized>(
+    reader: &R,
+    offset: usize,
+    length: usize,
+    f: impl FnOnce(&[u8]) -> T,
+) -> Result<T> {
+    with_decrypt_buffer(|buffer| {
+        let decrypted = reader.get_decrypt_slice_with(offset..(offset + length), buffer)?;
+        let resolved = decrypted
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| resolve_ascii_char(*byte, i as i32));
+
+        if length <= STACK_STRING_SIZE {
+            let mut stack = [0_u8; STACK_STRING_SIZE];
+            stack
+                .iter_mut()
+                .zip(resolved)
+                .for_each(|(slot, byte)| *slot = byte);
+            Ok(f(&stack[..length]))
+        } else {
+            Ok(f(&resolved.collect::<Vec<_>>()))
+        }
+    })
+}
+
+thread_local! {
+    static DECRYPT_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f` with a thread-local buffer, it can be passed to `Reader::decrypt_slice_into`
+/// to avoid allocating a new `Vec` for every decryption during bulk parsing.
+/// The nested call will get a new empty buffer instead of the shared one.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::reader::{with_decrypt_buffer, Reader, WzReader};
+/// let reader = WzReader::from_buff(b"test");
+///
+/// let len = with_decrypt_buffer(|buf| {
+///     reader.decrypt_slice_into(0..4, buf).unwrap();
+///     buf.len()
+/// });
+/// assert_eq!(len, 4);
+/// ```
+pub fn with_decrypt_buffer<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    DECRYPT_BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => f(&mut buffer),
+        Err(_) => f(&mut Vec::new()),
+    })
 }
 
 #[cfg(test)]
@@ -977,6 +1356,47 @@ mod test {
         Ok(setup_vec)
     }
 
+    #[test]
+    fn test_decrypt_wz_offset() -> Result<()> {
+        /* the same math in u64 with explicit masks */
+        fn expected(offset: u64, fstart: u64, hash: u64, encrypted_offset: u64) -> u64 {
+            let offset = (offset.wrapping_sub(fstart) ^ 0xFFFFFFFF) & 0xFFFFFFFF;
+            let offset = offset.wrapping_mul(hash) & 0xFFFFFFFF;
+            let offset = offset.wrapping_sub(WZ_OFFSET as u64) & 0xFFFFFFFF;
+            let shift = offset & 0x1F;
+            let offset = ((offset << shift) | (offset >> (32 - shift))) & 0xFFFFFFFF;
+            ((offset ^ encrypted_offset) + fstart * 2) & 0xFFFFFFFF
+        }
+
+        let cases = [
+            (62, 60, 0xA3F1, 0x12345678),
+            /* the subtraction underflows */
+            (0x1000, 60, 1, 0xFFFFFFFF),
+            (0xFFFF_FFF0, 0x3C, 0x7FFF_FFFF, 0),
+            (60, 60, 0xFFFF_FFFF, 0xDEADBEEF),
+        ];
+
+        for (offset, fstart, hash, encrypted_offset) in cases {
+            assert_eq!(
+                decrypt_wz_offset(offset, fstart, hash, encrypted_offset) as u64,
+                expected(
+                    offset as u64,
+                    fstart as u64,
+                    hash as u64,
+                    encrypted_offset as u64
+                )
+            );
+
+            let buf = encrypted_offset.to_le_bytes();
+            assert_eq!(
+                read_wz_offset(&buf, 0, fstart, offset, hash)?,
+                decrypt_wz_offset(offset, fstart, hash, encrypted_offset) as usize
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_wz_header() -> Result<()> {
         let reader = WzVecReader::new(setup()?);
@@ -1018,6 +1438,100 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_decrypt_with_key_snapshot() -> Result<()> {
+        let len = PRE_EXPAND_KEY_SIZE + 10;
+        let mut key = WzMutableKey::from_iv(WZ_MSEAIV);
+        key.ensure_key_size(len)?;
+
+        let data = vec![0_u8; len];
+        let mut reader = WzVecReader::new(data.clone()).with_iv(WZ_MSEAIV);
+
+        /* pre expanded, short one from the snapshot, long one need to expand */
+        assert!(reader.keys.read().unwrap().is_enough(PRE_EXPAND_KEY_SIZE));
+        assert_eq!(reader.get_decrypt_slice(0..16)?, key.get_range(0..16));
+        assert_eq!(reader.get_decrypt_slice(0..len)?, key.get_range(0..len));
+        assert_eq!(
+            reader
+                .create_slice_reader_without_hash()
+                .get_decrypt_slice(0..16)?,
+            key.get_range(0..16)
+        );
+
+        /* keys replaced, the snapshot shouldn't be used anymore */
+        reader.keys = Arc::new(RwLock::new(WzMutableKey::from_iv([0; 4])));
+        assert_eq!(reader.get_decrypt_slice(0..16)?, data[0..16]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_slice_into_buffer() -> Result<()> {
+        let reader = WzVecReader::new(vec![1_u8; 64]).with_iv(WZ_MSEAIV);
+        let expected = reader.get_decrypt_slice(0..32)?;
+
+        let mut buf = vec![0_u8; 100];
+        reader.decrypt_slice_into(0..32, &mut buf)?;
+        assert_eq!(buf, expected);
+
+        with_decrypt_buffer(|outer| -> Result<()> {
+            reader.decrypt_slice_into(0..16, outer)?;
+
+            /* nested call won't touch the outer buffer */
+            let inner_len = with_decrypt_buffer(|inner| {
+                reader.decrypt_slice_into(0..32, inner).map(|_| inner.len())
+            })?;
+
+            assert_eq!(inner_len, 32);
+            assert_eq!(outer.as_slice(), &expected[0..16]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_dyn_data_source() -> Result<()> {
+        use crate::property::{WzRawData, WzString};
+
+        let reader = WzReader::from_buff(&[]);
+        let encrypted = reader.encrypt_str("test", &WzStringType::Ascii);
+
+        let source: Arc<DynDataSource> = Arc::new(WzReader::from_buff(&encrypted));
+        let as_reader: &dyn Reader = source.as_ref();
+        assert_eq!(
+            as_reader.resolve_wz_string_meta(&WzStringType::Ascii, 0, 4)?,
+            "test"
+        );
+
+        let string = WzString::<DynDataSource>::from_meta(WzStringMeta::new_ascii(0, 4), &source);
+        assert_eq!(string.get_string()?, "test");
+
+        let raw_data = WzRawData::<DynDataSource>::new(&source, 1, 2);
+        assert_eq!(raw_data.get_buffer(), &encrypted[1..3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decrypt_cow() -> Result<()> {
+        let data = vec![1_u8; 32];
+
+        let reader = WzVecReader::new(data.clone());
+        assert!(matches!(reader.get_decrypt_cow(0..32)?, Cow::Borrowed(_)));
+
+        let reader = WzVecReader::new(data.clone()).with_iv(WZ_GMSIV);
+        let decrypted = reader.get_decrypt_cow(0..32)?;
+        assert!(matches!(decrypted, Cow::Owned(_)));
+        assert_eq!(decrypted, reader.get_decrypt_slice(0..32)?);
+
+        let mut buffer = Vec::new();
+        assert_eq!(
+            reader.get_decrypt_slice_with(0..32, &mut buffer)?,
+            decrypted.as_ref()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_wz_create_encrypt_str_ascii_with_iv() -> Result<()> {
         let mut reader = WzVecReader::new(Vec::new()).with_iv(WZ_MSEAIV);
@@ -1157,6 +1671,28 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_checked_seek_and_skip() -> Result<()> {
+        let reader = WzVecReader::new(vec![0; 10]);
+        let slice_reader = reader.create_slice_reader_without_hash();
+
+        slice_reader.try_seek(8)?;
+        slice_reader.try_skip(2)?;
+        assert_eq!(slice_reader.pos.get(), 10);
+
+        assert!(matches!(
+            slice_reader.try_skip(1),
+            Err(super::Error::OutOfBounds(10, 11, 10))
+        ));
+        assert!(slice_reader.try_seek(11).is_err());
+        assert!(slice_reader.check_range(5, usize::MAX).is_err());
+
+        /* position unchanged after failed */
+        assert_eq!(slice_reader.pos.get(), 10);
+
+        Ok(())
+    }
+
     #[test]
     fn test_wz_ascii_string() -> Result<()> {
         let reader = WzVecReader::new(setup()?);
@@ -1209,6 +1745,31 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_wz_read_name() -> Result<()> {
+        let reader = WzVecReader::new(setup()?);
+        let slice_reader = reader.create_slice_reader();
+
+        /* on the stack and the long one */
+        slice_reader.seek(119);
+        assert_eq!(slice_reader.read_wz_name()?.as_str(), "a".repeat(20));
+        assert_eq!(slice_reader.read_wz_name()?.as_str(), "a".repeat(200));
+        assert_eq!(slice_reader.read_wz_name()?.as_str(), "a".repeat(20));
+
+        assert_eq!(
+            slice_reader.read_wz_name_at_offset(140)?.as_str(),
+            "a".repeat(200)
+        );
+
+        let reader = WzVecReader::new(setup()?).with_iv(WZ_GMSIV);
+        let slice_reader = reader.create_slice_reader();
+
+        slice_reader.seek(791);
+        assert_eq!(slice_reader.read_wz_name()?.as_str(), "a".repeat(20));
+
+        Ok(())
+    }
+
     #[test]
     fn test_wz_unicode_string() -> Result<()> {
         let reader = WzVecReader::new(setup()?);
@@ -0,0 +1,89 @@
+//! The lock used by the tree, `std::sync::RwLock` by default,
+//! or `parking_lot::RwLock` with the `parking_lot` feature.
+//!
+//! The parking_lot one keeps the same api as std(`read().unwrap()`), it never poisons,
+//! so switching the feature won't break any code.
+
+#[cfg(not(feature = "parking_lot"))]
+pub use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(feature = "parking_lot")]
+pub use parking_lot_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(feature = "parking_lot")]
+mod parking_lot_lock {
+    use std::fmt;
+    use std::sync::{LockResult, TryLockError, TryLockResult};
+
+    pub type RwLockReadGuard<'a, T> = parking_lot::RwLockReadGuard<'a, T>;
+    pub type RwLockWriteGuard<'a, T> = parking_lot::RwLockWriteGuard<'a, T>;
+
+    /// `parking_lot::RwLock` with the `std::sync::RwLock` api.
+    #[derive(Default)]
+    pub struct RwLock<T: ?Sized>(parking_lot::RwLock<T>);
+
+    impl<T> RwLock<T> {
+        #[inline]
+        pub fn new(value: T) -> Self {
+            Self(parking_lot::RwLock::new(value))
+        }
+        #[inline]
+        pub fn into_inner(self) -> LockResult<T> {
+            Ok(self.0.into_inner())
+        }
+    }
+
+    impl<T: ?Sized> RwLock<T> {
+        #[inline]
+        pub fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+            Ok(self.0.read())
+        }
+        #[inline]
+        pub fn write(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+            Ok(self.0.write())
+        }
+        #[inline]
+        pub fn try_read(&self) -> TryLockResult<RwLockReadGuard<'_, T>> {
+            self.0.try_read().ok_or(TryLockError::WouldBlock)
+        }
+        #[inline]
+        pub fn try_write(&self) -> TryLockResult<RwLockWriteGuard<'_, T>> {
+            self.0.try_write().ok_or(TryLockError::WouldBlock)
+        }
+        #[inline]
+        pub fn get_mut(&mut self) -> LockResult<&mut T> {
+            Ok(self.0.get_mut())
+        }
+        /// Always `false`, parking_lot never poisons.
+        #[inline]
+        pub fn is_poisoned(&self) -> bool {
+            false
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_std_compatible_api() {
+            let lock = RwLock::new(1);
+
+            *lock.write().unwrap() += 1;
+            assert_eq!(*lock.read().unwrap(), 2);
+
+            let read = lock.read().unwrap();
+            assert!(lock.try_write().is_err());
+            assert!(lock.try_read().is_ok());
+            drop(read);
+
+            assert_eq!(lock.into_inner().unwrap(), 2);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use crate::{node::Error, version::WzMapleVersion, WzNodeArc};
+use std::io;
+use std::path::Path;
+
+use super::{hash_node, parse_all, resolve_base};
+
+/// The node at a path in one of the bases, see `BaseSet::find_across_versions`.
+#[derive(Debug, Clone)]
+pub struct VersionEntry {
+    pub label: String,
+    /// `None` when the base doesn't contain it.
+    pub node: Option<WzNodeArc>,
+    /// hash of the node and it's subtree by `hash_node`.
+    pub hash: Option<u64>,
+    /// whether it's different from the previous base, include added and removed, always `false` for the first one.
+    pub changed: bool,
+}
+
+/// Several `Base` trees(like different game versions) side by side under labels.
+///
+/// The keys are shared between the bases with same iv, so loading more versions won't expand
+/// the same keys again.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::BaseSet;
+/// let mut base_set = BaseSet::new();
+/// base_set.load("v249", "path/to/v249/Data/Base/Base.wz", None).unwrap();
+/// base_set.load("v250", "path/to/v250/Data/Base/Base.wz", None).unwrap();
+///
+/// let info = base_set.at("v250", "Mob/100100.img/info").unwrap();
+///
+/// for (label, node) in base_set.at_all("Mob/100100.img/info/maxHP") {
+///     println!("{label}: {:?}", node.map(|node| node.read().unwrap().to_string()));
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct BaseSet {
+    bases: Vec<(String, WzNodeArc)>,
+}
+
+impl BaseSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve the `Base.wz` by `resolve_base` and add it under the label, the old one with same label will be replaced.
+    pub fn load<P>(
+        &mut self,
+        label: &str,
+        path: P,
+        version: Option<WzMapleVersion>,
+    ) -> Result<WzNodeArc, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let base = resolve_base(path, version)?;
+        self.insert(label, base.clone());
+        Ok(base)
+    }
+
+    /// Add a already resolved root under the label, returns the replaced one.
+    pub fn insert(&mut self, label: &str, base: WzNodeArc) -> Option<WzNodeArc> {
+        if let Some((_, old)) = self.bases.iter_mut().find(|(name, _)| name == label) {
+            return Some(std::mem::replace(old, base));
+        }
+        self.bases.push((label.to_string(), base));
+        None
+    }
+
+    pub fn remove(&mut self, label: &str) -> Option<WzNodeArc> {
+        let index = self.bases.iter().position(|(name, _)| name == label)?;
+        Some(self.bases.remove(index).1)
+    }
+
+    pub fn get(&self, label: &str) -> Option<&WzNodeArc> {
+        self.bases
+            .iter()
+            .find(|(name, _)| name == label)
+            .map(|(_, base)| base)
+    }
+
+    /// The labels in the order they added.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.bases.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.bases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bases.is_empty()
+    }
+
+    /// Get the node by path like `Mob/100100.img/info` in the base of the label, parse all nodes in the path.
+    ///
+    /// # Errors
+    /// `Error::NodeNotFound` when the label or the node not found.
+    pub fn at(&self, label: &str, path: &str) -> Result<WzNodeArc, Error> {
+        let base = self.get(label).ok_or(Error::NodeNotFound)?;
+        let node = base.read().unwrap().at_path_parsed(path)?;
+        Ok(node)
+    }
+
+    /// Get the node at same path in every base, `None` for the base that failed.
+    pub fn at_all(&self, path: &str) -> Vec<(&str, Option<WzNodeArc>)> {
+        self.bases
+            .iter()
+            .map(|(label, base)| {
+                let node = base.read().unwrap().at_path_parsed(path).ok();
+                (label.as_str(), node)
+            })
+            .collect()
+    }
+
+    /// Find the node at same path in every base(in the order they added), and whether it changed from the previous one.
+    /// The node will be fully parsed to hash the subtree, so prefer the path to a image or deeper.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use wz_reader::util::BaseSet;
+    /// # let base_set = BaseSet::new();
+    /// for entry in base_set.find_across_versions("Mob/100100.img/stand/0") {
+    ///     if entry.changed {
+    ///         println!("changed in {}", entry.label);
+    ///     }
+    /// }
+    /// ```
+    pub fn find_across_versions(&self, path: &str) -> Vec<VersionEntry> {
+        let mut previous_hash = None;
+
+        self.at_all(path)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (label, node))| {
+                let hash = node.as_ref().map(|node| {
+                    // ignore the error, the failed one just has no children
+                    let _ = parse_all(node);
+                    hash_node(node)
+                });
+
+                let changed = index > 0 && hash != previous_hash;
+                previous_hash = hash;
+
+                VersionEntry {
+                    label: label.to_string(),
+                    node,
+                    hash,
+                    changed,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{WzDirectory, WzNode};
+
+    fn setup_base(hp: Option<i32>) -> WzNodeArc {
+        let base = WzNode::from_str("Base", 1, None).into_lock();
+        let mob = add_child(
+            "Mob",
+            WzDirectory {
+                is_parsed: true,
+                ..Default::default()
+            },
+            &base,
+        );
+        if let Some(hp) = hp {
+            add_child("maxHP", hp, &add_child("info", 1, &mob));
+        }
+        base
+    }
+
+    #[test]
+    fn test_base_set() {
+        let mut base_set = BaseSet::new();
+        assert!(base_set.insert("v250", setup_base(Some(20))).is_none());
+        base_set.insert("v249", setup_base(Some(10)));
+        base_set.insert("v248", setup_base(None));
+
+        assert_eq!(base_set.len(), 3);
+        assert_eq!(
+            base_set.labels().collect::<Vec<_>>(),
+            vec!["v250", "v249", "v248"]
+        );
+
+        let hp = base_set.at("v249", "Mob/info/maxHP").unwrap();
+        assert_eq!(hp.read().unwrap().get_full_path(), "Base/Mob/info/maxHP");
+
+        assert!(matches!(
+            base_set.at("v100", "Mob/info/maxHP"),
+            Err(Error::NodeNotFound)
+        ));
+
+        let all = base_set.at_all("Mob/info/maxHP");
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].0, "v250");
+        assert!(all[0].1.is_some());
+        assert!(all[2].1.is_none());
+
+        let replaced = base_set.insert("v250", setup_base(None));
+        assert!(replaced.is_some());
+        assert!(base_set.at("v250", "Mob/info").is_err());
+
+        assert!(base_set.remove("v248").is_some());
+        assert!(base_set.get("v248").is_none());
+        assert_eq!(base_set.len(), 2);
+    }
+
+    #[test]
+    fn test_find_across_versions() {
+        let mut base_set = BaseSet::new();
+        base_set.insert("v1", setup_base(None));
+        base_set.insert("v2", setup_base(Some(10)));
+        base_set.insert("v3", setup_base(Some(10)));
+        base_set.insert("v4", setup_base(Some(20)));
+        base_set.insert("v5", setup_base(None));
+
+        let entries = base_set.find_across_versions("Mob/info");
+
+        let changed = entries
+            .iter()
+            .map(|entry| (entry.label.as_str(), entry.changed))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            vec![
+                ("v1", false),
+                ("v2", true),
+                ("v3", false),
+                ("v4", true),
+                ("v5", true)
+            ]
+        );
+        assert!(entries[0].node.is_none());
+        assert_eq!(entries[1].hash, entries[2].hash);
+        assert!(entries[4].hash.is_none());
+    }
+}
@@ -0,0 +1,13 @@
+/// Run the blocking work on tokio's blocking pool, and resume the panic if it panicked.
+/// Returns the `JoinError` when the task is cancelled, like the runtime is shutting down.
+pub(crate) async fn run_blocking<F, T>(f: F) -> Result<T, tokio::task::JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => Ok(result),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{
+    property::{png::get_image, Vector2D, WzPngParseError},
+    wz_image, WzImage, WzNodeArc, WzNodeGetter,
+};
+use hashbrown::HashMap;
+use image::{imageops, RgbaImage};
+
+/// The layer names of `Base.wz/zmap.img`, to resolve the string `z` like `body` or `capeOverHead`.
+/// The earlier layer in the zmap is drawn above the later one.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::util::ZMap;
+/// let zmap = ZMap::from_names(["weapon", "body", "backBody"]);
+///
+/// assert!(zmap.get("weapon") > zmap.get("body"));
+/// assert_eq!(zmap.get("cape"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ZMap {
+    names: HashMap<String, i32>,
+}
+
+impl ZMap {
+    /// Create from the layer names in the zmap order, the top one first.
+    pub fn from_names<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let names = names.into_iter().map(Into::into).collect::<Vec<String>>();
+        let count = names.len() as i32;
+
+        Self {
+            names: names
+                .into_iter()
+                .enumerate()
+                .map(|(index, name)| (name, count - index as i32))
+                .collect(),
+        }
+    }
+    /// Read the layer names from the `zmap.img`, the children of a parsed node are unordered,
+    /// so it's parsed again from the image to keep the file order.
+    pub fn from_image(image: &WzImage) -> Result<Self, wz_image::Error> {
+        let (children, _) = image.resolve_children(None)?;
+        Ok(Self::from_names(
+            children.into_iter().map(|(name, _)| name.to_string()),
+        ))
+    }
+    /// The z of the layer, the higher one is drawn above.
+    pub fn get(&self, name: &str) -> Option<i32> {
+        self.names.get(name).copied()
+    }
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// A canvas to be composed, the position is decided by the canvas's `origin` and `offset`.
+#[derive(Debug, Clone)]
+pub struct CanvasLayer {
+    pub node: WzNodeArc,
+    /// extra offset of the origin, like the position of `map/neck` anchor in character parts.
+    pub offset: Vector2D,
+    /// override the `z` child of the canvas.
+    pub z: Option<i32>,
+}
+
+impl CanvasLayer {
+    pub fn new(node: &WzNodeArc) -> Self {
+        Self {
+            node: node.clone(),
+            offset: Vector2D(0, 0),
+            z: None,
+        }
+    }
+    pub fn with_offset(mut self, offset: Vector2D) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn with_z(mut self, z: i32) -> Self {
+        self.z = Some(z);
+        self
+    }
+    /// The z of the layer, fallback to the numeric `z` child, otherwise 0.
+    /// The string `z` is not resolved here, see `compose_canvases_with_zmap`.
+    pub fn get_z(&self) -> i32 {
+        self.z
+            .or_else(|| self.node.get_int_at("z"))
+            .unwrap_or_default()
+    }
+    /// Same as `get_z`, but the string `z` is resolved by the `zmap`.
+    fn resolve_z(&self, zmap: Option<&ZMap>) -> Result<i32, WzPngParseError> {
+        if let Some(z) = self.z.or_else(|| self.node.get_int_at("z")) {
+            return Ok(z);
+        }
+
+        match self.node.get_str_at("z") {
+            Some(name) => zmap
+                .and_then(|zmap| zmap.get(&name))
+                .ok_or(WzPngParseError::UnknownZLayer(name)),
+            None => Ok(0),
+        }
+    }
+    fn get_origin(&self) -> Vector2D {
+        self.node.get_vec2_at("origin").unwrap_or(Vector2D(0, 0))
+    }
+}
+
+/// The result of `compose_canvases`.
+#[derive(Debug, Clone)]
+pub struct ComposedCanvas {
+    pub image: RgbaImage,
+    /// where the shared anchor point is in the image, use it like the canvas's `origin`.
+    pub origin: Vector2D,
+}
+
+/// Render the canvases into a single image, every canvas's `origin` is placed at the same anchor point(plus the `offset`).
+/// Layers are drawn from the lowest z to highest, the same z keep the given order.
+/// `_inlink` and `_outlink` are resolved when getting the image.
+///
+/// Only the numeric `z` is supported, the canvas with a string `z`(like the character parts) returns `UnknownZLayer`,
+/// use `compose_canvases_with_zmap` for them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, compose_canvases, CanvasLayer};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let body = base.read().unwrap().at_path_parsed("Character/00002000.img/stand1/0/body").unwrap();
+/// let arm = base.read().unwrap().at_path_parsed("Character/00002000.img/stand1/0/arm").unwrap();
+///
+/// let composed = compose_canvases(&[CanvasLayer::new(&body), CanvasLayer::new(&arm)]).unwrap();
+///
+/// composed.image.save("stand1.png").unwrap();
+/// ```
+pub fn compose_canvases(layers: &[CanvasLayer]) -> Result<ComposedCanvas, WzPngParseError> {
+    compose_canvases_inner(layers, None)
+}
+
+/// Same as `compose_canvases`, but the string `z` is resolved by the `zmap`,
+/// returns `UnknownZLayer` when it's not in the `zmap`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, compose_canvases_with_zmap, CanvasLayer, ZMap};
+/// # use wz_reader::WzNodeCast;
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let zmap = base.read().unwrap().at_path("Base/zmap.img").unwrap();
+/// let zmap = ZMap::from_image(zmap.read().unwrap().try_as_image().unwrap()).unwrap();
+///
+/// let body = base.read().unwrap().at_path_parsed("Character/00002000.img/stand1/0/body").unwrap();
+/// let arm = base.read().unwrap().at_path_parsed("Character/00002000.img/stand1/0/arm").unwrap();
+///
+/// let composed =
+///     compose_canvases_with_zmap(&[CanvasLayer::new(&body), CanvasLayer::new(&arm)], &zmap).unwrap();
+/// ```
+pub fn compose_canvases_with_zmap(
+    layers: &[CanvasLayer],
+    zmap: &ZMap,
+) -> Result<ComposedCanvas, WzPngParseError> {
+    compose_canvases_inner(layers, Some(zmap))
+}
+
+fn compose_canvases_inner(
+    layers: &[CanvasLayer],
+    zmap: Option<&ZMap>,
+) -> Result<ComposedCanvas, WzPngParseError> {
+    let mut placed = layers
+        .iter()
+        .map(|layer| {
+            let image = get_image(&layer.node)?.to_rgba8();
+            let top_left = layer.offset - layer.get_origin();
+            Ok((layer.resolve_z(zmap)?, top_left, image))
+        })
+        .collect::<Result<Vec<_>, WzPngParseError>>()?;
+
+    if placed.is_empty() {
+        return Ok(ComposedCanvas {
+            image: RgbaImage::new(0, 0),
+            origin: Vector2D(0, 0),
+        });
+    }
+
+    /* stable sort, so the same z keep the given order */
+    placed.sort_by_key(|(z, _, _)| *z);
+
+    let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for (_, top_left, image) in &placed {
+        left = left.min(top_left.0);
+        top = top.min(top_left.1);
+        right = right.max(top_left.0 + image.width() as i32);
+        bottom = bottom.max(top_left.1 + image.height() as i32);
+    }
+
+    let mut canvas = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+
+    for (_, top_left, image) in &placed {
+        imageops::overlay(
+            &mut canvas,
+            image,
+            (top_left.0 - left) as i64,
+            (top_left.1 - top) as i64,
+        );
+    }
+
+    Ok(ComposedCanvas {
+        image: canvas,
+        origin: Vector2D(-left, -top),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{
+        property::{WzPng, WzString},
+        WzNode,
+    };
+    use image::{DynamicImage, Rgba};
+
+    fn add_canvas(
+        name: &str,
+        size: (u32, u32),
+        color: [u8; 4],
+        origin: Vector2D,
+        parent: &WzNodeArc,
+    ) -> WzNodeArc {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(size.0, size.1, Rgba(color)));
+        let canvas = add_child(name, WzPng::from_image(&image, 2).unwrap(), parent);
+        add_child("origin", origin, &canvas);
+        canvas
+    }
+
+    #[test]
+    fn test_compose_canvases() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+
+        let red = add_canvas("red", (4, 4), [255, 0, 0, 255], Vector2D(2, 2), &root);
+        let blue = add_canvas("blue", (2, 2), [0, 0, 255, 255], Vector2D(0, 0), &root);
+        add_child("z", 1, &blue);
+
+        /* blue is listed first but has higher z */
+        let composed =
+            compose_canvases(&[CanvasLayer::new(&blue), CanvasLayer::new(&red)]).unwrap();
+
+        assert_eq!(composed.image.dimensions(), (4, 4));
+        assert_eq!(composed.origin, Vector2D(2, 2));
+        assert_eq!(composed.image.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(composed.image.get_pixel(2, 2), &Rgba([0, 0, 255, 255]));
+        assert_eq!(composed.image.get_pixel(3, 3), &Rgba([0, 0, 255, 255]));
+
+        /* override z and move it out of the red one */
+        let composed = compose_canvases(&[
+            CanvasLayer::new(&blue)
+                .with_z(-1)
+                .with_offset(Vector2D(2, 0)),
+            CanvasLayer::new(&red),
+        ])
+        .unwrap();
+
+        assert_eq!(composed.image.dimensions(), (6, 4));
+        assert_eq!(composed.origin, Vector2D(2, 2));
+        assert_eq!(composed.image.get_pixel(4, 2), &Rgba([0, 0, 255, 255]));
+        assert_eq!(composed.image.get_pixel(3, 2), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_compose_canvases_with_zmap() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+
+        let red = add_canvas("red", (2, 2), [255, 0, 0, 255], Vector2D(0, 0), &root);
+        add_child("z", WzString::from_str("arm", [0; 4]), &red);
+        let blue = add_canvas("blue", (2, 2), [0, 0, 255, 255], Vector2D(0, 0), &root);
+        add_child("z", WzString::from_str("body", [0; 4]), &blue);
+
+        let layers = [CanvasLayer::new(&red), CanvasLayer::new(&blue)];
+
+        assert!(matches!(
+            compose_canvases(&layers),
+            Err(WzPngParseError::UnknownZLayer(name)) if name == "arm"
+        ));
+
+        /* arm is above body */
+        let zmap = ZMap::from_names(["arm", "body"]);
+        let composed = compose_canvases_with_zmap(&layers, &zmap).unwrap();
+
+        assert_eq!(composed.image.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+        let zmap = ZMap::from_names(["body"]);
+        assert!(compose_canvases_with_zmap(&layers, &zmap).is_err());
+    }
+
+    #[test]
+    fn test_compose_empty() {
+        let composed = compose_canvases(&[]).unwrap();
+
+        assert_eq!(composed.image.dimensions(), (0, 0));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::property::{WzPng, WzSubProperty, WzValue};
+use crate::{WzNodeArc, WzObjectType};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Added,
+    Removed,
+    /// the type or the value has been changed, the children are compared separately.
+    Changed,
+}
+
+/// A difference between two trees, the added or removed subtree only report it's root.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeDiff {
+    /// path relative to the compared roots, like `100100.img/info/maxHP`.
+    pub path: String,
+    pub kind: DiffKind,
+    /// short description of the old node, like `Int: 10` or `PNG 32x32`, `None` when added.
+    pub old: Option<String>,
+    /// short description of the new node, `None` when removed.
+    pub new: Option<String>,
+}
+
+/// Print like `~ 100100.img/info/maxHP: Int: 10 -> Int: 20`.
+impl fmt::Display for NodeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, &self.old, &self.new) {
+            (DiffKind::Added, _, Some(new)) => write!(f, "+ {} ({new})", self.path),
+            (DiffKind::Removed, Some(old), _) => write!(f, "- {} ({old})", self.path),
+            (DiffKind::Changed, Some(old), Some(new)) => {
+                write!(f, "~ {}: {old} -> {new}", self.path)
+            }
+            _ => write!(f, "? {}", self.path),
+        }
+    }
+}
+
+fn describe(object_type: &WzObjectType) -> String {
+    match object_type {
+        WzObjectType::Property(WzSubProperty::PNG(png)) => {
+            format!("PNG {}x{}", png.width, png.height)
+        }
+        WzObjectType::Property(WzSubProperty::Sound(sound)) => {
+            format!("Sound {}ms", sound.duration)
+        }
+        WzObjectType::Value(_) => object_type.to_string(),
+        _ => object_type.type_name().to_string(),
+    }
+}
+
+/// Compare the data of the node itself, not including the children.
+/// The containers(file, directory, image) are always the same, their content are the children.
+fn is_same_data(old: &WzObjectType, new: &WzObjectType) -> bool {
+    if old.type_name() != new.type_name() {
+        return false;
+    }
+
+    match (old, new) {
+        (
+            WzObjectType::Property(WzSubProperty::PNG(old)),
+            WzObjectType::Property(WzSubProperty::PNG(new)),
+        ) => {
+            old.width == new.width
+                && old.height == new.height
+                && old.format() == new.format()
+                && compressed_data(old) == compressed_data(new)
+        }
+        (
+            WzObjectType::Property(WzSubProperty::Sound(old)),
+            WzObjectType::Property(WzSubProperty::Sound(new)),
+        ) => {
+            old.duration == new.duration
+                && old.get_raw_header() == new.get_raw_header()
+                && old.get_raw_buffer() == new.get_raw_buffer()
+        }
+        (WzObjectType::Value(WzValue::Lua(old)), WzObjectType::Value(WzValue::Lua(new))) => {
+            old.extract_lua().ok() == new.extract_lua().ok()
+        }
+        (
+            WzObjectType::Value(WzValue::RawData(old)),
+            WzObjectType::Value(WzValue::RawData(new)),
+        ) => old.get_buffer() == new.get_buffer(),
+        (WzObjectType::Value(WzValue::Video(old)), WzObjectType::Value(WzValue::Video(new))) => {
+            old.get_buffer() == new.get_buffer()
+        }
+        (WzObjectType::Value(_), WzObjectType::Value(_)) => old.to_string() == new.to_string(),
+        _ => true,
+    }
+}
+
+/// The zlib stream of the canvas, fallback to the stored bytes when the encrypted chunks are broken.
+fn compressed_data(png: &WzPng) -> Cow<'_, [u8]> {
+    png.raw_compressed()
+        .unwrap_or(Cow::Borrowed(png.get_raw_buffer()))
+}
+
+/// Feed the data of the node itself to the hasher, same data as `is_same_data` compares.
+fn hash_data(object_type: &WzObjectType, hasher: &mut DefaultHasher) {
+    object_type.type_name().hash(hasher);
+
+    match object_type {
+        WzObjectType::Property(WzSubProperty::PNG(png)) => {
+            (png.width, png.height, png.format()).hash(hasher);
+            compressed_data(png).hash(hasher);
+        }
+        WzObjectType::Property(WzSubProperty::Sound(sound)) => {
+            sound.duration.hash(hasher);
+            sound.get_raw_header().hash(hasher);
+            sound.get_raw_buffer().hash(hasher);
+        }
+        WzObjectType::Value(WzValue::Lua(lua)) => lua.extract_lua().ok().hash(hasher),
+        WzObjectType::Value(WzValue::RawData(raw_data)) => raw_data.get_buffer().hash(hasher),
+        WzObjectType::Value(WzValue::Video(video)) => video.get_buffer().hash(hasher),
+        WzObjectType::Value(_) => object_type.to_string().hash(hasher),
+        _ => {}
+    }
+}
+
+fn hash_node_inner(node: &WzNodeArc, hasher: &mut DefaultHasher) {
+    let node_read = node.read().unwrap();
+
+    hash_data(&node_read.object_type, hasher);
+
+    let mut children = node_read.children.iter().collect::<Vec<_>>();
+    children.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+    children.len().hash(hasher);
+    for (name, child) in children {
+        name.as_str().hash(hasher);
+        hash_node_inner(child, hasher);
+    }
+}
+
+/// Hash the node and it's subtree, the trees have no difference in `diff_nodes` will get the same hash.
+/// It won't parse anything, so parse it first(like `parse_all`).
+///
+/// The hash is only stable in the same build, don't store it.
+pub fn hash_node(node: &WzNodeArc) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_node_inner(node, &mut hasher);
+    hasher.finish()
+}
+
+fn diff_inner(
+    old: &WzNodeArc,
+    new: &WzNodeArc,
+    force_parse: bool,
+    path: &mut String,
+    diffs: &mut Vec<NodeDiff>,
+) {
+    if force_parse {
+        // ignore the error, the failed one just has no children
+        let _ = old.write().unwrap().parse(old);
+        let _ = new.write().unwrap().parse(new);
+    }
+
+    {
+        let old_read = old.read().unwrap();
+        let new_read = new.read().unwrap();
+
+        if !is_same_data(&old_read.object_type, &new_read.object_type) {
+            diffs.push(NodeDiff {
+                path: path.clone(),
+                kind: DiffKind::Changed,
+                old: Some(describe(&old_read.object_type)),
+                new: Some(describe(&new_read.object_type)),
+            });
+        }
+
+        let mut names = old_read
+            .children
+            .keys()
+            .chain(
+                new_read
+                    .children
+                    .keys()
+                    .filter(|name| !old_read.children.contains_key(*name)),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        for name in names {
+            let len = path.len();
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&name);
+
+            match (old_read.children.get(&name), new_read.children.get(&name)) {
+                (Some(old_child), Some(new_child)) => {
+                    diff_inner(old_child, new_child, force_parse, path, diffs)
+                }
+                (Some(old_child), None) => diffs.push(NodeDiff {
+                    path: path.clone(),
+                    kind: DiffKind::Removed,
+                    old: Some(describe(&old_child.read().unwrap().object_type)),
+                    new: None,
+                }),
+                (None, Some(new_child)) => diffs.push(NodeDiff {
+                    path: path.clone(),
+                    kind: DiffKind::Added,
+                    old: None,
+                    new: Some(describe(&new_child.read().unwrap().object_type)),
+                }),
+                (None, None) => {}
+            }
+
+            path.truncate(len);
+        }
+    }
+
+    if force_parse {
+        for node in [old, new] {
+            let mut node_write = node.write().unwrap();
+            if matches!(node_write.object_type, WzObjectType::Image(_)) {
+                node_write.unparse();
+            }
+        }
+    }
+}
+
+/// Compare two trees, like the `Base.wz` of two patches. The children are matched by name,
+/// and the diffs are sorted by path.
+///
+/// With `force_parse` every node will be parsed along the way, and the images are unparsed after compared
+/// to release memory, like `walk_node`.
+///
+/// Canvases and sounds are compared by the data, so a re-encoded canvas with same size is still reported.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::{diff_nodes, DiffKind}};
+/// let old = WzNode::from_str("old", 1, None).into_lock();
+/// let hp = WzNode::from_str("hp", 10, Some(&old)).into_lock();
+/// old.write().unwrap().add(&hp);
+///
+/// let new = WzNode::from_str("new", 1, None).into_lock();
+/// let hp = WzNode::from_str("hp", 20, Some(&new)).into_lock();
+/// new.write().unwrap().add(&hp);
+///
+/// let diffs = diff_nodes(&old, &new, false);
+///
+/// assert_eq!(diffs.len(), 1);
+/// assert_eq!(diffs[0].kind, DiffKind::Changed);
+/// assert_eq!(diffs[0].to_string(), "~ hp: Int: 10 -> Int: 20");
+/// ```
+pub fn diff_nodes(old: &WzNodeArc, new: &WzNodeArc, force_parse: bool) -> Vec<NodeDiff> {
+    let mut diffs = Vec::new();
+    diff_inner(old, new, force_parse, &mut String::new(), &mut diffs);
+    diffs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzString, WzImage, WzNode};
+
+    fn setup_tree(hp: i32, name: &str) -> WzNodeArc {
+        let root = WzNode::from_str(
+            "100100.img",
+            WzImage {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+        let info = add_child("info", 1, &root);
+        add_child("maxHP", hp, &info);
+        add_child("name", WzString::from_str(name, [0; 4]), &info);
+        root
+    }
+
+    #[test]
+    fn test_diff_nodes() {
+        let old = setup_tree(10, "Snail");
+        let new = setup_tree(10, "Blue Snail");
+
+        add_child("removed", 1, &add_child("stand", 1, &old));
+        let stand = add_child("stand", 1, &new);
+        add_child("0", 1, &add_child("move", 1, &new));
+        add_child("speed", 1.5f32, &stand);
+
+        let info = old.read().unwrap().at("info").unwrap();
+        info.write().unwrap().remove_child("maxHP");
+        add_child("maxHP", 10.0, &info);
+
+        let diffs = diff_nodes(&old, &new, false);
+
+        let lines = diffs
+            .iter()
+            .map(|diff| diff.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "~ info/maxHP: Double: 10 -> Int: 10",
+                "~ info/name: String: Snail -> String: Blue Snail",
+                "+ move (Int: 1)",
+                "- stand/removed (Int: 1)",
+                "+ stand/speed (Float: 1.5)",
+            ]
+        );
+        assert_eq!(diffs[2].kind, DiffKind::Added);
+        assert_eq!(diffs[2].old, None);
+    }
+
+    #[test]
+    fn test_diff_same_tree() {
+        let old = setup_tree(10, "Snail");
+        let new = setup_tree(10, "Snail");
+
+        assert!(diff_nodes(&old, &new, false).is_empty());
+        assert_eq!(hash_node(&old), hash_node(&new));
+
+        add_child("speed", 1, &old);
+        assert_ne!(hash_node(&old), hash_node(&new));
+        assert_ne!(hash_node(&setup_tree(11, "Snail")), hash_node(&new));
+    }
+}
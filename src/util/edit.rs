@@ -0,0 +1,365 @@
+use crate::{WzNode, WzNodeArc, WzObjectType};
+use hashbrown::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error("Parent node not found: {0}")]
+    ParentNotFound(String),
+
+    #[error("Root node can't be removed")]
+    RemoveRoot,
+}
+
+/// A edited node, only exist along the path that has been written.
+#[derive(Debug, Default)]
+struct ShadowNode {
+    /// overridden object type, `None` means fall through to the base.
+    object_type: Option<WzObjectType>,
+    children: HashMap<String, ShadowNode>,
+    removed: bool,
+    /// the node is newly created, so the base's children are not visible.
+    opaque: bool,
+}
+
+/// A copy-on-write editing layer on top of a parsed tree.
+///
+/// Reads fall through to the base tree, writes only allocate shadow nodes inside the session,
+/// the base tree will never be locked for writing, so many sessions can share one huge Base.
+/// Path is relative to the base node, and the base need to be parsed before, it won't parse anything.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzObjectType};
+/// # use wz_reader::property::WzValue;
+/// # use wz_reader::util::EditSession;
+/// let base = WzNode::from_str("base", 1, None).into_lock();
+/// let hp = WzNode::from_str("hp", 100, Some(&base)).into_lock();
+/// base.write().unwrap().add(&hp);
+///
+/// let mut session = EditSession::new(&base);
+/// session.set("hp", 200).unwrap();
+/// session.set("mp", 50).unwrap();
+///
+/// assert!(matches!(session.get("hp"), Some(WzObjectType::Value(WzValue::Int(200)))));
+/// assert_eq!(session.children(""), vec!["hp", "mp"]);
+///
+/// // the base is untouched
+/// assert!(base.read().unwrap().at("mp").is_none());
+/// ```
+#[derive(Debug)]
+pub struct EditSession {
+    base: WzNodeArc,
+    root: ShadowNode,
+}
+
+/// The position of a path in both base and shadow tree.
+struct Cursor<'a> {
+    base: Option<WzNodeArc>,
+    shadow: Option<&'a ShadowNode>,
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|name| !name.is_empty())
+}
+
+impl EditSession {
+    pub fn new(base: &WzNodeArc) -> Self {
+        Self {
+            base: base.clone(),
+            root: ShadowNode::default(),
+        }
+    }
+
+    #[inline]
+    pub fn base(&self) -> &WzNodeArc {
+        &self.base
+    }
+
+    /// Whether anything has been written in this session.
+    #[inline]
+    pub fn is_modified(&self) -> bool {
+        !self.root.children.is_empty() || self.root.object_type.is_some()
+    }
+
+    fn cursor(&self, path: &str) -> Option<Cursor<'_>> {
+        let mut cursor = Cursor {
+            base: Some(self.base.clone()),
+            shadow: Some(&self.root),
+        };
+
+        for name in split_path(path) {
+            let shadow = cursor.shadow.and_then(|shadow| shadow.children.get(name));
+
+            if shadow.is_some_and(|shadow| shadow.removed) {
+                return None;
+            }
+
+            let is_opaque = cursor.shadow.is_some_and(|shadow| shadow.opaque);
+            let base = if is_opaque {
+                None
+            } else {
+                cursor.base.and_then(|base| base.read().unwrap().at(name))
+            };
+
+            if base.is_none() && shadow.is_none() {
+                return None;
+            }
+
+            cursor = Cursor { base, shadow };
+        }
+
+        Some(cursor)
+    }
+
+    #[inline]
+    pub fn exists(&self, path: &str) -> bool {
+        self.cursor(path).is_some()
+    }
+
+    /// Get the object type of the node, edited one first.
+    pub fn get(&self, path: &str) -> Option<WzObjectType> {
+        let cursor = self.cursor(path)?;
+
+        if let Some(object_type) = cursor.shadow.and_then(|shadow| shadow.object_type.as_ref()) {
+            return Some(object_type.clone());
+        }
+
+        cursor
+            .base
+            .map(|base| base.read().unwrap().object_type.clone())
+    }
+
+    /// Get the sorted children names of the node, include the added one and exclude the removed one.
+    pub fn children(&self, path: &str) -> Vec<String> {
+        let Some(cursor) = self.cursor(path) else {
+            return Vec::new();
+        };
+
+        let mut names = Vec::new();
+
+        let is_opaque = cursor.shadow.is_some_and(|shadow| shadow.opaque);
+        if let (Some(base), false) = (&cursor.base, is_opaque) {
+            names.extend(
+                base.read()
+                    .unwrap()
+                    .children
+                    .keys()
+                    .map(|name| name.to_string()),
+            );
+        }
+
+        if let Some(shadow) = cursor.shadow {
+            names.retain(|name| !shadow.children.get(name).is_some_and(|child| child.removed));
+            names.extend(
+                shadow
+                    .children
+                    .iter()
+                    .filter(|(name, child)| !child.removed && !names.contains(name))
+                    .map(|(name, _)| name.clone())
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        names.sort();
+        names
+    }
+
+    /// Get or create the shadow node along the path, the parent of the path must exist.
+    fn shadow_mut(&mut self, path: &str) -> Result<&mut ShadowNode, EditError> {
+        let names = split_path(path).collect::<Vec<_>>();
+
+        if let Some((_, parent_names)) = names.split_last() {
+            let parent_path = parent_names.join("/");
+            if !self.exists(&parent_path) {
+                return Err(EditError::ParentNotFound(parent_path));
+            }
+        }
+
+        let is_new = !self.exists(path);
+
+        let mut shadow = &mut self.root;
+        for name in names {
+            shadow = shadow.children.entry(name.to_string()).or_default();
+        }
+
+        if is_new {
+            /* a removed or not existing node, become a new node */
+            *shadow = ShadowNode {
+                opaque: true,
+                ..Default::default()
+            };
+        }
+
+        Ok(shadow)
+    }
+
+    /// Set the object type of the node, create it when not exist.
+    pub fn set(
+        &mut self,
+        path: &str,
+        object_type: impl Into<WzObjectType>,
+    ) -> Result<(), EditError> {
+        let shadow = self.shadow_mut(path)?;
+        shadow.object_type = Some(object_type.into());
+        Ok(())
+    }
+
+    /// Remove the node and it's subtree, returns `false` when the node not exist.
+    pub fn remove(&mut self, path: &str) -> Result<bool, EditError> {
+        if split_path(path).next().is_none() {
+            return Err(EditError::RemoveRoot);
+        }
+
+        if !self.exists(path) {
+            return Ok(false);
+        }
+
+        let shadow = self.shadow_mut(path)?;
+        *shadow = ShadowNode {
+            removed: true,
+            ..Default::default()
+        };
+
+        Ok(true)
+    }
+
+    /// Discard all edits.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.root = ShadowNode::default();
+    }
+
+    /// Build a standalone node tree of the path with all edits applied,
+    /// base nodes are copied, so it's not cheap for a big subtree.
+    pub fn materialize(&self, path: &str) -> Option<WzNodeArc> {
+        self.materialize_inner(path, None)
+    }
+
+    fn materialize_inner(&self, path: &str, parent: Option<&WzNodeArc>) -> Option<WzNodeArc> {
+        let object_type = self.get(path)?;
+        let name = split_path(path)
+            .last()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| self.base.read().unwrap().name.to_string());
+
+        let node = WzNode::from_str(&name, object_type, parent).into_lock();
+
+        for child_name in self.children(path) {
+            let child_path = if path.is_empty() {
+                child_name
+            } else {
+                format!("{path}/{child_name}")
+            };
+            if let Some(child) = self.materialize_inner(&child_path, Some(&node)) {
+                node.write().unwrap().add(&child);
+            }
+        }
+
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzValue, WzNodeCast};
+
+    fn setup_node_tree() -> WzNodeArc {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let info = add_child("info", 1, &root);
+        add_child("hp", 100, &info);
+        add_child("mp", 50, &info);
+        add_child("name", 1, &root);
+
+        root
+    }
+
+    fn get_int(session: &EditSession, path: &str) -> Option<i32> {
+        match session.get(path)? {
+            WzObjectType::Value(WzValue::Int(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_read_fall_through() {
+        let root = setup_node_tree();
+        let session = EditSession::new(&root);
+
+        assert!(!session.is_modified());
+        assert_eq!(get_int(&session, "info/hp"), Some(100));
+        assert_eq!(session.children("info"), vec!["hp", "mp"]);
+        assert!(!session.exists("info/exp"));
+    }
+
+    #[test]
+    fn test_set_and_remove() {
+        let root = setup_node_tree();
+        let mut session = EditSession::new(&root);
+
+        session.set("info/hp", 200).unwrap();
+        session.set("info/exp", 10).unwrap();
+        assert!(session.remove("info/mp").unwrap());
+        assert!(!session.remove("info/mp").unwrap());
+
+        assert_eq!(get_int(&session, "info/hp"), Some(200));
+        assert_eq!(get_int(&session, "info/exp"), Some(10));
+        assert!(session.get("info/mp").is_none());
+        assert_eq!(session.children("info"), vec!["exp", "hp"]);
+
+        assert!(matches!(
+            session.set("not/exist", 1),
+            Err(EditError::ParentNotFound(_))
+        ));
+        assert!(matches!(session.remove(""), Err(EditError::RemoveRoot)));
+
+        /* base is untouched */
+        let hp = root.read().unwrap().at_path("info/hp").unwrap();
+        assert_eq!(hp.read().unwrap().try_as_int(), Some(&100));
+        assert!(root.read().unwrap().at_path("info/exp").is_none());
+    }
+
+    #[test]
+    fn test_recreate_removed_node() {
+        let root = setup_node_tree();
+        let mut session = EditSession::new(&root);
+
+        session.remove("info").unwrap();
+        assert!(!session.exists("info/hp"));
+
+        session.set("info", 2).unwrap();
+        assert!(session.children("info").is_empty());
+        assert!(!session.exists("info/hp"));
+
+        session.reset();
+        assert!(session.exists("info/hp"));
+    }
+
+    #[test]
+    fn test_materialize() {
+        let root = setup_node_tree();
+        let mut session = EditSession::new(&root);
+
+        session.set("info/exp", 10).unwrap();
+        session.remove("name").unwrap();
+
+        let node = session.materialize("").unwrap();
+        let node = node.read().unwrap();
+
+        assert_eq!(node.name.as_str(), "root");
+        assert!(node.at("name").is_none());
+        assert!(node.at_path("info/exp").is_some());
+        assert!(node.at_path("info/hp").is_some());
+        assert_eq!(
+            node.at_path("info/exp")
+                .unwrap()
+                .read()
+                .unwrap()
+                .get_full_path(),
+            "root/info/exp"
+        );
+    }
+}
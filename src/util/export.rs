@@ -0,0 +1,911 @@
+#[cfg(feature = "image")]
+use crate::WzObjectType;
+use crate::{
+    node,
+    property::{WzLuaParseError, WzPngParseError, WzSoundError, WzSoundType},
+    WzNodeArc, WzNodeCast,
+};
+#[cfg(feature = "image")]
+use image::ImageFormat;
+use std::cell::RefCell;
+#[cfg(feature = "image")]
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+#[cfg(feature = "image")]
+use std::sync::Arc;
+use thiserror::Error;
+
+#[cfg(all(feature = "rayon", any(feature = "image", feature = "json")))]
+use rayon::prelude::*;
+
+#[cfg(all(feature = "json", feature = "image"))]
+use super::parse_all;
+use super::walk_node_with_path;
+#[cfg(feature = "json")]
+use super::PathCache;
+#[cfg(feature = "image")]
+use super::{node_util, walk_node_with_control, WalkControl};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error("Error exporting sound: {0}")]
+    SoundError(#[from] WzSoundError),
+
+    #[error("Error exporting image: {0}")]
+    ImageError(#[from] WzPngParseError),
+
+    #[error("Error exporting lua: {0}")]
+    LuaError(#[from] WzLuaParseError),
+
+    #[cfg(feature = "json")]
+    #[error("Error exporting json: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Error parsing node: {0}")]
+    ParseError(#[from] node::Error),
+
+    #[error("Unsafe output path: {0}")]
+    UnsafePath(String),
+}
+
+/// The result of a batch export, failed node will not stop the export.
+#[derive(Debug)]
+pub struct ExportResult<T> {
+    pub exported: Vec<T>,
+    /// full path of the failed node and the error.
+    pub failed: Vec<(String, ExportError)>,
+    /// full path of the skipped node, like a canvas that linked to a already exported one.
+    pub skipped: Vec<String>,
+}
+
+impl<T> Default for ExportResult<T> {
+    fn default() -> Self {
+        Self {
+            exported: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+/// A exported sound, also a row of the `manifest.csv`.
+#[derive(Debug, Clone)]
+pub struct SoundManifestEntry {
+    /// full path of the sound node.
+    pub path: String,
+    /// the saved file.
+    pub file: PathBuf,
+    pub duration: u32,
+    pub sound_type: WzSoundType,
+}
+
+/// A exported image.
+#[cfg(feature = "image")]
+#[derive(Debug, Clone)]
+pub struct ImageExportEntry {
+    /// full path of the canvas node.
+    pub path: String,
+    /// the saved file.
+    pub file: PathBuf,
+}
+
+/// Get the path relative to walk root, fallback to the node name when walking root itself.
+pub(crate) fn get_relative_path<'a>(root_path: &str, path: &'a str) -> &'a str {
+    let relative = path[root_path.len()..].trim_start_matches('/');
+    if relative.is_empty() {
+        path.rsplit('/').next().unwrap_or(path)
+    } else {
+        relative
+    }
+}
+
+/// Join the path relative to walk root onto `dir`. The node names come from the file, so a crafted one like `..`
+/// could write outside `dir`, the path with `.`, `..`, empty, absolute or prefix components is rejected.
+pub(crate) fn join_node_path(dir: &Path, relative: &str) -> Result<PathBuf, ExportError> {
+    let mut file = dir.to_path_buf();
+
+    for segment in relative.split('/') {
+        /* on windows `\` and `C:` split the segment into more components */
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if name.to_str() == Some(segment) => {
+                file.push(name)
+            }
+            _ => return Err(ExportError::UnsafePath(relative.to_string())),
+        }
+    }
+
+    Ok(file)
+}
+
+/// Quote the csv field if needed.
+pub(crate) fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_sound_manifest(dir: &Path, entries: &[SoundManifestEntry]) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(File::create(dir.join("manifest.csv"))?);
+
+    writeln!(writer, "path,file,duration,type")?;
+
+    for entry in entries {
+        writeln!(
+            writer,
+            "{},{},{},{:?}",
+            escape_csv_field(&entry.path),
+            escape_csv_field(&entry.file.to_string_lossy()),
+            entry.duration,
+            entry.sound_type
+        )?;
+    }
+
+    writer.flush()
+}
+
+/// Walk through the node(will parse along the way) and save every sound into `dir`, the folder structure
+/// will be same as the node tree. Sound will get `.mp3` or `.wav` extension base on it's type,
+/// and a `manifest.csv` contains path, file, duration and type will be written to `dir`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::export_sounds};
+/// let node = WzNode::from_wz_file("path/to/Sound.wz", None).unwrap().into_lock();
+///
+/// let result = export_sounds(&node, "./output").unwrap();
+///
+/// for (path, error) in result.failed {
+///     println!("failed to export {path}: {error}");
+/// }
+/// ```
+pub fn export_sounds<P>(
+    node: &WzNodeArc,
+    dir: P,
+) -> Result<ExportResult<SoundManifestEntry>, ExportError>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let root_path = node.read().unwrap().get_full_path();
+    let result = RefCell::new(ExportResult::default());
+
+    walk_node_with_path(node, true, &|node, _, path| {
+        let node_read = node.read().unwrap();
+        let Some(sound) = node_read.try_as_sound() else {
+            return;
+        };
+
+        let mut file = match join_node_path(dir, get_relative_path(&root_path, path)) {
+            Ok(file) => file,
+            Err(e) => {
+                result.borrow_mut().failed.push((path.to_string(), e));
+                return;
+            }
+        };
+        match sound.sound_type {
+            WzSoundType::Mp3 => file.as_mut_os_string().push(".mp3"),
+            WzSoundType::Wav => file.as_mut_os_string().push(".wav"),
+            WzSoundType::Binary => {}
+        }
+
+        let save_result = file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(ExportError::from)
+            .and_then(|_| Ok(File::create(&file)?))
+            .and_then(|mut writer| Ok(sound.write_to(&mut writer)?));
+
+        let mut result = result.borrow_mut();
+
+        match save_result {
+            Ok(_) => result.exported.push(SoundManifestEntry {
+                path: path.to_string(),
+                file,
+                duration: sound.duration,
+                sound_type: sound.sound_type.clone(),
+            }),
+            Err(e) => result.failed.push((path.to_string(), e)),
+        }
+    });
+
+    let result = result.into_inner();
+
+    write_sound_manifest(dir, &result.exported)?;
+
+    Ok(result)
+}
+
+/// Follow the `_inlink`/`_outlink` until reach the canvas that actually has the image.
+#[cfg(feature = "image")]
+fn resolve_canvas_target(node: &WzNodeArc) -> WzNodeArc {
+    let mut target = node.clone();
+    /* prevent circular link */
+    for _ in 0..8 {
+        match node_util::resolve_link(&target, true) {
+            Some(next) if next.read().unwrap().try_as_png().is_some() => target = next,
+            _ => break,
+        }
+    }
+    target
+}
+
+#[cfg(feature = "image")]
+fn save_canvas(target: &WzNodeArc, file: &Path, format: ImageFormat) -> Result<(), ExportError> {
+    let image = target
+        .read()
+        .unwrap()
+        .try_as_png()
+        .ok_or(WzPngParseError::NotPngProperty)?
+        .extract_png()?;
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    image
+        .save_with_format(file, format)
+        .map_err(WzPngParseError::from)?;
+
+    Ok(())
+}
+
+/// Walk through the node and save every canvas into `dir` with the `format`, the folder structure will be same as the node tree.
+/// Every `WzImage` is parsed, exported and then unparsed to release memory, like the walk helpers do.
+/// `_inlink` and `_outlink` will be resolved, and a canvas linked to an image that already exported will be skipped.
+/// Images are decoded concurrently when `rayon` feature is enabled.
+///
+/// Note: the `format` need the corresponding feature of `image` crate, like `image/png`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::export_images};
+/// # use image::ImageFormat;
+/// let node = WzNode::from_wz_file("path/to/UI.wz", None).unwrap().into_lock();
+///
+/// let result = export_images(&node, "./output", ImageFormat::Png).unwrap();
+///
+/// println!("exported {} images", result.exported.len());
+/// ```
+#[cfg(feature = "image")]
+pub fn export_images<P>(
+    node: &WzNodeArc,
+    dir: P,
+    format: ImageFormat,
+) -> Result<ExportResult<ImageExportEntry>, ExportError>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let root_path = node.read().unwrap().get_full_path();
+    let extension = format.extensions_str().first().copied().unwrap_or("png");
+
+    let result = RefCell::new(ExportResult::default());
+    /* full path of the canvases that actually has the image and already exported */
+    let exported_targets = RefCell::new(HashSet::new());
+    /* the canvases linked to another image, exported after every image is walked */
+    let deferred = RefCell::new(Vec::new());
+
+    let export_canvas = |(path, target): &(String, WzNodeArc)| {
+        let mut file = join_node_path(dir, get_relative_path(&root_path, path))
+            .map_err(|e| (path.clone(), e))?;
+        file.as_mut_os_string().push(".");
+        file.as_mut_os_string().push(extension);
+
+        match save_canvas(target, &file, format) {
+            Ok(_) => Ok(ImageExportEntry {
+                path: path.clone(),
+                file,
+            }),
+            Err(e) => Err((path.clone(), e)),
+        }
+    };
+
+    let export_batch = |mut canvases: Vec<(String, String, WzNodeArc)>| {
+        /* make sure the canvas that not a link get exported first */
+        canvases.sort_by_key(|(path, target_path, _)| path != target_path);
+
+        let mut targets = Vec::with_capacity(canvases.len());
+        let mut result = result.borrow_mut();
+
+        for (path, target_path, target) in canvases {
+            if exported_targets.borrow_mut().insert(target_path) {
+                targets.push((path, target));
+            } else {
+                result.skipped.push(path);
+            }
+        }
+
+        #[cfg(feature = "rayon")]
+        let exported = targets.par_iter().map(export_canvas).collect::<Vec<_>>();
+
+        #[cfg(not(feature = "rayon"))]
+        let exported = targets.iter().map(export_canvas).collect::<Vec<_>>();
+
+        for item in exported {
+            match item {
+                Ok(entry) => result.exported.push(entry),
+                Err(failed) => result.failed.push(failed),
+            }
+        }
+    };
+
+    walk_node_with_control(node, false, &|node| {
+        let is_container = matches!(
+            node.read().unwrap().object_type,
+            WzObjectType::File(_) | WzObjectType::MsFile(_) | WzObjectType::Directory(_)
+        );
+        let is_wz_image = matches!(
+            node.read().unwrap().object_type,
+            WzObjectType::Image(_) | WzObjectType::MsImage(_)
+        );
+
+        if let Err(e) = node.write().unwrap().parse(node) {
+            let path = node.read().unwrap().get_full_path();
+            result.borrow_mut().failed.push((path, e.into()));
+            return WalkControl::SkipSubtree;
+        }
+
+        if is_container {
+            return WalkControl::Continue;
+        }
+
+        /* a WzImage, or the walk root is already inside one */
+        let image_path = node.read().unwrap().get_full_path();
+        let canvases = RefCell::new(Vec::new());
+
+        walk_node_with_path(node, false, &|node, _, path| {
+            if node.read().unwrap().try_as_png().is_none() {
+                return;
+            }
+            let target = resolve_canvas_target(node);
+            let target_path = if Arc::ptr_eq(node, &target) {
+                path.to_string()
+            } else {
+                target.read().unwrap().get_full_path()
+            };
+            let is_in_image = target_path
+                .strip_prefix(image_path.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+
+            if is_in_image {
+                canvases
+                    .borrow_mut()
+                    .push((path.to_string(), target_path, target));
+            } else {
+                deferred
+                    .borrow_mut()
+                    .push((path.to_string(), target_path, target));
+            }
+        });
+
+        export_batch(canvases.into_inner());
+
+        if is_wz_image {
+            node.write().unwrap().unparse();
+        }
+
+        WalkControl::SkipSubtree
+    });
+
+    export_batch(deferred.into_inner());
+
+    Ok(result.into_inner())
+}
+
+/// What kind of file a dumped node become.
+#[cfg(all(feature = "json", feature = "image"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpKind {
+    /// `WzImage` and it's primitives as simple json.
+    Json,
+    Image,
+    Sound,
+    Lua,
+    /// `RawData` and `Video`.
+    Binary,
+}
+
+/// A dumped file, also a row of the `manifest.csv`.
+#[cfg(all(feature = "json", feature = "image"))]
+#[derive(Debug, Clone)]
+pub struct DumpEntry {
+    /// full path of the node.
+    pub path: String,
+    /// the saved file.
+    pub file: PathBuf,
+    pub kind: DumpKind,
+}
+
+#[cfg(all(feature = "json", feature = "image"))]
+fn write_dump_manifest(dir: &Path, entries: &[DumpEntry]) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(File::create(dir.join("manifest.csv"))?);
+
+    writeln!(writer, "path,file,kind")?;
+
+    for entry in entries {
+        writeln!(
+            writer,
+            "{},{},{:?}",
+            escape_csv_field(&entry.path),
+            escape_csv_field(&entry.file.to_string_lossy()),
+            entry.kind
+        )?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(all(feature = "json", feature = "image"))]
+fn dump_single_node(
+    node: &WzNodeArc,
+    kind: DumpKind,
+    file: &Path,
+    image_format: ImageFormat,
+) -> Result<(), ExportError> {
+    if kind == DumpKind::Image {
+        return save_canvas(&resolve_canvas_target(node), file, image_format);
+    }
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let node_read = node.read().unwrap();
+
+    match kind {
+        DumpKind::Json => {
+            let json = node_read.to_simple_json()?;
+            serde_json::to_writer(BufWriter::new(File::create(file)?), &json)?;
+        }
+        DumpKind::Sound => {
+            if let Some(sound) = node_read.try_as_sound() {
+                sound.write_to(&mut File::create(file)?)?;
+            }
+        }
+        DumpKind::Lua => {
+            if let Some(lua) = node_read.try_as_lua() {
+                fs::write(file, lua.extract_lua()?)?;
+            }
+        }
+        DumpKind::Binary => {
+            if let Some(raw_data) = node_read.try_as_raw_data() {
+                fs::write(file, raw_data.get_buffer())?;
+            } else if let Some(video) = node_read.try_as_video() {
+                fs::write(file, video.get_buffer())?;
+            }
+        }
+        DumpKind::Image => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Fully parse the node and dump everything into `dir` like the "export folder" of GUI wz tools:
+/// every `WzImage` become a simple json file, canvases saved as `image_format`, sounds as mp3/wav,
+/// lua as text and raw data/video as binary. All files are listed in `manifest.csv`.
+/// The `_outlink` in json still point to the Base, use `rewrite_links` before dumping if needed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::dump_node};
+/// # use image::ImageFormat;
+/// let node = WzNode::from_wz_file("path/to/Etc.wz", None).unwrap().into_lock();
+///
+/// let result = dump_node(&node, "./output", ImageFormat::Png).unwrap();
+///
+/// for (path, error) in result.failed {
+///     println!("failed to dump {path}: {error}");
+/// }
+/// ```
+#[cfg(all(feature = "json", feature = "image"))]
+pub fn dump_node<P>(
+    node: &WzNodeArc,
+    dir: P,
+    image_format: ImageFormat,
+) -> Result<ExportResult<DumpEntry>, ExportError>
+where
+    P: AsRef<Path>,
+{
+    use crate::WzObjectType;
+
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut result = ExportResult::default();
+
+    for (path, e) in parse_all(node) {
+        result.failed.push((path, e.into()));
+    }
+
+    let root_path = node.read().unwrap().get_full_path();
+    let is_root_container = matches!(
+        node.read().unwrap().object_type,
+        WzObjectType::File(_) | WzObjectType::Directory(_) | WzObjectType::MsFile(_)
+    );
+    let tasks = RefCell::new(Vec::new());
+    let unsafe_paths = RefCell::new(Vec::new());
+
+    walk_node_with_path(node, false, &|current, depth, path| {
+        let node_read = current.read().unwrap();
+
+        let kind = match &node_read.object_type {
+            WzObjectType::Image(_) => DumpKind::Json,
+            _ if depth == 0 && !is_root_container => DumpKind::Json,
+            _ if node_read.try_as_png().is_some() => DumpKind::Image,
+            _ if node_read.try_as_sound().is_some() => DumpKind::Sound,
+            _ if node_read.try_as_lua().is_some() => DumpKind::Lua,
+            _ if node_read.try_as_raw_data().is_some() || node_read.try_as_video().is_some() => {
+                DumpKind::Binary
+            }
+            _ => return,
+        };
+
+        let extension = match kind {
+            DumpKind::Json => "json",
+            DumpKind::Image => image_format
+                .extensions_str()
+                .first()
+                .copied()
+                .unwrap_or("png"),
+            DumpKind::Sound => match node_read.try_as_sound().map(|sound| &sound.sound_type) {
+                Some(WzSoundType::Wav) => "wav",
+                Some(WzSoundType::Mp3) => "mp3",
+                _ => "bin",
+            },
+            DumpKind::Lua => "lua",
+            DumpKind::Binary => "bin",
+        };
+
+        let mut file = match join_node_path(dir, get_relative_path(&root_path, path)) {
+            Ok(file) => file,
+            Err(e) => {
+                unsafe_paths.borrow_mut().push((path.to_string(), e));
+                return;
+            }
+        };
+        file.as_mut_os_string().push(".");
+        file.as_mut_os_string().push(extension);
+
+        tasks
+            .borrow_mut()
+            .push((path.to_string(), kind, file, current.clone()));
+    });
+
+    let tasks = tasks.into_inner();
+    result.failed.extend(unsafe_paths.into_inner());
+
+    let dump_task =
+        |(path, kind, file, node): &(String, DumpKind, PathBuf, WzNodeArc)| match dump_single_node(
+            node,
+            *kind,
+            file,
+            image_format,
+        ) {
+            Ok(_) => Ok(DumpEntry {
+                path: path.clone(),
+                file: file.clone(),
+                kind: *kind,
+            }),
+            Err(e) => Err((path.clone(), e)),
+        };
+
+    #[cfg(feature = "rayon")]
+    let dumped = tasks.par_iter().map(dump_task).collect::<Vec<_>>();
+
+    #[cfg(not(feature = "rayon"))]
+    let dumped = tasks.iter().map(dump_task).collect::<Vec<_>>();
+
+    for item in dumped {
+        match item {
+            Ok(entry) => result.exported.push(entry),
+            Err(failed) => result.failed.push(failed),
+        }
+    }
+
+    write_dump_manifest(dir, &result.exported)?;
+
+    Ok(result)
+}
+
+/// How `export_json` write the files.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonExportLayout {
+    /// a `<path>.json` per `WzImage`, the folder structure will be same as the node tree.
+    PerImage,
+    /// split all `WzImage` into `n` files named `shard-<i>.jsonl`,
+    /// every line is a `{"path": ..., "data": ...}` for one `WzImage`.
+    Shards(usize),
+}
+
+/// A exported json file.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone)]
+pub struct JsonExportEntry {
+    /// full path of the `WzImage`.
+    pub path: String,
+    /// the file contains it.
+    pub file: PathBuf,
+}
+
+#[cfg(feature = "json")]
+fn image_to_simple_json(node: &WzNodeArc) -> Result<serde_json::Value, ExportError> {
+    let need_unparse = node.read().unwrap().children.is_empty();
+    node.write().unwrap().parse(node)?;
+
+    let json = node.read().unwrap().to_simple_json();
+
+    /* release the memory if it's parsed by us */
+    if need_unparse {
+        node.write().unwrap().unparse();
+    }
+
+    Ok(json?)
+}
+
+/// Serialize every `WzImage` under the node to simple json and write into `dir`,
+/// `WzImage` are parsed and serialized concurrently when `rayon` feature is enabled,
+/// and will be unparsed after written if it's not parsed before, so it can handle the whole Base without eating all memory.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, export_json, JsonExportLayout};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+///
+/// let result = export_json(&base, "./output", JsonExportLayout::PerImage).unwrap();
+///
+/// for (path, error) in result.failed {
+///     println!("failed to export {path}: {error}");
+/// }
+/// ```
+#[cfg(feature = "json")]
+pub fn export_json<P>(
+    node: &WzNodeArc,
+    dir: P,
+    layout: JsonExportLayout,
+) -> Result<ExportResult<JsonExportEntry>, ExportError>
+where
+    P: AsRef<Path>,
+{
+    use super::collect_images;
+
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+
+    let mut result = ExportResult::default();
+    let mut images = Vec::new();
+    let mut errors = Vec::new();
+
+    collect_images(node, &mut images, &mut errors);
+
+    for (path, e) in errors {
+        result.failed.push((path, e.into()));
+    }
+
+    let root_path = node.read().unwrap().get_full_path();
+    let mut path_cache = PathCache::new();
+    let images = images
+        .into_iter()
+        .map(|image| {
+            let path = path_cache.get_full_path(&image).to_string();
+            (path, image)
+        })
+        .collect::<Vec<_>>();
+
+    match layout {
+        JsonExportLayout::PerImage => {
+            let export_image = |(path, image): &(String, WzNodeArc)| {
+                let mut file = join_node_path(dir, get_relative_path(&root_path, path))
+                    .map_err(|e| (path.clone(), e))?;
+                file.as_mut_os_string().push(".json");
+
+                let write = || -> Result<(), ExportError> {
+                    let json = image_to_simple_json(image)?;
+                    if let Some(parent) = file.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    serde_json::to_writer(BufWriter::new(File::create(&file)?), &json)?;
+                    Ok(())
+                };
+
+                match write() {
+                    Ok(_) => Ok(JsonExportEntry {
+                        path: path.clone(),
+                        file,
+                    }),
+                    Err(e) => Err((path.clone(), e)),
+                }
+            };
+
+            #[cfg(feature = "rayon")]
+            let exported = images.par_iter().map(export_image).collect::<Vec<_>>();
+
+            #[cfg(not(feature = "rayon"))]
+            let exported = images.iter().map(export_image).collect::<Vec<_>>();
+
+            for item in exported {
+                match item {
+                    Ok(entry) => result.exported.push(entry),
+                    Err(failed) => result.failed.push(failed),
+                }
+            }
+        }
+        JsonExportLayout::Shards(count) => {
+            let count = count.max(1);
+            let shards = (0..count)
+                .map(|index| {
+                    let images = images.iter().skip(index).step_by(count).collect::<Vec<_>>();
+                    (dir.join(format!("shard-{index}.jsonl")), images)
+                })
+                .collect::<Vec<_>>();
+
+            let export_shard = |(file, images): &(PathBuf, Vec<&(String, WzNodeArc)>)| {
+                let mut exported = Vec::new();
+                let mut failed = Vec::new();
+
+                let mut writer = match File::create(file) {
+                    Ok(file) => BufWriter::new(file),
+                    Err(e) => {
+                        let failed = images
+                            .iter()
+                            .map(|(path, _)| (path.clone(), ExportError::IoError(e.kind().into())))
+                            .collect();
+                        return (exported, failed);
+                    }
+                };
+
+                for (path, image) in images {
+                    /* serialize the whole line first, so a failed one won't leave a partial line */
+                    let write = |writer: &mut BufWriter<File>| -> Result<(), ExportError> {
+                        let line = serde_json::json!({
+                            "path": path,
+                            "data": image_to_simple_json(image)?,
+                        });
+                        let mut buf = serde_json::to_vec(&line)?;
+                        buf.push(b'\n');
+                        writer.write_all(&buf)?;
+                        Ok(())
+                    };
+
+                    match write(&mut writer) {
+                        Ok(_) => exported.push(JsonExportEntry {
+                            path: path.clone(),
+                            file: file.clone(),
+                        }),
+                        Err(e) => failed.push((path.clone(), e)),
+                    }
+                }
+
+                if let Err(e) = writer.flush() {
+                    failed.push((file.to_string_lossy().to_string(), e.into()));
+                }
+
+                (exported, failed)
+            };
+
+            #[cfg(feature = "rayon")]
+            let exported = shards.par_iter().map(export_shard).collect::<Vec<_>>();
+
+            #[cfg(not(feature = "rayon"))]
+            let exported = shards.iter().map(export_shard).collect::<Vec<_>>();
+
+            for (exported, failed) in exported {
+                result.exported.extend(exported);
+                result.failed.extend(failed);
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{property::WzSound, WzNode, WzReader};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_get_relative_path() {
+        assert_eq!(
+            get_relative_path("Sound.wz", "Sound.wz/Bgm00.img/Title"),
+            "Bgm00.img/Title"
+        );
+        assert_eq!(
+            get_relative_path("Sound.wz/Bgm00.img/Title", "Sound.wz/Bgm00.img/Title"),
+            "Title"
+        );
+    }
+
+    #[test]
+    fn test_export_sounds() -> Result<(), ExportError> {
+        let reader = Arc::new(WzReader::from_buff(&[1, 2, 3, 4]));
+        let sound = WzSound::new(&reader, 0, 4, 0, 0, 1000, WzSoundType::Mp3);
+
+        let root = WzNode::from_str("Bgm00.img", 1, None).into_lock();
+        let sound = WzNode::from_str("Title, Theme", sound, Some(&root)).into_lock();
+        root.write().unwrap().add(&sound);
+
+        let dir = tempfile::tempdir()?;
+
+        let result = export_sounds(&root, dir.path())?;
+
+        assert!(result.failed.is_empty());
+        assert_eq!(result.exported.len(), 1);
+
+        let file = dir.path().join("Title, Theme.mp3");
+        assert_eq!(result.exported[0].file, file);
+        assert_eq!(fs::read(file)?, vec![1, 2, 3, 4]);
+
+        let manifest = fs::read_to_string(dir.path().join("manifest.csv"))?;
+        let mut lines = manifest.lines();
+        assert_eq!(lines.next(), Some("path,file,duration,type"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("\"Bgm00.img/Title, Theme\","));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_join_node_path() {
+        let dir = Path::new("out");
+
+        assert_eq!(
+            join_node_path(dir, "Bgm00.img/Title").unwrap(),
+            dir.join("Bgm00.img").join("Title")
+        );
+        assert_eq!(
+            join_node_path(dir, "Title, Theme").unwrap(),
+            dir.join("Title, Theme")
+        );
+
+        for relative in ["..", "a/../../b", "a/./b", "a//b", "", "/etc/passwd"] {
+            assert!(matches!(
+                join_node_path(dir, relative),
+                Err(ExportError::UnsafePath(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_export_sounds_reject_parent_dir() -> Result<(), ExportError> {
+        let reader = Arc::new(WzReader::from_buff(&[1, 2, 3, 4]));
+        let sound = WzSound::new(&reader, 0, 4, 0, 0, 1000, WzSoundType::Mp3);
+
+        let root = WzNode::from_str("Bgm00.img", 1, None).into_lock();
+        let parent_dir = WzNode::from_str("..", 1, Some(&root)).into_lock();
+        let sound = WzNode::from_str("escape", sound, Some(&parent_dir)).into_lock();
+        parent_dir.write().unwrap().add(&sound);
+        root.write().unwrap().add(&parent_dir);
+
+        let base = tempfile::tempdir()?;
+        let dir = base.path().join("output");
+
+        let result = export_sounds(&root, &dir)?;
+
+        assert!(result.exported.is_empty());
+        assert_eq!(result.failed.len(), 1);
+        assert!(matches!(result.failed[0].1, ExportError::UnsafePath(_)));
+        assert!(!base.path().join("escape.mp3").exists());
+
+        Ok(())
+    }
+}
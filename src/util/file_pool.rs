@@ -0,0 +1,111 @@
+use crate::sync::RwLock;
+use crate::{WzNode, WzNodeArc, WzObjectType};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Keep the number of opened wz files bounded, by closing the least recently used one with `WzNode::close_file`.
+/// The closed file will be reopened when parsing again, so the tree can be used as usual.
+/// But the changes made to a closed file's subtree are lost, see `WzNode::close_file`, don't `touch` the edited files.
+///
+/// The pool only know the file is used when `touch` is called, so call it before accessing a node,
+/// the `WzFile` that the node belongs to will be tracked.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, FileHandlePool};
+/// # use std::time::Duration;
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let mut pool = FileHandlePool::new(8);
+///
+/// let mob = base.read().unwrap().at("Mob").unwrap();
+/// pool.touch(&mob);
+/// let info = mob.read().unwrap().at_path_parsed("100100.img/info").unwrap();
+///
+/// // in a timer
+/// pool.close_idle(Duration::from_secs(600));
+/// ```
+#[derive(Debug)]
+pub struct FileHandlePool {
+    max_open: usize,
+    /// ordered by the last used time, the most recently used one is the last.
+    files: Vec<(Weak<RwLock<WzNode>>, Instant)>,
+}
+
+fn is_opened_file(node: &WzNodeArc) -> bool {
+    matches!(&node.read().unwrap().object_type, WzObjectType::File(file) if !file.is_closed)
+}
+
+impl FileHandlePool {
+    pub fn new(max_open: usize) -> Self {
+        Self {
+            max_open,
+            files: Vec::new(),
+        }
+    }
+
+    /// Mark the `WzFile` of the node(itself or the nearest parent) as used, and close the least recently used ones
+    /// when the opened files are more than `max_open`.
+    pub fn touch(&mut self, node: &WzNodeArc) {
+        let is_file = matches!(node.read().unwrap().object_type, WzObjectType::File(_));
+        let file = if is_file {
+            Arc::clone(node)
+        } else {
+            let parent_file = node
+                .read()
+                .unwrap()
+                .filter_parent(|node| matches!(node.object_type, WzObjectType::File(_)));
+            let Some(file) = parent_file else {
+                return;
+            };
+            file
+        };
+
+        let weak = Arc::downgrade(&file);
+        self.files
+            .retain(|(tracked, _)| tracked.strong_count() > 0 && !Weak::ptr_eq(tracked, &weak));
+        self.files.push((weak, Instant::now()));
+
+        self.close_exceeded();
+    }
+
+    fn close_exceeded(&mut self) {
+        let mut opened = self.open_count();
+
+        /* never close the one just touched */
+        let last = self.files.len().saturating_sub(1);
+
+        for (file, _) in &self.files[..last] {
+            if opened <= self.max_open {
+                break;
+            }
+            let Some(file) = file.upgrade() else {
+                continue;
+            };
+            if is_opened_file(&file) && file.write().unwrap().close_file() {
+                opened -= 1;
+            }
+        }
+    }
+
+    /// Close the files not used for `max_idle`, returns the number of closed files.
+    pub fn close_idle(&mut self, max_idle: Duration) -> usize {
+        let now = Instant::now();
+
+        self.files
+            .iter()
+            .filter(|(_, last_used)| now.duration_since(*last_used) >= max_idle)
+            .filter_map(|(file, _)| file.upgrade())
+            .filter(|file| is_opened_file(file) && file.write().unwrap().close_file())
+            .count()
+    }
+
+    /// The number of tracked files that still opened.
+    pub fn open_count(&self) -> usize {
+        self.files
+            .iter()
+            .filter_map(|(file, _)| file.upgrade())
+            .filter(is_opened_file)
+            .count()
+    }
+}
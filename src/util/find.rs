@@ -0,0 +1,176 @@
+use crate::{WzNode, WzNodeArc, WzObjectType};
+
+fn find_all_inner(
+    node: &WzNodeArc,
+    name: &str,
+    filter: &dyn Fn(&WzObjectType) -> bool,
+    result: &mut Vec<WzNodeArc>,
+) {
+    let node_read = node.read().unwrap();
+
+    if node_read.name.as_str() == name && filter(&node_read.object_type) {
+        result.push(node.clone());
+    }
+
+    for child in node_read.children.values() {
+        find_all_inner(child, name, filter, result);
+    }
+}
+
+/// Find all nodes(include itself) with the given name under the node. It won't parse any node,
+/// so make sure the node is parsed before, like using `parse_all`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::find_all_by_name};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let delay = WzNode::from_str("delay", 100, Some(&root)).into_lock();
+/// root.write().unwrap().add(&delay);
+///
+/// assert_eq!(find_all_by_name(&root, "delay").len(), 1);
+/// ```
+pub fn find_all_by_name(node: &WzNodeArc, name: &str) -> Vec<WzNodeArc> {
+    find_all_by_name_with_type(node, name, &|_| true)
+}
+
+/// Same as `find_all_by_name`, but only keep the node that `filter` returns true on it's `WzObjectType`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzObjectType, util::find_all_by_name_with_type};
+/// # use wz_reader::property::WzValue;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let delay = WzNode::from_str("delay", 100, Some(&root)).into_lock();
+/// root.write().unwrap().add(&delay);
+///
+/// let result = find_all_by_name_with_type(&root, "delay", &|object_type| {
+///     matches!(object_type, WzObjectType::Value(WzValue::Short(_)))
+/// });
+///
+/// assert!(result.is_empty());
+/// ```
+pub fn find_all_by_name_with_type(
+    node: &WzNodeArc,
+    name: &str,
+    filter: &dyn Fn(&WzObjectType) -> bool,
+) -> Vec<WzNodeArc> {
+    let mut result = Vec::new();
+    find_all_inner(node, name, filter, &mut result);
+    result
+}
+
+/// A lazy iterator of nodes matching the predicate, created by `find_nodes`.
+pub struct FindNodes<F> {
+    stack: Vec<WzNodeArc>,
+    predicate: F,
+}
+
+impl<F> Iterator for FindNodes<F>
+where
+    F: Fn(&WzNode) -> bool,
+{
+    type Item = WzNodeArc;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            let is_match = {
+                let node_read = node.read().unwrap();
+                self.stack.extend(node_read.children.values().cloned());
+                (self.predicate)(&node_read)
+            };
+
+            if is_match {
+                return Some(node);
+            }
+        }
+        None
+    }
+}
+
+/// Find nodes(include itself) that `predicate` returns true lazily, it only walk as far as the iterator consumed.
+/// Just like `find_all_by_name`, it won't parse any node.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzNodeCast, util::find_nodes};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// for i in 0..10 {
+///     let child = WzNode::from_str(&i.to_string(), i, Some(&root)).into_lock();
+///     root.write().unwrap().add(&child);
+/// }
+///
+/// let first_two = find_nodes(&root, |node| node.try_as_int().is_some())
+///     .take(2)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(first_two.len(), 2);
+/// ```
+pub fn find_nodes<F>(node: &WzNodeArc, predicate: F) -> FindNodes<F>
+where
+    F: Fn(&WzNode) -> bool,
+{
+    FindNodes {
+        stack: vec![node.clone()],
+        predicate,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzValue, WzNodeCast};
+
+    fn setup_node_tree() -> WzNodeArc {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+
+        let frame0 = add_child("0", 0, &root);
+        let frame1 = add_child("1", 1, &root);
+
+        add_child("delay", 100, &frame0);
+        add_child("delay", 120_i16, &frame1);
+        add_child("a0", 255, &frame1);
+
+        root
+    }
+
+    #[test]
+    fn test_find_all_by_name() {
+        let root = setup_node_tree();
+
+        let result = find_all_by_name(&root, "delay");
+        assert_eq!(result.len(), 2);
+
+        let result = find_all_by_name(&root, "not_exist");
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_find_all_by_name_with_type() {
+        let root = setup_node_tree();
+
+        let result = find_all_by_name_with_type(&root, "delay", &|object_type| {
+            matches!(object_type, WzObjectType::Value(WzValue::Short(_)))
+        });
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].read().unwrap().get_full_path(), "root/1/delay");
+    }
+
+    #[test]
+    fn test_find_nodes() {
+        let root = setup_node_tree();
+
+        let result = find_nodes(&root, |node| node.try_as_int().is_some()).collect::<Vec<_>>();
+        /* root, 0, 1, 0/delay, 1/a0 */
+        assert_eq!(result.len(), 5);
+
+        let mut iter = find_nodes(&root, |node| node.name.as_str() == "delay");
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_some());
+        assert!(iter.next().is_none());
+    }
+}
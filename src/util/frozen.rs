@@ -0,0 +1,282 @@
+use crate::{node::Error, WzNode, WzNodeArc, WzNodeName, WzObjectType};
+use hashbrown::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::ImageHandle;
+
+/// A immutable snapshot of a node tree, reading it never take any lock,
+/// useful for read-mostly server that only need to lookup the parsed data.
+///
+/// Updating is copy-on-write, only the nodes along the path are copied, other subtrees are shared
+/// between the old and new snapshot, so the readers holding the old one are never blocked.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::FrozenNode};
+/// # use std::sync::Arc;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let info = WzNode::from_str("info", 1, Some(&root)).into_lock();
+/// root.write().unwrap().add(&info);
+///
+/// let frozen = Arc::new(FrozenNode::from_node(&root.read().unwrap()));
+/// assert!(frozen.at("info").is_some());
+///
+/// let updated = frozen
+///     .with_child_at("info", FrozenNode::new("hp", 100))
+///     .unwrap();
+///
+/// assert!(updated.at_path("info/hp").is_some());
+/// assert!(frozen.at_path("info/hp").is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrozenNode {
+    pub name: WzNodeName,
+    pub object_type: WzObjectType,
+    pub children: HashMap<WzNodeName, Arc<FrozenNode>>,
+}
+
+impl FrozenNode {
+    pub fn new(name: &str, object_type: impl Into<WzObjectType>) -> Self {
+        Self {
+            name: name.into(),
+            object_type: object_type.into(),
+            children: HashMap::new(),
+        }
+    }
+
+    /// Snapshot the node and it's whole subtree, it won't parse anything.
+    pub fn from_node(node: &WzNode) -> Self {
+        Self {
+            name: node.name.clone(),
+            object_type: node.object_type.clone(),
+            children: node
+                .children
+                .iter()
+                .map(|(name, child)| {
+                    let child = FrozenNode::from_node(&child.read().unwrap());
+                    (name.clone(), Arc::new(child))
+                })
+                .collect(),
+        }
+    }
+
+    /// Build a normal `WzNode` tree back from the snapshot.
+    pub fn to_node(&self, parent: Option<&WzNodeArc>) -> WzNodeArc {
+        let node = WzNode::new(&self.name, self.object_type.clone(), parent).into_lock();
+
+        {
+            let mut node_write = node.write().unwrap();
+            for child in self.children.values() {
+                let child = child.to_node(Some(&node));
+                node_write.add(&child);
+            }
+        }
+
+        node
+    }
+
+    #[inline]
+    pub fn at(&self, name: &str) -> Option<&Arc<FrozenNode>> {
+        self.children.get(name)
+    }
+
+    /// Get node by path like `a/b/c`.
+    pub fn at_path(&self, path: &str) -> Option<&Arc<FrozenNode>> {
+        let mut pathes = path.split('/');
+        let first = self.at(pathes.next().unwrap())?;
+        pathes.try_fold(first, |node, name| node.at(name))
+    }
+
+    /// Copy the nodes along the path, and call `f` on the last one.
+    fn update_at(
+        self: &Arc<Self>,
+        mut pathes: std::str::Split<'_, char>,
+        f: &dyn Fn(&mut FrozenNode) -> bool,
+    ) -> Option<Arc<Self>> {
+        let mut node = self.as_ref().clone();
+
+        match pathes.next() {
+            None | Some("") => {
+                if !f(&mut node) {
+                    return None;
+                }
+            }
+            Some(name) => {
+                let child = node.at(name)?.update_at(pathes, f)?;
+                node.children.insert(name.into(), child);
+            }
+        }
+
+        Some(Arc::new(node))
+    }
+
+    /// Returns a new snapshot with the `child` added under the `parent_path`(empty for self),
+    /// the child with the same name will be replaced. Returns `None` when the parent not found.
+    pub fn with_child_at(
+        self: &Arc<Self>,
+        parent_path: &str,
+        child: FrozenNode,
+    ) -> Option<Arc<Self>> {
+        let child = Arc::new(child);
+        self.update_at(parent_path.split('/'), &|node| {
+            node.children.insert(child.name.clone(), Arc::clone(&child));
+            true
+        })
+    }
+
+    /// Returns a new snapshot without the node at `path`. Returns `None` when the node not found.
+    pub fn without_node_at(self: &Arc<Self>, path: &str) -> Option<Arc<Self>> {
+        let (parent_path, name) = path.rsplit_once('/').unwrap_or(("", path));
+        self.update_at(parent_path.split('/'), &|node| {
+            node.children.remove(name).is_some()
+        })
+    }
+}
+
+/// A image that parsed on first access and immutable after that, so reading the parsed subtree
+/// never take any lock, only the first parsing is synchronized.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::FrozenImage;
+/// let node = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+/// node.write().unwrap().parse(&node).unwrap();
+/// let image = node.read().unwrap().at("100100.img").unwrap();
+///
+/// let frozen = FrozenImage::from_node(&image).unwrap();
+///
+/// std::thread::scope(|s| {
+///     s.spawn(|| frozen.get().unwrap().at("info").is_some());
+///     s.spawn(|| frozen.get().unwrap().at("stand").is_some());
+/// });
+/// ```
+#[derive(Debug)]
+pub struct FrozenImage {
+    handle: ImageHandle,
+    parsed: OnceLock<Arc<FrozenNode>>,
+    parsing: Mutex<()>,
+}
+
+impl FrozenImage {
+    pub fn new(handle: ImageHandle) -> Self {
+        Self {
+            handle,
+            parsed: OnceLock::new(),
+            parsing: Mutex::new(()),
+        }
+    }
+
+    /// Create from a `WzImage` or `MsImage` node, see `ImageHandle::from_node`.
+    pub fn from_node(node: &WzNodeArc) -> Option<Self> {
+        ImageHandle::from_node(node).map(Self::new)
+    }
+
+    #[inline]
+    pub fn handle(&self) -> &ImageHandle {
+        &self.handle
+    }
+
+    #[inline]
+    pub fn is_parsed(&self) -> bool {
+        self.parsed.get().is_some()
+    }
+
+    /// Get the parsed subtree, parse it when first called. Other threads calling it at the same time
+    /// will wait for the parsing instead of parsing it again.
+    pub fn get(&self) -> Result<&Arc<FrozenNode>, Error> {
+        if let Some(parsed) = self.parsed.get() {
+            return Ok(parsed);
+        }
+
+        let _parsing = self.parsing.lock().unwrap();
+
+        if let Some(parsed) = self.parsed.get() {
+            return Ok(parsed);
+        }
+
+        let node = self.handle.parse()?;
+        let frozen = Arc::new(FrozenNode::from_node(&node.read().unwrap()));
+
+        Ok(self.parsed.get_or_init(|| frozen))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{WzNodeCast, WzNodeGetter};
+
+    fn setup_frozen_tree() -> Arc<FrozenNode> {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let info = add_child("info", 1, &root);
+        add_child("hp", 100, &info);
+        add_child("mp", 50, &info);
+        add_child("other", 1, &root);
+
+        let frozen = FrozenNode::from_node(&root.read().unwrap());
+        Arc::new(frozen)
+    }
+
+    #[test]
+    fn test_frozen_node_read() {
+        let frozen = setup_frozen_tree();
+
+        assert_eq!(frozen.name.as_str(), "root");
+        assert!(frozen.at("info").is_some());
+        assert!(matches!(
+            frozen.at_path("info/hp").unwrap().object_type,
+            WzObjectType::Value(crate::property::WzValue::Int(100))
+        ));
+        assert!(frozen.at_path("info/none").is_none());
+    }
+
+    #[test]
+    fn test_frozen_node_copy_on_write() {
+        let frozen = setup_frozen_tree();
+
+        let updated = frozen
+            .with_child_at("info", FrozenNode::new("hp", 200))
+            .unwrap();
+        let updated = updated.without_node_at("info/mp").unwrap();
+
+        /* the old snapshot is untouched */
+        assert!(frozen.at_path("info/mp").is_some());
+        assert!(matches!(
+            frozen.at_path("info/hp").unwrap().object_type,
+            WzObjectType::Value(crate::property::WzValue::Int(100))
+        ));
+
+        assert!(updated.at_path("info/mp").is_none());
+        assert!(matches!(
+            updated.at_path("info/hp").unwrap().object_type,
+            WzObjectType::Value(crate::property::WzValue::Int(200))
+        ));
+
+        /* untouched subtree is shared */
+        assert!(Arc::ptr_eq(
+            frozen.at("other").unwrap(),
+            updated.at("other").unwrap()
+        ));
+
+        assert!(frozen
+            .with_child_at("none", FrozenNode::new("a", 1))
+            .is_none());
+        assert!(frozen.without_node_at("info/none").is_none());
+    }
+
+    #[test]
+    fn test_frozen_node_to_node() {
+        let frozen = setup_frozen_tree();
+
+        let node = frozen.to_node(None);
+
+        assert_eq!(node.get_int_at("info/hp"), Some(100));
+        let hp = node.read().unwrap().at_path("info/hp").unwrap();
+        assert_eq!(hp.read().unwrap().get_full_path(), "root/info/hp");
+        assert!(hp.read().unwrap().try_as_int().is_some());
+    }
+}
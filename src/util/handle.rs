@@ -0,0 +1,190 @@
+use crate::{
+    node::Error,
+    property::{WzLua, WzPng, WzRawData, WzSound, WzSubProperty, WzValue, WzVideo},
+    WzImage, WzNode, WzNodeArc, WzObjectType,
+};
+use std::cell::RefCell;
+
+use super::{node_util, walk_node_with_path};
+
+/// A detached snapshot of a image, only hold the reader and offset, so it can be sent to other thread
+/// and parsed there without touching the original tree.
+#[derive(Debug, Clone)]
+pub struct ImageHandle {
+    /// full path of the image node when the handle created.
+    pub path: String,
+    image: WzImage,
+}
+
+impl ImageHandle {
+    /// Create from a `WzImage` or `MsImage` node, the `MsImage` will be decrypted to a new buffer here.
+    pub fn from_node(node: &WzNodeArc) -> Option<Self> {
+        let path = node.read().unwrap().get_full_path();
+        Self::from_node_with_path(node, &path)
+    }
+
+    /// Same as `from_node`, with the full path already known, like the one from `walk_node_with_path`.
+    fn from_node_with_path(node: &WzNodeArc, path: &str) -> Option<Self> {
+        let mut image = match &node.read().unwrap().object_type {
+            WzObjectType::Image(image) => image.as_ref().clone(),
+            WzObjectType::MsImage(image) => image.to_wz_image(),
+            _ => return None,
+        };
+        image.is_parsed = false;
+
+        Some(Self {
+            path: path.to_string(),
+            image,
+        })
+    }
+
+    /// Parse the image into a standalone node, it has no parent, so `_outlink` can't be resolved in it.
+    pub fn parse(&self) -> Result<WzNodeArc, Error> {
+        let node = WzNode::new(&self.image.name, self.image.clone(), None).into_lock();
+        node_util::parse_node(&node)?;
+        Ok(node)
+    }
+}
+
+/// The decodable data of a leaf, each one only hold the reader and offset.
+#[derive(Debug, Clone)]
+pub enum LeafData {
+    PNG(WzPng),
+    Sound(WzSound),
+    Lua(WzLua),
+    RawData(WzRawData),
+    Video(WzVideo),
+}
+
+/// A detached snapshot of a leaf node, the data can be decoded in other thread without holding any lock,
+/// even the tree has been modified or the node has been dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::{collect_leaf_handles, LeafData};
+/// let node = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+/// wz_reader::util::parse_all(&node);
+///
+/// let handles = collect_leaf_handles(&node);
+///
+/// std::thread::spawn(move || {
+///     for handle in handles {
+///         if let LeafData::PNG(png) = &handle.data {
+///             png.extract_png().unwrap().save(format!("{}.png", handle.path)).unwrap();
+///         }
+///     }
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct LeafHandle {
+    /// full path of the leaf node when the handle created.
+    pub path: String,
+    pub data: LeafData,
+}
+
+impl LeafHandle {
+    /// Create from a leaf node, canvas with `_inlink` or `_outlink` will take the target's data.
+    pub fn from_node(node: &WzNodeArc) -> Option<Self> {
+        let path = node.read().unwrap().get_full_path();
+        Self::from_node_with_path(node, &path)
+    }
+
+    /// Same as `from_node`, with the full path already known, like the one from `walk_node_with_path`.
+    fn from_node_with_path(node: &WzNodeArc, path: &str) -> Option<Self> {
+        let linked = node_util::resolve_link(node, false);
+        let data_node = linked.as_ref().unwrap_or(node);
+
+        let data = match &data_node.read().unwrap().object_type {
+            WzObjectType::Property(WzSubProperty::PNG(png)) => LeafData::PNG(png.as_ref().clone()),
+            WzObjectType::Property(WzSubProperty::Sound(sound)) => {
+                LeafData::Sound(sound.as_ref().clone())
+            }
+            WzObjectType::Value(WzValue::Lua(lua)) => LeafData::Lua(lua.clone()),
+            WzObjectType::Value(WzValue::RawData(raw_data)) => LeafData::RawData(raw_data.clone()),
+            WzObjectType::Value(WzValue::Video(video)) => LeafData::Video(video.clone()),
+            _ => return None,
+        };
+
+        Some(Self {
+            path: path.to_string(),
+            data,
+        })
+    }
+}
+
+/// Collect the handles of all leaves under the node, it won't parse anything.
+pub fn collect_leaf_handles(node: &WzNodeArc) -> Vec<LeafHandle> {
+    let handles = RefCell::new(Vec::new());
+
+    walk_node_with_path(node, false, &|node, _, path| {
+        if let Some(handle) = LeafHandle::from_node_with_path(node, path) {
+            handles.borrow_mut().push(handle);
+        }
+    });
+
+    handles.into_inner()
+}
+
+/// Collect the handles of all images under the node, include itself.
+pub fn collect_image_handles(node: &WzNodeArc) -> Vec<ImageHandle> {
+    let handles = RefCell::new(Vec::new());
+
+    walk_node_with_path(node, false, &|node, _, path| {
+        if let Some(handle) = ImageHandle::from_node_with_path(node, path) {
+            handles.borrow_mut().push(handle);
+        }
+    });
+
+    handles.into_inner()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::property::WzString;
+
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+
+    #[test]
+    fn test_handles_are_send_sync() {
+        assert_send_sync::<ImageHandle>();
+        assert_send_sync::<LeafHandle>();
+    }
+
+    #[test]
+    fn test_leaf_handle_follow_link() {
+        let image = WzNode::from_str(
+            "test.img",
+            WzImage {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+
+        let mut png = WzPng::default();
+        png.width = 5;
+        let target = WzNode::from_str("target", png, Some(&image)).into_lock();
+        let canvas = WzNode::from_str("canvas", WzPng::default(), Some(&image)).into_lock();
+        let inlink = WzNode::from_str(
+            "_inlink",
+            WzString::from_str("target", [0; 4]),
+            Some(&canvas),
+        )
+        .into_lock();
+
+        canvas.write().unwrap().add(&inlink);
+        image.write().unwrap().add(&target);
+        image.write().unwrap().add(&canvas);
+
+        let handles = collect_leaf_handles(&image);
+        assert_eq!(handles.len(), 2);
+
+        let handle = LeafHandle::from_node(&canvas).unwrap();
+        assert_eq!(handle.path, "test.img/canvas");
+        assert!(matches!(handle.data, LeafData::PNG(ref png) if png.width == 5));
+    }
+}
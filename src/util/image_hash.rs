@@ -0,0 +1,295 @@
+use crate::{
+    property::{png::get_image, WzPngParseError, WzSubProperty},
+    WzNodeArc, WzObjectType,
+};
+use hashbrown::HashMap;
+use image::{imageops, DynamicImage, GrayImage, Luma};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// The perceptual hashes of a canvas, the visually identical canvases get the same `ImageHash`,
+/// and the similar ones(re-encoded, slightly changed) get a small `distance`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHash {
+    pub width: u32,
+    pub height: u32,
+    /// aHash, every bit is whether the pixel is brighter than the average in a 8x8 thumbnail.
+    pub average: u64,
+    /// dHash, every bit is whether the pixel is brighter than the right one in a 9x8 thumbnail.
+    pub difference: u64,
+    /// the average rgba, the hashes only look at the brightness, so a red and a blue square are the same without it.
+    pub color: [u8; 4],
+}
+
+impl ImageHash {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let rgba = image.to_rgba8();
+
+        /* the transparent pixels are black, whatever the color is */
+        let luma = GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+            Luma([(luma * a as u32 / 255) as u8])
+        });
+
+        let mut color_sum = [0_u64; 4];
+        for pixel in rgba.pixels() {
+            for (sum, channel) in color_sum.iter_mut().zip(pixel.0) {
+                *sum += channel as u64;
+            }
+        }
+        let count = (rgba.width() as u64 * rgba.height() as u64).max(1);
+
+        Self {
+            width: rgba.width(),
+            height: rgba.height(),
+            average: average_hash(&luma),
+            difference: difference_hash(&luma),
+            color: color_sum.map(|sum| (sum / count) as u8),
+        }
+    }
+    /// The number of different bits of both hashes, 0 means the hashes are the same.
+    pub fn distance(&self, other: &ImageHash) -> u32 {
+        (self.average ^ other.average).count_ones()
+            + (self.difference ^ other.difference).count_ones()
+    }
+    /// Same size, close average color and the `distance` is not more than `max_distance`.
+    pub fn is_similar(&self, other: &ImageHash, max_distance: u32) -> bool {
+        let is_close_color = self
+            .color
+            .iter()
+            .zip(other.color)
+            .all(|(a, b)| a.abs_diff(b) <= 8);
+
+        self.width == other.width
+            && self.height == other.height
+            && is_close_color
+            && self.distance(other) <= max_distance
+    }
+}
+
+fn average_hash(luma: &GrayImage) -> u64 {
+    let thumbnail = imageops::resize(luma, 8, 8, imageops::FilterType::Triangle);
+    let average = thumbnail.pixels().map(|p| p.0[0] as u32).sum::<u32>() / 64;
+
+    thumbnail
+        .pixels()
+        .fold(0, |hash, p| (hash << 1) | (p.0[0] as u32 > average) as u64)
+}
+
+fn difference_hash(luma: &GrayImage) -> u64 {
+    let thumbnail = imageops::resize(luma, 9, 8, imageops::FilterType::Triangle);
+
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = thumbnail.get_pixel(x, y).0[0];
+            let right = thumbnail.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+    hash
+}
+
+/// Get the perceptual hash of a canvas node, the `_inlink` and `_outlink` will be resolved like `get_image`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, property::WzPng, util::image_hash};
+/// let image = image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])).into();
+/// let a = WzNode::from_str("a", WzPng::from_image(&image, 2).unwrap(), None).into_lock();
+/// let b = WzNode::from_str("b", WzPng::from_image(&image, 1).unwrap(), None).into_lock();
+///
+/// assert_eq!(image_hash(&a).unwrap(), image_hash(&b).unwrap());
+/// ```
+pub fn image_hash(node: &WzNodeArc) -> Result<ImageHash, WzPngParseError> {
+    Ok(ImageHash::from_image(&get_image(node)?))
+}
+
+fn collect_canvases(node: &WzNodeArc, result: &mut Vec<WzNodeArc>) {
+    let node_read = node.read().unwrap();
+
+    /* the linked one is already deduplicated */
+    if matches!(
+        node_read.object_type,
+        WzObjectType::Property(WzSubProperty::PNG(_))
+    ) && node_read.at("_inlink").is_none()
+        && node_read.at("_outlink").is_none()
+    {
+        result.push(node.clone());
+    }
+
+    for child in node_read.children.values() {
+        collect_canvases(child, result);
+    }
+}
+
+/// Group the visually identical canvases under the node, only the groups have more than one canvas are returned,
+/// in the order they found. It won't parse any node, so make sure the node is parsed before, like using `parse_all`.
+///
+/// With `max_distance` 0 only the canvases with same `ImageHash` are grouped, otherwise the similar ones
+/// by `ImageHash::is_similar` are grouped too. The canvases already has `_inlink` or `_outlink` are skipped,
+/// and the ones failed to decode are ignored.
+///
+/// The hashes are computed from small thumbnails, compare the pixels before replacing a canvas with a `_inlink`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, parse_all, find_duplicate_images};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let obj = base.read().unwrap().at_path("Map/Obj").unwrap();
+/// parse_all(&obj);
+///
+/// for group in find_duplicate_images(&obj, 0) {
+///     let paths = group.iter().map(|node| node.read().unwrap().get_full_path()).collect::<Vec<_>>();
+///     println!("{paths:?}");
+/// }
+/// ```
+pub fn find_duplicate_images(node: &WzNodeArc, max_distance: u32) -> Vec<Vec<WzNodeArc>> {
+    let mut canvases = Vec::new();
+    collect_canvases(node, &mut canvases);
+
+    #[cfg(feature = "rayon")]
+    let iter = canvases.into_par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let iter = canvases.into_iter();
+
+    let hashes = iter
+        .filter_map(|node| {
+            let image = match &node.read().unwrap().object_type {
+                WzObjectType::Property(WzSubProperty::PNG(png)) => png.extract_png().ok()?,
+                _ => return None,
+            };
+            Some((ImageHash::from_image(&image), node))
+        })
+        .collect::<Vec<_>>();
+
+    let mut groups: Vec<(ImageHash, Vec<WzNodeArc>)> = Vec::new();
+
+    if max_distance == 0 {
+        let mut indices = HashMap::new();
+        for (hash, node) in hashes {
+            let index = *indices.entry(hash).or_insert_with(|| {
+                groups.push((hash, Vec::new()));
+                groups.len() - 1
+            });
+            groups[index].1.push(node);
+        }
+    } else {
+        for (hash, node) in hashes {
+            match groups
+                .iter_mut()
+                .find(|(first, _)| first.is_similar(&hash, max_distance))
+            {
+                Some((_, group)) => group.push(node),
+                None => groups.push((hash, vec![node])),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(_, group)| group)
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{
+        property::{WzPng, WzString},
+        WzNode,
+    };
+    use image::{Rgba, RgbaImage};
+
+    fn add_canvas(name: &str, image: RgbaImage, parent: &WzNodeArc) -> WzNodeArc {
+        add_child(name, WzPng::from_image(&image.into(), 2).unwrap(), parent)
+    }
+
+    /// `with_dot` draws a black dot on it, the thumbnails are still close.
+    fn gradient(with_dot: bool) -> RgbaImage {
+        RgbaImage::from_fn(16, 16, |x, y| {
+            if with_dot && (2..4).contains(&x) && (2..4).contains(&y) {
+                return Rgba([0, 0, 0, 255]);
+            }
+            let c = (255 - x * 16) as u8;
+            Rgba([c, c, c, 255])
+        })
+    }
+
+    #[test]
+    fn test_image_hash() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let red = RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 255, 255]));
+
+        let a = image_hash(&add_canvas("a", red.clone(), &root)).unwrap();
+        let b = image_hash(&add_canvas("b", red, &root)).unwrap();
+        let c = image_hash(&add_canvas("c", blue, &root)).unwrap();
+        let d = image_hash(&add_canvas("d", gradient(false), &root)).unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a.distance(&c), 0);
+        assert_ne!(a, c);
+        assert!(!a.is_similar(&c, 0));
+        assert_eq!(d.difference, u64::MAX);
+        assert!(d.distance(&a) > 0);
+
+        let not_canvas = add_child("e", 1, &root);
+        assert!(matches!(
+            image_hash(&not_canvas),
+            Err(WzPngParseError::NotPngProperty)
+        ));
+    }
+
+    #[test]
+    fn test_find_duplicate_images() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let red = RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255]));
+
+        add_canvas("red1", red.clone(), &root);
+        add_canvas(
+            "small",
+            RgbaImage::from_pixel(8, 8, Rgba([255, 0, 0, 255])),
+            &root,
+        );
+        add_canvas("gradient1", gradient(false), &root);
+        let folder = add_child("folder", 1, &root);
+        add_canvas("red2", red.clone(), &folder);
+        add_canvas("gradient2", gradient(true), &folder);
+
+        let linked = add_canvas("linked", red, &folder);
+        add_child("_inlink", WzString::from_str("red1", [0; 4]), &linked);
+
+        let names = |groups: Vec<Vec<WzNodeArc>>| {
+            groups
+                .iter()
+                .map(|group| {
+                    let mut names = group
+                        .iter()
+                        .map(|node| node.read().unwrap().name.to_string())
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    names
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(find_duplicate_images(&root, 0)),
+            vec![vec!["red1", "red2"]]
+        );
+
+        let mut similar = names(find_duplicate_images(&root, 16));
+        similar.sort();
+        assert_eq!(
+            similar,
+            vec![vec!["gradient1", "gradient2"], vec!["red1", "red2"]]
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    property::{resolve_string_from_node, WzString, WzValue},
+    WzNodeArc, WzNodeCast, WzObjectType,
+};
+use std::cell::RefCell;
+
+use super::{node_util, walk_node, walk_node_with_path, PathCache};
+
+/// How `rewrite_links` deal with the `_inlink` and `_outlink`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRewriteMode {
+    /// copy the target's data into the node, and remove the link.
+    Inline,
+    /// rewrite `_outlink` to the path relative to the root when the target is inside the root,
+    /// `_inlink` with target inside the root is kept as is, otherwise fallback to `Inline`.
+    Relative,
+}
+
+/// Find all node that has `_inlink` or `_outlink`, and the target node.
+fn collect_links(root: &WzNodeArc) -> Vec<(WzNodeArc, &'static str, WzNodeArc)> {
+    let links = RefCell::new(Vec::new());
+
+    walk_node(root, false, &|node| {
+        for link_name in ["_inlink", "_outlink"] {
+            let link_node = node.read().unwrap().at(link_name);
+            let Some(link_node) = link_node else {
+                continue;
+            };
+            let Ok(link) = resolve_string_from_node(&link_node) else {
+                continue;
+            };
+            let target = if link_name == "_inlink" {
+                node_util::resolve_inlink(&link, node)
+            } else {
+                node_util::resolve_outlink(&link, node, false)
+            };
+            if let Some(target) = target {
+                links.borrow_mut().push((node.clone(), link_name, target));
+                break;
+            }
+        }
+    });
+
+    links.into_inner()
+}
+
+fn inline_link(node: &WzNodeArc, target: &WzNodeArc) {
+    let object_type = target.read().unwrap().object_type.clone();
+    let mut node_write = node.write().unwrap();
+    node_write.object_type = object_type;
+    node_write.children.remove("_inlink");
+    node_write.children.remove("_outlink");
+}
+
+/// Rewrite `_inlink` and `_outlink` under the root, so the subtree still valid after exporting or copying it out of the Base.
+/// The link target need to be parsed before, like using `parse_all` on the Base.
+/// Returns the number of link has been handled, include the `_inlink` kept as is in `Relative` mode,
+/// unresolvable link will be ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, parse_all, rewrite_links, LinkRewriteMode};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// parse_all(&base);
+///
+/// let obj = base.read().unwrap().at_path("Map/Obj").unwrap();
+/// rewrite_links(&obj, LinkRewriteMode::Relative);
+/// ```
+pub fn rewrite_links(root: &WzNodeArc, mode: LinkRewriteMode) -> usize {
+    let root_path = format!("{}/", root.read().unwrap().get_full_path());
+    let mut count = 0;
+    let mut path_cache = PathCache::new();
+
+    for (node, link_name, target) in collect_links(root) {
+        let target_path = path_cache.get_full_path(&target);
+        let relative_path = target_path.strip_prefix(&root_path);
+
+        match (mode, relative_path) {
+            (LinkRewriteMode::Relative, Some(relative_path)) => {
+                if link_name == "_outlink" {
+                    if let Some(link_node) = node.read().unwrap().at(link_name) {
+                        link_node.write().unwrap().object_type = WzObjectType::Value(
+                            WzValue::String(WzString::from_str(relative_path, [0; 4])),
+                        );
+                        count += 1;
+                    }
+                } else {
+                    /* `_inlink` is relative to the image already */
+                    count += 1;
+                }
+            }
+            _ => {
+                inline_link(&node, &target);
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+/// The kind of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Inlink,
+    Outlink,
+    Uol,
+}
+
+/// A link that can't be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// full path of the link node itself.
+    pub path: String,
+    /// the link value.
+    pub target: String,
+    pub kind: LinkKind,
+}
+
+/// Resolve every `_inlink`, `_outlink` and UOL under the root, and report those can't be resolved.
+/// `_outlink` target will be parsed when needed, but the root itself need to be parsed before, like using `parse_all`.
+///
+/// Note: a resolved UOL is already replaced by it's target while parsing, so the remaining UOL usually are broken.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, parse_all, validate_links};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let map = base.read().unwrap().at("Map").unwrap();
+/// parse_all(&map);
+///
+/// for broken in validate_links(&map) {
+///     println!("{:?} {} -> {}", broken.kind, broken.path, broken.target);
+/// }
+/// ```
+pub fn validate_links(root: &WzNodeArc) -> Vec<BrokenLink> {
+    let links = RefCell::new(Vec::new());
+
+    walk_node_with_path(root, false, &|node, _, path| {
+        let node_read = node.read().unwrap();
+
+        let (kind, target) = if let Some(uol) = node_read.try_as_uol() {
+            (LinkKind::Uol, uol.get_string())
+        } else if node_read.name.as_str() == "_inlink" {
+            (LinkKind::Inlink, resolve_string_from_node(node))
+        } else if node_read.name.as_str() == "_outlink" {
+            (LinkKind::Outlink, resolve_string_from_node(node))
+        } else {
+            return;
+        };
+
+        if let Ok(target) = target {
+            links.borrow_mut().push((
+                node.clone(),
+                BrokenLink {
+                    path: path.to_string(),
+                    target,
+                    kind,
+                },
+            ));
+        }
+    });
+
+    links
+        .into_inner()
+        .into_iter()
+        .filter_map(|(node, link)| {
+            let resolved = match link.kind {
+                LinkKind::Inlink => node_util::resolve_inlink(&link.target, &node),
+                LinkKind::Outlink => node_util::resolve_outlink(&link.target, &node, true),
+                LinkKind::Uol => node
+                    .read()
+                    .unwrap()
+                    .parent
+                    .upgrade()
+                    .and_then(|parent| parent.read().unwrap().at_path_relative(&link.target)),
+            };
+            resolved.is_none().then_some(link)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzPng, WzDirectory, WzFile, WzImage, WzNode, WzNodeCast};
+
+    fn setup_node_tree() -> WzNodeArc {
+        let parsed_file = WzFile {
+            is_parsed: true,
+            ..Default::default()
+        };
+        let parsed_dir = || WzDirectory {
+            is_parsed: true,
+            ..Default::default()
+        };
+
+        let root = WzNode::from_str("Base", parsed_file, None).into_lock();
+        let dir = add_child("dir", parsed_dir(), &root);
+        let other_dir = add_child("other", parsed_dir(), &root);
+
+        let parsed_image = || WzImage {
+            is_parsed: true,
+            ..Default::default()
+        };
+
+        let img1 = add_child("1.img", parsed_image(), &dir);
+        let img2 = add_child("2.img", parsed_image(), &dir);
+        let img3 = add_child("3.img", parsed_image(), &other_dir);
+
+        let mut png = WzPng::default();
+        png.width = 5;
+
+        add_child("target", png.clone(), &img2);
+        add_child("target", png, &img3);
+
+        let canvas1 = add_child("canvas1", WzPng::default(), &img1);
+        add_child(
+            "_outlink",
+            WzString::from_str("dir/2.img/target", [0; 4]),
+            &canvas1,
+        );
+
+        let canvas2 = add_child("canvas2", WzPng::default(), &img1);
+        add_child(
+            "_outlink",
+            WzString::from_str("other/3.img/target", [0; 4]),
+            &canvas2,
+        );
+
+        root
+    }
+
+    #[test]
+    fn test_rewrite_links_inline() {
+        let root = setup_node_tree();
+        let dir = root.read().unwrap().at("dir").unwrap();
+
+        assert_eq!(rewrite_links(&dir, LinkRewriteMode::Inline), 2);
+
+        let canvas = root.read().unwrap().at_path("dir/1.img/canvas1").unwrap();
+        let canvas = canvas.read().unwrap();
+
+        assert_eq!(canvas.try_as_png().map(|png| png.width), Some(5));
+        assert!(canvas.at("_outlink").is_none());
+    }
+
+    #[test]
+    fn test_rewrite_links_relative() {
+        let root = setup_node_tree();
+        let dir = root.read().unwrap().at("dir").unwrap();
+
+        let img1 = root.read().unwrap().at_path("dir/1.img").unwrap();
+        add_child("target", WzPng::default(), &img1);
+        let canvas3 = add_child("canvas3", WzPng::default(), &img1);
+        add_child("_inlink", WzString::from_str("target", [0; 4]), &canvas3);
+
+        assert_eq!(rewrite_links(&dir, LinkRewriteMode::Relative), 3);
+
+        /* `_inlink` inside the root is kept */
+        let inlink = canvas3.read().unwrap().at("_inlink").unwrap();
+        assert_eq!(resolve_string_from_node(&inlink).unwrap(), "target");
+
+        let outlink = root
+            .read()
+            .unwrap()
+            .at_path("dir/1.img/canvas1/_outlink")
+            .unwrap();
+
+        assert_eq!(resolve_string_from_node(&outlink).unwrap(), "2.img/target");
+
+        /* target is outside of the root, so fallback to inline */
+        let canvas = root.read().unwrap().at_path("dir/1.img/canvas2").unwrap();
+        let canvas = canvas.read().unwrap();
+
+        assert_eq!(canvas.try_as_png().map(|png| png.width), Some(5));
+        assert!(canvas.at("_outlink").is_none());
+    }
+
+    #[test]
+    fn test_validate_links() {
+        let root = setup_node_tree();
+        let img1 = root.read().unwrap().at_path("dir/1.img").unwrap();
+
+        let canvas = WzNode::from_str("canvas3", WzPng::default(), Some(&img1)).into_lock();
+        let inlink = WzNode::from_str(
+            "_inlink",
+            WzString::from_str("not/exist", [0; 4]),
+            Some(&canvas),
+        )
+        .into_lock();
+        let uol = WzNode::from_str(
+            "uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("../canvas1", [0; 4]))),
+            Some(&canvas),
+        )
+        .into_lock();
+        let broken_uol = WzNode::from_str(
+            "broken_uol",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("../nothing", [0; 4]))),
+            Some(&canvas),
+        )
+        .into_lock();
+
+        canvas.write().unwrap().add(&inlink);
+        canvas.write().unwrap().add(&uol);
+        canvas.write().unwrap().add(&broken_uol);
+        img1.write().unwrap().add(&canvas);
+
+        let mut broken = validate_links(&root);
+        broken.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(broken.len(), 2);
+        assert_eq!(broken[0].path, "Base/dir/1.img/canvas3/_inlink");
+        assert_eq!(broken[0].kind, LinkKind::Inlink);
+        assert_eq!(broken[1].path, "Base/dir/1.img/canvas3/broken_uol");
+        assert_eq!(broken[1].target, "../nothing");
+    }
+}
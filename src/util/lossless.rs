@@ -0,0 +1,809 @@
+use crate::{
+    file::WzFileMeta,
+    ms::header::MsHeader,
+    property::{
+        string, Vector2D, WzLua, WzPng, WzRawData, WzSound, WzSoundType, WzString, WzStringType,
+        WzSubProperty, WzValue, WzVideo,
+    },
+    MsFile, WzDirectory, WzFile, WzImage, WzNode, WzNodeArc, WzObjectType, WzReader,
+};
+use flate2::{write::ZlibEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LosslessError {
+    #[error("Error decoding string: {0}")]
+    StringError(#[from] string::WzStringParseError),
+
+    #[cfg(feature = "json")]
+    #[error("Error serializing json: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "msgpack")]
+    #[error("Error encoding MessagePack: {0}")]
+    MsgpackEncodeError(#[from] rmp_serde::encode::Error),
+
+    #[cfg(feature = "msgpack")]
+    #[error("Error decoding MessagePack: {0}")]
+    MsgpackDecodeError(#[from] rmp_serde::decode::Error),
+
+    #[cfg(feature = "bincode")]
+    #[error("Error serializing bincode: {0}")]
+    BincodeError(#[from] bincode::Error),
+}
+
+/// A node with everything needed to rebuild it without the original wz file,
+/// binary data like canvas, sound and lua are kept as is(base64 in human readable format).
+///
+/// `WzFile`, `WzDirectory` and `WzImage` are restored as parsed, since the original file is not included,
+/// and `MsImage` will be restored as `WzImage` like it has been parsed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LosslessNode {
+    pub name: String,
+    pub object: LosslessObject,
+    pub children: Vec<LosslessNode>,
+}
+
+/// Using the default externally tagged representation instead of `tag` and `content` like `WzObjectType`,
+/// since non self-describing format like bincode can't deserialize it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LosslessObject {
+    File(WzFileMeta),
+    MsFile(MsHeader),
+    Directory,
+    Image,
+    Property,
+    Convex,
+    PNG {
+        width: u32,
+        height: u32,
+        format: (u32, u32),
+        header: i32,
+        iv: [u8; 4],
+        #[serde(with = "bytes_serde")]
+        data: Vec<u8>,
+    },
+    Sound {
+        duration: u32,
+        sound_type: WzSoundType,
+        #[serde(with = "bytes_serde")]
+        header: Vec<u8>,
+        #[serde(with = "bytes_serde")]
+        data: Vec<u8>,
+    },
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Vector(Vector2D),
+    /// the decrypted bytes like `WzString::get_raw_bytes`, so the legacy string not valid utf8 or utf16 is kept.
+    String {
+        #[serde(with = "bytes_serde")]
+        raw: Vec<u8>,
+        string_type: WzStringType,
+        iv: [u8; 4],
+    },
+    UOL {
+        #[serde(with = "bytes_serde")]
+        raw: Vec<u8>,
+        string_type: WzStringType,
+        iv: [u8; 4],
+    },
+    ParsedString(String),
+    RawData(#[serde(with = "bytes_serde")] Vec<u8>),
+    Video(#[serde(with = "bytes_serde")] Vec<u8>),
+    Lua(#[serde(with = "bytes_serde")] Vec<u8>),
+    Null,
+}
+
+fn reader_from_buff(buff: &[u8], iv: [u8; 4]) -> Arc<WzReader> {
+    Arc::new(WzReader::from_buff(buff).with_iv(iv))
+}
+
+impl LosslessObject {
+    fn from_object_type(object_type: &WzObjectType) -> Result<Self, LosslessError> {
+        Ok(match object_type {
+            WzObjectType::File(file) => LosslessObject::File(file.wz_file_meta.clone()),
+            WzObjectType::MsFile(file) => LosslessObject::MsFile(file.header.clone()),
+            WzObjectType::Directory(_) => LosslessObject::Directory,
+            WzObjectType::Image(_) | WzObjectType::MsImage(_) => LosslessObject::Image,
+            WzObjectType::Property(WzSubProperty::Property) => LosslessObject::Property,
+            WzObjectType::Property(WzSubProperty::Convex) => LosslessObject::Convex,
+            WzObjectType::Property(WzSubProperty::PNG(png)) => LosslessObject::PNG {
+                width: png.width,
+                height: png.height,
+                format: png.get_format_pair(),
+                header: png.header,
+                iv: png.get_iv(),
+                data: png.get_raw_buffer().to_vec(),
+            },
+            WzObjectType::Property(WzSubProperty::Sound(sound)) => LosslessObject::Sound {
+                duration: sound.duration,
+                sound_type: sound.sound_type.clone(),
+                header: sound.get_raw_header().to_vec(),
+                data: sound.get_raw_buffer().to_vec(),
+            },
+            WzObjectType::Value(value) => match value {
+                WzValue::Short(value) => LosslessObject::Short(*value),
+                WzValue::Int(value) => LosslessObject::Int(*value),
+                WzValue::Long(value) => LosslessObject::Long(*value),
+                WzValue::Float(value) => LosslessObject::Float(*value),
+                WzValue::Double(value) => LosslessObject::Double(*value),
+                WzValue::Vector(value) => LosslessObject::Vector(*value),
+                WzValue::String(string) => LosslessObject::String {
+                    raw: string.get_raw_bytes()?,
+                    string_type: string.string_type.clone(),
+                    iv: string.get_iv(),
+                },
+                WzValue::UOL(string) => LosslessObject::UOL {
+                    raw: string.get_raw_bytes()?,
+                    string_type: string.string_type.clone(),
+                    iv: string.get_iv(),
+                },
+                WzValue::ParsedString(string) => LosslessObject::ParsedString(string.clone()),
+                WzValue::RawData(raw_data) => {
+                    LosslessObject::RawData(raw_data.get_buffer().to_vec())
+                }
+                WzValue::Video(video) => LosslessObject::Video(video.get_buffer().to_vec()),
+                WzValue::Lua(lua) => LosslessObject::Lua(lua.get_buffer().to_vec()),
+                WzValue::Null => LosslessObject::Null,
+            },
+        })
+    }
+
+    fn into_object_type(self) -> WzObjectType {
+        match self {
+            LosslessObject::File(wz_file_meta) => WzFile {
+                wz_file_meta,
+                is_parsed: true,
+                ..Default::default()
+            }
+            .into(),
+            LosslessObject::MsFile(header) => MsFile {
+                header,
+                is_parsed: true,
+                ..Default::default()
+            }
+            .into(),
+            LosslessObject::Directory => WzDirectory {
+                is_parsed: true,
+                ..Default::default()
+            }
+            .into(),
+            LosslessObject::Image => WzImage {
+                is_parsed: true,
+                ..Default::default()
+            }
+            .into(),
+            LosslessObject::Property => WzObjectType::Property(WzSubProperty::Property),
+            LosslessObject::Convex => WzObjectType::Property(WzSubProperty::Convex),
+            LosslessObject::PNG {
+                width,
+                height,
+                format,
+                header,
+                iv,
+                data,
+            } => {
+                let reader = reader_from_buff(&data, iv);
+                WzPng::new(&reader, (width, height), format, (0, data.len()), header).into()
+            }
+            LosslessObject::Sound {
+                duration,
+                sound_type,
+                header,
+                data,
+            } => {
+                let header_size = header.len();
+                let length = data.len() as u32;
+                let reader = reader_from_buff(&[header, data].concat(), [0; 4]);
+                WzSound::new(
+                    &reader,
+                    header_size,
+                    length,
+                    0,
+                    header_size,
+                    duration,
+                    sound_type,
+                )
+                .into()
+            }
+            LosslessObject::Short(value) => value.into(),
+            LosslessObject::Int(value) => value.into(),
+            LosslessObject::Long(value) => value.into(),
+            LosslessObject::Float(value) => value.into(),
+            LosslessObject::Double(value) => value.into(),
+            LosslessObject::Vector(value) => value.into(),
+            LosslessObject::String {
+                raw,
+                string_type,
+                iv,
+            } => WzString::from_raw_bytes(&raw, iv, string_type).into(),
+            LosslessObject::UOL {
+                raw,
+                string_type,
+                iv,
+            } => WzObjectType::Value(WzValue::UOL(WzString::from_raw_bytes(
+                &raw,
+                iv,
+                string_type,
+            ))),
+            LosslessObject::ParsedString(string) => {
+                WzObjectType::Value(WzValue::ParsedString(string))
+            }
+            LosslessObject::RawData(data) => WzRawData::from_bytes(data).into(),
+            LosslessObject::Video(data) => WzVideo::from_bytes(data).into(),
+            LosslessObject::Lua(data) => {
+                WzLua::new(&reader_from_buff(&data, [0; 4]), 0, data.len()).into()
+            }
+            LosslessObject::Null => WzObjectType::Value(WzValue::Null),
+        }
+    }
+}
+
+impl LosslessNode {
+    /// Capture the node and it's whole subtree, it won't parse anything.
+    pub fn from_node(node: &WzNode) -> Result<Self, LosslessError> {
+        let mut children = node
+            .children
+            .values()
+            .map(|child| LosslessNode::from_node(&child.read().unwrap()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(LosslessNode {
+            name: node.name.to_string(),
+            object: LosslessObject::from_object_type(&node.object_type)?,
+            children,
+        })
+    }
+
+    /// Rebuild the `WzNode` tree.
+    pub fn into_node(self, parent: Option<&WzNodeArc>) -> WzNodeArc {
+        let node = WzNode::from_str(&self.name, self.object.into_object_type(), parent).into_lock();
+
+        {
+            let mut node_write = node.write().unwrap();
+            for child in self.children {
+                let child = child.into_node(Some(&node));
+                node_write.add(&child);
+            }
+        }
+
+        node
+    }
+}
+
+/// width, height, format, header and the data of a canvas.
+type CanvasKey<'a> = (u32, u32, (u32, u32), i32, &'a [u8]);
+
+/// The canvas data compared by `LosslessNode::inline_duplicate_canvases`, `None` when it's not a canvas.
+fn canvas_key(object: &LosslessObject) -> Option<CanvasKey<'_>> {
+    match object {
+        LosslessObject::PNG {
+            width,
+            height,
+            format,
+            header,
+            data,
+            ..
+        } => Some((*width, *height, *format, *header, data)),
+        _ => None,
+    }
+}
+
+/// The string with the type guessed from the content like `WzString::from_str`.
+fn string_object(string: &str, iv: [u8; 4]) -> LosslessObject {
+    let (raw, string_type) = if string.is_ascii() {
+        (string.as_bytes().to_vec(), WzStringType::Ascii)
+    } else {
+        (
+            string.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            WzStringType::Unicode,
+        )
+    };
+
+    LosslessObject::String {
+        raw,
+        string_type,
+        iv,
+    }
+}
+
+/// The 1x1 transparent canvas left in the linked canvas, same as the official archives.
+fn placeholder_canvas(iv: [u8; 4]) -> LosslessObject {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    /* writing to a Vec never fails */
+    encoder.write_all(&[0; 4]).unwrap();
+    let data = encoder.finish().unwrap();
+
+    LosslessObject::PNG {
+        width: 1,
+        height: 1,
+        format: (2, 0),
+        header: u16::from_le_bytes([data[0], data[1]]) as i32,
+        iv,
+        data,
+    }
+}
+
+impl LosslessNode {
+    fn is_linked(&self) -> bool {
+        self.children
+            .iter()
+            .any(|child| child.name == "_inlink" || child.name == "_outlink")
+    }
+
+    fn descendant(&self, indices: &[usize]) -> &LosslessNode {
+        indices
+            .iter()
+            .fold(self, |node, index| &node.children[*index])
+    }
+
+    fn descendant_mut(&mut self, indices: &[usize]) -> &mut LosslessNode {
+        indices
+            .iter()
+            .fold(self, |node, index| &mut node.children[*index])
+    }
+
+    /// Collect the path and the child indices of the canvases in a image, in the depth first order.
+    fn collect_canvases(
+        &self,
+        path: &mut String,
+        indices: &mut Vec<usize>,
+        result: &mut Vec<(String, Vec<usize>)>,
+    ) {
+        for (index, child) in self.children.iter().enumerate() {
+            let len = path.len();
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(&child.name);
+            indices.push(index);
+
+            if canvas_key(&child.object).is_some() && !child.is_linked() {
+                result.push((path.clone(), indices.clone()));
+            }
+            child.collect_canvases(path, indices, result);
+
+            indices.pop();
+            path.truncate(len);
+        }
+    }
+
+    fn inline_duplicate_canvases_in_image(&mut self) -> usize {
+        let mut canvases = Vec::new();
+        self.collect_canvases(&mut String::new(), &mut Vec::new(), &mut canvases);
+
+        /* the hash of the canvas data to the index of the original canvases */
+        let mut originals: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut duplicates = Vec::new();
+
+        for (index, (_, indices)) in canvases.iter().enumerate() {
+            let key = canvas_key(&self.descendant(indices).object);
+
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+
+            let candidates = originals.entry(hasher.finish()).or_default();
+            let original = candidates.iter().find(|original| {
+                canvas_key(&self.descendant(&canvases[**original].1).object) == key
+            });
+
+            match original {
+                Some(original) => duplicates.push((indices.clone(), canvases[*original].0.clone())),
+                None => candidates.push(index),
+            }
+        }
+
+        let count = duplicates.len();
+
+        /* the later one first, so the nested canvases won't be affected by sorting the parent's children */
+        for (indices, target) in duplicates.into_iter().rev() {
+            let canvas = self.descendant_mut(&indices);
+            let iv = match canvas.object {
+                LosslessObject::PNG { iv, .. } => iv,
+                _ => [0; 4],
+            };
+
+            canvas.object = placeholder_canvas(iv);
+            canvas.children.push(LosslessNode {
+                name: "_inlink".to_string(),
+                object: string_object(&target, iv),
+                children: Vec::new(),
+            });
+            canvas.children.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+
+        count
+    }
+
+    /// Replace the byte-identical canvases in every image with a 1x1 canvas and a `_inlink` to the first one
+    /// like the official archives, it makes the output much smaller for the frame-heavy images.
+    /// The `_inlink` will be resolved by `get_image` after restored.
+    ///
+    /// Only the canvases under a `WzImage` are checked, since `_inlink` is relative to the image.
+    /// The canvases already have `_inlink` or `_outlink` are not touched. Returns the number of replaced canvases.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use wz_reader::{WzNode, util::LosslessNode};
+    /// # let image = WzNode::from_str("100100.img", 1, None).into_lock();
+    /// let mut lossless = LosslessNode::from_node(&image.read().unwrap()).unwrap();
+    /// lossless.inline_duplicate_canvases();
+    ///
+    /// # #[cfg(feature = "json")]
+    /// let json = serde_json::to_string(&lossless).unwrap();
+    /// ```
+    pub fn inline_duplicate_canvases(&mut self) -> usize {
+        if matches!(self.object, LosslessObject::Image) {
+            return self.inline_duplicate_canvases_in_image();
+        }
+
+        self.children
+            .iter_mut()
+            .map(|child| child.inline_duplicate_canvases())
+            .sum()
+    }
+}
+
+/// Serialize the node to json without losing any data, use `from_lossless_json` to get it back.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzNodeCast};
+/// # use wz_reader::property::{WzString, WzStringType};
+/// # use wz_reader::util::{to_lossless_json, from_lossless_json};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let string = WzString::from_str_with_type("hi", [0; 4], WzStringType::Unicode);
+/// let child = WzNode::from_str("child", string, Some(&root)).into_lock();
+/// root.write().unwrap().add(&child);
+///
+/// let json = to_lossless_json(&root).unwrap();
+/// let restored = from_lossless_json(json).unwrap();
+///
+/// let child = restored.read().unwrap().at("child").unwrap();
+/// let child = child.read().unwrap();
+/// let string = child.try_as_string().unwrap();
+///
+/// assert_eq!(string.get_string().unwrap(), "hi");
+/// assert_eq!(string.string_type, WzStringType::Unicode);
+/// ```
+#[cfg(feature = "json")]
+pub fn to_lossless_json(node: &WzNodeArc) -> Result<serde_json::Value, LosslessError> {
+    let lossless = LosslessNode::from_node(&node.read().unwrap())?;
+    Ok(serde_json::to_value(lossless)?)
+}
+
+/// Rebuild the node from the json generated by `to_lossless_json`.
+#[cfg(feature = "json")]
+pub fn from_lossless_json(json: serde_json::Value) -> Result<WzNodeArc, LosslessError> {
+    let lossless: LosslessNode = serde_json::from_value(json)?;
+    Ok(lossless.into_node(None))
+}
+
+/// Serialize the node to MessagePack without losing any data, much smaller and faster than json,
+/// use `from_msgpack` to get it back.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::{to_msgpack, from_msgpack};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+///
+/// let bytes = to_msgpack(&root).unwrap();
+/// let restored = from_msgpack(&bytes).unwrap();
+///
+/// assert_eq!(restored.read().unwrap().name.as_str(), "root");
+/// ```
+#[cfg(feature = "msgpack")]
+pub fn to_msgpack(node: &WzNodeArc) -> Result<Vec<u8>, LosslessError> {
+    let lossless = LosslessNode::from_node(&node.read().unwrap())?;
+    Ok(rmp_serde::to_vec(&lossless)?)
+}
+
+/// Rebuild the node from the bytes generated by `to_msgpack`.
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack(bytes: &[u8]) -> Result<WzNodeArc, LosslessError> {
+    let lossless: LosslessNode = rmp_serde::from_slice(bytes)?;
+    Ok(lossless.into_node(None))
+}
+
+/// Serialize the node to bincode without losing any data, use `from_bincode` to get it back.
+/// The output is not self-describing, so it only for caching with the same version of this crate.
+#[cfg(feature = "bincode")]
+pub fn to_bincode(node: &WzNodeArc) -> Result<Vec<u8>, LosslessError> {
+    let lossless = LosslessNode::from_node(&node.read().unwrap())?;
+    Ok(bincode::serialize(&lossless)?)
+}
+
+/// Rebuild the node from the bytes generated by `to_bincode`.
+#[cfg(feature = "bincode")]
+pub fn from_bincode(bytes: &[u8]) -> Result<WzNodeArc, LosslessError> {
+    let lossless: LosslessNode = bincode::deserialize(bytes)?;
+    Ok(lossless.into_node(None))
+}
+
+/// Bytes as base64 string in human readable format like json, otherwise as raw bytes.
+mod bytes_serde {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::de::{self, Deserializer, SeqAccess, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if s.is_human_readable() {
+            s.serialize_str(&STANDARD.encode(bytes))
+        } else {
+            s.serialize_bytes(bytes)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("bytes or base64 string")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            STANDARD.decode(value).map_err(E::custom)
+        }
+
+        fn visit_bytes<E>(self, value: &[u8]) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Vec<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(value)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<u8>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<Vec<u8>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if d.is_human_readable() {
+            d.deserialize_str(BytesVisitor)
+        } else {
+            d.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{util::node_util, WzNodeCast};
+
+    fn setup_node_tree() -> WzNodeArc {
+        let reader = reader_from_buff(&[1, 2, 3, 4, 5, 6, 7, 8], [1, 2, 3, 4]);
+
+        let root = WzNode::from_str("root", WzImage::default(), None).into_lock();
+        let png = WzPng::new(&reader, (2, 3), (2, 0), (2, 4), 0x9C78);
+        let canvas = add_child("canvas", png, &root);
+        add_child("origin", Vector2D(1, 2), &canvas);
+        add_child(
+            "sound",
+            WzSound::new(&reader, 4, 4, 0, 4, 100, WzSoundType::Mp3),
+            &root,
+        );
+        add_child(
+            "unicode",
+            WzString::from_str_with_type("ascii", [0; 4], WzStringType::Unicode),
+            &root,
+        );
+        add_child(
+            "legacy",
+            WzString::from_raw_bytes(&[0x68, 0xB0, 0xA1], [0; 4], WzStringType::Ascii),
+            &root,
+        );
+        add_child("lua", WzLua::new(&reader, 0, 8), &root);
+        add_child("long", i64::MAX, &root);
+
+        root
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_lossless_json_round_trip() {
+        let root = setup_node_tree();
+
+        let json = to_lossless_json(&root).unwrap();
+        let restored = from_lossless_json(json.clone()).unwrap();
+
+        assert_eq!(to_lossless_json(&restored).unwrap(), json);
+
+        let restored = restored.read().unwrap();
+
+        let canvas = restored.at("canvas").unwrap();
+        let canvas = canvas.read().unwrap();
+        let png = canvas.try_as_png().unwrap();
+        assert_eq!(png.get_raw_buffer(), &[3, 4, 5, 6]);
+        assert_eq!(png.get_iv(), [1, 2, 3, 4]);
+        assert_eq!(png.get_format_pair(), (2, 0));
+        assert!(canvas.at("origin").is_some());
+
+        let sound = restored.at("sound").unwrap();
+        let sound = sound.read().unwrap();
+        let sound = sound.try_as_sound().unwrap();
+        assert_eq!(sound.get_raw_header(), &[1, 2, 3, 4]);
+        assert_eq!(sound.get_raw_buffer(), &[5, 6, 7, 8]);
+        assert_eq!(sound.duration, 100);
+
+        let unicode = restored.at("unicode").unwrap();
+        let unicode = unicode.read().unwrap();
+        let unicode = unicode.try_as_string().unwrap();
+        assert_eq!(unicode.string_type, WzStringType::Unicode);
+        assert_eq!(unicode.get_string().unwrap(), "ascii");
+
+        /* not valid utf8, the raw bytes are kept */
+        let legacy = restored.at("legacy").unwrap();
+        let legacy = legacy.read().unwrap();
+        assert_eq!(
+            legacy.try_as_string().unwrap().get_raw_bytes().unwrap(),
+            [0x68, 0xB0, 0xA1]
+        );
+
+        let lua = restored.at("lua").unwrap();
+        let lua = lua.read().unwrap();
+        assert_eq!(
+            lua.try_as_lua().unwrap().get_buffer(),
+            &[1, 2, 3, 4, 5, 6, 7, 8]
+        );
+
+        assert!(matches!(
+            restored.object_type,
+            WzObjectType::Image(ref image) if image.is_parsed
+        ));
+    }
+
+    #[test]
+    fn test_inline_duplicate_canvases() {
+        let reader = reader_from_buff(&[1, 2, 3, 4, 5, 6, 7, 8], [1, 2, 3, 4]);
+        let png = |offset| WzPng::new(&reader, (2, 3), (2, 0), (offset, 4), 0x9C78);
+
+        let root = WzNode::from_str("root", WzDirectory::default(), None).into_lock();
+        let image = add_child("image.img", WzImage::default(), &root);
+        let stand = add_child(
+            "stand",
+            WzObjectType::Property(WzSubProperty::Property),
+            &image,
+        );
+        let first = add_child("0", png(0), &stand);
+        add_child("origin", Vector2D(1, 2), &first);
+        add_child("1", png(4), &stand);
+        let duplicate = add_child("2", png(0), &stand);
+        add_child("origin", Vector2D(3, 4), &duplicate);
+        add_child("walk", png(0), &image);
+
+        let linked = add_child("linked", png(0), &image);
+        add_child("_inlink", WzString::from_str("stand/1", [0; 4]), &linked);
+
+        let mut lossless = LosslessNode::from_node(&root.read().unwrap()).unwrap();
+        assert_eq!(lossless.inline_duplicate_canvases(), 2);
+
+        let restored = lossless.into_node(None);
+        let image = restored.read().unwrap().at("image.img").unwrap();
+
+        for path in ["stand/2", "walk"] {
+            let canvas = image.read().unwrap().at_path(path).unwrap();
+            let canvas = canvas.read().unwrap();
+            assert_eq!(canvas.try_as_png().unwrap().width, 1);
+
+            let inlink = canvas.at("_inlink").unwrap();
+            let inlink = inlink.read().unwrap();
+            assert_eq!(
+                inlink.try_as_string().unwrap().get_string().unwrap(),
+                "stand/0"
+            );
+        }
+
+        /* the other children are kept */
+        let origin = image.read().unwrap().at_path("stand/2/origin").unwrap();
+        assert_eq!(
+            origin.read().unwrap().try_as_vector2d(),
+            Some(&Vector2D(3, 4))
+        );
+
+        let duplicate = image.read().unwrap().at_path("walk").unwrap();
+        let target = node_util::resolve_inlink("stand/0", &duplicate).unwrap();
+        assert_eq!(
+            target
+                .read()
+                .unwrap()
+                .try_as_png()
+                .unwrap()
+                .get_raw_buffer(),
+            &[1, 2, 3, 4]
+        );
+
+        let linked = image.read().unwrap().at_path("linked/_inlink").unwrap();
+        let linked = linked.read().unwrap();
+        assert_eq!(
+            linked.try_as_string().unwrap().get_string().unwrap(),
+            "stand/1"
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_lossless_json_base64() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let raw = WzRawData::from_bytes(vec![1, 2, 3]);
+        let child = WzNode::from_str("raw", raw, Some(&root)).into_lock();
+        root.write().unwrap().add(&child);
+
+        let json = to_lossless_json(&root).unwrap();
+
+        assert_eq!(
+            json["children"][0]["object"],
+            serde_json::json!({ "RawData": "AQID" })
+        );
+    }
+
+    #[cfg(all(feature = "json", feature = "msgpack"))]
+    #[test]
+    fn test_msgpack_round_trip() {
+        let root = setup_node_tree();
+
+        let bytes = to_msgpack(&root).unwrap();
+        let restored = from_msgpack(&bytes).unwrap();
+
+        assert_eq!(
+            to_lossless_json(&restored).unwrap(),
+            to_lossless_json(&root).unwrap()
+        );
+        assert!(bytes.len() < to_lossless_json(&root).unwrap().to_string().len());
+    }
+
+    #[cfg(all(feature = "json", feature = "bincode"))]
+    #[test]
+    fn test_bincode_round_trip() {
+        let root = setup_node_tree();
+
+        let bytes = to_bincode(&root).unwrap();
+        let restored = from_bincode(&bytes).unwrap();
+
+        assert_eq!(
+            to_lossless_json(&restored).unwrap(),
+            to_lossless_json(&root).unwrap()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    property::{WzSubProperty, WzValue},
+    WzNodeArc, WzObjectType,
+};
+use std::borrow::Cow;
+use std::io::Write;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::escape_csv_field;
+
+/// A asset in the tree, also a row of the manifest.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// full path of the node.
+    pub path: String,
+    /// `WzObjectType::type_name`, like `PNG` or `Sound`.
+    pub node_type: String,
+    /// only the canvases have size.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// only the sounds have duration, in milliseconds.
+    pub duration: Option<u32>,
+    /// the bytes of the stored data, the compressed pixels of the canvas, the block of the image, etc.
+    pub size: usize,
+    /// FNV-1a 64 of the stored data in hex, it's stable across builds and platforms so it can be saved and
+    /// compared later. The encrypted data is hashed as it is, so the same data in different version may differ.
+    pub hash: String,
+}
+
+/// The FNV-1a 64 of the bytes, in hex.
+fn content_hash<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for chunk in chunks {
+        for &byte in chunk {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// Get the manifest entry of a single node, `None` when the node is not a asset, like a directory or a `Int`.
+/// The assets are `Image`, `PNG`, `Sound`, `RawData`, `Video` and `Lua`.
+pub fn manifest_entry(node: &WzNodeArc, path: &str) -> Option<ManifestEntry> {
+    let node_read = node.read().unwrap();
+
+    let mut entry = ManifestEntry {
+        path: path.to_string(),
+        node_type: node_read.object_type.type_name().to_string(),
+        width: None,
+        height: None,
+        duration: None,
+        size: 0,
+        hash: String::new(),
+    };
+
+    match &node_read.object_type {
+        WzObjectType::Image(image) => {
+            let data = image
+                .reader
+                .get_slice(image.offset..image.offset + image.block_size);
+            entry.size = data.len();
+            entry.hash = content_hash([data]);
+        }
+        WzObjectType::Property(WzSubProperty::PNG(png)) => {
+            /* the stored bytes when the encrypted chunks are broken */
+            let data = png
+                .raw_compressed()
+                .unwrap_or(Cow::Borrowed(png.get_raw_buffer()));
+            entry.width = Some(png.width);
+            entry.height = Some(png.height);
+            entry.size = data.len();
+            entry.hash = content_hash([&*data]);
+        }
+        WzObjectType::Property(WzSubProperty::Sound(sound)) => {
+            let data = sound.get_raw_buffer();
+            entry.duration = Some(sound.duration);
+            entry.size = data.len();
+            entry.hash = content_hash([sound.get_raw_header(), data]);
+        }
+        WzObjectType::Value(WzValue::RawData(raw_data)) => {
+            let data = raw_data.get_buffer();
+            entry.size = data.len();
+            entry.hash = content_hash([data]);
+        }
+        WzObjectType::Value(WzValue::Video(video)) => {
+            let data = video.get_buffer();
+            entry.size = data.len();
+            entry.hash = content_hash([data]);
+        }
+        WzObjectType::Value(WzValue::Lua(lua)) => {
+            let data = lua.get_buffer();
+            entry.size = data.len();
+            entry.hash = content_hash([data]);
+        }
+        _ => return None,
+    }
+
+    Some(entry)
+}
+
+fn build_manifest_inner(
+    node: &WzNodeArc,
+    force_parse: bool,
+    path: &mut String,
+    entries: &mut Vec<ManifestEntry>,
+) {
+    if force_parse {
+        // ignore the error, the failed one just has no children
+        let _ = node.write().unwrap().parse(node);
+    }
+
+    if let Some(entry) = manifest_entry(node, path) {
+        entries.push(entry);
+    }
+
+    let mut children = node
+        .read()
+        .unwrap()
+        .children
+        .iter()
+        .map(|(name, child)| (name.clone(), child.clone()))
+        .collect::<Vec<_>>();
+    children.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+    for (name, child) in children {
+        let len = path.len();
+        path.push('/');
+        path.push_str(name.as_str());
+        build_manifest_inner(&child, force_parse, path, entries);
+        path.truncate(len);
+    }
+}
+
+/// Walk through the node and collect the assets into a manifest, sorted by path. With `force_parse` the images
+/// will be parsed along the way, so the assets inside them are included too.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, build_manifest, write_manifest_csv};
+/// # use std::fs::File;
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let sound = base.read().unwrap().at("Sound").unwrap();
+///
+/// let manifest = build_manifest(&sound, true);
+/// write_manifest_csv(&manifest, File::create("manifest.csv").unwrap()).unwrap();
+/// ```
+pub fn build_manifest(node: &WzNodeArc, force_parse: bool) -> Vec<ManifestEntry> {
+    let mut path = node.read().unwrap().get_full_path();
+    let mut entries = Vec::new();
+    build_manifest_inner(node, force_parse, &mut path, &mut entries);
+    entries
+}
+
+/// Write the manifest as csv with the header `path,type,width,height,duration,size,hash`,
+/// the missing fields are empty.
+pub fn write_manifest_csv<W: Write>(
+    entries: &[ManifestEntry],
+    mut writer: W,
+) -> Result<(), std::io::Error> {
+    let optional = |value: Option<u32>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    writeln!(writer, "path,type,width,height,duration,size,hash")?;
+
+    for entry in entries {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            escape_csv_field(&entry.path),
+            entry.node_type,
+            optional(entry.width),
+            optional(entry.height),
+            optional(entry.duration),
+            entry.size,
+            entry.hash
+        )?;
+    }
+
+    writer.flush()
+}
+
+/// Write the manifest as a json array of `ManifestEntry`.
+#[cfg(feature = "json")]
+pub fn write_manifest_json<W: Write>(
+    entries: &[ManifestEntry],
+    writer: W,
+) -> Result<(), serde_json::Error> {
+    serde_json::to_writer(writer, entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{
+        property::{WzRawData, WzString, WzVideo},
+        WzNode,
+    };
+
+    #[test]
+    fn test_build_manifest() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        add_child("b", WzVideo::from_bytes(vec![1, 2, 3]), &root);
+        let folder = add_child("a", 1, &root);
+        add_child("data", WzRawData::from_bytes(vec![1, 2, 3]), &folder);
+        add_child("name", WzString::from_str("not asset", [0; 4]), &folder);
+        add_child("other", WzRawData::from_bytes(vec![3, 2, 1]), &folder);
+
+        let manifest = build_manifest(&root, false);
+
+        assert_eq!(
+            manifest.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+            vec!["root/a/data", "root/a/other", "root/b"]
+        );
+        assert_eq!(manifest[0].node_type, "RawData");
+        assert_eq!(manifest[2].node_type, "Video");
+        assert_eq!(manifest[0].size, 3);
+        assert_eq!(manifest[0].hash, content_hash([&[1_u8, 2, 3][..]]));
+        assert_eq!(manifest[0].hash, manifest[2].hash);
+        assert_ne!(manifest[0].hash, manifest[1].hash);
+
+        let mut csv = Vec::new();
+        write_manifest_csv(&manifest, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("path,type,width,height,duration,size,hash")
+        );
+        assert_eq!(
+            lines.next(),
+            Some(format!("root/a/data,RawData,,,,3,{}", manifest[0].hash).as_str())
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_canvas_manifest() {
+        use crate::property::WzPng;
+
+        let red = image::RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255])).into();
+        let png = WzPng::from_image(&red, 2).unwrap();
+        let size = png.get_raw_buffer().len();
+        let canvas = WzNode::from_str("canvas", png, None).into_lock();
+
+        let entry = manifest_entry(&canvas, "canvas").unwrap();
+        assert_eq!((entry.width, entry.height), (Some(4), Some(2)));
+        assert_eq!(entry.duration, None);
+        assert_eq!(entry.size, size);
+    }
+
+    #[test]
+    fn test_content_hash() {
+        /* the FNV-1a 64 test vectors */
+        assert_eq!(content_hash([&b""[..]]), "cbf29ce484222325");
+        assert_eq!(content_hash([&b"a"[..]]), "af63dc4c8601ec8c");
+        assert_eq!(content_hash([&b"fo"[..], &b"obar"[..]]), "85944171f73967e8");
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{node::Error, WzNodeArc, WzNodeCast, WzNodeGetter};
+
+use super::node_util;
+
+/// The kind of a map piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapPieceKind {
+    /// entry under `<layer>/tile`, refer to `Map/Tile/<tS>.img/<u>/<no>`.
+    Tile,
+    /// entry under `<layer>/obj`, refer to `Map/Obj/<oS>.img/<l0>/<l1>/<l2>`.
+    Obj,
+}
+
+/// A tile or obj entry in a map img, with the canvases it refer to.
+#[derive(Debug, Clone)]
+pub struct MapPiece {
+    pub kind: MapPieceKind,
+    /// the layer(`0` to `7`) of the entry.
+    pub layer: i32,
+    /// the tile or obj entry node itself, that has `x`, `y`, `z` etc.
+    pub entry: WzNodeArc,
+    /// the path of the target from the data root, like `Map/Tile/woodMarble.img/bsc/0`.
+    pub target_path: String,
+    /// tile has a single canvas, obj has the frames sorted by the index.
+    /// Empty when the target can't be resolved.
+    pub canvases: Vec<WzNodeArc>,
+}
+
+/// Read a child as string, some of the `l0`, `l1`, `l2` are stored as number.
+fn get_name_at(node: &WzNodeArc, path: &str) -> Option<String> {
+    node.get_str_at(path)
+        .or_else(|| node.get_long_at(path).map(|value| value.to_string()))
+}
+
+fn get_numeric_children(node: &WzNodeArc) -> Vec<(i32, WzNodeArc)> {
+    let mut children = node
+        .read()
+        .unwrap()
+        .children
+        .iter()
+        .filter_map(|(name, child)| Some((name.parse::<i32>().ok()?, child.clone())))
+        .collect::<Vec<_>>();
+
+    children.sort_by_key(|(index, _)| *index);
+    children
+}
+
+fn get_canvases(target: &WzNodeArc) -> Vec<WzNodeArc> {
+    if target.read().unwrap().try_as_png().is_some() {
+        return vec![target.clone()];
+    }
+
+    get_numeric_children(target)
+        .into_iter()
+        .map(|(_, frame)| frame)
+        .filter(|frame| frame.read().unwrap().try_as_png().is_some())
+        .collect()
+}
+
+/// Resolve every tile and obj entry of a map img to the canvases in the shared `Map/Tile` and `Map/Obj`.
+/// The map img will be parsed, and the tile/obj set images are parsed when needed,
+/// so the map img need to be under the `Base` like `resolve_outlink`.
+///
+/// Pieces are sorted by layer, then the entry index. Entry missing the needed properties will be skipped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, resolve_map_pieces};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let map = base.write().unwrap().at_path_parsed("Map/Map/Map1/100000000.img").unwrap();
+///
+/// for piece in resolve_map_pieces(&map).unwrap() {
+///     println!("{:?} {} -> {} frames", piece.kind, piece.target_path, piece.canvases.len());
+/// }
+/// ```
+pub fn resolve_map_pieces(map_img: &WzNodeArc) -> Result<Vec<MapPiece>, Error> {
+    node_util::parse_node(map_img)?;
+
+    let mut pieces = Vec::new();
+
+    for (layer_index, layer) in get_numeric_children(map_img) {
+        let tile_set = layer.get_str_at("info/tS");
+
+        let tiles = layer.read().unwrap().at("tile");
+        if let (Some(tiles), Some(tile_set)) = (tiles, &tile_set) {
+            for (_, entry) in get_numeric_children(&tiles) {
+                let (Some(u), Some(no)) = (get_name_at(&entry, "u"), get_name_at(&entry, "no"))
+                else {
+                    continue;
+                };
+                pieces.push((
+                    MapPieceKind::Tile,
+                    layer_index,
+                    entry,
+                    format!("Map/Tile/{tile_set}.img/{u}/{no}"),
+                ));
+            }
+        }
+
+        let objs = layer.read().unwrap().at("obj");
+        if let Some(objs) = objs {
+            for (_, entry) in get_numeric_children(&objs) {
+                let names = ["oS", "l0", "l1", "l2"].map(|name| get_name_at(&entry, name));
+                let [Some(obj_set), Some(l0), Some(l1), Some(l2)] = names else {
+                    continue;
+                };
+                pieces.push((
+                    MapPieceKind::Obj,
+                    layer_index,
+                    entry,
+                    format!("Map/Obj/{obj_set}.img/{l0}/{l1}/{l2}"),
+                ));
+            }
+        }
+    }
+
+    Ok(pieces
+        .into_iter()
+        .map(|(kind, layer, entry, target_path)| {
+            let canvases = node_util::resolve_outlink(&target_path, map_img, true)
+                .map(|target| get_canvases(&target))
+                .unwrap_or_default();
+
+            MapPiece {
+                kind,
+                layer,
+                entry,
+                target_path,
+                canvases,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{
+        property::{WzPng, WzString},
+        WzDirectory, WzFile, WzImage, WzNode,
+    };
+
+    fn add_string(name: &str, value: &str, parent: &WzNodeArc) {
+        add_child(name, WzString::from_str(value, [0; 4]), parent);
+    }
+
+    /// returns the Base and the map img, the Base need to be kept alive.
+    fn setup_node_tree() -> (WzNodeArc, WzNodeArc) {
+        let parsed_dir = || WzDirectory {
+            is_parsed: true,
+            ..Default::default()
+        };
+        let parsed_image = || WzImage {
+            is_parsed: true,
+            ..Default::default()
+        };
+
+        let root = WzNode::from_str(
+            "Base",
+            WzFile {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+        let map_dir = add_child("Map", parsed_dir(), &root);
+
+        let tile_img = add_child(
+            "bsc.img",
+            parsed_image(),
+            &add_child("Tile", parsed_dir(), &map_dir),
+        );
+        add_child("0", WzPng::default(), &add_child("bsc", 1, &tile_img));
+
+        let obj_img = add_child(
+            "house.img",
+            parsed_image(),
+            &add_child("Obj", parsed_dir(), &map_dir),
+        );
+        let frames = add_child("0", 1, &add_child("b", 1, &add_child("a", 1, &obj_img)));
+        add_child("1", WzPng::default(), &frames);
+        add_child("0", WzPng::default(), &frames);
+
+        let map_img = add_child(
+            "100000000.img",
+            parsed_image(),
+            &add_child("Map", parsed_dir(), &map_dir),
+        );
+
+        let layer = add_child("0", 1, &map_img);
+        add_string("tS", "bsc", &add_child("info", 1, &layer));
+
+        let tile = add_child("0", 1, &add_child("tile", 1, &layer));
+        add_string("u", "bsc", &tile);
+        add_child("no", 0, &tile);
+
+        let objs = add_child("obj", 1, &layer);
+        let obj = add_child("0", 1, &objs);
+        add_string("oS", "house", &obj);
+        add_string("l0", "a", &obj);
+        add_string("l1", "b", &obj);
+        add_child("l2", 0, &obj);
+
+        let broken_obj = add_child("1", 1, &objs);
+        add_string("oS", "nothing", &broken_obj);
+        add_string("l0", "a", &broken_obj);
+        add_string("l1", "b", &broken_obj);
+        add_string("l2", "0", &broken_obj);
+
+        /* missing l2 */
+        add_string("oS", "house", &add_child("2", 1, &objs));
+
+        (root, map_img)
+    }
+
+    #[test]
+    fn test_resolve_map_pieces() {
+        let (_root, map_img) = setup_node_tree();
+
+        let pieces = resolve_map_pieces(&map_img).unwrap();
+
+        assert_eq!(pieces.len(), 3);
+
+        assert_eq!(pieces[0].kind, MapPieceKind::Tile);
+        assert_eq!(pieces[0].target_path, "Map/Tile/bsc.img/bsc/0");
+        assert_eq!(pieces[0].canvases.len(), 1);
+
+        assert_eq!(pieces[1].kind, MapPieceKind::Obj);
+        assert_eq!(pieces[1].layer, 0);
+        assert_eq!(pieces[1].target_path, "Map/Obj/house.img/a/b/0");
+        let frame_names = pieces[1]
+            .canvases
+            .iter()
+            .map(|frame| frame.read().unwrap().name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(frame_names, vec!["0", "1"]);
+
+        assert!(pieces[2].canvases.is_empty());
+        assert_eq!(
+            pieces[2].entry.read().unwrap().get_full_path(),
+            "Base/Map/Map/100000000.img/0/obj/1"
+        );
+    }
+}
@@ -1,12 +1,74 @@
+pub mod base_set;
+#[cfg(feature = "tokio")]
+pub(crate) mod blocking;
+#[cfg(feature = "image")]
 pub mod color;
+#[cfg(feature = "image")]
+pub mod compose;
+pub mod diff;
+pub mod edit;
+pub mod export;
+pub mod file_pool;
+pub mod find;
+pub mod frozen;
+pub mod handle;
+#[cfg(feature = "image")]
+pub mod image_hash;
+pub mod link;
+#[cfg(feature = "serde")]
+pub mod lossless;
+pub mod manifest;
+pub mod map;
 pub mod maple_crypto_constants;
 pub mod node_util;
+pub mod parse_all;
+pub(crate) mod parse_arena;
 pub mod parse_property;
+pub mod path_cache;
+pub mod prefetch;
 pub(crate) mod resolver;
+pub mod search;
+pub mod stats;
+pub mod string_table;
+pub mod summary;
+#[cfg(test)]
+pub(crate) mod test_util;
+#[cfg(feature = "image")]
+pub mod transcode;
+pub mod tree;
 pub mod walk;
+pub mod watch;
 pub mod wz_mutable_key;
 
+pub use base_set::*;
+#[cfg(feature = "image")]
+pub use compose::*;
+pub use diff::*;
+pub use edit::*;
+pub use export::*;
+pub use file_pool::*;
+pub use find::*;
+pub use frozen::*;
+pub use handle::*;
+#[cfg(feature = "image")]
+pub use image_hash::*;
+pub use link::*;
+#[cfg(feature = "serde")]
+pub use lossless::*;
+pub use manifest::*;
+pub use map::*;
+pub use parse_all::*;
 pub use parse_property::*;
+pub use path_cache::*;
+pub use prefetch::*;
 pub use resolver::*;
+pub use search::*;
+pub use stats::*;
+pub use string_table::*;
+pub use summary::*;
+#[cfg(feature = "image")]
+pub use transcode::*;
+pub use tree::*;
 pub use walk::*;
+pub use watch::*;
 pub use wz_mutable_key::*;
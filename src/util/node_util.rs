@@ -1,5 +1,9 @@
-use crate::{node::Error, WzNode, WzNodeArc, WzNodeCast};
-use std::sync::Arc;
+use crate::{
+    node::Error,
+    property::{resolve_string_from_node, Vector2D, WzSubProperty},
+    WzNode, WzNodeArc, WzNodeCast, WzObjectType,
+};
+use std::sync::{Arc, Weak};
 
 #[inline]
 /// Just wrap around of `node.write().unwrap().parse(&node)`
@@ -7,6 +11,14 @@ pub fn parse_node(node: &WzNodeArc) -> Result<(), Error> {
     node.write().unwrap().parse(node)
 }
 
+/// Same as `parse_node`, but the parsing run on tokio's blocking pool,
+/// so parsing a huge image won't block the async runtime.
+#[cfg(feature = "tokio")]
+pub async fn parse_async(node: &WzNodeArc) -> Result<(), Error> {
+    let node = Arc::clone(node);
+    super::blocking::run_blocking(move || parse_node(&node)).await?
+}
+
 #[inline]
 /// Resolve a `_inlink` path, a `_inlink` path always start from a `WzImage`.
 pub fn resolve_inlink(path: &str, node: &WzNodeArc) -> Option<WzNodeArc> {
@@ -27,6 +39,26 @@ pub fn resolve_outlink(path: &str, node: &WzNodeArc, force_parse: bool) -> Optio
     }
 }
 
+/// Resolve the node's `_inlink` or `_outlink` child(usually a canvas), `_inlink` will be tried first.
+/// Returns `None` when the node has no link or the link target not found.
+pub fn resolve_link(node: &WzNodeArc, force_parse: bool) -> Option<WzNodeArc> {
+    let (inlink, outlink) = {
+        let node_read = node.read().unwrap();
+        let get_link = |name: &str| {
+            node_read
+                .at(name)
+                .and_then(|node| resolve_string_from_node(&node).ok())
+        };
+        (get_link("_inlink"), get_link("_outlink"))
+    };
+
+    if let Some(target) = inlink.and_then(|inlink| resolve_inlink(&inlink, node)) {
+        return Some(target);
+    }
+
+    outlink.and_then(|outlink| resolve_outlink(&outlink, node, force_parse))
+}
+
 #[inline]
 /// Make sure WzNode tree's all node has correct parent.
 pub fn resolve_childs_parent(node: &WzNodeArc) {
@@ -52,59 +84,196 @@ pub fn get_resolved_uol_path(path: &str, uol_path: &str) -> String {
     pathes.join("/")
 }
 
+/// Find the direct target of a uol node, it won't follow the uol chain.
+fn get_uol_target(node: &WzNodeArc, wz_image: &Option<&mut WzNode>) -> Option<WzNodeArc> {
+    let uol_target_path = node
+        .read()
+        .unwrap()
+        .try_as_uol()
+        .and_then(|s| s.get_string().ok())?;
+
+    let mut pathes = uol_target_path.split('/');
+
+    let first = if let Ok(node) = node.try_read() {
+        node.at_relative("..")
+    } else if let Some(image_node) = wz_image {
+        image_node.at(pathes.next().unwrap())
+    } else {
+        None
+    }?;
+
+    pathes.try_fold(first, |node, name| {
+        /* usually happen on parsing process, the WzImage is taking self a write lock
+        so just directly using wz_image here */
+        if let Ok(node) = node.try_read() {
+            return node.at_relative(name);
+        } else if let Some(image_node) = wz_image {
+            return image_node.at(name);
+        }
+        None
+    })
+}
+
+/// How the uol node should be handled while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UolPolicy {
+    /// replace the uol node with it's target, the original uol will be gone.
+    #[default]
+    ReplaceInPlace,
+    /// keep the uol node, and save the resolved target, get it by `WzNode::uol_target`.
+    KeepAndAnnotate,
+    /// keep the uol node untouched, resolve it when needed by `resolve_uol_target`.
+    LazyOnAccess,
+}
+
+/// Follow the uol chain until reach a non-uol node, returns `None` when the chain is circular or broken.
+fn follow_uol_chain(node: &WzNodeArc, wz_image: &Option<&mut WzNode>) -> Option<WzNodeArc> {
+    let mut visited = vec![Arc::as_ptr(node)];
+    let mut target = get_uol_target(node, wz_image)?;
+
+    loop {
+        let is_uol = target
+            .try_read()
+            .map(|target| target.try_as_uol().is_some())
+            .unwrap_or(false);
+
+        if !is_uol {
+            return Some(target);
+        }
+
+        if visited.contains(&Arc::as_ptr(&target)) {
+            return None;
+        }
+
+        visited.push(Arc::as_ptr(&target));
+        target = get_uol_target(&target, wz_image)?;
+    }
+}
+
 /// Make a uol node become valid node, second argument is optional,
 /// it prevent the parent is the WzImage while it currently parsing causing the deadlock.
+/// When the target is also a uol, it will follow the chain until reach a non-uol node,
+/// a circular chain will leave the uol untouched.
 pub fn resolve_uol(node: &WzNodeArc, wz_image: Option<&mut WzNode>) {
     let node_parent = node.read().unwrap().parent.upgrade().unwrap();
 
-    if let Some(ref mut uol_target_path) = node
-        .read()
-        .unwrap()
-        .try_as_uol()
-        .and_then(|s| s.get_string().ok())
-    {
-        let mut pathes = uol_target_path.split('/');
+    let uol_target = follow_uol_chain(node, &wz_image);
 
-        let first = if let Ok(node) = node.try_read() {
-            node.at_relative("..")
-        } else if let Some(image_node) = &wz_image {
-            image_node.at(pathes.next().unwrap())
-        } else {
-            None
-        };
+    if let Some(target_node) = uol_target {
+        let node_name = node.read().unwrap().name.clone();
 
-        let uol_target = if let Some(first) = first {
-            pathes.try_fold(first, |node, name| {
-                /* usually happen on parsing process, the WzImage is taking self a write lock
-                so just directly using wz_image here */
-                if let Ok(node) = node.try_read() {
-                    return node.at_relative(name);
-                } else if let Some(image_node) = &wz_image {
-                    return image_node.at(name);
-                }
-                None
-            })
-        } else {
-            None
-        };
+        /* when parent is locked, it means it's parent is WzImage, and it currently parsing */
+        if let Ok(mut parent) = node_parent.try_write() {
+            if let Some(origin) = parent.children.get_mut(&node_name) {
+                let _ = std::mem::replace(origin, target_node);
+            }
+        } else if let Some(wz_image) = wz_image {
+            if let Some(origin) = wz_image.children.get_mut(&node_name) {
+                let _ = std::mem::replace(origin, target_node);
+            }
+        }
+    }
+}
+
+/// Keep the uol node, but save the resolved target into `WzNode::uol_target`, the arguments is same as `resolve_uol`.
+pub fn annotate_uol(node: &WzNodeArc, wz_image: Option<&mut WzNode>) {
+    if let Some(target) = follow_uol_chain(node, &wz_image) {
+        node.write().unwrap().set_uol_target(&target);
+    }
+}
 
-        if let Some(target_node) = uol_target {
-            let node_name = node.read().unwrap().name.clone();
-
-            /* when parent is locked, it means it's parent is WzImage, and it currently parsing */
-            if let Ok(mut parent) = node_parent.try_write() {
-                if let Some(origin) = parent.children.get_mut(&node_name) {
-                    let _ = std::mem::replace(origin, target_node);
-                }
-            } else if let Some(wz_image) = wz_image {
-                if let Some(origin) = wz_image.children.get_mut(&node_name) {
-                    let _ = std::mem::replace(origin, target_node);
-                }
-            } else {
-                return;
+/// Get the target of a uol node without modify the tree, it will use the annotated `uol_target` first.
+/// Returns `None` if the node is not a uol or the target not found.
+pub fn resolve_uol_target(node: &WzNodeArc) -> Option<WzNodeArc> {
+    if let Some(target) = node.read().unwrap().uol_target() {
+        return Some(target);
+    }
+
+    node.read().unwrap().try_as_uol()?;
+
+    follow_uol_chain(node, &None)
+}
+
+/// Resolve every remaining uol node under the node, like those from deserialized json
+/// or image that parsed without the uol pass. Returns the number of resolved uol.
+pub fn resolve_all_uols(node: &WzNodeArc) -> usize {
+    let uol_nodes = std::cell::RefCell::new(Vec::new());
+
+    super::walk_node(node, false, &|node| {
+        if node.read().unwrap().try_as_uol().is_some() {
+            uol_nodes.borrow_mut().push(node.clone());
+        }
+    });
+
+    uol_nodes
+        .into_inner()
+        .into_iter()
+        .filter(|uol_node| {
+            let (name, parent) = {
+                let uol_read = uol_node.read().unwrap();
+                (uol_read.name.clone(), uol_read.parent.upgrade())
+            };
+            let Some(parent) = parent else {
+                return false;
+            };
+
+            resolve_uol(uol_node, None);
+
+            let current = parent.read().unwrap().children.get(&name).cloned();
+            current.is_some_and(|current| !Arc::ptr_eq(&current, uol_node))
+        })
+        .count()
+}
+
+/// Copy every `WzImage` and `MsImage` under the node(include itself) into owned buffers, so the parsed tree
+/// no longer reference the file, and the file can be replaced on disk. The parsed images will be parsed again
+/// from the new buffer, so the nodes under them are replaced by new ones. Returns the number of copied images.
+///
+/// The mmap is released after every node holding it(like the `WzFile` and `WzDirectory`) are dropped.
+///
+/// Note: since the parsed images are parsed again, any change made under them(edited values, added or removed nodes)
+/// is lost, and the old child nodes held by others are detached from the tree. The node doesn't track the changes
+/// so it can't refuse, copy the images before editing them.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::node_util};
+/// let node = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+/// let image = node.write().unwrap().at_path_parsed("100100.img").unwrap();
+/// node_util::parse_node(&image).unwrap();
+///
+/// node_util::copy_images_to_owned(&image).unwrap();
+/// drop(node);
+///
+/// // the Mob.wz is closed here, and the image still usable
+/// ```
+pub fn copy_images_to_owned(node: &WzNodeArc) -> Result<usize, Error> {
+    let mut node_write = node.write().unwrap();
+
+    let (image, is_parsed) = match &node_write.object_type {
+        WzObjectType::Image(image) => (image.to_owned_buffer(), image.is_parsed),
+        WzObjectType::MsImage(image) => (image.to_wz_image(), false),
+        _ => {
+            let children = node_write.children.values().cloned().collect::<Vec<_>>();
+            drop(node_write);
+
+            let mut count = 0;
+            for child in children {
+                count += copy_images_to_owned(&child)?;
             }
+            return Ok(count);
         }
+    };
+
+    node_write.object_type = image.into();
+
+    if is_parsed {
+        node_write.children.clear();
+        node_write.parse(node)?;
     }
+
+    Ok(1)
 }
 
 /// Get image node in the way, and return the rest of path.
@@ -158,11 +327,180 @@ pub fn get_node_without_parse(root: &WzNodeArc, path: &str) -> Option<WzNodeArc>
     }
 }
 
+/// Detach the node from it's parent, remove it from parent's children and clear the parent weak ref.
+/// Returns `false` if the node has no parent.
+pub fn detach(node: &WzNodeArc) -> bool {
+    let (parent, name) = {
+        let node_read = node.read().unwrap();
+        (node_read.parent.upgrade(), node_read.name.clone())
+    };
+
+    node.write().unwrap().parent = Weak::new();
+
+    let Some(parent) = parent else {
+        return false;
+    };
+
+    let mut parent_write = parent.write().unwrap();
+
+    /* only remove it when the child with same name is actually this node */
+    if parent_write
+        .children
+        .get(&name)
+        .is_some_and(|child| Arc::ptr_eq(child, node))
+    {
+        parent_write.children.remove(&name);
+    }
+
+    true
+}
+
+/// Move the node under `parent`, it will be detached from the original parent first.
+/// If the `parent` already has a child with the same name, it will be replaced and returned(detached).
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::node_util::attach_to;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let other = WzNode::from_str("other", 1, None).into_lock();
+/// let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+/// root.write().unwrap().add(&child);
+///
+/// attach_to(&child, &other).unwrap();
+///
+/// assert!(root.read().unwrap().at("child").is_none());
+/// assert!(other.read().unwrap().at("child").is_some());
+/// assert_eq!(child.read().unwrap().get_full_path(), "other/child");
+/// ```
+pub fn attach_to(node: &WzNodeArc, parent: &WzNodeArc) -> Result<Option<WzNodeArc>, Error> {
+    let mut ancestor = Some(Arc::clone(parent));
+    while let Some(current) = ancestor {
+        if Arc::ptr_eq(&current, node) {
+            return Err(Error::CircularAttach);
+        }
+        ancestor = current.read().unwrap().parent.upgrade();
+    }
+
+    detach(node);
+
+    let name = node.read().unwrap().name.clone();
+    node.write().unwrap().parent = Arc::downgrade(parent);
+
+    let replaced = parent
+        .write()
+        .unwrap()
+        .children
+        .insert(name, Arc::clone(node));
+
+    if let Some(replaced) = &replaced {
+        replaced.write().unwrap().parent = Weak::new();
+    }
+
+    Ok(replaced)
+}
+
+/// Rename the node, and also update the key in the parent's children.
+/// If a sibling already has the new name, it will be replaced and returned(detached) like `attach_to`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::node_util::rename;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+/// root.write().unwrap().add(&child);
+///
+/// rename(&child, "renamed");
+///
+/// assert!(root.read().unwrap().at("child").is_none());
+/// assert!(root.read().unwrap().at("renamed").is_some());
+/// ```
+pub fn rename(node: &WzNodeArc, new_name: &str) -> Option<WzNodeArc> {
+    let parent = node.read().unwrap().parent.upgrade();
+
+    let Some(parent) = parent else {
+        node.write().unwrap().name = new_name.into();
+        return None;
+    };
+
+    detach(node);
+    node.write().unwrap().name = new_name.into();
+
+    /* the node was a child of parent, so it can't be circular */
+    attach_to(node, &parent).unwrap_or(None)
+}
+
+/// Create a node with vectors as children named by index, like `0`, `1`, `2`...
+fn new_indexed_vectors<V: Into<Vector2D>>(
+    name: &str,
+    object_type: WzObjectType,
+    points: impl IntoIterator<Item = V>,
+    parent: Option<&WzNodeArc>,
+) -> WzNodeArc {
+    let node = WzNode::from_str(name, object_type, parent).into_lock();
+
+    {
+        let mut node_write = node.write().unwrap();
+        for (index, point) in points.into_iter().enumerate() {
+            let child = WzNode::from_str(&index.to_string(), point.into(), Some(&node)).into_lock();
+            node_write.add(&child);
+        }
+    }
+
+    node
+}
+
+/// Create a `Shape2D#Convex2D` node with the points as children, it won't be added to the parent.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, WzNodeCast, WzNodeGetter};
+/// # use wz_reader::property::Vector2D;
+/// # use wz_reader::util::node_util::new_convex;
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let convex = new_convex("foothold", [(0, 0), (10, 0), (10, 10)], Some(&root));
+/// root.write().unwrap().add(&convex);
+///
+/// assert!(convex.read().unwrap().is_convex());
+/// assert_eq!(root.get_vec2_at("foothold/2"), Some(Vector2D(10, 10)));
+/// ```
+pub fn new_convex<V: Into<Vector2D>>(
+    name: &str,
+    points: impl IntoIterator<Item = V>,
+    parent: Option<&WzNodeArc>,
+) -> WzNodeArc {
+    new_indexed_vectors(
+        name,
+        WzObjectType::Property(WzSubProperty::Convex),
+        points,
+        parent,
+    )
+}
+
+/// Create a normal property node with the points as children like `new_convex`,
+/// for the vector list in property like `lt`, `rb` pairs or a path.
+pub fn new_vector_list<V: Into<Vector2D>>(
+    name: &str,
+    points: impl IntoIterator<Item = V>,
+    parent: Option<&WzNodeArc>,
+) -> WzNodeArc {
+    new_indexed_vectors(
+        name,
+        WzObjectType::Property(WzSubProperty::Property),
+        points,
+        parent,
+    )
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{
-        property::{resolve_string_from_node, WzString, WzValue},
+        property::{WzString, WzValue},
         WzDirectory, WzFile, WzImage, WzNode, WzObjectType,
     };
 
@@ -171,22 +509,28 @@ mod test {
         let dir = WzNode::from_str("dir", WzDirectory::default(), Some(&root)).into_lock();
 
         let img1 = {
-            let mut img = WzImage::default();
-            img.is_parsed = true;
+            let img = WzImage {
+                is_parsed: true,
+                ..Default::default()
+            };
             WzNode::from_str("test1.img", img, Some(&dir))
         }
         .into_lock();
 
         let img2 = {
-            let mut img = WzImage::default();
-            img.is_parsed = true;
+            let img = WzImage {
+                is_parsed: true,
+                ..Default::default()
+            };
             WzNode::from_str("test2.img", img, Some(&dir))
         }
         .into_lock();
 
         let img3 = {
-            let mut img = WzImage::default();
-            img.is_parsed = true;
+            let img = WzImage {
+                is_parsed: true,
+                ..Default::default()
+            };
             WzNode::from_str("test3.img", img, Some(&dir))
         }
         .into_lock();
@@ -289,6 +633,30 @@ mod test {
         assert_eq!(outlink_target.read().unwrap().name.as_str(), "child2");
     }
 
+    #[test]
+    fn test_resolve_link() {
+        let root = setup_node_tree();
+
+        let node = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/2-dep1/2-dep2")
+            .unwrap();
+
+        let target = resolve_link(&node, false);
+
+        assert!(target.is_some());
+        assert_eq!(target.unwrap().read().unwrap().name.as_str(), "1-dep2");
+
+        let node = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/1-dep1")
+            .unwrap();
+
+        assert!(resolve_link(&node, false).is_none());
+    }
+
     #[test]
     fn test_resolve_childs_parent() {
         let root = setup_node_tree();
@@ -371,4 +739,167 @@ mod test {
             "Base/dir/test1.img/1-dep1/1-dep2"
         );
     }
+
+    #[test]
+    fn test_resolve_uol_chain() {
+        let root = setup_node_tree();
+
+        let parent = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/2-dep1/2-dep2")
+            .unwrap();
+
+        let add_uol = |name: &str, path: &str| {
+            let uol = WzNode::from_str(
+                name,
+                WzObjectType::Value(WzValue::UOL(WzString::from_str(path, [0, 0, 0, 0]))),
+                Some(&parent),
+            )
+            .into_lock();
+            parent.write().unwrap().add(&uol);
+            uol
+        };
+
+        let chain = add_uol("chain", "uol");
+        let cycle1 = add_uol("cycle1", "cycle2");
+        add_uol("cycle2", "cycle1");
+
+        resolve_uol(&chain, None);
+
+        let resolved = parent.read().unwrap().at("chain").unwrap();
+        assert_eq!(
+            resolved.read().unwrap().get_full_path(),
+            "Base/dir/test1.img/1-dep1/1-dep2"
+        );
+
+        resolve_uol(&cycle1, None);
+
+        let unresolved = parent.read().unwrap().at("cycle1").unwrap();
+        assert!(unresolved.read().unwrap().try_as_uol().is_some());
+    }
+
+    #[test]
+    fn test_annotate_uol() {
+        let root = setup_node_tree();
+
+        let uol_node = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/2-dep1/2-dep2/uol")
+            .unwrap();
+
+        assert_eq!(
+            resolve_uol_target(&uol_node)
+                .unwrap()
+                .read()
+                .unwrap()
+                .name
+                .as_str(),
+            "1-dep2"
+        );
+        assert!(uol_node.read().unwrap().uol_target().is_none());
+
+        annotate_uol(&uol_node, None);
+
+        let uol_node = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/2-dep1/2-dep2/uol")
+            .unwrap();
+
+        assert!(uol_node.read().unwrap().try_as_uol().is_some());
+        assert!(uol_node.read().unwrap().uol_target().is_some());
+    }
+
+    #[test]
+    fn test_resolve_all_uols() {
+        let root = setup_node_tree();
+
+        assert_eq!(resolve_all_uols(&root), 1);
+
+        let uol_node = root
+            .read()
+            .unwrap()
+            .at_path("dir/test1.img/2-dep1/2-dep2/uol")
+            .unwrap();
+
+        assert_eq!(uol_node.read().unwrap().name.as_str(), "1-dep2");
+        assert_eq!(resolve_all_uols(&root), 0);
+    }
+
+    #[test]
+    fn test_detach_and_attach() {
+        let root = setup_node_tree();
+        let img1 = root.read().unwrap().at_path("dir/test1.img").unwrap();
+        let img2 = root.read().unwrap().at_path("dir/test2.img").unwrap();
+        let node = img1.read().unwrap().at("1-dep1").unwrap();
+
+        assert!(detach(&node));
+        assert!(!detach(&node));
+        assert!(img1.read().unwrap().at("1-dep1").is_none());
+
+        assert!(attach_to(&node, &img2).unwrap().is_none());
+        assert_eq!(
+            node.read().unwrap().get_full_path(),
+            "Base/dir/test2.img/1-dep1"
+        );
+
+        /* replace the existing one with same name */
+        let same_name = WzNode::from_str("1-dep1", 1, None).into_lock();
+        let replaced = attach_to(&same_name, &img2).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&replaced, &node));
+        assert!(node.read().unwrap().parent.upgrade().is_none());
+
+        /* can't attach to it's descendant */
+        let img2_child = img2.read().unwrap().at("1-dep1").unwrap();
+        assert!(matches!(
+            attach_to(&img2, &img2_child),
+            Err(Error::CircularAttach)
+        ));
+    }
+
+    #[test]
+    fn test_rename() {
+        let root = setup_node_tree();
+        let img1 = root.read().unwrap().at_path("dir/test1.img").unwrap();
+        let node = img1.read().unwrap().at("1-dep1").unwrap();
+
+        assert!(rename(&node, "renamed").is_none());
+        assert!(img1.read().unwrap().at("1-dep1").is_none());
+        assert!(Arc::ptr_eq(
+            &img1.read().unwrap().at("renamed").unwrap(),
+            &node
+        ));
+
+        let sibling = img1.read().unwrap().at("2-dep1").unwrap();
+        let replaced = rename(&node, "2-dep1").unwrap();
+
+        assert!(Arc::ptr_eq(&replaced, &sibling));
+        assert!(img1.read().unwrap().at("renamed").is_none());
+        assert_eq!(node.read().unwrap().name.as_str(), "2-dep1");
+    }
+
+    #[test]
+    fn test_new_vector_list() {
+        let root = setup_node_tree();
+        let points = vec![Vector2D(-1, 2), Vector2D(3, 4)];
+        let list = new_vector_list("lt", points.clone(), Some(&root));
+
+        let list = list.read().unwrap();
+
+        assert!(matches!(
+            list.object_type,
+            WzObjectType::Property(WzSubProperty::Property)
+        ));
+        assert_eq!(list.children.len(), 2);
+        for (index, point) in points.iter().enumerate() {
+            let child = list.at(&index.to_string()).unwrap();
+            assert_eq!(child.read().unwrap().try_as_vector2d(), Some(point));
+            assert_eq!(
+                child.read().unwrap().get_full_path(),
+                format!("Base/lt/{index}")
+            );
+        }
+    }
 }
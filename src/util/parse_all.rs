@@ -0,0 +1,190 @@
+use crate::{node::Error, WzNodeArc, WzObjectType};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::node_util::UolPolicy;
+
+/// A node that failed to parse, with it's full path.
+pub type ParseAllError = (String, Error);
+
+/// Parse every `WzFile`/`WzDirectory` under the node and collect the `WzImage` need to parse.
+pub(crate) fn collect_images(
+    node: &WzNodeArc,
+    images: &mut Vec<WzNodeArc>,
+    errors: &mut Vec<ParseAllError>,
+) {
+    let is_image = matches!(
+        node.read().unwrap().object_type,
+        WzObjectType::Image(_) | WzObjectType::MsImage(_)
+    );
+
+    if is_image {
+        images.push(node.clone());
+        return;
+    }
+
+    let result = node.write().unwrap().parse(node);
+
+    if let Err(e) = result {
+        errors.push((node.read().unwrap().get_full_path(), e));
+        return;
+    }
+
+    for child in node.read().unwrap().children.values() {
+        collect_images(child, images, errors);
+    }
+}
+
+/// Fully parse the whole subtree of the node, `WzImage` will be parsed concurrently when `rayon` feature is enabled.
+/// It won't stop at the first error, all failed node will be returned with it's full path.
+pub fn parse_all(node: &WzNodeArc) -> Vec<ParseAllError> {
+    parse_all_with_progress(node, &|_, _| {})
+}
+
+/// Same as `parse_all`, but calling `progress(parsed, total)` every time a `WzImage` is parsed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::parse_all_with_progress};
+/// let node = WzNode::from_wz_file("path/to/Base.wz", None).unwrap().into_lock();
+///
+/// let errors = parse_all_with_progress(&node, &|parsed, total| {
+///     println!("{parsed}/{total}");
+/// });
+///
+/// for (path, error) in errors {
+///     println!("failed to parse {path}: {error}");
+/// }
+/// ```
+pub fn parse_all_with_progress(
+    node: &WzNodeArc,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Vec<ParseAllError> {
+    parse_all_inner(node, UolPolicy::default(), progress)
+}
+
+/// Same as `parse_all`, but the `WzImage` are parsed with the `uol_policy`, see `WzNode::parse_with_uol_policy`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::{parse_all_with_uol_policy, node_util::UolPolicy}};
+/// let node = WzNode::from_wz_file("path/to/Character.wz", None).unwrap().into_lock();
+///
+/// let errors = parse_all_with_uol_policy(&node, UolPolicy::KeepAndAnnotate);
+/// ```
+pub fn parse_all_with_uol_policy(node: &WzNodeArc, uol_policy: UolPolicy) -> Vec<ParseAllError> {
+    parse_all_inner(node, uol_policy, &|_, _| {})
+}
+
+fn parse_all_inner(
+    node: &WzNodeArc,
+    uol_policy: UolPolicy,
+    progress: &(dyn Fn(usize, usize) + Sync),
+) -> Vec<ParseAllError> {
+    let mut images = Vec::new();
+    let mut errors = Vec::new();
+
+    collect_images(node, &mut images, &mut errors);
+
+    let total = images.len();
+    let parsed = AtomicUsize::new(0);
+
+    let parse_image = |node: &WzNodeArc| {
+        let result = node
+            .write()
+            .unwrap()
+            .parse_with_uol_policy(node, uol_policy);
+        let error = result
+            .err()
+            .map(|e| (node.read().unwrap().get_full_path(), e));
+
+        progress(parsed.fetch_add(1, Ordering::Relaxed) + 1, total);
+
+        error
+    };
+
+    #[cfg(feature = "rayon")]
+    let image_errors = images
+        .par_iter()
+        .filter_map(parse_image)
+        .collect::<Vec<_>>();
+
+    #[cfg(not(feature = "rayon"))]
+    let image_errors = images.iter().filter_map(parse_image).collect::<Vec<_>>();
+
+    errors.extend(image_errors);
+
+    errors
+}
+
+/// The checksum of a `WzImage`, see `verify_checksums`.
+#[derive(Debug, Clone)]
+pub struct ImageChecksum {
+    /// full path of the image node.
+    pub path: String,
+    /// the checksum stored in the wz file.
+    pub expected: Option<i32>,
+    pub actual: i32,
+}
+
+impl ImageChecksum {
+    /// Whether the computed checksum is same as the stored one, always `true` when nothing stored.
+    pub fn is_valid(&self) -> bool {
+        self.expected.unwrap_or(self.actual) == self.actual
+    }
+}
+
+/// Compute the checksum of every `WzImage` under the node and compare with the one stored in wz file,
+/// useful to detect corrupted download or bad patch. Only `WzFile`/`WzDirectory` will be parsed, the images are not.
+/// Checksums are computed concurrently when `rayon` feature is enabled.
+///
+/// Returns the checksums of all images, and the nodes failed to parse.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::verify_checksums};
+/// let node = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+///
+/// let (checksums, _) = verify_checksums(&node);
+///
+/// for checksum in checksums.iter().filter(|checksum| !checksum.is_valid()) {
+///     println!("{} is corrupted", checksum.path);
+/// }
+/// ```
+pub fn verify_checksums(node: &WzNodeArc) -> (Vec<ImageChecksum>, Vec<ParseAllError>) {
+    let mut images = Vec::new();
+    let mut errors = Vec::new();
+
+    collect_images(node, &mut images, &mut errors);
+
+    let compute_checksum = |node: &WzNodeArc| {
+        let node_read = node.read().unwrap();
+        match &node_read.object_type {
+            WzObjectType::Image(image) => Some(ImageChecksum {
+                path: node_read.get_full_path(),
+                expected: image.checksum,
+                actual: image.compute_checksum(),
+            }),
+            _ => None,
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    let checksums = images
+        .par_iter()
+        .filter_map(compute_checksum)
+        .collect::<Vec<_>>();
+
+    #[cfg(not(feature = "rayon"))]
+    let checksums = images
+        .iter()
+        .filter_map(compute_checksum)
+        .collect::<Vec<_>>();
+
+    (checksums, errors)
+}
@@ -0,0 +1,123 @@
+//! The arena of the short-lived strings while parsing a image, like the extended property types. With `bumpalo`
+//! feature they are bump allocated and freed at once after the image parsed, otherwise they are allocated as usual.
+//! The children lists are moved into the nodes, so they are not allocated in the arena.
+
+#[cfg(feature = "bumpalo")]
+mod imp {
+    use crate::property::WzStringType;
+    use crate::reader::{with_resolved_ascii, Error, Reader};
+    use crate::WzSliceReader;
+    use std::cell::RefCell;
+
+    pub(crate) type ParseArena = bumpalo::Bump;
+    pub(crate) type ArenaStr<'a> = bumpalo::collections::String<'a>;
+
+    /// The arena bigger than this will be dropped instead of reused, so a huge image won't keep the memory.
+    const MAX_RETAINED_BYTES: usize = 4 * 1024 * 1024;
+
+    thread_local! {
+        static PARSE_ARENA: RefCell<ParseArena> = RefCell::new(ParseArena::new());
+    }
+
+    /// Run `f` with a thread-local arena, it's reset after `f` returned.
+    /// The nested call will get a new arena instead of the shared one.
+    pub(crate) fn with_parse_arena<T>(f: impl FnOnce(&ParseArena) -> T) -> T {
+        PARSE_ARENA.with(|arena| {
+            let Ok(mut arena) = arena.try_borrow_mut() else {
+                return f(&ParseArena::new());
+            };
+
+            let result = f(&arena);
+
+            if arena.allocated_bytes() > MAX_RETAINED_BYTES {
+                *arena = ParseArena::new();
+            } else {
+                arena.reset();
+            }
+
+            result
+        })
+    }
+
+    /// Same as `read_wz_string_block`, but the string is allocated in the arena.
+    pub(crate) fn read_wz_string_block_in<'a>(
+        reader: &WzSliceReader,
+        offset: usize,
+        arena: &'a ParseArena,
+    ) -> Result<ArenaStr<'a>, Error> {
+        let meta = reader.read_wz_string_block_meta(offset)?;
+
+        match meta.string_type {
+            WzStringType::Ascii => {
+                with_resolved_ascii(reader, meta.offset, meta.length as usize, |bytes| {
+                    ArenaStr::from_str_in(&String::from_utf8_lossy(bytes), arena)
+                })
+            }
+            _ => {
+                let string = reader.resolve_wz_string_meta(
+                    &meta.string_type,
+                    meta.offset,
+                    meta.length as usize,
+                )?;
+                Ok(ArenaStr::from_str_in(&string, arena))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "bumpalo"))]
+mod imp {
+    use crate::reader::Error;
+    use crate::WzSliceReader;
+
+    /// Nothing without `bumpalo` feature.
+    pub(crate) struct ParseArena;
+    pub(crate) type ArenaStr<'a> = String;
+
+    #[inline]
+    pub(crate) fn with_parse_arena<T>(f: impl FnOnce(&ParseArena) -> T) -> T {
+        f(&ParseArena)
+    }
+
+    #[inline]
+    pub(crate) fn read_wz_string_block_in<'a>(
+        reader: &WzSliceReader,
+        offset: usize,
+        _arena: &'a ParseArena,
+    ) -> Result<ArenaStr<'a>, Error> {
+        reader.read_wz_string_block(offset)
+    }
+}
+
+pub(crate) use imp::*;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WzReader;
+
+    #[test]
+    fn test_read_wz_string_block_in() {
+        /* the ascii `Canvas` with zero iv, then a offset block points to the string */
+        let mut buf = vec![0x73, (-6_i8) as u8];
+        buf.extend(
+            b"Canvas"
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b ^ (0xAA + i as u8)),
+        );
+        buf.push(0x1B);
+        buf.extend_from_slice(&1_i32.to_le_bytes());
+
+        let reader = WzReader::from_buff(&buf);
+        let slice_reader = reader.create_slice_reader_without_hash();
+
+        with_parse_arena(|arena| {
+            let inline = read_wz_string_block_in(&slice_reader, 0, arena).unwrap();
+            let offset = read_wz_string_block_in(&slice_reader, 0, arena).unwrap();
+            assert_eq!(&*inline, "Canvas");
+            assert_eq!(&*offset, "Canvas");
+        });
+        assert_eq!(slice_reader.pos.get(), buf.len());
+    }
+}
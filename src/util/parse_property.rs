@@ -2,12 +2,16 @@ use crate::property::{
     get_sound_type_from_header, Vector2D, WzPng, WzRawData, WzSound, WzString, WzSubProperty,
     WzValue, WzVideo,
 };
+use crate::sync::RwLock;
 use crate::{
     reader, WzNode, WzNodeArc, WzNodeArcVec, WzNodeName, WzObjectType, WzReader, WzSliceReader,
 };
-use std::sync::Arc;
+use hashbrown::HashMap;
+use std::sync::{Arc, Weak};
 use thiserror::Error;
 
+use super::parse_arena::{read_wz_string_block_in, with_parse_arena, ParseArena};
+
 #[derive(Debug, Error)]
 pub enum WzPropertyParseError {
     #[error("Node not found")]
@@ -22,32 +26,187 @@ pub enum WzPropertyParseError {
     #[error("Unknown extended property type: {0}, name: {1}, at position: {2}")]
     UnknownExtendedPropertyType(String, WzNodeName, usize),
 
-    #[error("Binary reading error")]
+    #[error("Binary reading error: {0}")]
     ReaderError(#[from] reader::Error),
 }
 
+/// A `Property` skipped by `WzImage::parse_to_depth`, the node is created without children,
+/// use `expand` to parse them later.
+#[derive(Debug, Clone)]
+pub struct WzDeferredProperty {
+    pub node: Weak<RwLock<WzNode>>,
+    pub reader: Arc<WzReader>,
+    /// the offset of the image, the strings in the block are relative to it.
+    pub origin_offset: usize,
+    /// the offset of the property list.
+    pub offset: usize,
+}
+
+impl WzDeferredProperty {
+    /// Parse the children of the node, the `Property` deeper than `max_depth`(relative to the node) are deferred again.
+    /// The uol nodes are kept, resolve them after expanded, like `resolve_uol_target`.
+    ///
+    /// # Errors
+    /// `NodeNotFound` when the node is dropped.
+    pub fn expand(
+        &self,
+        max_depth: usize,
+    ) -> Result<Vec<WzDeferredProperty>, WzPropertyParseError> {
+        let node = self
+            .node
+            .upgrade()
+            .ok_or(WzPropertyParseError::NodeNotFound)?;
+
+        let reader = self.reader.create_slice_reader_without_hash();
+        reader.try_seek(self.offset)?;
+
+        with_parse_arena(|arena| {
+            let mut ctx = ParseContext::new(max_depth, arena);
+            let (childs, _) = parse_property_list_inner(
+                Some(&node),
+                &self.reader,
+                &reader,
+                self.origin_offset,
+                &mut ctx,
+            )?;
+
+            let mut node_write = node.write().unwrap();
+            node_write.children.reserve(childs.len());
+            for (name, child) in childs {
+                node_write.children.insert(name, child);
+            }
+
+            Ok(ctx.deferred)
+        })
+    }
+}
+
+/// The type bytes collected by `read_property_types`, with the path of the property currently parsing.
+#[derive(Default)]
+struct PropertyTypeRecord {
+    path: String,
+    types: HashMap<String, u8>,
+}
+
+/// The state while parsing a property list. Track the level of the list, the `Property` blocks deeper than
+/// `max_depth` are skipped and recorded in `deferred`. The temporary strings are allocated in the `arena`.
+struct ParseContext<'a> {
+    level: usize,
+    max_depth: usize,
+    deferred: Vec<WzDeferredProperty>,
+    arena: &'a ParseArena,
+    property_types: Option<PropertyTypeRecord>,
+}
+
+impl<'a> ParseContext<'a> {
+    fn new(max_depth: usize, arena: &'a ParseArena) -> Self {
+        Self {
+            level: 1,
+            max_depth: max_depth.max(1),
+            deferred: Vec::new(),
+            arena,
+            property_types: None,
+        }
+    }
+    fn unlimited(arena: &'a ParseArena) -> Self {
+        Self::new(usize::MAX, arena)
+    }
+    /// Push the name to the path and record the type byte when recording, returns the length of the path to restore.
+    fn enter_property(&mut self, name: &str, property_type: u8) -> usize {
+        let Some(record) = &mut self.property_types else {
+            return 0;
+        };
+        let len = record.path.len();
+        if len > 0 {
+            record.path.push('/');
+        }
+        record.path.push_str(name);
+        record.types.insert(record.path.clone(), property_type);
+        len
+    }
+    fn leave_property(&mut self, len: usize) {
+        if let Some(record) = &mut self.property_types {
+            record.path.truncate(len);
+        }
+    }
+}
+
 pub fn parse_property_list(
     parent: Option<&WzNodeArc>,
     org_reader: &Arc<WzReader>,
     reader: &WzSliceReader,
     origin_offset: usize,
+) -> Result<(WzNodeArcVec, Vec<WzNodeArc>), WzPropertyParseError> {
+    with_parse_arena(|arena| {
+        parse_property_list_inner(
+            parent,
+            org_reader,
+            reader,
+            origin_offset,
+            &mut ParseContext::unlimited(arena),
+        )
+    })
+}
+
+/// Parse the property list only to collect the type byte of every property, keyed by the path relative to the list
+/// like `info/speed`. The parsed nodes don't keep the byte(`11` and `2` are both a `Short`), see `WzNode::to_debug_json`.
+#[cfg(feature = "json")]
+pub(crate) fn read_property_types(
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    origin_offset: usize,
+) -> Result<HashMap<String, u8>, WzPropertyParseError> {
+    with_parse_arena(|arena| {
+        let mut ctx = ParseContext::unlimited(arena);
+        ctx.property_types = Some(PropertyTypeRecord::default());
+        parse_property_list_inner(None, org_reader, reader, origin_offset, &mut ctx)?;
+        Ok(ctx.property_types.take().unwrap_or_default().types)
+    })
+}
+
+/// Same as `parse_property_list`, but the `Property` blocks deeper than `max_depth` are not parsed,
+/// see `WzDeferredProperty`. `max_depth` 1 means only the properties in the list itself.
+pub fn parse_property_list_to_depth(
+    parent: Option<&WzNodeArc>,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    origin_offset: usize,
+    max_depth: usize,
+) -> Result<(WzNodeArcVec, Vec<WzNodeArc>, Vec<WzDeferredProperty>), WzPropertyParseError> {
+    with_parse_arena(|arena| {
+        let mut ctx = ParseContext::new(max_depth, arena);
+        let (childs, uol_nodes) =
+            parse_property_list_inner(parent, org_reader, reader, origin_offset, &mut ctx)?;
+        Ok((childs, uol_nodes, ctx.deferred))
+    })
+}
+
+fn parse_property_list_inner<'a>(
+    parent: Option<&WzNodeArc>,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    origin_offset: usize,
+    ctx: &mut ParseContext<'a>,
 ) -> Result<(WzNodeArcVec, Vec<WzNodeArc>), WzPropertyParseError> {
     let entry_count = reader.read_wz_int()?;
 
-    let mut childs: WzNodeArcVec = Vec::with_capacity(entry_count as usize);
-    let mut uol_nodes: Vec<WzNodeArc> = Vec::new();
+    let mut childs = Vec::with_capacity(entry_count.max(0) as usize);
+    let mut uol_nodes = Vec::new();
 
     for _ in 0..entry_count {
-        let name: WzNodeName = reader.read_wz_string_block(origin_offset)?.into();
+        let name: WzNodeName = reader.read_wz_name_block(origin_offset)?;
         let property_type = reader.read_u8()?;
-        let parsed_node = parse_property_node(
+        let path_len = ctx.enter_property(&name, property_type);
+        let parsed_node = parse_property_node_inner(
             name,
             property_type,
             parent,
             org_reader,
             reader,
             origin_offset,
+            ctx,
         )?;
+        ctx.leave_property(path_len);
 
         if let Some(uol_node) = parsed_node.2 {
             uol_nodes.extend(uol_node);
@@ -67,6 +226,30 @@ pub fn parse_property_node(
     reader: &WzSliceReader,
     origin_offset: usize,
 ) -> Result<(WzNodeName, WzNodeArc, Option<Vec<WzNodeArc>>), WzPropertyParseError> {
+    with_parse_arena(|arena| {
+        parse_property_node_inner(
+            name,
+            property_type,
+            parent,
+            org_reader,
+            reader,
+            origin_offset,
+            &mut ParseContext::unlimited(arena),
+        )
+    })
+}
+
+type ParsedNode = (WzNodeName, WzNodeArc, Option<Vec<WzNodeArc>>);
+
+fn parse_property_node_inner<'a>(
+    name: WzNodeName,
+    property_type: u8,
+    parent: Option<&WzNodeArc>,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    origin_offset: usize,
+    ctx: &mut ParseContext<'a>,
+) -> Result<ParsedNode, WzPropertyParseError> {
     let result: (WzNodeName, WzNodeArc);
 
     match property_type {
@@ -117,8 +300,15 @@ pub fn parse_property_node(
             let block_size = reader.read_u32()?;
             let next_pos = reader.pos.get() + block_size as usize;
 
-            let parse_result =
-                parse_extended_prop(parent, org_reader, reader, next_pos, origin_offset, name);
+            let parse_result = parse_extended_prop_inner(
+                parent,
+                org_reader,
+                reader,
+                next_pos,
+                origin_offset,
+                name,
+                ctx,
+            );
 
             let node = if let Ok(node) = parse_result {
                 node
@@ -140,7 +330,7 @@ pub fn parse_property_node(
                 return parse_result;
             };
 
-            reader.seek(next_pos);
+            reader.try_seek(next_pos)?;
 
             return Ok(node);
         }
@@ -164,9 +354,31 @@ pub fn parse_extended_prop(
     origin_offset: usize,
     property_name: WzNodeName,
 ) -> Result<(WzNodeName, WzNodeArc, Option<Vec<WzNodeArc>>), WzPropertyParseError> {
-    let extend_property_type = reader.read_wz_string_block(origin_offset)?;
+    with_parse_arena(|arena| {
+        parse_extended_prop_inner(
+            parent,
+            org_reader,
+            reader,
+            end_of_block,
+            origin_offset,
+            property_name,
+            &mut ParseContext::unlimited(arena),
+        )
+    })
+}
 
-    parse_more(
+fn parse_extended_prop_inner<'a>(
+    parent: Option<&WzNodeArc>,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    end_of_block: usize,
+    origin_offset: usize,
+    property_name: WzNodeName,
+    ctx: &mut ParseContext<'a>,
+) -> Result<ParsedNode, WzPropertyParseError> {
+    let extend_property_type = read_wz_string_block_in(reader, origin_offset, ctx.arena)?;
+
+    parse_more_inner(
         parent,
         org_reader,
         reader,
@@ -174,6 +386,7 @@ pub fn parse_extended_prop(
         origin_offset,
         property_name,
         &extend_property_type,
+        ctx,
     )
 }
 
@@ -186,6 +399,45 @@ pub fn parse_more(
     property_name: WzNodeName,
     extend_property_type: &str,
 ) -> Result<(WzNodeName, WzNodeArc, Option<Vec<WzNodeArc>>), WzPropertyParseError> {
+    with_parse_arena(|arena| {
+        parse_more_inner(
+            parent,
+            org_reader,
+            reader,
+            end_of_block,
+            origin_offset,
+            property_name,
+            extend_property_type,
+            &mut ParseContext::unlimited(arena),
+        )
+    })
+}
+
+/// Parse the nested property list one level deeper.
+fn parse_nested_list<'a>(
+    parent: &WzNodeArc,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    origin_offset: usize,
+    ctx: &mut ParseContext<'a>,
+) -> Result<(WzNodeArcVec, Vec<WzNodeArc>), WzPropertyParseError> {
+    ctx.level += 1;
+    let result = parse_property_list_inner(Some(parent), org_reader, reader, origin_offset, ctx);
+    ctx.level -= 1;
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn parse_more_inner<'a>(
+    parent: Option<&WzNodeArc>,
+    org_reader: &Arc<WzReader>,
+    reader: &WzSliceReader,
+    end_of_block: usize,
+    origin_offset: usize,
+    property_name: WzNodeName,
+    extend_property_type: &str,
+    ctx: &mut ParseContext<'a>,
+) -> Result<ParsedNode, WzPropertyParseError> {
     match extend_property_type {
         "Property" => {
             let node = WzNode::new(
@@ -195,9 +447,21 @@ pub fn parse_more(
             )
             .into_lock();
 
-            reader.skip(2);
+            reader.try_skip(2)?;
+
+            /* the caller will seek to the end of the block */
+            if ctx.level >= ctx.max_depth {
+                ctx.deferred.push(WzDeferredProperty {
+                    node: Arc::downgrade(&node),
+                    reader: Arc::clone(org_reader),
+                    origin_offset,
+                    offset: reader.pos.get(),
+                });
+                return Ok((property_name, node, None));
+            }
+
             let (childs, uol_nodes) =
-                parse_property_list(Some(&node), org_reader, reader, origin_offset)?;
+                parse_nested_list(&node, org_reader, reader, origin_offset, ctx)?;
 
             {
                 let mut node_write = node.write().unwrap();
@@ -210,7 +474,7 @@ pub fn parse_more(
             Ok((property_name, node, Some(uol_nodes)))
         }
         "Canvas" => {
-            reader.skip(1);
+            reader.try_skip(1)?;
             let has_child = reader.read_u8()? == 1;
 
             let node = WzNode::new(
@@ -220,12 +484,12 @@ pub fn parse_more(
             )
             .into_lock();
 
-            let mut uol_nodes: Option<Vec<WzNodeArc>> = None;
+            let mut uol_nodes = None;
 
             if has_child {
-                reader.skip(2);
+                reader.try_skip(2)?;
                 let (childs, uols) =
-                    parse_property_list(Some(&node), org_reader, reader, origin_offset)?;
+                    parse_nested_list(&node, org_reader, reader, origin_offset, ctx)?;
                 let mut node_write = node.write().unwrap();
                 node_write.children.reserve(childs.len());
                 for (name, child) in childs {
@@ -238,10 +502,11 @@ pub fn parse_more(
             let height = reader.read_wz_int()?;
             let format1 = reader.read_wz_int()?;
             let format2 = reader.read_i8()?;
-            reader.skip(4);
+            reader.try_skip(4)?;
             let canvas_slice_size = (reader.read_i32()? - 1) as usize;
-            reader.skip(1);
+            reader.try_skip(1)?;
             let canvas_offset = reader.pos.get();
+            reader.check_range(canvas_offset, canvas_slice_size)?;
             let canvas_header = reader.read_u16()?;
             let wz_png = WzPng::new(
                 org_reader,
@@ -263,20 +528,22 @@ pub fn parse_more(
             .into_lock();
 
             let entry_count = reader.read_wz_int()?;
-            let mut uol_nodes: Vec<WzNodeArc> = Vec::new();
+            let mut uol_nodes = Vec::new();
 
             {
                 let mut node_write = node.write().unwrap();
                 node_write.children.reserve(entry_count as usize);
                 for i in 0..entry_count {
                     let name: WzNodeName = i.to_string().into();
-                    let parsed_node = parse_extended_prop(
+                    /* the entries have no block size, so they can't be skipped */
+                    let parsed_node = parse_extended_prop_inner(
                         Some(&node),
                         org_reader,
                         reader,
                         end_of_block,
                         origin_offset,
                         name,
+                        &mut ParseContext::unlimited(ctx.arena),
                     )?;
 
                     if let Some(uols) = parsed_node.2 {
@@ -296,14 +563,22 @@ pub fn parse_more(
             Ok((property_name, node.into_lock(), None))
         }
         "Sound_DX8" => {
-            reader.skip(1);
+            reader.try_skip(1)?;
             let _sound_start_offset = reader.pos.get();
             let sound_size = reader.read_wz_int()? as u32;
             let sound_duration = reader.read_wz_int()? as u32;
-            let sound_offset = end_of_block - (sound_size as usize);
-
             let header_offset: usize = reader.pos.get();
 
+            let sound_offset = end_of_block
+                .checked_sub(sound_size as usize)
+                .filter(|offset| *offset >= header_offset)
+                .ok_or(reader::Error::OutOfBounds(
+                    header_offset,
+                    header_offset + sound_size as usize,
+                    end_of_block,
+                ))?;
+            reader.check_range(sound_offset, sound_size as usize)?;
+
             let header_size = sound_offset - header_offset;
 
             let sound_type = get_sound_type_from_header(
@@ -326,7 +601,7 @@ pub fn parse_more(
             Ok((property_name, node.into_lock(), None))
         }
         "UOL" => {
-            reader.skip(1);
+            reader.try_skip(1)?;
             let str_meta = reader.read_wz_string_block_meta(origin_offset)?;
             let node = WzNode::new(
                 &property_name,
@@ -335,9 +610,7 @@ pub fn parse_more(
             )
             .into_lock();
 
-            let uol_nodes = Some(vec![Arc::clone(&node)]);
-
-            Ok((property_name, node, uol_nodes))
+            Ok((property_name, node.clone(), Some(vec![node])))
         }
         "RawData" => {
             let raw_data_version = reader.read_u8()?;
@@ -355,12 +628,12 @@ pub fn parse_more(
             )
             .into_lock();
 
-            let mut uol_nodes: Option<Vec<WzNodeArc>> = None;
+            let mut uol_nodes = None;
 
             if has_child {
-                reader.skip(2);
+                reader.try_skip(2)?;
                 let (childs, uols) =
-                    parse_property_list(Some(&node), org_reader, reader, origin_offset)?;
+                    parse_nested_list(&node, org_reader, reader, origin_offset, ctx)?;
                 let mut node_write = node.write().unwrap();
                 node_write.children.reserve(childs.len());
                 for (name, child) in childs {
@@ -371,6 +644,7 @@ pub fn parse_more(
 
             let raw_data_size = reader.read_wz_int()? as usize;
             let raw_data_offset = reader.pos.get();
+            reader.check_range(raw_data_offset, raw_data_size)?;
 
             node.write().unwrap().object_type =
                 WzRawData::new(org_reader, raw_data_offset, raw_data_size).into();
@@ -379,9 +653,10 @@ pub fn parse_more(
         }
         "Canvas#Video" => {
             // origin observation, first 3 bytes is [0x00, 0x00, 0x01]
-            reader.skip(3);
+            reader.try_skip(3)?;
             let video_size = reader.read_wz_int()? as usize;
             let video_offset = reader.pos.get();
+            reader.check_range(video_offset, video_size)?;
             // pos - size shoud same as end_of_block
             let node = WzNode::new(
                 &property_name,
@@ -434,7 +709,7 @@ pub fn get_node(
             match property_type {
                 0 => { /* do nothing */ }
                 2 | 11 => {
-                    reader.skip(2);
+                    reader.try_skip(2)?;
                 }
                 3 | 19 => {
                     reader.read_wz_int()?;
@@ -446,11 +721,11 @@ pub fn get_node(
                     let float_type: u8 = reader.read_u8()?;
 
                     if float_type == 0x80 {
-                        reader.skip(4);
+                        reader.try_skip(4)?;
                     }
                 }
                 5 => {
-                    reader.skip(8);
+                    reader.try_skip(8)?;
                 }
                 8 => {
                     let string_type = reader.read_u8()?;
@@ -460,7 +735,7 @@ pub fn get_node(
                             reader.read_wz_string_meta()?;
                         }
                         1 | 0x1B => {
-                            reader.skip(4);
+                            reader.try_skip(4)?;
                         }
                         _ => {}
                     }
@@ -469,13 +744,13 @@ pub fn get_node(
                     if name == current_name {
                         current_path = next_path;
                         // skip block size
-                        reader.skip(4);
+                        reader.try_skip(4)?;
                         reader.read_wz_string_block_meta(origin_offset)?;
-                        reader.skip(2);
+                        reader.try_skip(2)?;
                         break;
                     } else {
                         let block_size = reader.read_u32()?;
-                        reader.skip(block_size as usize);
+                        reader.try_skip(block_size as usize)?;
                     }
                 }
                 _ => {
@@ -495,3 +770,77 @@ pub fn get_node(
 
     Err(WzPropertyParseError::NodeNotFound)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// one entry named `a` with extended property `X` and the given block size
+    fn setup_extended_block(block_size: u32) -> Vec<u8> {
+        let mut buf = vec![1, 0x00, 0xFF, b'a' ^ 0xAA, 9];
+        buf.extend_from_slice(&block_size.to_le_bytes());
+        buf.extend_from_slice(&[0x73, 0xFF, b'X' ^ 0xAA]);
+        buf
+    }
+
+    #[test]
+    fn test_malformed_block_size() {
+        let org_reader = Arc::new(WzReader::from_buff(&setup_extended_block(0xFFFF)));
+        let reader = org_reader.create_slice_reader_without_hash();
+
+        let result = parse_property_list(None, &org_reader, &reader, 0);
+
+        assert!(matches!(
+            result,
+            Err(WzPropertyParseError::ReaderError(
+                reader::Error::OutOfBounds(_, _, 12)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_extended_property() {
+        let org_reader = Arc::new(WzReader::from_buff(&setup_extended_block(3)));
+        let reader = org_reader.create_slice_reader_without_hash();
+
+        let (childs, _) = parse_property_list(None, &org_reader, &reader, 0).unwrap();
+
+        assert_eq!(childs.len(), 1);
+        assert_eq!(childs[0].0.as_str(), "a");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_read_property_types() {
+        /* a short stored as `11` and a int stored as `19` */
+        let buf = [
+            2,
+            0x00,
+            0xFF,
+            b'a' ^ 0xAA,
+            11,
+            5,
+            0,
+            0x00,
+            0xFF,
+            b'b' ^ 0xAA,
+            19,
+            7,
+        ];
+        let org_reader = Arc::new(WzReader::from_buff(&buf));
+        let reader = org_reader.create_slice_reader_without_hash();
+
+        let types = read_property_types(&org_reader, &reader, 0).unwrap();
+
+        assert_eq!(types.len(), 2);
+        assert_eq!(types.get("a"), Some(&11));
+        assert_eq!(types.get("b"), Some(&19));
+
+        reader.try_seek(0).unwrap();
+        let (childs, _) = parse_property_list(None, &org_reader, &reader, 0).unwrap();
+        assert!(matches!(
+            childs[0].1.read().unwrap().object_type,
+            WzObjectType::Value(WzValue::Short(5))
+        ));
+    }
+}
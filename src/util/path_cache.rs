@@ -0,0 +1,131 @@
+use crate::sync::RwLock;
+use crate::{WzNode, WzNodeArc};
+use hashbrown::HashMap;
+use std::sync::{Arc, Weak};
+
+/// Cache the full paths of the nodes and their parents, so getting the paths of many nodes under the same parents
+/// (like every canvas in a image) only climb to the nearest cached parent, instead of the root every time.
+///
+/// The cache won't notice the node renamed or moved, use it for a walk or an export and drop it,
+/// or `clear` it after changing the tree.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::PathCache};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let child = WzNode::from_str("1", 1, Some(&root)).into_lock();
+/// let grandchild = WzNode::from_str("2", 1, Some(&child)).into_lock();
+///
+/// let mut cache = PathCache::new();
+///
+/// assert_eq!(&*cache.get_full_path(&grandchild), "root/1/2");
+/// assert_eq!(&*cache.get_full_path(&child), "root/1");
+/// assert_eq!(cache.len(), 3);
+/// ```
+#[derive(Debug, Default)]
+pub struct PathCache {
+    /// keyed by the address of the node, the weak ref make sure it's still the same node.
+    paths: HashMap<usize, (Weak<RwLock<WzNode>>, Arc<str>)>,
+}
+
+#[inline]
+fn node_key(node: &WzNodeArc) -> usize {
+    Arc::as_ptr(node) as usize
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_cached(&self, node: &WzNodeArc) -> Option<Arc<str>> {
+        let (weak, path) = self.paths.get(&node_key(node))?;
+        /* the address may be reused by a new node after the old one dropped */
+        if weak.as_ptr() == Arc::as_ptr(node) && weak.strong_count() > 0 {
+            Some(path.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Same as `WzNode::get_full_path`, the node and it's parents will be cached.
+    pub fn get_full_path(&mut self, node: &WzNodeArc) -> Arc<str> {
+        let mut uncached = Vec::new();
+        let mut prefix = None;
+        let mut current = Some(node.clone());
+
+        while let Some(node) = current {
+            if let Some(path) = self.get_cached(&node) {
+                prefix = Some(path);
+                break;
+            }
+            current = node.read().unwrap().parent.upgrade();
+            uncached.push(node);
+        }
+
+        for node in uncached.into_iter().rev() {
+            let node_read = node.read().unwrap();
+            let path: Arc<str> = match &prefix {
+                Some(prefix) => format!("{prefix}/{}", node_read.name).into(),
+                None => node_read.name.as_str().into(),
+            };
+            self.paths
+                .insert(node_key(&node), (Arc::downgrade(&node), path.clone()));
+            prefix = Some(path);
+        }
+
+        /* the node itself is either cached or just inserted */
+        prefix.unwrap()
+    }
+
+    /// The number of cached nodes.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+
+    #[test]
+    fn test_path_cache() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let stand = add_child("stand", 1, &root);
+        let frames = (0..3)
+            .map(|i| add_child(&i.to_string(), 1, &stand))
+            .collect::<Vec<_>>();
+
+        let mut cache = PathCache::new();
+        assert!(cache.is_empty());
+
+        for frame in &frames {
+            assert_eq!(
+                *cache.get_full_path(frame),
+                frame.read().unwrap().get_full_path()
+            );
+        }
+        assert_eq!(cache.len(), 5);
+        assert_eq!(&*cache.get_full_path(&root), "root");
+        assert_eq!(cache.len(), 5);
+
+        /* a node dropped and created again won't get the stale path */
+        stand.write().unwrap().remove_child("0");
+        drop(frames);
+        let renamed = add_child("renamed", 1, &stand);
+        assert_eq!(&*cache.get_full_path(&renamed), "root/stand/renamed");
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
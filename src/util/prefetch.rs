@@ -0,0 +1,235 @@
+use crate::sync::RwLock;
+use crate::{node::Error, WzNode, WzNodeArc, WzObjectType};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Default)]
+struct PrefetchQueue {
+    images: VecDeque<Weak<RwLock<WzNode>>>,
+    /// the images taken by the workers but not finished yet.
+    in_progress: usize,
+}
+
+#[derive(Debug, Default)]
+struct PrefetchShared {
+    queue: Mutex<PrefetchQueue>,
+    /// notify the workers there are new images or it's stopped.
+    available: Condvar,
+    /// notify `wait_idle` the queue is drained.
+    idle: Condvar,
+    is_stopped: AtomicBool,
+    parsed: AtomicUsize,
+}
+
+fn is_unparsed_image(node: &WzNodeArc) -> bool {
+    match &node.read().unwrap().object_type {
+        WzObjectType::Image(image) => !image.is_parsed,
+        WzObjectType::MsImage(_) => true,
+        _ => false,
+    }
+}
+
+fn worker(shared: &PrefetchShared) {
+    loop {
+        let image = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if shared.is_stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(image) = queue.images.pop_front() {
+                    queue.in_progress += 1;
+                    break image;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+
+        /* the user may parse it first, or drop the tree already */
+        if let Some(image) = image.upgrade() {
+            if is_unparsed_image(&image) && image.write().unwrap().parse(&image).is_ok() {
+                shared.parsed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let mut queue = shared.queue.lock().unwrap();
+        queue.in_progress -= 1;
+        if queue.images.is_empty() && queue.in_progress == 0 {
+            shared.idle.notify_all();
+        }
+    }
+}
+
+/// Parse the images of a directory on background threads, so they are most likely parsed when the user
+/// clicks into them, for the interactive browsers.
+///
+/// Only the latest directory is prefetched, calling `prefetch` again drops the pending images of the previous one,
+/// and at most `budget` images are queued each time. The failed images are ignored, the user will get the error
+/// when parsing it again. The workers are stopped when the `Prefetcher` dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, Prefetcher};
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+/// let prefetcher = Prefetcher::new(2, 32);
+///
+/// // when the user opens a folder
+/// let mob = base.read().unwrap().at("Mob").unwrap();
+/// prefetcher.parse(&mob).unwrap();
+///
+/// // most likely parsed already
+/// let info = mob.read().unwrap().at_path_parsed("100100.img/info").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Prefetcher {
+    shared: Arc<PrefetchShared>,
+    workers: Vec<JoinHandle<()>>,
+    budget: usize,
+}
+
+impl Prefetcher {
+    /// Spawn `threads` workers(at least one), `budget` is the max images queued for a directory.
+    pub fn new(threads: usize, budget: usize) -> Self {
+        let shared = Arc::new(PrefetchShared::default());
+
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                std::thread::spawn(move || worker(&shared))
+            })
+            .collect();
+
+        Self {
+            shared,
+            workers,
+            budget,
+        }
+    }
+
+    /// Same as `WzNode::parse`, then prefetch the images under it.
+    pub fn parse(&self, node: &WzNodeArc) -> Result<(), Error> {
+        node.write().unwrap().parse(node)?;
+        self.prefetch(node);
+        Ok(())
+    }
+
+    /// Queue the unparsed images of the node's children in name order, replacing the pending ones.
+    /// Returns the number of queued images. The node itself won't be parsed, use `parse` for it.
+    pub fn prefetch(&self, node: &WzNodeArc) -> usize {
+        let mut images = node
+            .read()
+            .unwrap()
+            .children
+            .iter()
+            .filter(|(_, child)| is_unparsed_image(child))
+            .map(|(name, child)| (name.clone(), Arc::downgrade(child)))
+            .collect::<Vec<_>>();
+        images.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+        images.truncate(self.budget);
+
+        let count = images.len();
+
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.images = images.into_iter().map(|(_, image)| image).collect();
+        drop(queue);
+
+        self.shared.available.notify_all();
+
+        count
+    }
+
+    /// Drop the pending images, the ones already parsing will still finish.
+    pub fn cancel(&self) {
+        self.shared.queue.lock().unwrap().images.clear();
+        self.shared.idle.notify_all();
+    }
+
+    /// The number of images waiting to be parsed.
+    pub fn pending(&self) -> usize {
+        self.shared.queue.lock().unwrap().images.len()
+    }
+
+    /// The number of images parsed by the prefetcher so far.
+    pub fn parsed_count(&self) -> usize {
+        self.shared.parsed.load(Ordering::Relaxed)
+    }
+
+    /// Block until all the queued images are parsed.
+    pub fn wait_idle(&self) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        while !queue.images.is_empty() || queue.in_progress > 0 {
+            queue = self.shared.idle.wait(queue).unwrap();
+        }
+    }
+}
+
+impl Drop for Prefetcher {
+    fn drop(&mut self) {
+        self.shared.is_stopped.store(true, Ordering::Relaxed);
+        /* hold the lock so no worker is between checking the flag and waiting */
+        drop(self.shared.queue.lock().unwrap());
+        self.shared.available.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{version::WzMapleVersion, WzImage};
+
+    #[test]
+    fn test_prefetch() {
+        let wz_file = WzNode::from_wz_file_full(
+            "tests/test.wz",
+            Some(WzMapleVersion::BMS),
+            Some(123),
+            None,
+            None,
+        )
+        .unwrap()
+        .into_lock();
+
+        let prefetcher = Prefetcher::new(2, 8);
+        prefetcher.parse(&wz_file).unwrap();
+        prefetcher.wait_idle();
+
+        let image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+        assert!(matches!(
+            &image.read().unwrap().object_type,
+            WzObjectType::Image(image) if image.is_parsed
+        ));
+        assert!(image.read().unwrap().at_path("1/int").is_some());
+        assert_eq!(prefetcher.parsed_count(), 1);
+        assert_eq!(prefetcher.pending(), 0);
+
+        /* already parsed */
+        assert_eq!(prefetcher.prefetch(&wz_file), 0);
+    }
+
+    #[test]
+    fn test_prefetch_budget() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        for name in ["c.img", "a.img", "b.img"] {
+            /* the empty images fail to parse, they are just skipped */
+            let image = WzNode::from_str(name, WzImage::default(), Some(&root)).into_lock();
+            root.write().unwrap().add(&image);
+        }
+        let not_image = WzNode::from_str("d", 1, Some(&root)).into_lock();
+        root.write().unwrap().add(&not_image);
+
+        let prefetcher = Prefetcher::new(1, 2);
+        assert_eq!(prefetcher.prefetch(&root), 2);
+        prefetcher.wait_idle();
+        assert_eq!(prefetcher.parsed_count(), 0);
+
+        prefetcher.cancel();
+        assert_eq!(prefetcher.pending(), 0);
+    }
+}
@@ -1,7 +1,13 @@
-use crate::{version::WzMapleVersion, SharedWzMutableKey, WzNode, WzNodeArc, WzNodeCast};
+use crate::{
+    version::{self, WzMapleVersion},
+    SharedWzMutableKey, WzDirectory, WzImage, WzNode, WzNodeArc, WzNodeCast, WzNodeName,
+};
 use std::fs::DirEntry;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::node_util;
 
 /// Get a wz file path by directory, like `Map` -> `Map/Map.wz`.
 pub fn get_root_wz_file_path(dir: &DirEntry) -> Option<String> {
@@ -27,14 +33,14 @@ pub fn resolve_root_wz_file_dir_full(
 ) -> Result<WzNodeArc, io::Error> {
     let root_node: WzNodeArc =
         WzNode::from_wz_file_full(&dir, version, patch_version, parent, default_keys)
-            .unwrap()
+            .map_err(invalid_data)?
             .into();
     let wz_dir = dir.as_ref().parent().unwrap();
 
     {
         let mut root_node_write = root_node.write().unwrap();
 
-        root_node_write.parse(&root_node).unwrap();
+        root_node_write.parse(&root_node).map_err(invalid_data)?;
 
         for entry in wz_dir.read_dir()? {
             let entry = entry?;
@@ -78,7 +84,7 @@ pub fn resolve_root_wz_file_dir_full(
                     None,
                     default_keys,
                 )
-                .unwrap()
+                .map_err(invalid_data)?
                 .into_lock();
 
                 let mut node_write = node.write().unwrap();
@@ -96,6 +102,10 @@ pub fn resolve_root_wz_file_dir_full(
     Ok(root_node)
 }
 
+fn invalid_data(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
 /// resolve_root_wz_file_dir_full with less arguments for easier use
 pub fn resolve_root_wz_file_dir(
     dir: impl AsRef<Path>,
@@ -104,15 +114,264 @@ pub fn resolve_root_wz_file_dir(
     resolve_root_wz_file_dir_full(dir, None, None, parent, None)
 }
 
-/// Construct `WzNode` tree from `Base.wz`
+/// The layout of the game data, see `detect_base_layout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaseLayout {
+    /// the classic wz folders, with the path of `Base.wz`.
+    Wz(PathBuf),
+    /// the newer `.ms` packaging, with the folder contains the `.ms` files.
+    Ms(PathBuf),
+    /// the extracted `.img` files, with the folder contains them.
+    ImgDump(PathBuf),
+}
+
+fn has_extension_in(dir: &Path, extension: &str) -> bool {
+    dir.read_dir().is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.path().extension().is_some_and(|ext| ext == extension))
+    })
+}
+
+/// Whether the folder contains any `.img` file within the `depth`.
+fn has_img_within(dir: &Path, depth: usize) -> bool {
+    let Ok(entries) = dir.read_dir() else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            depth > 0 && has_img_within(&path, depth - 1)
+        } else {
+            path.extension().is_some_and(|ext| ext == "img")
+        }
+    })
+}
+
+/// Detect the layout of the path, it can be the `Base.wz`, the install folder or the `Data` folder.
+/// The classic `Base.wz` is preferred, then the `.ms` files under `Packs`, then the `.img` files.
+pub fn detect_base_layout(path: impl AsRef<Path>) -> Option<BaseLayout> {
+    let path = path.as_ref();
+
+    if path.ends_with("Base.wz") {
+        return path.is_file().then(|| BaseLayout::Wz(path.to_path_buf()));
+    }
+
+    if !path.is_dir() {
+        return None;
+    }
+
+    let data_dirs = [path.join("Data"), path.to_path_buf()];
+
+    for dir in &data_dirs {
+        for base in [dir.join("Base").join("Base.wz"), dir.join("Base.wz")] {
+            if base.is_file() {
+                return Some(BaseLayout::Wz(base));
+            }
+        }
+    }
+
+    for dir in &data_dirs {
+        for packs in [dir.join("Packs"), dir.clone()] {
+            if has_extension_in(&packs, "ms") {
+                return Some(BaseLayout::Ms(packs));
+            }
+        }
+    }
+
+    data_dirs
+        .into_iter()
+        .find(|dir| has_img_within(dir, 2))
+        .map(BaseLayout::ImgDump)
+}
+
+fn new_virtual_dir(name: &str, parent: Option<&WzNodeArc>) -> WzNodeArc {
+    let dir = WzDirectory {
+        is_parsed: true,
+        ..Default::default()
+    };
+    WzNode::new(&name.into(), dir, parent).into_lock()
+}
+
+/// Get the child directory, create a virtual one if not exist.
+fn get_or_create_dir(parent: &WzNodeArc, name: &str) -> WzNodeArc {
+    let mut parent_write = parent.write().unwrap();
+    if let Some(dir) = parent_write.at(name) {
+        return dir;
+    }
+    let dir = new_virtual_dir(name, Some(parent));
+    parent_write.children.insert(name.into(), dir.clone());
+    dir
+}
+
+/// Get the folder of the `.ms` file's entries that has no folder in it's name,
+/// like `Mob` of `Mob_00000.ms` and `Character/Weapon` of `Character_Weapon_00000.ms`.
+fn get_ms_category(stem: &str) -> String {
+    let mut pathes = stem.split('_').collect::<Vec<_>>();
+    while pathes.len() > 1 && pathes.last().unwrap().parse::<u32>().is_ok() {
+        pathes.pop();
+    }
+    pathes.join("/")
+}
+
+/// Construct a `Base` rooted tree from the `.ms` files, the `dir` can be the folder contains them
+/// or the install/`Data` folder that has a `Packs` folder.
+///
+/// Every entry is put into the folders by it's entry name like `Mob/100100.img`,
+/// so the paths are same as the classic wz layout, and `_outlink` can be resolved from the virtual `Base` directory.
+/// The entry name without folder will be put under the category of the file, see `get_ms_category`.
+/// The `.ms` file failed to open will be skipped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::resolve_ms_base;
+/// let base = resolve_ms_base("path/to/MapleStory/Data").unwrap();
+/// let info = base.read().unwrap().at_path_parsed("Mob/100100.img/info").unwrap();
+/// ```
+pub fn resolve_ms_base(dir: impl AsRef<Path>) -> Result<WzNodeArc, io::Error> {
+    let dir = dir.as_ref();
+    let dir = [
+        dir.to_path_buf(),
+        dir.join("Packs"),
+        dir.join("Data").join("Packs"),
+    ]
+    .into_iter()
+    .find(|dir| has_extension_in(dir, "ms"))
+    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no .ms files found"))?;
+
+    let root = new_virtual_dir("Base", None);
+
+    let mut ms_paths = dir
+        .read_dir()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    ms_paths.retain(|path| path.extension().is_some_and(|ext| ext == "ms"));
+    ms_paths.sort();
+
+    for ms_path in ms_paths {
+        let Ok(ms_node) = WzNode::from_ms_file(&ms_path, None) else {
+            continue;
+        };
+        let ms_node = ms_node.into_lock();
+        if node_util::parse_node(&ms_node).is_err() {
+            continue;
+        }
+
+        let category = get_ms_category(&ms_path.file_stem().unwrap().to_string_lossy());
+
+        for (entry_name, image) in ms_node.write().unwrap().children.drain() {
+            let mut pathes = entry_name.split(['/', '\\']).collect::<Vec<_>>();
+            if pathes.len() == 1 {
+                pathes.splice(0..0, category.split('/'));
+            }
+            let name = pathes.pop().unwrap();
+
+            let parent = pathes.into_iter().fold(root.clone(), |parent, dir_name| {
+                get_or_create_dir(&parent, dir_name)
+            });
+
+            let mut image_write = image.write().unwrap();
+            image_write.name = name.into();
+            image_write.parent = Arc::downgrade(&parent);
+            drop(image_write);
+
+            parent.write().unwrap().children.insert(name.into(), image);
+        }
+    }
+
+    Ok(root)
+}
+
+/// Open every `.img` file under the folder, the iv of the previous file will be tried first.
+fn resolve_img_dir(
+    dir: &Path,
+    parent: &WzNodeArc,
+    iv: &mut Option<[u8; 4]>,
+) -> Result<(), io::Error> {
+    let mut paths = dir
+        .read_dir()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    for path in paths {
+        let file_name = path.file_name().unwrap().to_string_lossy();
+
+        if path.is_dir() {
+            /* some tools export the folder as `Mob.wz` */
+            let name = file_name.strip_suffix(".wz").unwrap_or(&file_name);
+            let child = get_or_create_dir(parent, name);
+            resolve_img_dir(&path, &child, iv)?;
+            continue;
+        }
+
+        if path.extension() != Some("img".as_ref()) {
+            continue;
+        }
+
+        let image = match WzImage::from_file(&path, *iv) {
+            Err(_) if iv.is_some() => WzImage::from_file(&path, None),
+            result => result,
+        };
+        let Ok(image) = image else {
+            continue;
+        };
+        *iv = Some(image.reader.wz_iv);
+
+        let name: WzNodeName = file_name.as_ref().into();
+        let node = WzNode::new(&name, image, Some(parent)).into_lock();
+        parent.write().unwrap().children.insert(name, node);
+    }
+
+    Ok(())
+}
+
+/// Construct `WzNode` tree from the game data, the `path` can be the `Base.wz`, or the install folder
+/// that will be detected by `detect_base_layout`:
+///   - classic wz folders: `Base.wz` and the other wz files listed in it.
+///   - `.ms` packaging: see `resolve_ms_base`.
+///   - extracted `.img` files: the folders and images under a virtual `Base` directory.
+///
+/// The `version` is used to decrypt the wz and img files, `.ms` files have their own keys.
+/// With `rayon` feature the directories inside each wz file are resolved concurrently.
+///
+/// The images are left unparsed, to keep the uol nodes parse them with `parse_all_with_uol_policy`,
+/// or pass the `UolPolicy` to the walk helpers like `walk_node`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::resolve_base;
+/// let base = resolve_base("path/to/MapleStory", None).unwrap();
+/// let mob = base.read().unwrap().at_path_parsed("Mob/100100.img/info").unwrap();
+/// ```
 pub fn resolve_base(
     path: impl AsRef<Path>,
     version: Option<WzMapleVersion>,
 ) -> Result<WzNodeArc, io::Error> {
-    if !path.as_ref().ends_with("Base.wz") {
-        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a Base.wz"));
+    match detect_base_layout(&path) {
+        Some(BaseLayout::Wz(base_path)) => resolve_wz_base(base_path, version),
+        Some(BaseLayout::Ms(dir)) => resolve_ms_base(dir),
+        Some(BaseLayout::ImgDump(dir)) => {
+            let root = new_virtual_dir("Base", None);
+            let mut iv = version.map(version::get_iv_by_maple_version);
+            resolve_img_dir(&dir, &root, &mut iv)?;
+            Ok(root)
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unable to find Base.wz, .ms or .img files",
+        )),
     }
+}
 
+/// Construct `WzNode` tree from `Base.wz`
+fn resolve_wz_base(
+    path: impl AsRef<Path>,
+    version: Option<WzMapleVersion>,
+) -> Result<WzNodeArc, io::Error> {
     let base_node = resolve_root_wz_file_dir_full(&path, version, None, None, None)?;
 
     let (patch_version, keys) = {
@@ -174,3 +433,68 @@ pub fn resolve_base(
 
     Ok(base_node)
 }
+
+/// Same as `resolve_base`, but all the file opening run on tokio's blocking pool.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::resolve_base_async;
+/// # async fn run() {
+/// let base = resolve_base_async("path/to/Base.wz", None).await.unwrap();
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn resolve_base_async(
+    path: impl AsRef<Path>,
+    version: Option<WzMapleVersion>,
+) -> Result<WzNodeArc, io::Error> {
+    let path = path.as_ref().to_path_buf();
+    super::blocking::run_blocking(move || resolve_base(path, version))
+        .await
+        .map_err(io::Error::from)?
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::property::WzString;
+
+    #[test]
+    fn test_get_ms_category() {
+        assert_eq!(get_ms_category("Mob_00000"), "Mob");
+        assert_eq!(
+            get_ms_category("Character_Weapon_00001"),
+            "Character/Weapon"
+        );
+        assert_eq!(get_ms_category("Etc"), "Etc");
+        assert_eq!(get_ms_category("00000"), "00000");
+    }
+
+    #[test]
+    fn test_outlink_in_virtual_base() {
+        let root = new_virtual_dir("Base", None);
+        let tile = get_or_create_dir(&get_or_create_dir(&root, "Map"), "Tile");
+        assert!(Arc::ptr_eq(
+            &tile,
+            &get_or_create_dir(&get_or_create_dir(&root, "Map"), "Tile")
+        ));
+
+        let target = WzNode::from_str("bsc.img", 1, Some(&tile)).into_lock();
+        tile.write().unwrap().add(&target);
+
+        let mob = get_or_create_dir(&root, "Mob");
+        let canvas = WzNode::from_str("canvas", 1, Some(&mob)).into_lock();
+        let outlink = WzNode::from_str(
+            "_outlink",
+            WzString::from_str("Map/Tile/bsc.img", [0; 4]),
+            Some(&canvas),
+        )
+        .into_lock();
+        canvas.write().unwrap().add(&outlink);
+        mob.write().unwrap().add(&canvas);
+
+        let resolved = node_util::resolve_link(&canvas, true).unwrap();
+        assert!(Arc::ptr_eq(&resolved, &target));
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{
+    property::{WzPng, WzSubProperty, WzValue},
+    WzNode, WzNodeArc, WzObjectType,
+};
+use std::cell::RefCell;
+
+use super::walk_node_with_path;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Options for `search_strings`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    /// ignore the case when matching.
+    pub case_insensitive: bool,
+    /// also matching the UOL's path.
+    pub include_uol: bool,
+    /// parse the node while walking, `WzImage` will be unparsed after walked.
+    pub force_parse: bool,
+}
+
+/// A matched string node.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// full path of the matched node.
+    pub path: String,
+    /// the decoded string.
+    pub value: String,
+}
+
+/// Decode the string of a string node, or UOL node when `include_uol` is true.
+pub(crate) fn get_node_string(node: &WzNode, include_uol: bool) -> Option<String> {
+    match &node.object_type {
+        WzObjectType::Value(WzValue::String(string)) => string.get_string().ok(),
+        WzObjectType::Value(WzValue::ParsedString(string)) => Some(string.clone()),
+        WzObjectType::Value(WzValue::UOL(string)) if include_uol => string.get_string().ok(),
+        _ => None,
+    }
+}
+
+/// Search all string nodes(and UOL if `include_uol` is set) under the node that contains the `query`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::{search_strings, SearchOptions}};
+/// # use wz_reader::property::WzString;
+/// let root = WzNode::from_str("Npc.img", 1, None).into_lock();
+/// let name = WzNode::from_str("name", WzString::from_str("Maple Administrator", [0; 4]), Some(&root)).into_lock();
+/// root.write().unwrap().add(&name);
+///
+/// let options = SearchOptions {
+///     case_insensitive: true,
+///     ..Default::default()
+/// };
+/// let result = search_strings(&root, "maple", &options);
+///
+/// assert_eq!(result[0].path, "Npc.img/name");
+/// ```
+pub fn search_strings(node: &WzNodeArc, query: &str, options: &SearchOptions) -> Vec<SearchMatch> {
+    let result = RefCell::new(Vec::new());
+
+    let query = if options.case_insensitive {
+        query.to_lowercase()
+    } else {
+        query.to_string()
+    };
+
+    walk_node_with_path(node, options.force_parse, &|node, _, path| {
+        let Some(value) = get_node_string(&node.read().unwrap(), options.include_uol) else {
+            return;
+        };
+
+        let is_match = if options.case_insensitive {
+            value.to_lowercase().contains(&query)
+        } else {
+            value.contains(&query)
+        };
+
+        if is_match {
+            result.borrow_mut().push(SearchMatch {
+                path: path.to_string(),
+                value,
+            });
+        }
+    });
+
+    result.into_inner()
+}
+
+/// A matched canvas node.
+#[derive(Debug, Clone)]
+pub struct CanvasMatch {
+    /// full path of the matched node.
+    pub path: String,
+    pub png: WzPng,
+}
+
+/// Search all canvas(`WzPng`) under the node that `predicate` returns true, it only use the metadata like
+/// `width`, `height` and `format()`, so there is no decoding.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::search_canvases};
+/// let node = WzNode::from_wz_file("path/to/UI.wz", None).unwrap().into_lock();
+///
+/// let large_images = search_canvases(&node, true, &|png| png.width * png.height > 1024 * 1024);
+///
+/// for canvas in large_images {
+///     println!("{} {}x{} format: {}", canvas.path, canvas.png.width, canvas.png.height, canvas.png.format());
+/// }
+/// ```
+pub fn search_canvases(
+    node: &WzNodeArc,
+    force_parse: bool,
+    predicate: &dyn Fn(&WzPng) -> bool,
+) -> Vec<CanvasMatch> {
+    let result = RefCell::new(Vec::new());
+
+    walk_node_with_path(node, force_parse, &|node, _, path| {
+        if let WzObjectType::Property(WzSubProperty::PNG(png)) = &node.read().unwrap().object_type {
+            if predicate(png) {
+                result.borrow_mut().push(CanvasMatch {
+                    path: path.to_string(),
+                    png: png.as_ref().clone(),
+                });
+            }
+        }
+    });
+
+    result.into_inner()
+}
+
+/// A prebuilt (path, string) index of a subtree, for repeated lookups without walking the tree again.
+/// With `serde` feature it can be serialized to disk and loaded back later.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::StringIndex};
+/// # use wz_reader::property::WzString;
+/// let root = WzNode::from_str("Eqp.img", 1, None).into_lock();
+/// let name = WzNode::from_str("name", WzString::from_str("Zakum Helmet", [0; 4]), Some(&root)).into_lock();
+/// root.write().unwrap().add(&name);
+///
+/// let index = StringIndex::build(&root, false);
+///
+/// assert_eq!(index.get("Eqp.img/name"), Some("Zakum Helmet"));
+/// assert_eq!(index.search("zakum", true).count(), 1);
+/// ```
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "StringIndexEntries"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StringIndex {
+    /// sorted by path.
+    entries: Vec<SearchMatch>,
+    /// the lowercased values of `entries`, so the case insensitive search won't allocate per entry.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    folded: Vec<String>,
+}
+
+/// The serialized form of `StringIndex`, the folded values are rebuilt when loaded.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct StringIndexEntries {
+    entries: Vec<SearchMatch>,
+}
+
+#[cfg(feature = "serde")]
+impl From<StringIndexEntries> for StringIndex {
+    fn from(value: StringIndexEntries) -> Self {
+        Self::from_entries(value.entries)
+    }
+}
+
+impl StringIndex {
+    fn from_entries(entries: Vec<SearchMatch>) -> Self {
+        let folded = entries
+            .iter()
+            .map(|entry| entry.value.to_lowercase())
+            .collect();
+
+        Self { entries, folded }
+    }
+
+    /// Collect all string nodes under the node, UOL is not included.
+    pub fn build(node: &WzNodeArc, force_parse: bool) -> Self {
+        let entries = RefCell::new(Vec::new());
+
+        walk_node_with_path(node, force_parse, &|node, _, path| {
+            if let Some(value) = get_node_string(&node.read().unwrap(), false) {
+                entries.borrow_mut().push(SearchMatch {
+                    path: path.to_string(),
+                    value,
+                });
+            }
+        });
+
+        let mut entries = entries.into_inner();
+        entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+        Self::from_entries(entries)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the string by full path.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.entries
+            .binary_search_by(|entry| entry.path.as_str().cmp(path))
+            .ok()
+            .map(|index| self.entries[index].value.as_str())
+    }
+
+    /// Iterate all entries that the string contains the `query`.
+    pub fn search<'a>(
+        &'a self,
+        query: &str,
+        case_insensitive: bool,
+    ) -> impl Iterator<Item = &'a SearchMatch> + 'a {
+        let query = if case_insensitive {
+            query.to_lowercase()
+        } else {
+            query.to_string()
+        };
+
+        self.entries
+            .iter()
+            .zip(&self.folded)
+            .filter(move |(entry, folded)| {
+                if case_insensitive {
+                    folded.contains(&query)
+                } else {
+                    entry.value.contains(&query)
+                }
+            })
+            .map(|(entry, _)| entry)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SearchMatch> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::property::WzString;
+    use crate::util::test_util::add_child;
+
+    fn setup_node_tree() -> WzNodeArc {
+        let root = WzNode::from_str("Quest.img", 1, None).into_lock();
+
+        add_child(
+            "0",
+            WzObjectType::Value(WzValue::String(WzString::from_str(
+                "Hello Maple World",
+                [0; 4],
+            ))),
+            &root,
+        );
+        add_child(
+            "1",
+            WzObjectType::Value(WzValue::ParsedString("Say hello to the NPC".to_string())),
+            &root,
+        );
+        add_child(
+            "2",
+            WzObjectType::Value(WzValue::UOL(WzString::from_str("../hello", [0; 4]))),
+            &root,
+        );
+        add_child("3", WzObjectType::Value(WzValue::Int(1)), &root);
+
+        root
+    }
+
+    #[test]
+    fn test_search_strings() {
+        let root = setup_node_tree();
+
+        let result = search_strings(&root, "hello", &SearchOptions::default());
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "Quest.img/1");
+        assert_eq!(result[0].value, "Say hello to the NPC");
+    }
+
+    #[test]
+    fn test_search_strings_case_insensitive_with_uol() {
+        let root = setup_node_tree();
+
+        let options = SearchOptions {
+            case_insensitive: true,
+            include_uol: true,
+            ..Default::default()
+        };
+        let mut result = search_strings(&root, "HELLO", &options);
+        result.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].path, "Quest.img/0");
+        assert_eq!(result[2].value, "../hello");
+    }
+
+    #[test]
+    fn test_search_canvases() {
+        let root = setup_node_tree();
+
+        let mut png = WzPng::default();
+        png.width = 64;
+        png.height = 32;
+        let canvas = WzNode::from_str("canvas", png, Some(&root)).into_lock();
+        root.write().unwrap().add(&canvas);
+
+        let result = search_canvases(&root, false, &|png| png.width == 64);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, "Quest.img/canvas");
+        assert_eq!(result[0].png.height, 32);
+
+        let result = search_canvases(&root, false, &|png| png.width > 64);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_string_index() {
+        let root = setup_node_tree();
+
+        let index = StringIndex::build(&root, false);
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.get("Quest.img/0"), Some("Hello Maple World"));
+        assert_eq!(index.get("Quest.img/2"), None);
+        assert_eq!(index.search("hello", false).count(), 1);
+        assert_eq!(index.search("hello", true).count(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_string_index_serialize() {
+        let root = setup_node_tree();
+
+        let index = StringIndex::build(&root, false);
+
+        let json = serde_json::to_string(&index).unwrap();
+        let loaded: StringIndex = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(index, loaded);
+        assert_eq!(loaded.get("Quest.img/1"), Some("Say hello to the NPC"));
+        assert_eq!(loaded.search("HELLO", true).count(), 2);
+    }
+}
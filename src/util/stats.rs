@@ -0,0 +1,205 @@
+use crate::{WzNodeArc, WzNodeArcVec, WzObjectType};
+use hashbrown::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// The stage of the parsing, see `ParseStats::time_of`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseStage {
+    /// `WzFile` and `MsFile`, include the directories under it.
+    File,
+    Directory,
+    /// `WzImage` and `MsImage`.
+    Image,
+    /// resolving the uol nodes after a image parsed.
+    Uol,
+}
+
+impl ParseStage {
+    pub(crate) fn from_object_type(object_type: &WzObjectType) -> Option<Self> {
+        match object_type {
+            WzObjectType::File(_) | WzObjectType::MsFile(_) => Some(ParseStage::File),
+            WzObjectType::Directory(_) => Some(ParseStage::Directory),
+            WzObjectType::Image(_) | WzObjectType::MsImage(_) => Some(ParseStage::Image),
+            _ => None,
+        }
+    }
+}
+
+/// A opt-in statistics collector of the parsing, collect the numbers during `collect`.
+///
+/// The times are summed from all threads, so it can be larger than the wall time when parsing concurrently.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::{parse_all, ParseStage, ParseStats}};
+/// # use std::sync::Arc;
+/// let node = WzNode::from_wz_file("path/to/Mob.wz", None).unwrap().into_lock();
+///
+/// let stats = Arc::new(ParseStats::default());
+/// stats.collect(|| parse_all(&node));
+///
+/// println!("{} images parsed", stats.images_parsed());
+/// println!("{} bytes decrypted", stats.bytes_decrypted());
+/// println!("{:?} spent on images", stats.time_of(ParseStage::Image));
+/// println!("{:?}", stats.node_counts());
+/// ```
+#[derive(Debug, Default)]
+pub struct ParseStats {
+    images_parsed: AtomicUsize,
+    bytes_decrypted: AtomicU64,
+    node_counts: Mutex<HashMap<&'static str, usize>>,
+    stage_times: Mutex<HashMap<ParseStage, Duration>>,
+}
+
+static IS_COLLECTING: AtomicBool = AtomicBool::new(false);
+static CURRENT: RwLock<Option<Arc<ParseStats>>> = RwLock::new(None);
+
+/// Get the collecting stats, it's cheap when nothing collecting.
+#[inline]
+pub(crate) fn current() -> Option<Arc<ParseStats>> {
+    if !IS_COLLECTING.load(Ordering::Relaxed) {
+        return None;
+    }
+    CURRENT.read().unwrap().clone()
+}
+
+#[inline]
+pub(crate) fn record_decrypted(size: usize) {
+    if let Some(stats) = current() {
+        stats
+            .bytes_decrypted
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+fn count_nodes(node: &WzNodeArc, counts: &mut HashMap<&'static str, usize>) {
+    let node_read = node.read().unwrap();
+    *counts.entry(node_read.object_type.type_name()).or_default() += 1;
+    for child in node_read.children.values() {
+        count_nodes(child, counts);
+    }
+}
+
+impl ParseStats {
+    /// Collect the stats of all parsing(from any thread) during `f`.
+    ///
+    /// Only one stats can be collecting at the same time, the previous one will be paused and restored after `f`.
+    pub fn collect<T>(self: &Arc<Self>, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.write().unwrap().replace(Arc::clone(self));
+        IS_COLLECTING.store(true, Ordering::Relaxed);
+
+        let result = f();
+
+        let mut current = CURRENT.write().unwrap();
+        IS_COLLECTING.store(previous.is_some(), Ordering::Relaxed);
+        *current = previous;
+
+        result
+    }
+
+    /// Record the nodes created by parsing a node.
+    pub(crate) fn record_parse(&self, stage: ParseStage, elapsed: Duration, childs: &WzNodeArcVec) {
+        if stage == ParseStage::Image {
+            self.images_parsed.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut counts = HashMap::new();
+        for (_, child) in childs {
+            count_nodes(child, &mut counts);
+        }
+
+        let mut node_counts = self.node_counts.lock().unwrap();
+        for (type_name, count) in counts {
+            *node_counts.entry(type_name).or_default() += count;
+        }
+        drop(node_counts);
+
+        self.record_time(stage, elapsed);
+    }
+
+    pub(crate) fn record_time(&self, stage: ParseStage, elapsed: Duration) {
+        *self.stage_times.lock().unwrap().entry(stage).or_default() += elapsed;
+    }
+
+    pub fn images_parsed(&self) -> usize {
+        self.images_parsed.load(Ordering::Relaxed)
+    }
+    /// The size of the strings and data that actually decrypted.
+    pub fn bytes_decrypted(&self) -> u64 {
+        self.bytes_decrypted.load(Ordering::Relaxed)
+    }
+    /// The number of nodes created by parsing, keyed by `WzObjectType::type_name`.
+    pub fn node_counts(&self) -> HashMap<&'static str, usize> {
+        self.node_counts.lock().unwrap().clone()
+    }
+    pub fn node_count(&self, type_name: &str) -> usize {
+        self.node_counts
+            .lock()
+            .unwrap()
+            .get(type_name)
+            .copied()
+            .unwrap_or_default()
+    }
+    /// The total time spent on the stage.
+    pub fn time_of(&self, stage: ParseStage) -> Duration {
+        self.stage_times
+            .lock()
+            .unwrap()
+            .get(&stage)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WzNode;
+
+    #[test]
+    fn test_collect_stats() {
+        let outer = Arc::new(ParseStats::default());
+        let inner = Arc::new(ParseStats::default());
+
+        /* not collecting */
+        record_decrypted(100);
+
+        outer.collect(|| {
+            record_decrypted(10);
+            inner.collect(|| record_decrypted(1));
+            record_decrypted(10);
+        });
+
+        record_decrypted(100);
+
+        /* other tests might decrypting at the same time */
+        assert!(outer.bytes_decrypted() >= 20);
+        assert!(inner.bytes_decrypted() >= 1);
+        assert!(!IS_COLLECTING.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_record_parse() {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let child = WzNode::from_str("child", 1, Some(&root)).into_lock();
+        let value = WzNode::from_str("value", 1, Some(&child)).into_lock();
+        child.write().unwrap().add(&value);
+
+        let stats = ParseStats::default();
+        stats.record_parse(
+            ParseStage::Image,
+            Duration::from_millis(2),
+            &vec![("child".into(), child)],
+        );
+        stats.record_time(ParseStage::Image, Duration::from_millis(1));
+
+        assert_eq!(stats.images_parsed(), 1);
+        assert_eq!(stats.node_count("Int"), 2);
+        assert_eq!(stats.node_counts().len(), 1);
+        assert_eq!(stats.time_of(ParseStage::Image), Duration::from_millis(3));
+        assert_eq!(stats.time_of(ParseStage::File), Duration::ZERO);
+    }
+}
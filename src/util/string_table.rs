@@ -0,0 +1,220 @@
+use crate::sync::RwLock;
+use crate::{node::Error, WzNodeArc, WzNodeGetter};
+use hashbrown::HashMap;
+use std::sync::Arc;
+
+use super::node_util;
+
+/// The categories of `String.wz` that have a id to name mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StringCategory {
+    /// Eqp, Consume, Ins, Etc, Cash and Pet
+    Item,
+    Mob,
+    Map,
+    Skill,
+    Npc,
+}
+
+impl StringCategory {
+    /// The images that contain the strings of this category.
+    pub fn image_names(&self) -> &'static [&'static str] {
+        match self {
+            StringCategory::Item => &[
+                "Eqp.img",
+                "Consume.img",
+                "Ins.img",
+                "Etc.img",
+                "Cash.img",
+                "Pet.img",
+            ],
+            StringCategory::Mob => &["Mob.img"],
+            StringCategory::Map => &["Map.img"],
+            StringCategory::Skill => &["Skill.img"],
+            StringCategory::Npc => &["Npc.img"],
+        }
+    }
+    /// The child that hold the name under a id node.
+    pub fn name_key(&self) -> &'static str {
+        match self {
+            StringCategory::Map => "mapName",
+            _ => "name",
+        }
+    }
+}
+
+pub type StringMap = HashMap<i32, String>;
+
+/// A id to name lookup table of `String.wz`, each category only be parsed on first access.
+///
+/// The id nodes can be nested in any depth, like `Eqp.img/Eqp/Cap/1002140` or `Map.img/victoria/100000000`,
+/// the node that has a numeric name and a name child will be taken.
+/// Missing images(like older version without some of them) are ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::WzNode;
+/// # use wz_reader::util::{StringCategory, StringTable};
+/// let string_wz = WzNode::from_wz_file("path/to/String.wz", None).unwrap().into_lock();
+/// let table = StringTable::new(&string_wz);
+///
+/// let mob_name = table.get_name(StringCategory::Mob, 100100).unwrap();
+/// let items = table.get(StringCategory::Item).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct StringTable {
+    node: WzNodeArc,
+    cache: RwLock<HashMap<StringCategory, Arc<StringMap>>>,
+}
+
+fn collect_names(node: &WzNodeArc, name_key: &str, map: &mut StringMap) {
+    let node_read = node.read().unwrap();
+
+    if let Ok(id) = node_read.name.parse::<i32>() {
+        if let Some(name) = node_read.get_str_at(name_key) {
+            map.insert(id, name);
+            return;
+        }
+    }
+
+    for child in node_read.children.values() {
+        collect_names(child, name_key, map);
+    }
+}
+
+impl StringTable {
+    /// Create from the `String.wz` node (or the `String` node under `Base.wz`).
+    pub fn new(node: &WzNodeArc) -> Self {
+        Self {
+            node: Arc::clone(node),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get the whole id to name map of the category, will parse the images on first access.
+    pub fn get(&self, category: StringCategory) -> Result<Arc<StringMap>, Error> {
+        if let Some(map) = self.cache.read().unwrap().get(&category) {
+            return Ok(Arc::clone(map));
+        }
+
+        node_util::parse_node(&self.node)?;
+
+        let mut map = StringMap::new();
+
+        for image_name in category.image_names() {
+            let Some(image) = self.node.read().unwrap().at(image_name) else {
+                continue;
+            };
+            node_util::parse_node(&image)?;
+            collect_names(&image, category.name_key(), &mut map);
+        }
+
+        let map = Arc::new(map);
+
+        self.cache
+            .write()
+            .unwrap()
+            .insert(category, Arc::clone(&map));
+
+        Ok(map)
+    }
+
+    /// Get the name of the id, returns `None` when not found or the category failed to parse.
+    pub fn get_name(&self, category: StringCategory, id: i32) -> Option<String> {
+        self.get(category).ok()?.get(&id).cloned()
+    }
+
+    /// Whether the category has been loaded.
+    pub fn is_loaded(&self, category: StringCategory) -> bool {
+        self.cache.read().unwrap().contains_key(&category)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzString, WzFile, WzImage, WzNode};
+
+    fn setup_node_tree() -> WzNodeArc {
+        fn add_string(name: &str, value: &str, parent: &WzNodeArc) {
+            add_child(name, WzString::from_str(value, [0; 4]), parent);
+        }
+        let parsed_image = || WzImage {
+            is_parsed: true,
+            ..Default::default()
+        };
+
+        let root = WzNode::from_str(
+            "String",
+            WzFile {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+
+        let eqp = add_child("Eqp.img", parsed_image(), &root);
+        let cap = add_child("Cap", 1, &add_child("Eqp", 1, &eqp));
+        add_string("name", "Blue Bandana", &add_child("1002140", 1, &cap));
+
+        let consume = add_child("Consume.img", parsed_image(), &root);
+        let potion = add_child("2000000", 1, &consume);
+        add_string("name", "Red Potion", &potion);
+        add_string("desc", "Restores 50 HP.", &potion);
+
+        let map = add_child("Map.img", parsed_image(), &root);
+        let victoria = add_child("victoria", 1, &map);
+        let henesys = add_child("100000000", 1, &victoria);
+        add_string("streetName", "Victoria Road", &henesys);
+        add_string("mapName", "Henesys", &henesys);
+
+        let skill = add_child("Skill.img", parsed_image(), &root);
+        add_string("bookName", "Beginner", &add_child("000", 1, &skill));
+        add_string("name", "Three Snails", &add_child("0001000", 1, &skill));
+
+        root
+    }
+
+    #[test]
+    fn test_string_table_get() {
+        let root = setup_node_tree();
+        let table = StringTable::new(&root);
+
+        let items = table.get(StringCategory::Item).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items.get(&1002140).unwrap(), "Blue Bandana");
+        assert_eq!(items.get(&2000000).unwrap(), "Red Potion");
+
+        assert_eq!(
+            table.get_name(StringCategory::Map, 100000000).as_deref(),
+            Some("Henesys")
+        );
+
+        let skills = table.get(StringCategory::Skill).unwrap();
+        assert_eq!(skills.len(), 1);
+        assert_eq!(skills.get(&1000).unwrap(), "Three Snails");
+
+        /* missing image */
+        assert!(table.get(StringCategory::Mob).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_string_table_lazy() {
+        let root = setup_node_tree();
+        let table = StringTable::new(&root);
+
+        assert!(!table.is_loaded(StringCategory::Item));
+
+        let first = table.get(StringCategory::Item).unwrap();
+
+        assert!(table.is_loaded(StringCategory::Item));
+        assert!(!table.is_loaded(StringCategory::Map));
+        assert!(Arc::ptr_eq(
+            &first,
+            &table.get(StringCategory::Item).unwrap()
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::WzNodeArc;
+use hashbrown::HashMap;
+use std::fmt;
+
+/// The numbers of a node tree, see `summarize`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeSummary {
+    /// total nodes, include the root.
+    pub node_count: usize,
+    /// nodes without any child.
+    pub leaf_count: usize,
+    /// the deepest depth, the root is 0.
+    pub max_depth: usize,
+    /// number of nodes at each depth, index is the depth.
+    pub depth_counts: Vec<usize>,
+    /// number of nodes keyed by `WzObjectType::type_name`, the `WzValue` are counted by it's variant like `Int`.
+    pub type_counts: HashMap<&'static str, usize>,
+    leaf_depth_sum: usize,
+}
+
+impl TreeSummary {
+    /// Get the number of nodes of the type, like `"PNG"` or `"Int"`.
+    pub fn count(&self, type_name: &str) -> usize {
+        self.type_counts.get(type_name).copied().unwrap_or_default()
+    }
+
+    /// The average depth of the leaves.
+    pub fn average_leaf_depth(&self) -> f64 {
+        if self.leaf_count == 0 {
+            return 0.0;
+        }
+        self.leaf_depth_sum as f64 / self.leaf_count as f64
+    }
+}
+
+/// Print like
+/// ```text
+/// nodes: 5, leaves: 3, max depth: 2, average leaf depth: 1.67
+/// depth 0: 1
+/// depth 1: 2
+/// depth 2: 2
+/// Int: 4
+/// Image: 1
+/// ```
+/// the types are sorted by count.
+impl fmt::Display for TreeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "nodes: {}, leaves: {}, max depth: {}, average leaf depth: {:.2}",
+            self.node_count,
+            self.leaf_count,
+            self.max_depth,
+            self.average_leaf_depth()
+        )?;
+
+        for (depth, count) in self.depth_counts.iter().enumerate() {
+            writeln!(f, "depth {depth}: {count}")?;
+        }
+
+        let mut types = self.type_counts.iter().collect::<Vec<_>>();
+        types.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (type_name, count) in types {
+            writeln!(f, "{type_name}: {count}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn summarize_inner(node: &WzNodeArc, depth: usize, summary: &mut TreeSummary) {
+    let node_read = node.read().unwrap();
+
+    summary.node_count += 1;
+    summary.max_depth = summary.max_depth.max(depth);
+    if summary.depth_counts.len() <= depth {
+        summary.depth_counts.resize(depth + 1, 0);
+    }
+    summary.depth_counts[depth] += 1;
+    *summary
+        .type_counts
+        .entry(node_read.object_type.type_name())
+        .or_default() += 1;
+
+    if node_read.children.is_empty() {
+        summary.leaf_count += 1;
+        summary.leaf_depth_sum += depth;
+        return;
+    }
+
+    for child in node_read.children.values() {
+        summarize_inner(child, depth + 1, summary);
+    }
+}
+
+/// Count the nodes of the subtree by type and depth, it won't parse anything,
+/// so parse it first(like `parse_all`) to get the numbers of the whole tree.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::summarize};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let child = WzNode::from_str("child", 2, Some(&root)).into_lock();
+/// root.write().unwrap().add(&child);
+///
+/// let summary = summarize(&root);
+///
+/// assert_eq!(summary.node_count, 2);
+/// assert_eq!(summary.count("Int"), 2);
+/// assert_eq!(summary.max_depth, 1);
+/// println!("{summary}");
+/// ```
+pub fn summarize(root: &WzNodeArc) -> TreeSummary {
+    let mut summary = TreeSummary::default();
+    summarize_inner(root, 0, &mut summary);
+    summary
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::test_util::add_child;
+    use crate::{property::WzPng, WzImage, WzNode};
+
+    #[test]
+    fn test_summarize() {
+        let root = WzNode::from_str(
+            "test.img",
+            WzImage {
+                is_parsed: true,
+                ..Default::default()
+            },
+            None,
+        )
+        .into_lock();
+        let info = add_child("info", 1, &root);
+        add_child("hp", 100, &info);
+        add_child("mp", 50, &info);
+        let stand = add_child("stand", 1, &root);
+        add_child("0", WzPng::default(), &add_child("0", 1, &stand));
+
+        let summary = summarize(&root);
+
+        assert_eq!(summary.node_count, 7);
+        assert_eq!(summary.leaf_count, 3);
+        assert_eq!(summary.max_depth, 3);
+        assert_eq!(summary.depth_counts, vec![1, 2, 3, 1]);
+        assert_eq!(summary.count("Image"), 1);
+        assert_eq!(summary.count("Int"), 5);
+        assert_eq!(summary.count("PNG"), 1);
+        assert_eq!(summary.count("Sound"), 0);
+        assert!((summary.average_leaf_depth() - 7.0 / 3.0).abs() < f64::EPSILON);
+
+        let text = summary.to_string();
+        assert!(text.starts_with("nodes: 7, leaves: 3, max depth: 3"));
+        assert!(text.contains("depth 3: 1\nInt: 5\n"));
+    }
+}
@@ -0,0 +1,14 @@
+//! The helpers to build the trees in the tests.
+
+use crate::{WzNode, WzNodeArc, WzObjectType};
+
+/// Create a node and add it to `parent`, returns the child.
+pub(crate) fn add_child(
+    name: &str,
+    object_type: impl Into<WzObjectType>,
+    parent: &WzNodeArc,
+) -> WzNodeArc {
+    let child = WzNode::from_str(name, object_type, Some(parent)).into_lock();
+    parent.write().unwrap().add(&child);
+    child
+}
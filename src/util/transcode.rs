@@ -0,0 +1,88 @@
+use crate::property::{WzPng, WzPngParseError};
+use crate::{WzNodeArc, WzNodeCast};
+use std::cell::RefCell;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::{parse_all, walk_node_with_path, ExportResult};
+
+/// Re-encode every canvas under the node to the wz pixel `format`, so the tree can be written out
+/// for clients or tools only support certain format. The node will be fully parsed first, and
+/// the pixel data is rebuilt in memory, the original wz file is untouched.
+///
+/// Canvas already in the `format` will be `skipped`, the `exported` are the full path of transcoded canvases.
+/// See `WzPng::from_image` for supported formats and the size limit of DXT and `517` formats.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::transcode_canvases};
+/// let node = WzNode::from_wz_file("path/to/Map.wz", None).unwrap().into_lock();
+///
+/// // BGRA8888
+/// let result = transcode_canvases(&node, 2);
+///
+/// for (path, error) in result.failed {
+///     println!("failed to transcode {path}: {error}");
+/// }
+/// ```
+pub fn transcode_canvases(node: &WzNodeArc, format: u32) -> ExportResult<String> {
+    let mut result = ExportResult::default();
+
+    for (path, e) in parse_all(node) {
+        result.failed.push((path, e.into()));
+    }
+
+    let canvases = RefCell::new(Vec::new());
+
+    walk_node_with_path(node, false, &|node, _, path| {
+        if let Some(png) = node.read().unwrap().try_as_png() {
+            canvases
+                .borrow_mut()
+                .push((path.to_string(), png.format() == format, node.clone()));
+        }
+    });
+
+    let mut targets = Vec::new();
+
+    for (path, is_same_format, node) in canvases.into_inner() {
+        if is_same_format {
+            result.skipped.push(path);
+        } else {
+            targets.push((path, node));
+        }
+    }
+
+    let transcode = |(path, node): &(String, WzNodeArc)| {
+        let transcoded = node
+            .read()
+            .unwrap()
+            .try_as_png()
+            .ok_or(WzPngParseError::NotPngProperty)
+            .and_then(|png| WzPng::from_image(&png.extract_png()?, format));
+
+        match transcoded {
+            Ok(png) => {
+                node.write().unwrap().object_type = png.into();
+                Ok(path.clone())
+            }
+            Err(e) => Err((path.clone(), e.into())),
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    let transcoded = targets.par_iter().map(transcode).collect::<Vec<_>>();
+
+    #[cfg(not(feature = "rayon"))]
+    let transcoded = targets.iter().map(transcode).collect::<Vec<_>>();
+
+    for item in transcoded {
+        match item {
+            Ok(path) => result.exported.push(path),
+            Err(failed) => result.failed.push(failed),
+        }
+    }
+
+    result
+}
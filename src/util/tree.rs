@@ -0,0 +1,114 @@
+use crate::{WzNode, WzNodeArc};
+use std::io::{self, Write};
+
+/// Print the node and it's children like the `tree` command, with the type and value of each node.
+/// Children are sorted by name, `max_depth` limit how deep it goes, `None` for no limit.
+/// It won't parse anything, so make sure the node is parsed before.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::print_tree};
+/// let root = WzNode::from_str("root", 1, None).into_lock();
+/// let child = WzNode::from_str("child", 2, Some(&root)).into_lock();
+/// root.write().unwrap().add(&child);
+///
+/// let mut output = Vec::new();
+/// print_tree(&root, None, &mut output).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "root (Int: 1)\n└── child (Int: 2)\n"
+/// );
+/// ```
+pub fn print_tree<W: Write>(
+    node: &WzNodeArc,
+    max_depth: Option<usize>,
+    writer: &mut W,
+) -> io::Result<()> {
+    let node = node.read().unwrap();
+    writeln!(writer, "{} ({})", node.name, node.object_type)?;
+    print_children(&node, max_depth, 1, &mut String::new(), writer)
+}
+
+fn print_children<W: Write>(
+    node: &WzNode,
+    max_depth: Option<usize>,
+    depth: usize,
+    prefix: &mut String,
+    writer: &mut W,
+) -> io::Result<()> {
+    if max_depth.is_some_and(|max_depth| depth > max_depth) {
+        return Ok(());
+    }
+
+    let mut children = node.children.iter().collect::<Vec<_>>();
+    children.sort_by(|a, b| a.0.cmp(b.0));
+
+    let last_index = children.len().saturating_sub(1);
+    for (index, (_, child)) in children.into_iter().enumerate() {
+        let is_last = index == last_index;
+        let child = child.read().unwrap();
+
+        let branch = if is_last { "└── " } else { "├── " };
+        writeln!(
+            writer,
+            "{prefix}{branch}{} ({})",
+            child.name, child.object_type
+        )?;
+
+        let prefix_len = prefix.len();
+        prefix.push_str(if is_last { "    " } else { "│   " });
+        print_children(&child, max_depth, depth + 1, prefix, writer)?;
+        prefix.truncate(prefix_len);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::property::Vector2D;
+    use crate::util::test_util::add_child;
+
+    fn setup_node_tree() -> WzNodeArc {
+        let root = WzNode::from_str("root", 1, None).into_lock();
+        let info = add_child("info", 1, &root);
+        add_child("origin", Vector2D(1, 2), &info);
+        add_child("speed", 10_i16, &info);
+        add_child("z", 1.5_f32, &root);
+
+        root
+    }
+
+    #[test]
+    fn test_print_tree() {
+        let root = setup_node_tree();
+        let mut output = Vec::new();
+
+        print_tree(&root, None, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "root (Int: 1)\n\
+             ├── info (Int: 1)\n\
+             │   ├── origin (Vector: (1, 2))\n\
+             │   └── speed (Short: 10)\n\
+             └── z (Float: 1.5)\n"
+        );
+    }
+
+    #[test]
+    fn test_print_tree_max_depth() {
+        let root = setup_node_tree();
+        let mut output = Vec::new();
+
+        print_tree(&root, Some(1), &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "root (Int: 1)\n├── info (Int: 1)\n└── z (Float: 1.5)\n"
+        );
+    }
+}
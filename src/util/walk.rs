@@ -1,33 +1,235 @@
 use crate::{WzNodeArc, WzObjectType};
 
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use super::node_util::UolPolicy;
+
+/// Whether the walker parse the nodes along the way, the `force_parse` argument of the walk helpers.
+/// `true` is the same as `Parse(UolPolicy::default())`, and `false` is `Skip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkParse {
+    /// walk the nodes as they are.
+    Skip,
+    /// parse every node with the uol policy, and unparse `WzImage` after it's walked.
+    Parse(UolPolicy),
+}
+
+impl From<bool> for WalkParse {
+    fn from(force_parse: bool) -> Self {
+        if force_parse {
+            WalkParse::Parse(UolPolicy::default())
+        } else {
+            WalkParse::Skip
+        }
+    }
+}
+
+impl From<UolPolicy> for WalkParse {
+    fn from(uol_policy: UolPolicy) -> Self {
+        WalkParse::Parse(uol_policy)
+    }
+}
+
+impl WalkParse {
+    fn parse(self, node: &WzNodeArc) {
+        if let WalkParse::Parse(uol_policy) = self {
+            // ignore the error
+            let _ = node
+                .write()
+                .unwrap()
+                .parse_with_uol_policy(node, uol_policy);
+        }
+    }
+    fn unparse(self, node: &WzNodeArc) {
+        let is_wz_image = matches!(node.read().unwrap().object_type, WzObjectType::Image(_));
+
+        if self != WalkParse::Skip && is_wz_image {
+            if let Ok(mut node) = node.write() {
+                node.unparse();
+            }
+        }
+    }
+}
+
 /// recursively walk a wz node, passing `&WzNodeArc` to `f`.
 /// with `force_parse` it will parse every node along the way,
 /// and only unparse `WzImage` after `f` is called to release memory.
-pub fn walk_node(node: &WzNodeArc, force_parse: bool, f: &dyn Fn(&WzNodeArc)) {
-    if force_parse {
-        // ignore the error
-        let _ = node.write().unwrap().parse(node);
-    }
+/// Pass a `UolPolicy` as `force_parse` to keep the uol nodes while parsing, see `WalkParse`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::{WzNode, util::{walk_node, node_util::UolPolicy}};
+/// let node = WzNode::from_wz_file("path/to/Map.wz", None).unwrap().into_lock();
+///
+/// walk_node(&node, UolPolicy::KeepAndAnnotate, &|node| {
+///     if let Some(target) = node.read().unwrap().uol_target() {
+///         println!("{}", target.read().unwrap().get_full_path());
+///     }
+/// });
+/// ```
+pub fn walk_node(node: &WzNodeArc, force_parse: impl Into<WalkParse>, f: &dyn Fn(&WzNodeArc)) {
+    walk_node_inner(node, force_parse.into(), f);
+}
+
+fn walk_node_inner(node: &WzNodeArc, force_parse: WalkParse, f: &dyn Fn(&WzNodeArc)) {
+    force_parse.parse(node);
 
     f(node);
 
     for child in node.read().unwrap().children.values() {
-        walk_node(child, force_parse, f);
+        walk_node_inner(child, force_parse, f);
+    }
+
+    force_parse.unparse(node);
+}
+
+/// Same as `walk_node`, but also passing the depth(start from 0) and the full path of the node to `f`.
+/// The path is built along the way, so it's cheaper than calling `get_full_path` for every node.
+pub fn walk_node_with_path(
+    node: &WzNodeArc,
+    force_parse: impl Into<WalkParse>,
+    f: &dyn Fn(&WzNodeArc, usize, &str),
+) {
+    let mut path = node.read().unwrap().get_full_path();
+    walk_node_with_path_inner(node, force_parse.into(), 0, &mut path, f);
+}
+
+fn walk_node_with_path_inner(
+    node: &WzNodeArc,
+    force_parse: WalkParse,
+    depth: usize,
+    path: &mut String,
+    f: &dyn Fn(&WzNodeArc, usize, &str),
+) {
+    force_parse.parse(node);
+
+    f(node, depth, path);
+
+    for (name, child) in node.read().unwrap().children.iter() {
+        let len = path.len();
+        path.push('/');
+        path.push_str(name);
+        walk_node_with_path_inner(child, force_parse, depth + 1, path, f);
+        path.truncate(len);
     }
 
-    let is_wz_image = matches!(node.read().unwrap().object_type, WzObjectType::Image(_));
+    force_parse.unparse(node);
+}
+
+/// Tell the walker what to do after visiting a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalkControl {
+    /// keep walking into the children.
+    #[default]
+    Continue,
+    /// don't walk into the children of current node, but keep walking the siblings.
+    SkipSubtree,
+    /// stop the whole walk immediately.
+    Stop,
+}
+
+/// Same as `walk_node`, but `f` returns a `WalkControl` to decide skipping current node's children or stop walking.
+/// Returns `WalkControl::Stop` when the walk is stopped by `f`, otherwise `WalkControl::Continue`.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::{WzNode, util::{walk_node_with_control, WalkControl}};
+/// let root = WzNode::from_str("Base", 1, None).into_lock();
+/// let sound = WzNode::from_str("Sound", 1, Some(&root)).into_lock();
+/// root.write().unwrap().add(&sound);
+///
+/// walk_node_with_control(&root, false, &|node| {
+///     if node.read().unwrap().name.as_str() == "Sound" {
+///         return WalkControl::SkipSubtree;
+///     }
+///     WalkControl::Continue
+/// });
+/// ```
+pub fn walk_node_with_control(
+    node: &WzNodeArc,
+    force_parse: impl Into<WalkParse>,
+    f: &dyn Fn(&WzNodeArc) -> WalkControl,
+) -> WalkControl {
+    walk_node_with_control_inner(node, force_parse.into(), f)
+}
+
+fn walk_node_with_control_inner(
+    node: &WzNodeArc,
+    force_parse: WalkParse,
+    f: &dyn Fn(&WzNodeArc) -> WalkControl,
+) -> WalkControl {
+    force_parse.parse(node);
+
+    let mut control = f(node);
 
-    if force_parse && is_wz_image {
-        if let Ok(mut node) = node.write() {
-            node.unparse();
+    if control == WalkControl::Continue {
+        for child in node.read().unwrap().children.values() {
+            if walk_node_with_control_inner(child, force_parse, f) == WalkControl::Stop {
+                control = WalkControl::Stop;
+                break;
+            }
         }
     }
+
+    force_parse.unparse(node);
+
+    if control == WalkControl::Stop {
+        WalkControl::Stop
+    } else {
+        WalkControl::Continue
+    }
+}
+
+/// Same as `walk_node`, but every `WzImage` subtree will be walked concurrently in rayon's thread pool.
+/// Node inside a `WzImage` still walked in the same thread, so the `f` need to be `Sync`.
+#[cfg(feature = "rayon")]
+pub fn walk_node_parallel(
+    node: &WzNodeArc,
+    force_parse: impl Into<WalkParse>,
+    f: &(dyn Fn(&WzNodeArc) + Sync),
+) {
+    walk_node_parallel_inner(node, force_parse.into(), f);
+}
+
+#[cfg(feature = "rayon")]
+fn walk_node_parallel_inner(
+    node: &WzNodeArc,
+    force_parse: WalkParse,
+    f: &(dyn Fn(&WzNodeArc) + Sync),
+) {
+    let is_wz_image = matches!(
+        node.read().unwrap().object_type,
+        WzObjectType::Image(_) | WzObjectType::MsImage(_)
+    );
+
+    if is_wz_image {
+        return walk_node_inner(node, force_parse, f);
+    }
+
+    force_parse.parse(node);
+
+    f(node);
+
+    let children = node
+        .read()
+        .unwrap()
+        .children
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    children
+        .par_iter()
+        .for_each(|child| walk_node_parallel_inner(child, force_parse, f));
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{property::WzSubProperty, WzNode, WzObjectType};
+    use crate::{property::WzSubProperty, util::test_util::add_child, WzNode, WzObjectType};
 
     fn generate_mock_node() -> WzNodeArc {
         let root = WzNode::from_str(
@@ -49,6 +251,27 @@ mod test {
         root
     }
 
+    /// same shape as `generate_mock_node`, but the children are actually added to their parent
+    fn generate_linked_mock_node() -> WzNodeArc {
+        let root = WzNode::from_str(
+            "root",
+            WzObjectType::Property(WzSubProperty::Property),
+            None,
+        )
+        .into_lock();
+
+        let child1 = add_child("child1", 1, &root);
+        let child2 = add_child("child2", 2, &root);
+
+        add_child("child11", 11, &child1);
+        add_child("child12", 12, &child1);
+
+        add_child("child21", 21, &child2);
+        add_child("child22", 22, &child2);
+
+        root
+    }
+
     #[test]
     fn test_walk_node() {
         let root = generate_mock_node();
@@ -68,4 +291,80 @@ mod test {
             assert!(pathes.contains(node_read.get_full_path().as_str()));
         });
     }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_walk_node_parallel() {
+        let root = generate_linked_mock_node();
+
+        let visited = std::sync::Mutex::new(std::collections::HashSet::new());
+
+        walk_node_parallel(&root, false, &|node| {
+            let path = node.read().unwrap().get_full_path();
+            visited.lock().unwrap().insert(path);
+        });
+
+        let visited = visited.into_inner().unwrap();
+
+        assert_eq!(visited.len(), 7);
+        assert!(visited.contains("root/child1/child12"));
+        assert!(visited.contains("root/child2/child21"));
+    }
+
+    #[test]
+    fn test_walk_node_with_path() {
+        let root = generate_mock_node();
+
+        walk_node_with_path(&root, false, &|node, depth, path| {
+            let node_read = node.read().unwrap();
+            assert_eq!(node_read.get_full_path(), path);
+            assert_eq!(path.matches('/').count(), depth);
+        });
+    }
+
+    #[test]
+    fn test_walk_node_with_control_skip() {
+        let root = generate_linked_mock_node();
+
+        let visited = std::sync::Mutex::new(Vec::new());
+
+        let control = walk_node_with_control(&root, false, &|node| {
+            let node_read = node.read().unwrap();
+            visited.lock().unwrap().push(node_read.get_full_path());
+            if node_read.name.as_str() == "child1" {
+                WalkControl::SkipSubtree
+            } else {
+                WalkControl::Continue
+            }
+        });
+
+        let visited = visited.into_inner().unwrap();
+
+        assert_eq!(control, WalkControl::Continue);
+        assert_eq!(visited.len(), 5);
+        assert!(!visited.iter().any(|path| path.starts_with("root/child1/")));
+    }
+
+    #[test]
+    fn test_walk_node_with_control_stop() {
+        let root = generate_linked_mock_node();
+
+        let visited = std::sync::Mutex::new(Vec::new());
+
+        let control = walk_node_with_control(&root, false, &|node| {
+            let node_read = node.read().unwrap();
+            visited.lock().unwrap().push(node_read.get_full_path());
+            if node_read.children.is_empty() {
+                WalkControl::Stop
+            } else {
+                WalkControl::Continue
+            }
+        });
+
+        let visited = visited.into_inner().unwrap();
+
+        assert_eq!(control, WalkControl::Stop);
+        /* root -> child -> first leaf */
+        assert_eq!(visited.len(), 3);
+    }
 }
@@ -0,0 +1,219 @@
+use crate::sync::RwLock;
+use crate::{node, MsFile, WzFile, WzImage, WzNode, WzNodeArc, WzObjectType};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+
+    #[error("Error reloading node: {0}")]
+    ReloadError(#[from] node::Error),
+
+    #[error("Node is not opened from a file")]
+    NotFileNode,
+}
+
+/// Re-open the file and swap the node's content in place, the children will be cleared and
+/// the node need to be parsed again. Other threads holding the old children are not affected,
+/// they keep the old file opened until dropped.
+///
+/// The node need to be a `WzFile`, `MsFile` or a `WzImage` opened from `.img` file, the iv
+/// of the old one will be used, and the patch version of `WzFile` will be detected again.
+pub fn reload_node<P>(node: &WzNodeArc, path: P) -> Result<(), WatchError>
+where
+    P: AsRef<Path>,
+{
+    let object_type: WzObjectType = match &node.read().unwrap().object_type {
+        WzObjectType::File(file) => WzFile::from_file(
+            &path,
+            Some(file.reader.wz_iv),
+            None,
+            Some(&file.reader.keys),
+        )
+        .map_err(node::Error::from)?
+        .into(),
+        WzObjectType::Image(image) => WzImage::from_file(&path, Some(image.reader.wz_iv))
+            .map_err(node::Error::from)?
+            .into(),
+        WzObjectType::MsFile(file) => {
+            let mut ms_file =
+                MsFile::from_file_with_cipher(&path, file.cipher).map_err(node::Error::from)?;
+            ms_file.name_map = file.name_map.clone();
+            ms_file.into()
+        }
+        _ => return Err(WatchError::NotFileNode),
+    };
+
+    let mut node_write = node.write().unwrap();
+    node_write.object_type = object_type;
+    node_write.children.clear();
+
+    Ok(())
+}
+
+/// modified time and size, changing either one means the file changed.
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn get_file_stamp(path: &Path) -> FileStamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[derive(Debug)]
+struct WatchedFile {
+    path: PathBuf,
+    node: Weak<RwLock<WzNode>>,
+    stamp: FileStamp,
+}
+
+/// Watch the files backing the nodes by polling the modified time and size,
+/// and reload the changed one with `reload_node`. The node dropped will be removed from the watch list.
+///
+/// Note: overwriting a mmaped file in place is undefined behavior on some platforms, the patcher or editor
+/// should write to a new file and rename it, which most of them already do.
+///
+/// # Example
+///
+/// ```no_run
+/// # use wz_reader::util::{resolve_base, FileWatcher};
+/// # use std::time::Duration;
+/// let base = resolve_base("path/to/Base.wz", None).unwrap();
+///
+/// let mut watcher = FileWatcher::new();
+/// watcher.watch_wz_files(&base);
+///
+/// let handle = watcher.spawn(Duration::from_secs(1), |path, result| {
+///     println!("reloaded {}: {result:?}", path.display());
+/// });
+///
+/// // ...
+///
+/// let watcher = handle.stop();
+/// ```
+#[derive(Debug, Default)]
+pub struct FileWatcher {
+    files: Vec<WatchedFile>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watch the file of the node, see `reload_node` for the supported nodes.
+    pub fn watch<P>(&mut self, node: &WzNodeArc, path: P)
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        self.files.push(WatchedFile {
+            stamp: get_file_stamp(&path),
+            path,
+            node: Arc::downgrade(node),
+        });
+    }
+
+    /// Watch every `WzFile` under the node(include itself) with it's own path, like the tree from `resolve_base`.
+    /// Returns the number of files added. It won't parse anything and won't look into the images.
+    pub fn watch_wz_files(&mut self, node: &WzNodeArc) -> usize {
+        let node_read = node.read().unwrap();
+
+        let mut count = 0;
+
+        match &node_read.object_type {
+            WzObjectType::File(file) => {
+                self.watch(node, &file.wz_file_meta.path);
+                count += 1;
+            }
+            WzObjectType::Directory(_) => {}
+            _ => return 0,
+        }
+
+        for child in node_read.children.values() {
+            count += self.watch_wz_files(child);
+        }
+
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Check the files once and reload the changed ones, returns the path and the reload result of them.
+    ///
+    /// A failed reload(like the file is still writing) will be retried when the file changed again.
+    pub fn poll(&mut self) -> Vec<(PathBuf, Result<(), WatchError>)> {
+        self.files.retain(|file| file.node.strong_count() > 0);
+
+        let mut reloaded = Vec::new();
+
+        for file in self.files.iter_mut() {
+            let stamp = get_file_stamp(&file.path);
+
+            /* the file might be removed and about to be replaced */
+            if stamp.is_none() || stamp == file.stamp {
+                continue;
+            }
+
+            file.stamp = stamp;
+
+            let Some(node) = file.node.upgrade() else {
+                continue;
+            };
+
+            reloaded.push((file.path.clone(), reload_node(&node, &file.path)));
+        }
+
+        reloaded
+    }
+
+    /// Poll in a new thread every `interval`, `on_reload` will be called for every reloaded file.
+    pub fn spawn<F>(mut self, interval: Duration, mut on_reload: F) -> WatchHandle
+    where
+        F: FnMut(&Path, Result<(), WatchError>) + Send + 'static,
+    {
+        let is_stopped = Arc::new(AtomicBool::new(false));
+        let thread_is_stopped = Arc::clone(&is_stopped);
+
+        let thread = std::thread::spawn(move || {
+            while !thread_is_stopped.load(Ordering::Relaxed) {
+                for (path, result) in self.poll() {
+                    on_reload(&path, result);
+                }
+                std::thread::park_timeout(interval);
+            }
+            self
+        });
+
+        WatchHandle { is_stopped, thread }
+    }
+}
+
+/// The handle of the polling thread from `FileWatcher::spawn`.
+#[derive(Debug)]
+pub struct WatchHandle {
+    is_stopped: Arc<AtomicBool>,
+    thread: JoinHandle<FileWatcher>,
+}
+
+impl WatchHandle {
+    /// Stop the polling thread and get the watcher back.
+    pub fn stop(self) -> FileWatcher {
+        self.is_stopped.store(true, Ordering::Relaxed);
+        self.thread.thread().unpark();
+        self.thread.join().unwrap()
+    }
+}
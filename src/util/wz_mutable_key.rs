@@ -1,7 +1,10 @@
 use super::maple_crypto_constants::{get_trimmed_user_key, MAPLESTORY_USERKEY_DEFAULT, WZ_MSEAIV};
-use crate::reader::read_i32_at;
-use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, BlockSizeUser, KeyInit};
+use crate::reader::{read_i32_at, SharedWzMutableKey};
+use crate::sync::RwLock;
+use aes::cipher::{Block, BlockEncrypt, KeyInit};
 use aes::Aes256;
+use hashbrown::HashMap;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 
 const BATCH_SIZE: f64 = 4096_f64;
 
@@ -9,7 +12,8 @@ const BATCH_SIZE: f64 = 4096_f64;
 #[derive(Debug)]
 pub struct WzMutableKey {
     pub iv: [u8; 4],
-    keys: Vec<u8>,
+    /// shared with the readers' snapshot, expanding will copy it if any snapshot still alive.
+    keys: Arc<Vec<u8>>,
     aes_key: [u8; 32],
     /// iv == 0, without decrypt
     pub without_decrypt: bool,
@@ -19,7 +23,7 @@ impl WzMutableKey {
     pub fn new(iv: [u8; 4], aes_key: [u8; 32]) -> Self {
         Self {
             iv,
-            keys: vec![],
+            keys: Arc::default(),
             aes_key,
             without_decrypt: read_i32_at(&iv, 0).unwrap_or(0) == 0,
         }
@@ -27,7 +31,7 @@ impl WzMutableKey {
     pub fn new_lua() -> Self {
         Self {
             iv: WZ_MSEAIV,
-            keys: vec![],
+            keys: Arc::default(),
             aes_key: get_trimmed_user_key(&MAPLESTORY_USERKEY_DEFAULT),
             without_decrypt: false,
         }
@@ -35,7 +39,7 @@ impl WzMutableKey {
     pub fn from_iv(iv: [u8; 4]) -> Self {
         Self {
             iv,
-            keys: vec![],
+            keys: Arc::default(),
             aes_key: get_trimmed_user_key(&MAPLESTORY_USERKEY_DEFAULT),
             without_decrypt: read_i32_at(&iv, 0).unwrap_or(0) == 0,
         }
@@ -56,6 +60,11 @@ impl WzMutableKey {
     pub fn get_range(&self, range: std::ops::Range<usize>) -> &[u8] {
         &self.keys[range]
     }
+    /// Get the current expanded keys, it won't change even the keys expanded later.
+    #[inline]
+    pub fn snapshot_keys(&self) -> Arc<Vec<u8>> {
+        Arc::clone(&self.keys)
+    }
     #[inline]
     pub fn is_enough(&self, size: usize) -> bool {
         self.keys.len() >= size
@@ -78,54 +87,75 @@ impl WzMutableKey {
 
         let size = (((size as f64) / BATCH_SIZE).ceil() * BATCH_SIZE) as usize;
 
-        if self.keys.capacity() < size {
-            self.keys.reserve(size - self.keys.capacity());
+        let keys = Arc::make_mut(&mut self.keys);
+
+        if keys.capacity() < size {
+            keys.reserve(size - keys.capacity());
         }
 
-        // initialize the first block
-        if self.keys.is_empty() {
-            self.keys.resize(32, 0);
+        // the key schedule only computed once here, the `aes` crate will pick AES-NI or ARMv8 instructions at runtime when available
+        let cipher = Aes256::new(&self.aes_key.into());
 
-            let mut block = [0_u8; 16];
-            for (index, item) in block.iter_mut().enumerate() {
-                *item = self.iv[index % 4];
+        // the first block is the iv repeated
+        let mut block = Block::<Aes256>::default();
+        match keys.len() {
+            0 => {
+                for (index, item) in block.iter_mut().enumerate() {
+                    *item = self.iv[index % 4];
+                }
             }
-            ecb::Encryptor::<Aes256>::new(&self.aes_key.into())
-                .encrypt_padded_b2b_mut::<Pkcs7>(&block, &mut self.keys)
-                .map_err(|_| "Failed to encrypt block")?;
+            len => block.copy_from_slice(&keys[len - 16..]),
+        }
 
-            self.keys.truncate(16);
+        // every block is the encryption of the previous one, so it can't be done in parallel
+        while keys.len() < size {
+            cipher.encrypt_block(&mut block);
+            keys.extend_from_slice(&block);
         }
 
-        let start_index = self.keys.len();
+        keys.truncate(size);
 
-        // fill enouth 0 for later,
-        if self.keys.len() < size {
-            // + 16 is prevent encryption not enough to padding
-            self.keys.resize(size + 16, 0);
-        }
+        Ok(())
+    }
+}
 
-        let block_size = aes::Aes256::block_size();
+type SharedKeyRegistry = Mutex<HashMap<[u8; 4], Weak<RwLock<WzMutableKey>>>>;
 
-        for i in (start_index..size).step_by(16) {
-            let (in_buf, out_buf) = self.keys.split_at_mut(i);
-            ecb::Encryptor::<Aes256>::new(&self.aes_key.into())
-                // im not sure why this will actually append block_size * 2 to out_buf, so will be trimed at the end
-                .encrypt_padded_b2b_mut::<Pkcs7>(&in_buf[i - block_size..], out_buf)
-                .map_err(|_| "Failed to encrypt block")?;
-        }
+static SHARED_KEYS: OnceLock<SharedKeyRegistry> = OnceLock::new();
 
-        if self.keys.len() > size {
-            self.keys.truncate(size);
-        }
+/// Get the keys of the iv(with default user key) that shared by every reader using the same iv,
+/// so opening dozens of files only expand the key stream once.
+///
+/// The registry only hold a weak reference, the keys will be dropped after all the readers dropped.
+///
+/// # Example
+///
+/// ```
+/// # use wz_reader::util::{get_shared_keys, maple_crypto_constants::WZ_GMSIV};
+/// # use std::sync::Arc;
+/// let keys = get_shared_keys(WZ_GMSIV);
+///
+/// assert!(Arc::ptr_eq(&keys, &get_shared_keys(WZ_GMSIV)));
+/// ```
+pub fn get_shared_keys(iv: [u8; 4]) -> SharedWzMutableKey {
+    let mut registry = SHARED_KEYS.get_or_init(Default::default).lock().unwrap();
 
-        Ok(())
+    if let Some(keys) = registry.get(&iv).and_then(Weak::upgrade) {
+        return keys;
     }
+
+    let keys = Arc::new(RwLock::new(WzMutableKey::from_iv(iv)));
+
+    registry.retain(|_, keys| keys.strong_count() > 0);
+    registry.insert(iv, Arc::downgrade(&keys));
+
+    keys
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::util::maple_crypto_constants::WZ_GMSIV;
 
     #[test]
     fn test_expand_key() {
@@ -173,4 +203,54 @@ mod test {
         assert!(key.try_at(10000).is_some());
         assert!(key.try_at(20000).is_none());
     }
+
+    #[test]
+    fn test_key_stream() {
+        let mut key = WzMutableKey::from_iv(WZ_GMSIV);
+
+        assert!(key.ensure_key_size(16).is_ok());
+        assert_eq!(
+            key.get_range(0..16),
+            [150, 174, 63, 164, 72, 250, 221, 144, 70, 118, 5, 97, 151, 206, 120, 104]
+        );
+
+        /* continue from the last block */
+        assert!(key.ensure_key_size(8200).is_ok());
+        assert_eq!(
+            key.get_range(4090..4100),
+            [235, 137, 248, 89, 225, 255, 211, 249, 92, 118]
+        );
+        assert_eq!(
+            key.get_range(8180..8192),
+            [210, 51, 190, 170, 223, 15, 179, 119, 205, 12, 143, 106]
+        );
+
+        let mut lua_key = WzMutableKey::new_lua();
+        assert!(lua_key.ensure_key_size(32).is_ok());
+        assert_eq!(
+            lua_key.get_range(16..32),
+            [149, 116, 54, 108, 179, 89, 41, 94, 162, 89, 3, 126, 191, 125, 203, 116]
+        );
+    }
+
+    #[test]
+    fn test_shared_keys() {
+        let iv = [1, 2, 3, 4];
+
+        let keys = get_shared_keys(iv);
+        let other = get_shared_keys(iv);
+
+        assert!(Arc::ptr_eq(&keys, &other));
+        assert!(!Arc::ptr_eq(&keys, &get_shared_keys([4, 3, 2, 1])));
+
+        /* expanded by one, visible to other */
+        assert!(keys.write().unwrap().ensure_key_size(16).is_ok());
+        assert!(other.read().unwrap().is_enough(16));
+
+        drop(keys);
+        drop(other);
+
+        let new_keys = get_shared_keys(iv);
+        assert!(!new_keys.read().unwrap().is_enough(16));
+    }
 }
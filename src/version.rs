@@ -1,7 +1,7 @@
 use crate::util::maple_crypto_constants::{WZ_GMSIV, WZ_MSEAIV};
-use crate::util::wz_mutable_key::WzMutableKey;
+use crate::util::wz_mutable_key::{get_shared_keys, WzMutableKey};
+use crate::wz_image::is_valid_wz_image;
 use crate::{reader, Reader, WzHeader, WzSliceReader};
-use std::sync::{Arc, RwLock};
 
 pub fn get_iv_by_maple_version(version: WzMapleVersion) -> [u8; 4] {
     match version {
@@ -35,19 +35,111 @@ pub enum WzMapleVersion {
     UNKNOWN,
 }
 
-/// Verify IV from wz image
-pub fn verify_iv_from_wz_img(buf: &[u8], iv: &[u8; 4]) -> bool {
-    let reader = WzSliceReader::new(buf, &Arc::new(RwLock::new(WzMutableKey::from_iv(*iv))));
+/// How many children of the image are checked in `verify_iv_from_wz_img`.
+const VERIFY_IMG_ENTRY_COUNT: i32 = 4;
+
+/// Read a string block and make sure it's a valid string, the image start at 0.
+fn verify_string_block(reader: &WzSliceReader) -> Result<String, reader::Error> {
+    let meta = match reader.read_u8()? {
+        0 | 0x73 => reader.read_wz_string_meta()?,
+        1 | 0x1B => {
+            let offset = usize::try_from(reader.read_i32()?)
+                .map_err(|_| reader::Error::DecryptError(reader.pos.get()))?;
+            reader.read_wz_string_meta_at(offset)?
+        }
+        _ => return Err(reader::Error::DecryptError(reader.pos.get())),
+    };
+
+    reader.try_resolve_wz_string_meta(&meta.string_type, meta.offset, meta.length as usize)
+}
+
+/// Check the header and the first few children, skip the values without parsing them.
+fn verify_property_image(reader: &WzSliceReader) -> Result<(), reader::Error> {
+    /* the header is either 0x73 with the string right after, or 0x1B with a offset to the string */
+    if verify_string_block(reader)? != "Property" || reader.read_u16()? != 0 {
+        return Err(reader::Error::DecryptError(reader.pos.get()));
+    }
+
+    let entry_count = reader.read_wz_int()?;
+
+    if !(0..=1000000).contains(&entry_count) {
+        return Err(reader::Error::DecryptError(reader.pos.get()));
+    }
+
+    for _ in 0..entry_count.min(VERIFY_IMG_ENTRY_COUNT) {
+        if verify_string_block(reader)?.is_empty() {
+            return Err(reader::Error::DecryptError(reader.pos.get()));
+        }
+
+        match reader.read_u8()? {
+            0 => {}
+            2 | 11 => reader.try_skip(2)?,
+            3 | 19 => {
+                reader.read_wz_int()?;
+            }
+            20 => {
+                reader.read_wz_int64()?;
+            }
+            4 => {
+                if reader.read_u8()? == 0x80 {
+                    reader.try_skip(4)?;
+                }
+            }
+            5 => reader.try_skip(8)?,
+            8 => {
+                verify_string_block(reader)?;
+            }
+            9 => {
+                let block_size = reader.read_u32()?;
+                reader.try_skip(block_size as usize)?;
+            }
+            _ => return Err(reader::Error::DecryptError(reader.pos.get())),
+        }
+    }
 
-    reader.pos.set(1);
+    Ok(())
+}
+
+/// The lua image store the script right after the length, check the beginning of it is valid utf8.
+fn verify_lua_image(reader: &WzSliceReader, iv: [u8; 4]) -> Result<(), reader::Error> {
+    reader.try_skip(1)?;
+    let len = usize::try_from(reader.read_wz_int()?)
+        .map_err(|_| reader::Error::DecryptError(reader.pos.get()))?;
+    let len = len.min(64);
+    reader.check_range(reader.pos.get(), len)?;
+
+    let mut decoded = reader.get_slice_from_current(len).to_vec();
+    let mut keys = WzMutableKey::from_iv(iv);
+    keys.ensure_key_size(len)
+        .map_err(|_| reader::Error::DecryptError(reader.pos.get()))?;
+    keys.decrypt_slice(&mut decoded);
+
+    match std::str::from_utf8(&decoded) {
+        Ok(_) => Ok(()),
+        /* the char is just cut at the end */
+        Err(e) if e.error_len().is_none() => Ok(()),
+        Err(_) => Err(reader::Error::DecryptError(reader.pos.get())),
+    }
+}
+
+/// Verify IV from wz image, it checks the `Property` header, the entry count and the first few children
+/// are decoded correctly. The lua image is checked by decoding the beginning of the script.
+pub fn verify_iv_from_wz_img(buf: &[u8], iv: &[u8; 4]) -> bool {
+    let reader = WzSliceReader::new(buf, &get_shared_keys(*iv));
 
-    reader.read_wz_string().unwrap_or_default() == "Property"
+    match buf.first() {
+        Some(0x1) => verify_lua_image(&reader, *iv).is_ok(),
+        Some(_) => verify_property_image(&reader).is_ok(),
+        None => false,
+    }
 }
 
 /// Try to guess IV from wz image use fixed value. Currently will try GMS, EMS, BMS.
 pub fn guess_iv_from_wz_img(buf: &[u8]) -> Option<[u8; 4]> {
-    // not support other then WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET
-    if buf[0] != 0x73 {
+    if !buf
+        .first()
+        .is_some_and(|byte| is_valid_wz_image(*byte) || *byte == 0x1)
+    {
         return None;
     }
 
@@ -68,7 +160,7 @@ pub fn guess_iv_from_wz_img(buf: &[u8]) -> Option<[u8; 4]> {
 }
 
 pub fn verify_iv_from_wz_file(buf: &[u8], iv: &[u8; 4]) -> Result<(), reader::Error> {
-    let reader = WzSliceReader::new(buf, &Arc::new(RwLock::new(WzMutableKey::from_iv(*iv))));
+    let reader = WzSliceReader::new(buf, &get_shared_keys(*iv));
 
     let fstart = WzHeader::get_wz_fstart(buf)? as usize;
 
@@ -1,4 +1,5 @@
 use crate::property::{WzLua, WzRawData};
+use crate::util::WzDeferredProperty;
 use crate::version::{guess_iv_from_wz_img, verify_iv_from_wz_img};
 use crate::{reader, util, WzNode, WzNodeArc, WzNodeArcVec, WzNodeName, WzReader};
 use std::sync::Arc;
@@ -26,12 +27,15 @@ pub enum Error {
     ReaderError(#[from] reader::Error),
     #[error("Not a Image object")]
     NotImageObject,
+    #[error("Checksum mismatch, expected {0}, actual {1}")]
+    ChecksumMismatch(i32, i32),
 }
 
 pub const WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET: u8 = 0x73;
 pub const WZ_IMAGE_HEADER_BYTE_WITH_OFFSET: u8 = 0x1B;
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", serde(default))]
 #[derive(Debug, Clone, Default)]
 pub struct WzImage {
@@ -45,6 +49,9 @@ pub struct WzImage {
     pub block_size: usize,
     #[cfg_attr(feature = "serde", serde(skip))]
     pub is_parsed: bool,
+    /// the checksum stored in the `WzDirectory` entry, `None` when the image is not from a wz file.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub checksum: Option<i32>,
 }
 
 impl WzImage {
@@ -60,8 +67,13 @@ impl WzImage {
             offset,
             block_size,
             is_parsed: false,
+            checksum: None,
         }
     }
+    pub fn with_checksum(mut self, checksum: i32) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
     pub fn from_file<P>(path: P, wz_iv: Option<[u8; 4]>) -> Result<Self, Error>
     where
         P: AsRef<std::path::Path>,
@@ -73,8 +85,7 @@ impl WzImage {
             .to_str()
             .unwrap()
             .to_string();
-        let file = std::fs::File::open(path)?;
-        let map = unsafe { memmap2::Mmap::map(&file)? };
+        let map = reader::open_reader_buffer(path)?;
 
         let wz_iv = if let Some(iv) = wz_iv {
             if !verify_iv_from_wz_img(&map, &iv) {
@@ -94,22 +105,66 @@ impl WzImage {
             offset: 0,
             block_size,
             is_parsed: false,
+            checksum: None,
         })
     }
 
+    /// Copy the image data into a owned buffer, so it no longer reference the file(or mmap).
+    /// The returned image is not parsed.
+    pub fn to_owned_buffer(&self) -> WzImage {
+        let data = self
+            .reader
+            .get_slice(self.offset..self.offset + self.block_size);
+
+        let mut reader = WzReader::from_buff(data);
+        reader.wz_iv = self.reader.wz_iv;
+        let reader = reader.with_existing_keys(self.reader.keys.clone());
+
+        WzImage {
+            reader: Arc::new(reader),
+            name: self.name.clone(),
+            offset: 0,
+            block_size: self.block_size,
+            is_parsed: false,
+            checksum: self.checksum,
+        }
+    }
+
+    /// Compute the checksum of the image data, it's the sum of every byte.
+    pub fn compute_checksum(&self) -> i32 {
+        self.reader
+            .get_slice(self.offset..self.offset + self.block_size)
+            .iter()
+            .fold(0_i32, |sum, &byte| sum.wrapping_add(byte as i32))
+    }
+
+    /// Compute the checksum and compare with the stored one, returns the computed checksum.
+    /// Image without stored checksum always pass.
+    pub fn verify_checksum(&self) -> Result<i32, Error> {
+        let checksum = self.compute_checksum();
+
+        match self.checksum {
+            Some(expected) if expected != checksum => {
+                Err(Error::ChecksumMismatch(expected, checksum))
+            }
+            _ => Ok(checksum),
+        }
+    }
+
     /// Direct get child node inside `WzImage` without parsing the whole `WzImage`. Sometimes
     /// we just need a single node in `WzImage`, but don't want to parse it and
     /// unparse later, it waste time and memory.
     pub fn at_path(&self, path: &str) -> Result<WzNodeArc, Error> {
         let reader = self.reader.create_slice_reader_without_hash();
 
-        reader.seek(self.offset);
+        reader.try_seek(self.offset)?;
         let header_byte = reader.read_u8()?;
 
-        if header_byte != WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET {
+        if !is_valid_wz_image(header_byte) {
             return Err(Error::UnknownImageHeader(header_byte, reader.pos.get()));
         } else {
-            let name = reader.read_wz_string()?;
+            reader.try_seek(self.offset)?;
+            let name = reader.read_wz_string_block(self.offset)?;
             let value = reader.read_u16()?;
             if name != "Property" && value != 0 {
                 return Err(Error::ParseError(reader.pos.get()));
@@ -129,9 +184,47 @@ impl WzImage {
         &self,
         parent: Option<&WzNodeArc>,
     ) -> Result<(WzNodeArcVec, Vec<WzNodeArc>), Error> {
+        let (childs, uol_nodes, _) = self.resolve_children_to_depth(parent, usize::MAX)?;
+        Ok((childs, uol_nodes))
+    }
+
+    /// Only parse the first `max_depth` levels of properties, the deeper `Property` blocks are created without children
+    /// and returned as `WzDeferredProperty`, expand them when needed. `max_depth` 1 means only the direct children.
+    ///
+    /// The children of canvas and convex are always parsed, they can't be skipped. The uol nodes are kept
+    /// since the target may not be parsed yet, resolve them after expanded, like `resolve_uol_target`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use wz_reader::WzNode;
+    /// let wz_img = WzNode::from_img_file("path/to/Map/Map0/000010000.img", None, None).unwrap().into_lock();
+    /// let mut deferred = wz_img.write().unwrap().parse_to_depth(&wz_img, 1).unwrap();
+    ///
+    /// // expand the `back` when the user open it
+    /// let back = wz_img.read().unwrap().at("back").unwrap();
+    /// if let Some(index) = deferred.iter().position(|d| d.node.upgrade().is_some_and(|n| std::sync::Arc::ptr_eq(&n, &back))) {
+    ///     let block = deferred.swap_remove(index);
+    ///     deferred.extend(block.expand(1).unwrap());
+    /// }
+    /// ```
+    pub fn parse_to_depth(
+        &self,
+        parent: Option<&WzNodeArc>,
+        max_depth: usize,
+    ) -> Result<(WzNodeArcVec, Vec<WzDeferredProperty>), Error> {
+        let (childs, _, deferred) = self.resolve_children_to_depth(parent, max_depth)?;
+        Ok((childs, deferred))
+    }
+
+    fn resolve_children_to_depth(
+        &self,
+        parent: Option<&WzNodeArc>,
+        max_depth: usize,
+    ) -> Result<(WzNodeArcVec, Vec<WzNodeArc>, Vec<WzDeferredProperty>), Error> {
         let reader = self.reader.create_slice_reader_without_hash();
 
-        reader.seek(self.offset);
+        reader.try_seek(self.offset)?;
 
         let header_byte = reader.read_u8()?;
 
@@ -142,7 +235,7 @@ impl WzImage {
             let wz_raw_data = WzRawData::new(&self.reader, self.offset, self.block_size);
             let raw_data_node = WzNode::new(&name, wz_raw_data, parent);
 
-            return Ok((vec![(name, raw_data_node.into_lock())], vec![]));
+            return Ok((vec![(name, raw_data_node.into_lock())], vec![], vec![]));
         }
 
         match header_byte {
@@ -157,7 +250,7 @@ impl WzImage {
 
                     let lua_node = WzNode::new(&name, wz_lua, parent);
 
-                    return Ok((vec![(name, lua_node.into_lock())], vec![]));
+                    return Ok((vec![(name, lua_node.into_lock())], vec![], vec![]));
                 }
                 return Err(Error::LuaParseError);
             }
@@ -179,10 +272,12 @@ impl WzImage {
                     WzRawData::new(&self.reader, self.offset + 9, self.block_size - 9);
                 let raw_data_node = WzNode::new(&name, wz_raw_data, parent);
 
-                return Ok((vec![(name, raw_data_node.into_lock())], vec![]));
+                return Ok((vec![(name, raw_data_node.into_lock())], vec![], vec![]));
             }
-            WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET => {
-                let name = reader.read_wz_string()?;
+            WZ_IMAGE_HEADER_BYTE_WITHOUT_OFFSET | WZ_IMAGE_HEADER_BYTE_WITH_OFFSET => {
+                /* the 0x1B header store a offset to the "Property" string instead */
+                reader.try_seek(self.offset)?;
+                let name = reader.read_wz_string_block(self.offset)?;
                 let value = reader.read_u16()?;
                 if name != "Property" && value != 0 {
                     return Err(Error::WrongVersion);
@@ -193,7 +288,28 @@ impl WzImage {
             }
         }
 
-        util::parse_property_list(parent, &self.reader, &reader, self.offset).map_err(Error::from)
+        util::parse_property_list_to_depth(parent, &self.reader, &reader, self.offset, max_depth)
+            .map_err(Error::from)
+    }
+
+    /// The type byte of every property in the image keyed by the path relative to the image, see `WzNode::to_debug_json`.
+    /// It's empty for the lua and text images.
+    #[cfg(feature = "json")]
+    pub(crate) fn read_property_types(&self) -> Result<hashbrown::HashMap<String, u8>, Error> {
+        let reader = self.reader.create_slice_reader_without_hash();
+
+        reader.try_seek(self.offset)?;
+
+        if self.name.ends_with(".txt") || !is_valid_wz_image(reader.read_u8()?) {
+            return Ok(Default::default());
+        }
+
+        reader.try_seek(self.offset)?;
+        reader.read_wz_string_block(self.offset)?;
+        reader.try_skip(2)?;
+
+        util::parse_property::read_property_types(&self.reader, &reader, self.offset)
+            .map_err(Error::from)
     }
 }
 
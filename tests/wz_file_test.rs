@@ -1,12 +1,14 @@
 use wz_reader::property::{Vector2D, WzValue};
 use wz_reader::util::{self, node_util};
 use wz_reader::version::WzMapleVersion;
-use wz_reader::{node, wz_image, WzNode, WzNodeArc, WzNodeCast, WzObjectType};
+use wz_reader::{
+    file, node, wz_image, WzFile, WzNode, WzNodeArc, WzNodeCast, WzObjectType, WzReader,
+};
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
-/**
+/*
  * the test.wz file structure:
  *   test
  *     - wz_img.img
@@ -82,6 +84,29 @@ fn should_parsing_with_patch_version() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn should_error_on_truncated_header() -> Result<()> {
+    let buf = std::fs::read("tests/test.wz")?;
+    let fstart = WzReader::from_buff(&buf).get_wz_fstart()? as usize;
+
+    /* the header is intact but the encver is cut off */
+    let reader = WzReader::from_buff(&buf[..fstart + 1]);
+    let mut wz_file = WzFile {
+        offset: fstart + 2,
+        block_size: fstart + 1,
+        reader: std::sync::Arc::new(reader),
+        ..Default::default()
+    };
+    let parent = WzNode::from_str("test", 1, None).into_lock();
+
+    assert!(matches!(
+        wz_file.parse(&parent, None),
+        Err(file::Error::ReaderError(_))
+    ));
+
+    Ok(())
+}
+
 fn check_sample_wz_dir(wz_dir: &WzNodeArc) -> Result<()> {
     let wz_dir = wz_dir.read().unwrap();
 
@@ -452,6 +477,19 @@ fn should_guessing_iv() -> Result<()> {
 
     make_sure_wz_file_version(&wz_file, 123);
 
+    let int = wz_file.read().unwrap().at_path_parsed("wz_img.img/1/int")?;
+    let int_read = int.read().unwrap();
+    assert_eq!(int_read.wz_iv(), Some([0xB9, 0x7D, 0x63, 0xE9]));
+    assert_eq!(int_read.wz_patch_version(), Some(123));
+    assert_eq!(
+        int_read.wz_file_meta().map(|meta| meta.path),
+        Some(r"tests/test_need_iv.wz".to_string())
+    );
+
+    let standalone = WzNode::from_str("standalone", 1, None);
+    assert_eq!(standalone.wz_iv(), None);
+    assert_eq!(standalone.wz_patch_version(), None);
+
     Ok(())
 }
 
@@ -497,3 +535,633 @@ fn should_success_walk_thorugh_with_iv() {
         assert!(pathes.contains(node_read.get_full_path().as_str()));
     });
 }
+
+#[test]
+fn should_parse_all() {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    );
+    assert!(wz_file.is_ok());
+
+    let wz_file = wz_file.unwrap().into_lock();
+
+    let progress = std::sync::Mutex::new(Vec::new());
+
+    let errors = util::parse_all_with_progress(&wz_file, &|parsed, total| {
+        progress.lock().unwrap().push((parsed, total));
+    });
+
+    assert!(errors.is_empty());
+
+    let mut progress = progress.into_inner().unwrap();
+    progress.sort();
+    assert_eq!(progress, vec![(1, 2), (2, 2)]);
+
+    let wz_file = wz_file.read().unwrap();
+
+    assert!(wz_file.at_path("wz_img.img/1/int").is_some());
+    assert!(wz_file.at_path("wz_dir/wz_img_under_dir.img/hi").is_some());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn should_export_images() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    let dir = tempfile::tempdir()?;
+
+    let result = util::export_images(&wz_file, dir.path(), image::ImageFormat::Png)?;
+
+    assert!(result.failed.is_empty());
+    assert_eq!(result.exported.len(), 1);
+
+    let file = dir.path().join("wz_img.img/conv/1.png");
+    assert_eq!(result.exported[0].file, file);
+    assert!(file.exists());
+
+    /* image is unparsed after exported */
+    let wz_img = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    assert!(wz_img.read().unwrap().children.is_empty());
+
+    Ok(())
+}
+
+#[cfg(all(feature = "json", feature = "image"))]
+#[test]
+fn should_dump_node() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    let dir = tempfile::tempdir()?;
+
+    let result = util::dump_node(&wz_file, dir.path(), image::ImageFormat::Png)?;
+
+    assert!(result.failed.is_empty());
+    assert_eq!(result.exported.len(), 3);
+
+    assert!(dir.path().join("wz_img.img.json").exists());
+    assert!(dir.path().join("wz_img.img/conv/1.png").exists());
+    assert!(dir.path().join("wz_dir/wz_img_under_dir.img.json").exists());
+
+    let json = std::fs::read_to_string(dir.path().join("wz_dir/wz_img_under_dir.img.json"))?;
+    assert_eq!(json, r#"{"hi":1}"#);
+
+    let manifest = std::fs::read_to_string(dir.path().join("manifest.csv"))?;
+    assert_eq!(manifest.lines().count(), 4);
+
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn should_export_json() -> Result<()> {
+    use wz_reader::util::JsonExportLayout;
+
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    let dir = tempfile::tempdir()?;
+
+    let result = util::export_json(&wz_file, dir.path(), JsonExportLayout::PerImage)?;
+
+    assert!(result.failed.is_empty());
+    assert_eq!(result.exported.len(), 2);
+
+    let json = std::fs::read_to_string(dir.path().join("wz_dir/wz_img_under_dir.img.json"))?;
+    assert_eq!(json, r#"{"hi":1}"#);
+    assert!(dir.path().join("wz_img.img.json").exists());
+
+    /* image should be unparsed after exported */
+    let image = wz_file.read().unwrap().at_path("wz_img.img").unwrap();
+    assert!(image.read().unwrap().children.is_empty());
+
+    let dir = tempfile::tempdir()?;
+
+    let result = util::export_json(&wz_file, dir.path(), JsonExportLayout::Shards(2))?;
+
+    assert!(result.failed.is_empty());
+    assert_eq!(result.exported.len(), 2);
+
+    let lines = ["shard-0.jsonl", "shard-1.jsonl"]
+        .iter()
+        .map(|file| std::fs::read_to_string(dir.path().join(file)))
+        .collect::<std::io::Result<Vec<_>>>()?
+        .concat();
+
+    assert_eq!(lines.lines().count(), 2);
+    assert!(lines.contains(r#""data":{"hi":1}"#));
+
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn should_transcode_canvases() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    util::parse_all(&wz_file);
+
+    let canvas = wz_file
+        .read()
+        .unwrap()
+        .at_path("wz_img.img/conv/1")
+        .unwrap();
+    let original = canvas.read().unwrap().try_as_png().unwrap().extract_png()?;
+
+    let result = util::transcode_canvases(&wz_file, 2);
+
+    assert!(result.failed.is_empty());
+    assert_eq!(result.exported.len() + result.skipped.len(), 1);
+
+    let canvas = canvas.read().unwrap();
+    let png = canvas.try_as_png().unwrap();
+
+    assert_eq!(png.format(), 2);
+    assert_eq!(png.extract_png()?.to_rgba8(), original.to_rgba8());
+    /* children like origin are kept */
+    assert!(canvas.at("origin").is_some());
+
+    Ok(())
+}
+
+#[test]
+fn should_keep_uol_with_policy() -> Result<()> {
+    use wz_reader::util::node_util::UolPolicy;
+
+    for policy in [UolPolicy::KeepAndAnnotate, UolPolicy::LazyOnAccess] {
+        let wz_file = WzNode::from_wz_file_full(
+            r"tests/test.wz",
+            Some(WzMapleVersion::BMS),
+            Some(123),
+            None,
+            None,
+        )?
+        .into_lock();
+
+        node_util::parse_node(&wz_file)?;
+
+        let wz_img = wz_file.read().unwrap().at("wz_img.img").unwrap();
+        wz_img
+            .write()
+            .unwrap()
+            .parse_with_uol_policy(&wz_img, policy)?;
+
+        let uol = wz_img.read().unwrap().at_path("2/uol").unwrap();
+
+        assert!(uol.read().unwrap().try_as_uol().is_some());
+        assert_eq!(
+            uol.read().unwrap().uol_target().is_some(),
+            policy == UolPolicy::KeepAndAnnotate
+        );
+
+        let target = node_util::resolve_uol_target(&uol).unwrap();
+        assert_eq!(
+            wz_reader::property::resolve_string_from_node(&target)?,
+            "foo"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn should_pass_uol_policy_to_parse_all_and_walk() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use wz_reader::util::node_util::UolPolicy;
+
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    assert!(util::parse_all_with_uol_policy(&wz_file, UolPolicy::KeepAndAnnotate).is_empty());
+
+    let uol = wz_file.read().unwrap().at_path("wz_img.img/2/uol").unwrap();
+    assert!(uol.read().unwrap().uol_target().is_some());
+
+    wz_file
+        .read()
+        .unwrap()
+        .at("wz_img.img")
+        .unwrap()
+        .write()
+        .unwrap()
+        .unparse();
+
+    let kept = AtomicUsize::new(0);
+    util::walk_node(&wz_file, UolPolicy::KeepAndAnnotate, &|node| {
+        if node.read().unwrap().uol_target().is_some() {
+            kept.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    assert_eq!(kept.load(Ordering::Relaxed), 1);
+
+    /* the default policy replaces it */
+    let replaced = AtomicUsize::new(0);
+    util::walk_node(&wz_file, true, &|node| {
+        if node.read().unwrap().try_as_uol().is_some() {
+            replaced.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    assert_eq!(replaced.load(Ordering::Relaxed), 0);
+
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn should_parse_async() -> Result<()> {
+    let iv = wz_reader::version::get_iv_by_maple_version(WzMapleVersion::BMS);
+    let wz_file =
+        wz_reader::WzFile::open_async(r"tests/test.wz", Some(iv), Some(123), None).await?;
+    let wz_file = WzNode::new(&"test".into(), wz_file, None).into_lock();
+
+    node_util::parse_async(&wz_file).await?;
+
+    let image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    node_util::parse_async(&image).await?;
+
+    assert!(image.read().unwrap().at_path("conv/1").is_some());
+
+    Ok(())
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn should_decode_handles_in_other_thread() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    util::parse_all(&wz_file);
+
+    let images = util::collect_image_handles(&wz_file);
+    let leaves = util::collect_leaf_handles(&wz_file);
+
+    assert_eq!(images.len(), 2);
+    assert_eq!(leaves.len(), 1);
+
+    /* modify the tree while decoding */
+    let mut wz_file_write = wz_file.write().unwrap();
+
+    let handle = std::thread::spawn(move || {
+        let image = images
+            .iter()
+            .find(|image| image.path == "test/wz_img.img")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(image.read().unwrap().at_path("conv/1").is_some());
+
+        match &leaves[0].data {
+            util::LeafData::PNG(png) => png.extract_png().is_ok(),
+            _ => false,
+        }
+    });
+
+    wz_file_write.children.clear();
+
+    assert!(handle.join().unwrap());
+
+    Ok(())
+}
+
+#[test]
+fn should_parse_frozen_image_once() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    node_util::parse_node(&wz_file)?;
+    let image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    let frozen = util::FrozenImage::from_node(&image).unwrap();
+
+    assert!(!frozen.is_parsed());
+
+    let parsed = std::thread::scope(|s| {
+        let handles = (0..4)
+            .map(|_| s.spawn(|| std::sync::Arc::clone(frozen.get().unwrap())))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    assert!(frozen.is_parsed());
+    assert!(parsed
+        .iter()
+        .all(|node| std::sync::Arc::ptr_eq(node, &parsed[0])));
+    assert!(parsed[0].at_path("conv/1/origin").is_some());
+
+    /* the original node is untouched */
+    assert!(image.read().unwrap().children.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn should_verify_image_checksums() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    let (checksums, errors) = util::verify_checksums(&wz_file);
+
+    assert!(errors.is_empty());
+    assert_eq!(checksums.len(), 2);
+    for checksum in &checksums {
+        assert!(checksum.expected.is_some());
+        assert!(checksum.is_valid(), "{} checksum mismatch", checksum.path);
+    }
+
+    /* the images are not parsed */
+    let image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    assert!(image.read().unwrap().children.is_empty());
+
+    let mut wz_image = image.read().unwrap().try_as_image().unwrap().clone();
+    let actual = wz_image.verify_checksum()?;
+
+    wz_image.checksum = Some(actual + 1);
+    assert!(matches!(
+        wz_image.verify_checksum(),
+        Err(wz_image::Error::ChecksumMismatch(expected, _)) if expected == actual + 1
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn should_read_directory_entries() -> Result<()> {
+    use wz_reader::WzDirectoryEntryType;
+
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+    wz_file.write().unwrap().parse(&wz_file)?;
+
+    let wz_dir = wz_file.read().unwrap().at("wz_dir").unwrap();
+    let wz_dir_read = wz_dir.read().unwrap();
+    let directory = wz_dir_read.try_as_directory().unwrap();
+    assert!(directory.checksum.is_some());
+
+    let entries = directory.read_entries()?;
+    assert_eq!(entries.len(), 1);
+
+    let entry = &entries[0];
+    assert_eq!(entry.entry_type, WzDirectoryEntryType::Image);
+    assert_eq!(entry.name.as_str(), "wz_img_under_dir.img");
+    assert!(entry.entry_offset >= directory.offset);
+
+    let image_node = wz_dir_read.at("wz_img_under_dir.img").unwrap();
+    let image_read = image_node.read().unwrap();
+    let image = image_read.try_as_image().unwrap();
+    assert_eq!(entry.offset, image.offset);
+    assert_eq!(entry.block_size, image.block_size);
+    assert_eq!(Some(entry.checksum), image.checksum);
+
+    Ok(())
+}
+
+#[test]
+fn should_collect_parse_stats() -> Result<()> {
+    let wz_file = WzNode::from_wz_file_full(
+        r"tests/test.wz",
+        Some(WzMapleVersion::BMS),
+        Some(123),
+        None,
+        None,
+    )?
+    .into_lock();
+
+    let stats = std::sync::Arc::new(util::ParseStats::default());
+    let errors = stats.collect(|| util::parse_all(&wz_file));
+
+    assert!(errors.is_empty());
+    assert_eq!(stats.images_parsed(), 2);
+    assert_eq!(stats.node_count("Image"), 2);
+    assert_eq!(stats.node_count("Directory"), 1);
+    assert_eq!(stats.node_count("PNG"), 1);
+    assert!(stats.time_of(util::ParseStage::Image) > std::time::Duration::ZERO);
+
+    Ok(())
+}
+
+#[test]
+fn should_reload_changed_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("test.wz");
+    std::fs::copy("tests/test.wz", &path)?;
+
+    let wz_file = WzNode::from_wz_file(&path, None)?.into_lock();
+    node_util::parse_node(&wz_file)?;
+    let old_image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+
+    let mut watcher = util::FileWatcher::new();
+    assert_eq!(watcher.watch_wz_files(&wz_file), 1);
+    assert!(watcher.poll().is_empty());
+
+    /* replace the file like a patcher does */
+    let mut data = std::fs::read("tests/test.wz")?;
+    data.extend_from_slice(&[0; 16]);
+    let new_path = dir.path().join("test.wz.new");
+    std::fs::write(&new_path, data)?;
+    std::fs::rename(&new_path, &path)?;
+
+    let reloaded = watcher.poll();
+    assert_eq!(reloaded.len(), 1);
+    assert_eq!(reloaded[0].0, path);
+    assert!(reloaded[0].1.is_ok());
+
+    /* same node, need to parse again */
+    assert!(wz_file.read().unwrap().children.is_empty());
+    node_util::parse_node(&wz_file)?;
+    let new_image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    assert!(!std::sync::Arc::ptr_eq(&old_image, &new_image));
+
+    /* the old one still readable */
+    node_util::parse_node(&old_image)?;
+    assert!(old_image.read().unwrap().at("conv").is_some());
+
+    assert!(watcher.poll().is_empty());
+
+    drop(wz_file);
+    watcher.poll();
+    assert!(watcher.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn should_reopen_closed_file() -> Result<()> {
+    let wz_file = WzNode::from_wz_file(r"tests/test.wz", None)?.into_lock();
+
+    let int = wz_file.read().unwrap().at_path_parsed("wz_img.img/1/int");
+    assert!(int.is_err());
+
+    node_util::parse_node(&wz_file)?;
+    let old_int = wz_file.read().unwrap().at_path_parsed("wz_img.img/1/int")?;
+
+    assert!(wz_file.write().unwrap().close_file());
+    assert!(!wz_file.write().unwrap().close_file());
+    assert!(wz_file.read().unwrap().children.is_empty());
+
+    /* reopen on parsing */
+    node_util::parse_node(&wz_file)?;
+    let int = wz_file.read().unwrap().at_path_parsed("wz_img.img/1/int")?;
+    assert_eq!(
+        int.read().unwrap().try_as_int(),
+        old_int.read().unwrap().try_as_int()
+    );
+    assert!(!std::sync::Arc::ptr_eq(&int, &old_int));
+
+    /* the old node is still readable */
+    assert!(old_int.read().unwrap().try_as_int().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn should_bound_opened_files() -> Result<()> {
+    let open = || -> Result<WzNodeArc> {
+        let wz_file = WzNode::from_wz_file(r"tests/test.wz", None)?.into_lock();
+        node_util::parse_node(&wz_file)?;
+        Ok(wz_file)
+    };
+    let first = open()?;
+    let second = open()?;
+
+    let mut pool = util::FileHandlePool::new(1);
+
+    let image = first.read().unwrap().at("wz_img.img").unwrap();
+    pool.touch(&image);
+    assert_eq!(pool.open_count(), 1);
+
+    pool.touch(&second);
+    assert_eq!(pool.open_count(), 1);
+    assert!(first.read().unwrap().children.is_empty());
+    assert!(!second.read().unwrap().children.is_empty());
+
+    /* reopened by parsing, then close the other one when touched */
+    node_util::parse_node(&first)?;
+    pool.touch(&first);
+    assert_eq!(pool.open_count(), 1);
+    assert!(second.read().unwrap().children.is_empty());
+
+    assert_eq!(pool.close_idle(std::time::Duration::ZERO), 1);
+    assert_eq!(pool.open_count(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn should_copy_images_to_owned() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("test.wz");
+    std::fs::copy(r"tests/test.wz", &path)?;
+
+    let wz_file = WzNode::from_wz_file(&path, None)?.into_lock();
+    node_util::parse_node(&wz_file)?;
+
+    let image = wz_file.read().unwrap().at("wz_img.img").unwrap();
+    node_util::parse_node(&image)?;
+    let under_dir = wz_file
+        .read()
+        .unwrap()
+        .at_path_parsed("wz_dir/wz_img_under_dir.img")?;
+
+    let file_reader = match &wz_file.read().unwrap().object_type {
+        WzObjectType::File(file) => std::sync::Arc::downgrade(&file.reader),
+        _ => unreachable!(),
+    };
+
+    assert_eq!(node_util::copy_images_to_owned(&wz_file)?, 2);
+
+    drop(wz_file);
+    assert!(file_reader.upgrade().is_none());
+    std::fs::remove_file(&path)?;
+
+    /* the parsed one is parsed again */
+    let int = image.read().unwrap().at_path("1/int").unwrap();
+    assert_eq!(int.read().unwrap().try_as_int(), Some(&1));
+
+    let string = image.read().unwrap().at_path("2/string").unwrap();
+    let string = string
+        .read()
+        .unwrap()
+        .try_as_string()
+        .unwrap()
+        .get_string()?;
+    assert!(!string.is_empty());
+
+    let png = image.read().unwrap().at_path("conv/1").unwrap();
+    assert!(!png
+        .read()
+        .unwrap()
+        .try_as_png()
+        .unwrap()
+        .raw_compressed()
+        .unwrap()
+        .is_empty());
+
+    /* the unparsed one still parsable */
+    node_util::parse_node(&under_dir)?;
+    let hi = under_dir.read().unwrap().at("hi").unwrap();
+    assert_eq!(hi.read().unwrap().try_as_int(), Some(&1));
+
+    Ok(())
+}
@@ -80,7 +80,7 @@ fn should_error_with_wrong_iv() -> Result<()> {
 }
 
 fn check_sample_wz_img(wz_img: &WzNodeArc) -> Result<()> {
-    assert!(node_util::parse_node(&wz_img).is_ok());
+    assert!(node_util::parse_node(wz_img).is_ok());
 
     let wz_img_read = wz_img.read().unwrap();
 
@@ -352,3 +352,176 @@ fn should_success_walk_thorugh() {
         assert!(pathes.contains(node_read.get_full_path().as_str()));
     });
 }
+
+#[test]
+fn should_resolve_base_from_img_dump() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mob_dir = dir.path().join("Data").join("Mob.wz");
+    std::fs::create_dir_all(&mob_dir)?;
+    std::fs::copy("tests/test.img", mob_dir.join("100100.img"))?;
+    std::fs::copy("tests/test.img", mob_dir.join("100101.img"))?;
+    std::fs::write(mob_dir.join("readme.txt"), "not a img")?;
+
+    assert_eq!(
+        util::detect_base_layout(dir.path()),
+        Some(util::BaseLayout::ImgDump(dir.path().join("Data")))
+    );
+
+    let base = util::resolve_base(dir.path(), None)?;
+
+    let mob = base.read().unwrap().at("Mob").unwrap();
+    assert_eq!(mob.read().unwrap().children.len(), 2);
+
+    let int = base
+        .read()
+        .unwrap()
+        .at_path_parsed("Mob/100101.img/1/int")?;
+    assert_eq!(
+        int.read().unwrap().get_full_path(),
+        "Base/Mob/100101.img/1/int"
+    );
+    assert!(int.read().unwrap().try_as_int().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn should_detect_base_layout() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    assert_eq!(util::detect_base_layout(dir.path()), None);
+    assert!(util::resolve_base(dir.path(), None).is_err());
+
+    let packs = dir.path().join("Data").join("Packs");
+    std::fs::create_dir_all(&packs)?;
+    std::fs::write(packs.join("Mob_00000.ms"), [])?;
+
+    assert_eq!(
+        util::detect_base_layout(dir.path()),
+        Some(util::BaseLayout::Ms(packs))
+    );
+
+    let base_dir = dir.path().join("Data").join("Base");
+    std::fs::create_dir_all(&base_dir)?;
+    std::fs::copy("tests/test.wz", base_dir.join("Base.wz"))?;
+
+    /* classic one is preferred */
+    assert_eq!(
+        util::detect_base_layout(dir.path()),
+        Some(util::BaseLayout::Wz(base_dir.join("Base.wz")))
+    );
+    assert_eq!(
+        util::detect_base_layout(base_dir.join("Base.wz")),
+        Some(util::BaseLayout::Wz(base_dir.join("Base.wz")))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn should_guess_iv_with_offset_header() -> Result<()> {
+    let origin = std::fs::read(r"tests/test.img")?;
+    /* the encrypted "Property" string right after the 0x73 header */
+    let property = &origin[1..10];
+
+    let string_offset = 15_i32;
+    let mut buf = vec![wz_image::WZ_IMAGE_HEADER_BYTE_WITH_OFFSET];
+    buf.extend(string_offset.to_le_bytes());
+    buf.extend([0, 0]);
+    // one int property, it's name is the same "Property" string
+    buf.push(1);
+    buf.push(0x1B);
+    buf.extend(string_offset.to_le_bytes());
+    buf.extend([3, 7]);
+    assert_eq!(buf.len(), string_offset as usize);
+    buf.extend(property);
+
+    assert_eq!(
+        wz_reader::version::guess_iv_from_wz_img(&buf),
+        wz_reader::version::guess_iv_from_wz_img(&origin)
+    );
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("offset.img");
+    std::fs::write(&path, &buf)?;
+
+    let wz_img = WzNode::from_img_file(&path, None, None)?.into_lock();
+    node_util::parse_node(&wz_img)?;
+
+    let property = wz_img.read().unwrap().at("Property").unwrap();
+    assert_eq!(property.read().unwrap().try_as_int(), Some(&7));
+
+    let wz_image = wz_img.read().unwrap().try_as_image().unwrap().clone();
+    assert!(wz_image.at_path("Property").is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn should_verify_iv_with_image_structure() -> Result<()> {
+    use wz_reader::util::{maple_crypto_constants::WZ_MSEAIV, WzMutableKey};
+    use wz_reader::version::{guess_iv_from_wz_img, verify_iv_from_wz_img};
+
+    let origin = std::fs::read(r"tests/test.img")?;
+    assert!(verify_iv_from_wz_img(&origin, &[0; 4]));
+    assert!(!verify_iv_from_wz_img(&origin, &WZ_MSEAIV));
+
+    /* the "Property" header is fine, but the children are broken */
+    let mut broken = origin[..12].to_vec();
+    broken.extend([3, 0xFF, 0xFF, 0xFF]);
+    assert!(!verify_iv_from_wz_img(&broken, &[0; 4]));
+    assert_eq!(guess_iv_from_wz_img(&broken), None);
+
+    let script = b"function onEnter() return 1 end";
+    let mut encrypted = script.to_vec();
+    let mut keys = WzMutableKey::from_iv(WZ_MSEAIV);
+    keys.ensure_key_size(encrypted.len())?;
+    keys.decrypt_slice(&mut encrypted);
+
+    let mut lua = vec![0x1, script.len() as u8];
+    lua.extend(encrypted);
+    assert!(verify_iv_from_wz_img(&lua, &WZ_MSEAIV));
+    assert_eq!(guess_iv_from_wz_img(&lua), Some(WZ_MSEAIV));
+
+    Ok(())
+}
+
+#[test]
+fn should_parse_to_depth() -> Result<()> {
+    let wz_img = WzNode::from_img_file(r"tests/test.img", None, None)?.into_lock();
+    let deferred = wz_img.write().unwrap().parse_to_depth(&wz_img, 1)?;
+
+    assert_eq!(deferred.len(), 2);
+    {
+        let wz_img_read = wz_img.read().unwrap();
+        assert_eq!(wz_img_read.children.len(), 3);
+        for name in ["1", "2"] {
+            assert!(wz_img_read
+                .at(name)
+                .unwrap()
+                .read()
+                .unwrap()
+                .children
+                .is_empty());
+        }
+        /* the convex can't be skipped */
+        assert!(wz_img_read.at_path("conv/1/origin").is_some());
+    }
+
+    for block in &deferred {
+        assert!(block.expand(1)?.is_empty());
+    }
+
+    let int = wz_img.read().unwrap().at_path("1/int").unwrap();
+    assert_eq!(int.read().unwrap().try_as_int(), Some(&1));
+
+    /* the uol is kept */
+    let uol = wz_img.read().unwrap().at_path("2/uol").unwrap();
+    assert!(uol.read().unwrap().try_as_uol().is_some());
+
+    let full = WzNode::from_img_file(r"tests/test.img", None, None)?.into_lock();
+    assert!(full.write().unwrap().parse_to_depth(&full, 2)?.is_empty());
+    assert!(full.read().unwrap().at_path("2/string").is_some());
+
+    Ok(())
+}